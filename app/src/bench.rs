@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     path::{Path, PathBuf},
     process::Stdio,
     sync::Arc,
@@ -8,47 +8,60 @@ use std::{
 
 use chrono::Local;
 use common::{
-    bench::{Bench, BenchArgs, BenchInfo, BenchParams, Cmd, CmdsResult},
-    config::Config,
+    FAILED_MARKER,
+    background::stop_background_load,
+    bench::{
+        Bench, BenchArgs, BenchInfo, BenchParams, COOLDOWN_DIRNAME, Cmd, CmdsResult, DeviceInfo,
+        PREPARE_DIRNAME, running_benchmark, set_running_benchmark,
+    },
+    config::{Config, Devices, FailurePolicy, Settings},
+    cpu_governor::{restore_cpu_governor, set_cpu_governor},
+    kernel_log::{check_kernel_errors, kernel_log_mark},
+    perf::check_perf,
     plot::{PlotType, plot},
-    sensor::SensorRequest,
+    power_cap::restore_rapl_power_limit,
+    sensor::{
+        SensorReply, SensorRequest, export_sensor_csvs, sensor_filename, sensor_runtime,
+        start_prometheus_push, start_sample_ticker,
+    },
+    summary::RunSummary,
+    throttling::check_throttling,
     util::{
-        chown_user, get_cpu_topology, remove_indices, simple_command_with_output_no_dir,
-        write_one_line,
+        chown_user, get_cpu_topology, kill_process_tree, remove_indices,
+        simple_command_with_output_no_dir, write_one_line,
     },
 };
 use console::style;
 use default_sensors::SENSOR_ARGS;
-use eyre::{Context, Result, bail};
-use flume::unbounded;
+use eyre::{Context, ContextCompat, Result, bail, eyre};
+use flume::{Receiver, Sender, unbounded};
 use indicatif::{ProgressBar, ProgressStyle};
 use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
 use tokio::{
-    fs::{copy, create_dir_all, read_to_string, remove_dir_all, write},
+    fs::{copy, create_dir_all, read_to_string, remove_dir_all, rename, write},
     process::Command,
     spawn,
     sync::Mutex,
-    time::sleep,
+    time::{sleep, timeout},
 };
 use tracing::{debug, error, info, warn};
 
+use crate::{
+    config_file::{read_config, results_config_name, results_config_path},
+    validate_config,
+};
+
 pub async fn run_benchmark(
     config_file: String,
     no_progress: bool,
     skip_plot: bool,
     use_dir: Option<String>,
+    force: bool,
 ) -> Result<()> {
-    let config: Config = serde_yml::from_str(&read_to_string(&config_file).await?)?;
-    let unique_bench_names = config
-        .benches
-        .iter()
-        .map(|x| &x.name)
-        .collect::<HashSet<_>>();
-    if unique_bench_names.len() != config.benches.len() {
-        bail!(
-            "Bench names must be unique! Config file contains multiple benchmarks with the same name."
-        );
-    }
+    let config = read_config(&config_file).await?;
+    validate_config(&config)?;
 
     let cpu_min_freq = read_to_string("/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_min_freq")
         .await?
@@ -74,13 +87,46 @@ pub async fn run_benchmark(
             .await
             .context("Set CPU frequency")?;
     }
+    if let Some(cpu_governor) = &config.settings.cpu_governor {
+        set_cpu_governor(
+            &cpu_governor.governor,
+            cpu_governor.min_freq,
+            cpu_governor.max_freq,
+        )
+        .await
+        .context("Set CPU governor")?;
+    }
+
+    for device in config.settings.device.all() {
+        _ = simple_command_with_output_no_dir("umount", &[device]).await;
+    }
+    if let Some(loop_device) = &config.settings.loop_device {
+        loop_device.setup(config.settings.device.single()?).await?;
+    }
+    if let Some(dm_crypt) = &config.settings.dm_crypt {
+        dm_crypt.validate(config.settings.device.single()?).await?;
+        dm_crypt.setup(config.settings.device.single()?).await?;
+    }
+    let bench_settings = config.settings.bench_settings();
+    if config.settings.perf_events.is_some() {
+        check_perf().await?;
+    }
 
-    _ = simple_command_with_output_no_dir("umount", &[&config.settings.device]).await;
     let file_prefix = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
-    println!(
-        "Results created in folder: results/{}-{file_prefix}",
-        config.name
-    );
+    let results_path = match use_dir {
+        Some(use_dir) => PathBuf::from(use_dir),
+        None => PathBuf::from("results").join(format!("{}-{file_prefix}", config.name)),
+    };
+    let existing_runs = existing_runs(&results_path, &config, force).await?;
+    if existing_runs.is_empty() {
+        println!("Results created in folder: {}", results_path.display());
+    } else {
+        println!(
+            "Appending to {} existing runs in folder: {}",
+            existing_runs.len(),
+            results_path.display()
+        );
+    }
 
     let progress = Progress::new(!no_progress, &config)?;
 
@@ -89,7 +135,19 @@ pub async fn run_benchmark(
     let mut sensor_replies = Vec::new();
     let mut loaded_sensors = Vec::new();
     let mut sensor_handles = Vec::new();
+    let mut sensor_filenames = Vec::new();
 
+    // sensors spawn their readers on the current runtime
+    let sensor_runtime_guard = match config.settings.sensor_cpu {
+        Some(cpu) => Some(sensor_runtime(cpu)?.enter()),
+        None => None,
+    };
+    if let Some(period) = config.settings.sync_sample_interval {
+        start_sample_ticker(Duration::from_millis(period));
+    }
+    if let Some(url) = &config.settings.prometheus_push_gateway {
+        start_prometheus_push(url)?;
+    }
     for s in &config.sensors {
         if let Some(obj) = sensor_objects.iter().find(|s_obj| s_obj.name() == s.sensor) {
             let (req_tx, req_rx) = unbounded();
@@ -103,31 +161,38 @@ pub async fn run_benchmark(
                     .find(|x| x.name() == s.sensor)
                     .unwrap(),
             };
-            sensor_handles.push(obj.start(&**args, &config.settings, req_rx, resp_tx)?);
+            let filename = sensor_filename(obj.filename(), s.alias.as_deref());
+            sensor_handles.push(obj.start(
+                &**args,
+                &config.settings,
+                &filename,
+                req_rx,
+                resp_tx,
+            )?);
+            sensor_filenames.push(filename);
             sensors.push(req_tx);
             sensor_replies.push(resp_rx);
             loaded_sensors.push(s);
         }
     }
+    drop(sensor_runtime_guard);
 
     debug!("Loaded sensors: {loaded_sensors:?}");
-    let results_path = match use_dir {
-        Some(use_dir) => PathBuf::from(use_dir),
-        None => PathBuf::from("results").join(format!("{}-{file_prefix}", config.name)),
-    };
-
     let data_path = results_path.join("data");
     create_dir_all(&results_path).await?;
     let plot_path = results_path.join("plots");
     _ = remove_dir_all(&plot_path).await;
     create_dir_all(&plot_path).await?;
-    copy(config_file, results_path.join("config.yaml")).await?;
+    let mut sweep_progress = SweepProgress::new(&results_path, &config)?;
+    sweep_progress.write().await?;
+    copy(
+        &config_file,
+        results_path.join(results_config_name(Path::new(&config_file))),
+    )
+    .await?;
 
     debug!("Initial results setup done!");
-    let nvme_power_states = match &config.settings.nvme_power_states {
-        Some(ps) => ps.iter().map(|x| *x as i32).collect(),
-        None => vec![],
-    };
+    let power_states = config.settings.power_states();
 
     let s = sensors.clone();
     spawn(async move {
@@ -140,57 +205,91 @@ pub async fn run_benchmark(
         std::process::exit(0);
     });
 
-    let nvme_device_path = config
-        .settings
-        .device
-        .strip_prefix("/dev/")
-        .unwrap()
-        .to_string();
     let scheduler = config
         .settings
         .scheduler
         .clone()
         .unwrap_or("none".to_owned());
-    write_one_line(
-        format!("/sys/block/{nvme_device_path}/queue/scheduler"),
-        &scheduler,
-    )
-    .await
-    .context("Set IO scheduler")?;
-
-    let max_hw_sectors = read_to_string(format!(
-        "/sys/block/{nvme_device_path}/queue/max_hw_sectors_kb"
-    ))
-    .await?;
-    write_one_line(
-        format!("/sys/block/{nvme_device_path}/queue/max_sectors_kb"),
-        &max_hw_sectors,
-    )
-    .await
-    .context("Set max sectors")?;
-
-    let nvme_cli_device = strip_nvme_namespace(&config.settings.device);
-    let device_power_states = fetch_nvme_power_states(&nvme_cli_device)
+    for device in config.settings.device.all() {
+        let nvme_device_path = device.strip_prefix("/dev/").unwrap();
+        write_one_line(
+            format!("/sys/block/{nvme_device_path}/queue/scheduler"),
+            &scheduler,
+        )
         .await
-        .context("Fetch NVMe power states")?;
+        .context("Set IO scheduler")?;
+
+        let max_hw_sectors = read_to_string(format!(
+            "/sys/block/{nvme_device_path}/queue/max_hw_sectors_kb"
+        ))
+        .await?;
+        write_one_line(
+            format!("/sys/block/{nvme_device_path}/queue/max_sectors_kb"),
+            &max_hw_sectors,
+        )
+        .await
+        .context("Set max sectors")?;
+    }
+
+    let nvme_controllers = nvme_controllers(&config.settings.device);
+    // several devices are expected to be the same model, ie. the members of a RAID, so the power
+    // state table and device info are read from the first
+    let nvme_cli_device = &nvme_controllers[0];
+    let device_power_states =
+        resolve_device_power_states(&config.settings, nvme_cli_device).await?;
     debug!("Fetched NVMe power states: {device_power_states:?}");
+    let device = fetch_device_info(nvme_cli_device).await;
 
     let mut bench_info = BenchInfo {
-        param_map: HashMap::new(),
+        param_map: existing_runs,
         device_power_states,
         cpu_freq_limits: (cpu_min_freq, cpu_max_freq),
         cpu_topology,
+        warmup: None,
+        device,
     };
+
+    if let Some(warmup) = &config.settings.warmup {
+        progress.set_message(format!("Warmup: {}", style(warmup).dim()));
+        if let Err(err) = run_warmup(
+            warmup,
+            &config,
+            &bench_settings,
+            &results_path.join("warmup"),
+            &sensors,
+            &sensor_replies,
+        )
+        .await
+        {
+            error!("Failed to run warmup: {err}");
+            if let Some(dm_crypt) = &config.settings.dm_crypt {
+                _ = dm_crypt.close().await;
+            }
+            if let Some(loop_device) = &config.settings.loop_device {
+                _ = loop_device.teardown(config.settings.device.single()?).await;
+            }
+            for s in &sensors {
+                s.send_async(SensorRequest::Quit).await?;
+            }
+            return Err(err);
+        }
+        bench_info.warmup = Some(warmup.clone());
+        write(
+            results_path.join("info.json"),
+            serde_json::to_string_pretty(&bench_info)?,
+        )
+        .await?;
+    }
+
     let total_experiments = config.benches.len();
     let mut current_experiment = 0;
     let mut append_spdk_power_state = false;
+    let mut first_run = true;
 
     for experiment in &config.benches {
         current_experiment += 1;
-        let mut ps = nvme_power_states.clone();
-        if ps.is_empty() {
-            ps.push(-1);
-        }
+        let ps = power_states.states();
+        sweep_progress.current_name = Some(experiment.name.clone());
 
         let mut last_experiment: Option<Box<dyn Bench>> = None;
         let mut experiment_dirs = Vec::new();
@@ -198,19 +297,60 @@ pub async fn run_benchmark(
         let CmdsResult { cmds, program } =
             experiment
                 .bench
-                .cmds(&config.settings, &*bench_args, &experiment.name)?;
+                .cmds(&bench_settings, &*bench_args, &experiment.name)?;
+
+        let run_indices = run_indices(&experiment.name, &cmds, &bench_info.param_map)?;
 
         let total_commands = cmds.len();
-        for power_state in &ps {
+        for power_state in ps {
             for (
                 curr_cmd_idx,
                 Cmd {
                     args,
-                    idx,
+                    idx: _,
                     bench_obj,
                 },
             ) in cmds.iter().enumerate()
             {
+                let idx = &run_indices[curr_cmd_idx];
+                let completed = bench_info
+                    .param_map
+                    .iter()
+                    .filter(|(_, info)| {
+                        info.name == experiment.name
+                            && info.power_state == *power_state
+                            && info.idx == *idx
+                    })
+                    .map(|(dir, _)| dir.clone())
+                    .collect::<Vec<_>>();
+                let any_failed = completed
+                    .iter()
+                    .any(|dir| data_path.join(dir).join(FAILED_MARKER).exists());
+                if any_failed {
+                    // the runs are repeated in the same directories, so earlier results are removed
+                    debug!(
+                        "Re-running {} ps={power_state} idx={idx}, a run failed in {completed:?}",
+                        experiment.name
+                    );
+                    for dir in &completed {
+                        bench_info.param_map.remove(dir);
+                        remove_dir_all(data_path.join(dir)).await?;
+                    }
+                } else if !completed.is_empty() {
+                    debug!(
+                        "Skipping {} ps={power_state} idx={idx}, already run in {completed:?}",
+                        experiment.name
+                    );
+                    for _ in 0..experiment.repeat {
+                        progress.tick().await;
+                        sweep_progress
+                            .complete(&**bench_obj, &config.settings)
+                            .await?;
+                    }
+                    experiment_dirs.extend(completed);
+                    continue;
+                }
+
                 if *power_state != -1 {
                     if bench_obj.requires_custom_power_state_setter() {
                         if bench_obj.name() == "fio" {
@@ -218,25 +358,27 @@ pub async fn run_benchmark(
                         }
                     } else {
                         append_spdk_power_state = false;
-                        let mut ps_change_cmd = Command::new("nvme")
-                            .args([
-                                "set-feature",
-                                &nvme_cli_device,
-                                "-f",
-                                "2",
-                                "--value",
-                                &power_state.to_string(),
-                            ])
-                            .stdout(Stdio::null())
-                            .stderr(Stdio::null())
-                            .spawn()
-                            .context("Set nvme power state")?;
-                        let status = ps_change_cmd.wait().await?;
-
-                        if !status.success() {
-                            bail!("Could not change device power state to {power_state}");
+                        for nvme_cli_device in &nvme_controllers {
+                            let mut ps_change_cmd = Command::new("nvme")
+                                .args([
+                                    "set-feature",
+                                    nvme_cli_device,
+                                    "-f",
+                                    "2",
+                                    "--value",
+                                    &power_state.to_string(),
+                                ])
+                                .stdout(Stdio::null())
+                                .stderr(Stdio::null())
+                                .spawn()
+                                .context("Set nvme power state")?;
+                            let status = ps_change_cmd.wait().await?;
+
+                            if !status.success() {
+                                bail!("Could not change device power state to {power_state}");
+                            }
+                            info!("Power state of {nvme_cli_device} change to {power_state}");
                         }
-                        info!("Power state of {nvme_cli_device} change to {power_state}");
                     }
                 }
 
@@ -275,21 +417,66 @@ pub async fn run_benchmark(
                             iteration: i,
                             name: experiment.name.clone(),
                             idx: *idx,
+                            tags: experiment.tags.clone(),
+                            background: config
+                                .settings
+                                .background
+                                .as_ref()
+                                .map(|x| x.command_line(&bench_settings.device)),
                         },
                     );
                     create_dir_all(&final_path).await?;
                     chown_user(&final_path).await?;
 
-                    bench_obj
+                    if !first_run {
+                        cooldown(
+                            &config.settings,
+                            &final_path,
+                            bench_obj.clone(),
+                            &sensors,
+                            &sensor_replies,
+                        )
+                        .await?;
+                    }
+                    first_run = false;
+
+                    if let Some(dm_crypt) = &config.settings.dm_crypt {
+                        dm_crypt.open(config.settings.device.single()?).await?;
+                    }
+                    let measure_prepare = config.settings.measure_prepare.unwrap_or(false);
+                    if measure_prepare {
+                        let prepare_path = final_path.join(PREPARE_DIRNAME);
+                        create_dir_all(&prepare_path).await?;
+                        for s in &sensors {
+                            s.send_async(SensorRequest::StartRecording {
+                                dir: prepare_path.clone(),
+                                args: Vec::new(),
+                                program: "experiment_init".to_owned(),
+                                pid: std::process::id(),
+                                bench: bench_obj.clone(),
+                            })
+                            .await?;
+                        }
+                    }
+                    let init = bench_obj
                         .experiment_init(
                             &data_path,
-                            &config.settings,
+                            &bench_settings,
                             &*bench_args,
                             &last_experiment,
                             &config,
                             &final_path,
                         )
-                        .await?;
+                        .await;
+                    if measure_prepare {
+                        for s in &sensors {
+                            s.send_async(SensorRequest::StopRecording).await?;
+                        }
+                        for s in &sensor_replies {
+                            _ = s.recv_async().await?;
+                        }
+                    }
+                    init?;
                     chown_user(&final_path).await?;
 
                     let mut args = args.clone();
@@ -314,34 +501,160 @@ pub async fn run_benchmark(
                             .join(", ")
                     );
                     sleep(Duration::from_secs(1)).await;
-                    let result = bench_obj
-                        .run(
+                    let on_failure = config.settings.on_failure.clone().unwrap_or_default();
+                    // runs without an estimate never time out
+                    let run_timeout = match config.settings.timeout_multiplier {
+                        Some(multiplier) => Some(bench_obj.runtime_estimate()?)
+                            .filter(|x| *x > 0)
+                            .map(|x| Duration::from_secs_f64(x as f64 / 1000.0 * multiplier)),
+                        None => None,
+                    };
+                    let kernel_mark = match kernel_log_mark().await {
+                        Ok(mark) => Some(mark),
+                        Err(err) => {
+                            warn!("Not checking the kernel log for errors: {err:#}");
+                            None
+                        }
+                    };
+                    let background = match &config.settings.background {
+                        Some(background) => {
+                            match background.start(&bench_settings.device, &final_path).await {
+                                Ok(child) => Some(child),
+                                Err(err) => {
+                                    error!("Failed to start the background load: {err:#}");
+                                    abort_cleanup(&config.settings, &sensors).await?;
+                                    return Err(err.wrap_err("Start background load"));
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+                    let mut attempt = 0;
+                    let (result, wall_time) = loop {
+                        let run_start = Instant::now();
+                        let run = bench_obj.run(
                             &program,
                             &args,
                             &env,
-                            &config.settings,
+                            &bench_settings,
                             &sensors,
                             &final_path,
                             bench_obj.clone(),
                             &config,
                             &last_experiment,
+                        );
+                        let result = match run_timeout {
+                            Some(limit) => match timeout(limit, run).await {
+                                Ok(result) => result,
+                                Err(_) => {
+                                    abort_hung_run(&bench_settings.device).await;
+                                    Err(eyre!("Benchmark timed out after {}s", limit.as_secs()))
+                                }
+                            },
+                            None => run.await,
+                        };
+                        set_running_benchmark(None);
+                        let wall_time = run_start.elapsed();
+
+                        match (&result, &on_failure) {
+                            (Err(err), FailurePolicy::Retry(retries)) if attempt < *retries => {
+                                attempt += 1;
+                                warn!(
+                                    "Failed to run benchmark, retrying ({attempt}/{retries}): {err}"
+                                );
+                                reset_sensors(&sensors, &sensor_replies).await?;
+                                // tears down what the failed attempt started, ie. a database
+                                if let Err(err) = bench_obj
+                                    .post_experiment(
+                                        &data_path,
+                                        &final_path,
+                                        &bench_settings,
+                                        &*bench_args,
+                                    )
+                                    .await
+                                {
+                                    warn!("Could not clean up the failed attempt: {err:#}");
+                                }
+                                // the failed attempt may have left the device in any state, so it
+                                // is set up from scratch instead of reusing the last experiment's
+                                if let Err(err) = bench_obj
+                                    .experiment_init(
+                                        &data_path,
+                                        &bench_settings,
+                                        &*bench_args,
+                                        &None,
+                                        &config,
+                                        &final_path,
+                                    )
+                                    .await
+                                {
+                                    break (
+                                        Err(err.wrap_err("Error initializing retry")),
+                                        wall_time,
+                                    );
+                                }
+                            }
+                            _ => break (result, wall_time),
+                        }
+                    };
+                    if let Some(background) = background
+                        && let Err(err) = stop_background_load(background).await
+                    {
+                        error!("Failed to stop the background load: {err:#}");
+                        abort_cleanup(&config.settings, &sensors).await?;
+                        return Err(err.wrap_err("Stop background load"));
+                    }
+                    if let Some(mark) = kernel_mark
+                        && let Err(err) = check_kernel_errors(
+                            &final_path,
+                            &bench_settings
+                                .device
+                                .all()
+                                .iter()
+                                .chain(config.settings.device.all())
+                                .map(String::as_str)
+                                .collect::<Vec<_>>(),
+                            mark,
                         )
-                        .await;
-
-                    if let Err(err) = result {
-                        error!("Failed to run benchmark: {err}");
+                        .await
+                    {
+                        warn!("Could not check the kernel log for errors: {err:#}");
+                    }
 
-                        for s in &sensors {
-                            s.send_async(SensorRequest::Quit).await?;
+                    let failed = match result {
+                        Ok(_) => false,
+                        Err(err) if on_failure == FailurePolicy::Continue => {
+                            error!("Failed to run benchmark, continuing: {err}");
+                            write(final_path.join(FAILED_MARKER), format!("{err:?}")).await?;
+                            reset_sensors(&sensors, &sensor_replies).await?;
+                            // post_experiment is skipped for failed runs, the governor of the
+                            // cpu_governor setting is kept for the remaining runs
+                            restore_rapl_power_limit().await?;
+                            if config.settings.cpu_governor.is_none() {
+                                restore_cpu_governor().await?;
+                            }
+                            dirs.pop();
+                            true
                         }
+                        Err(err) => {
+                            error!("Failed to run benchmark: {err}");
+                            abort_cleanup(&config.settings, &sensors).await?;
+                            return Err(err);
+                        }
+                    };
 
-                        return Err(err);
-                    }
-
-                    for s in &sensor_replies {
-                        _ = s.recv_async().await?;
+                    if !failed {
+                        for s in &sensor_replies {
+                            _ = s.recv_async().await?;
+                        }
+                        if let Some(names) = &config.settings.sensor_column_names {
+                            export_sensor_csvs(&final_path, &sensor_filenames, names).await?;
+                        }
                     }
                     progress.tick().await;
+                    sweep_progress
+                        .complete(&**bench_obj, &config.settings)
+                        .await?;
 
                     progress.set_message(format!(
                         "Iteration {} [{} retries]",
@@ -354,10 +667,76 @@ pub async fn run_benchmark(
                     ));
                     debug!("Done with bench {} iter={}", experiment.name, i);
 
-                    bench_obj
-                        .post_experiment(&data_path, &final_path, &config.settings, &*bench_args)
-                        .await
-                        .context("Error running post experiment")?;
+                    if !failed {
+                        let processed = async {
+                            bench_obj
+                                .parse_results(&final_path, experiment.parser.as_deref())
+                                .await
+                                .context("Error parsing results")?;
+                            bench_obj
+                                .post_experiment(
+                                    &data_path,
+                                    &final_path,
+                                    &bench_settings,
+                                    &*bench_args,
+                                )
+                                .await
+                                .context("Error running post experiment")?;
+                            if let Some(detection) = &config.settings.throttle_detection {
+                                check_throttling(&final_path, detection, &config.sensors).await?;
+                            }
+                            let throughput = match bench_obj.primary_metric(&final_path).await {
+                                Ok(throughput) => throughput,
+                                Err(err) => {
+                                    warn!(
+                                        "Could not read the primary metric of {}: {err:#}",
+                                        final_path.display()
+                                    );
+                                    None
+                                }
+                            };
+                            // the summary is informational, the run itself succeeded
+                            if let Err(err) = RunSummary::record_run(
+                                &final_path,
+                                wall_time,
+                                throughput,
+                                &config,
+                                bench_info
+                                    .device_power_states
+                                    .first()
+                                    .map_or(f64::INFINITY, |x| x.0),
+                            )
+                            .await
+                            {
+                                warn!(
+                                    "Could not record the summary of {}: {err:#}",
+                                    final_path.display()
+                                );
+                            }
+                            Ok::<_, eyre::Report>(())
+                        }
+                        .await;
+                        match processed {
+                            Ok(_) => {}
+                            Err(err) if on_failure == FailurePolicy::Continue => {
+                                error!("Failed to process the results, continuing: {err:#}");
+                                write(final_path.join(FAILED_MARKER), format!("{err:?}")).await?;
+                                restore_rapl_power_limit().await?;
+                                if config.settings.cpu_governor.is_none() {
+                                    restore_cpu_governor().await?;
+                                }
+                                dirs.pop();
+                            }
+                            Err(err) => {
+                                error!("Failed to process the results: {err:#}");
+                                abort_cleanup(&config.settings, &sensors).await?;
+                                return Err(err);
+                            }
+                        }
+                    }
+                    if let Some(dm_crypt) = &config.settings.dm_crypt {
+                        dm_crypt.close().await?;
+                    }
 
                     if let Some(sleep_time) = &config.settings.sleep_between_experiments {
                         sleep(Duration::from_secs(*sleep_time)).await;
@@ -394,6 +773,7 @@ pub async fn run_benchmark(
                                 debug!("Removing {}", dirs[*item]);
                                 remove_dir_all(&data_path.join(&dirs[*item])).await?;
                                 progress.increment_total().await;
+                                sweep_progress.add_run(&**bench_obj, &config.settings)?;
                             }
                             remove_indices(&mut dirs, &outliers);
                         }
@@ -443,6 +823,8 @@ pub async fn run_benchmark(
     }
 
     progress.finish().await;
+    sweep_progress.current_name = None;
+    sweep_progress.write().await?;
     sleep(Duration::from_secs(1)).await;
 
     for s in sensors {
@@ -474,11 +856,190 @@ pub async fn run_benchmark(
     if let Some(cpu_freq) = &config.settings.cpu_freq {
         set_cpu_freq(cpu_max_freq, cpu_min_freq, &cpu_freq.default_governor).await?;
     }
+    restore_cpu_governor().await?;
+
+    if let Some(dm_crypt) = &config.settings.dm_crypt {
+        dm_crypt.teardown().await?;
+    }
+    if let Some(loop_device) = &config.settings.loop_device {
+        loop_device
+            .teardown(config.settings.device.single()?)
+            .await?;
+    }
 
     debug!("Exiting");
     Ok(())
 }
 
+/// Runs the first command of an experiment once to bring the device to a consistent
+/// (thermal) state, the results are written to `dir` and removed afterwards
+async fn run_warmup(
+    name: &str,
+    config: &Config,
+    settings: &Settings,
+    dir: &Path,
+    sensors: &[Sender<SensorRequest>],
+    sensor_replies: &[Receiver<SensorReply>],
+) -> Result<()> {
+    let experiment = config
+        .benches
+        .iter()
+        .find(|x| x.name == name)
+        .context(format!("Warmup experiment {name} does not exist"))?;
+    let bench_args = get_bench_args(&config.bench_args, &*experiment.bench);
+    let CmdsResult { cmds, program } =
+        experiment
+            .bench
+            .cmds(settings, &*bench_args, &experiment.name)?;
+    let Cmd {
+        args, bench_obj, ..
+    } = cmds
+        .into_iter()
+        .next()
+        .context(format!("Warmup experiment {name} has no commands"))?;
+
+    info!("Running warmup {name}");
+    _ = remove_dir_all(dir).await;
+    create_dir_all(dir).await?;
+    if let Some(dm_crypt) = &config.settings.dm_crypt {
+        dm_crypt.open(config.settings.device.single()?).await?;
+    }
+    bench_obj
+        .experiment_init(dir, settings, &*bench_args, &None, config, dir)
+        .await?;
+
+    let mut args = args;
+    bench_obj.add_path_args(&mut args, dir);
+    let env = bench_obj
+        .add_env(&*bench_args)
+        .context("Get benchmark env")?;
+    bench_obj
+        .run(
+            &program,
+            &args,
+            &env,
+            settings,
+            sensors,
+            dir,
+            bench_obj.clone(),
+            config,
+            &None,
+        )
+        .await?;
+    for s in sensor_replies {
+        _ = s.recv_async().await?;
+    }
+    bench_obj
+        .parse_results(dir, experiment.parser.as_deref())
+        .await
+        .context("Error parsing results")?;
+    bench_obj
+        .post_experiment(dir, dir, settings, &*bench_args)
+        .await
+        .context("Error running post experiment")?;
+    if let Some(dm_crypt) = &config.settings.dm_crypt {
+        dm_crypt.close().await?;
+    }
+
+    remove_dir_all(dir).await?;
+    if let Some(sleep_time) = &config.settings.sleep_between_experiments {
+        sleep(Duration::from_secs(*sleep_time)).await;
+    }
+    Ok(())
+}
+
+/// Kills the benchmark process of a run that exceeded [`Settings::timeout_multiplier`] and
+/// everything it spawned, other processes of the app like the background load keep running. The
+/// device is lazily unmounted as `post_experiment` is skipped for failed runs, the sensors are
+/// stopped by the failure handling.
+async fn abort_hung_run(device: &Devices) {
+    match running_benchmark().map(kill_process_tree) {
+        Some(Ok(killed)) => warn!("Benchmark timed out, killed {killed} processes"),
+        Some(Err(err)) => error!("Benchmark timed out, could not kill its processes: {err}"),
+        None => error!("Benchmark timed out before its process was started"),
+    }
+    for device in device.all() {
+        _ = simple_command_with_output_no_dir("umount", &["-l", device]).await;
+    }
+}
+
+/// Waits [`Settings::cooldown_secs`] before the init of a run, recording the sensors to
+/// [`COOLDOWN_DIRNAME`] in the run directory if [`Settings::record_cooldown`] is set
+async fn cooldown(
+    settings: &Settings,
+    run_path: &Path,
+    bench: Box<dyn Bench>,
+    sensors: &[Sender<SensorRequest>],
+    sensor_replies: &[Receiver<SensorReply>],
+) -> Result<()> {
+    let Some(secs) = settings.cooldown_secs.filter(|x| *x > 0) else {
+        return Ok(());
+    };
+    debug!("Cooling down for {secs}s");
+    if !settings.record_cooldown.unwrap_or(false) {
+        sleep(Duration::from_secs(secs)).await;
+        return Ok(());
+    }
+
+    let cooldown_path = run_path.join(COOLDOWN_DIRNAME);
+    create_dir_all(&cooldown_path).await?;
+    for s in sensors {
+        s.send_async(SensorRequest::StartRecording {
+            dir: cooldown_path.clone(),
+            args: Vec::new(),
+            program: "cooldown".to_owned(),
+            pid: std::process::id(),
+            bench: bench.clone(),
+        })
+        .await?;
+    }
+    sleep(Duration::from_secs(secs)).await;
+    for s in sensors {
+        s.send_async(SensorRequest::StopRecording).await?;
+    }
+    for s in sensor_replies {
+        _ = s.recv_async().await?;
+    }
+    chown_user(&cooldown_path).await?;
+    Ok(())
+}
+
+/// Undoes the device and system setup of a run and stops the sensors, before a failed run aborts
+/// the sweep. Errors of the individual steps are ignored, so that as much as possible is undone.
+async fn abort_cleanup(settings: &Settings, sensors: &[Sender<SensorRequest>]) -> Result<()> {
+    if let Some(dm_crypt) = &settings.dm_crypt {
+        _ = dm_crypt.close().await;
+    }
+    if let Some(loop_device) = &settings.loop_device
+        && let Ok(device) = settings.device.single()
+    {
+        _ = loop_device.teardown(device).await;
+    }
+    _ = restore_rapl_power_limit().await;
+    _ = restore_cpu_governor().await;
+
+    for s in sensors {
+        s.send_async(SensorRequest::Quit).await?;
+    }
+    Ok(())
+}
+
+/// Brings sensors back to an idle state after a failed run, a sensor that was never
+/// started will not reply so replies are only waited on for a short while.
+async fn reset_sensors(
+    sensors: &[Sender<SensorRequest>],
+    sensor_replies: &[Receiver<SensorReply>],
+) -> Result<()> {
+    for s in sensors {
+        s.send_async(SensorRequest::StopRecording).await?;
+    }
+    for s in sensor_replies {
+        _ = timeout(Duration::from_secs(10), s.recv_async()).await;
+        s.drain();
+    }
+    Ok(())
+}
+
 fn get_bench_args(bench_args: &[Box<dyn BenchArgs>], bench: &dyn Bench) -> Box<dyn BenchArgs> {
     for args in bench_args {
         if args.name() == bench.name() {
@@ -489,12 +1050,111 @@ fn get_bench_args(bench_args: &[Box<dyn BenchArgs>], bench: &dyn Bench) -> Box<d
     bench.default_bench_args()
 }
 
+/// Runs already recorded in an existing results folder, which new runs are appended to
+///
+/// The folder is only appended to if its config matches, ie. the same settings apart from the
+/// power states, the same bench args, and every experiment it ran is still in `config`. With
+/// `force` the folder is removed instead.
+async fn existing_runs(
+    results_path: &Path,
+    config: &Config,
+    force: bool,
+) -> Result<HashMap<String, BenchParams>> {
+    let config_path = results_config_path(results_path);
+    if !config_path.exists() {
+        return Ok(HashMap::new());
+    }
+    if force {
+        warn!("Overwriting existing results in {}", results_path.display());
+        remove_dir_all(results_path).await?;
+        return Ok(HashMap::new());
+    }
+
+    let existing = read_config(&config_path).await?;
+    let comparable_settings = |settings: &Settings| -> Result<serde_json::Value> {
+        let mut settings = serde_json::to_value(settings)?;
+        if let Some(settings) = settings.as_object_mut() {
+            settings.remove("nvme_power_states");
+        }
+        Ok(settings)
+    };
+    if comparable_settings(&existing.settings)? != comparable_settings(&config.settings)? {
+        bail!(
+            "Settings differ from the config in {}, use --force to overwrite it",
+            results_path.display()
+        );
+    }
+    if serde_json::to_value(&existing.bench_args)? != serde_json::to_value(&config.bench_args)? {
+        bail!(
+            "Bench args differ from the config in {}, use --force to overwrite it",
+            results_path.display()
+        );
+    }
+    for experiment in &existing.benches {
+        if !config
+            .benches
+            .iter()
+            .any(|x| x.name == experiment.name && x.bench.name() == experiment.bench.name())
+        {
+            bail!(
+                "Experiment {} of {} is missing from the config, use --force to overwrite it",
+                experiment.name,
+                results_path.display()
+            );
+        }
+    }
+
+    let info_path = results_path.join("info.json");
+    if !info_path.exists() {
+        return Ok(HashMap::new());
+    }
+    let bench_info: BenchInfo = serde_json::from_str(&read_to_string(&info_path).await?)
+        .context(format!("Reading {}", info_path.display()))?;
+    Ok(bench_info.param_map)
+}
+
+/// Index of each command of an experiment in its run folder names
+///
+/// Commands that already ran keep the index of their existing runs, new commands get an index
+/// after the existing ones, so that adding variables to a sweep does not collide with earlier runs
+fn run_indices(
+    name: &str,
+    cmds: &[Cmd],
+    param_map: &HashMap<String, BenchParams>,
+) -> Result<Vec<usize>> {
+    let existing = param_map
+        .values()
+        .filter(|info| info.name == name)
+        .map(|info| Ok((serde_json::to_value(&info.args)?, info.idx)))
+        .collect::<Result<Vec<_>>>()?;
+    let cmds = cmds
+        .iter()
+        .map(|cmd| Ok((serde_json::to_value(&cmd.bench_obj)?, cmd.idx)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(match_run_indices(&existing, &cmds))
+}
+
+/// See [`run_indices`], `existing` and `cmds` are the serialized arguments with their index
+fn match_run_indices(existing: &[(Value, usize)], cmds: &[(Value, usize)]) -> Vec<usize> {
+    if existing.is_empty() {
+        return cmds.iter().map(|(_, idx)| *idx).collect();
+    }
+
+    let mut next_idx = existing.iter().map(|(_, idx)| idx + 1).max().unwrap_or(0);
+    cmds.iter()
+        .map(|(args, _)| match existing.iter().find(|(x, _)| x == args) {
+            Some((_, idx)) => *idx,
+            None => {
+                next_idx += 1;
+                next_idx - 1
+            }
+        })
+        .collect()
+}
+
 fn calculate_total_units(config: &Config) -> usize {
     config.benches.iter().fold(0, |acc, exp| {
-        let power_states = match &config.settings.nvme_power_states {
-            Some(ps) => ps.len(),
-            None => 1,
-        };
+        let power_states = config.settings.power_states().len();
 
         let bench_args = get_bench_args(&config.bench_args, &*exp.bench);
         let commands = exp
@@ -506,6 +1166,105 @@ fn calculate_total_units(config: &Config) -> usize {
         acc + power_states * commands * exp.repeat
     })
 }
+/// Progress of the sweep, written to `progress.json` in the results folder after every run so
+/// that external tooling can follow a benchmark without parsing its logs
+#[derive(Serialize)]
+struct SweepProgress {
+    completed: u64,
+    total: u64,
+    /// Name of the experiment that is running, `None` once all experiments are done
+    current_name: Option<String>,
+    started_at: String,
+    /// Expected end of the sweep, from the `runtime_estimate` of the runs that are left
+    eta: String,
+    #[serde(skip)]
+    remaining_ms: u64,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl SweepProgress {
+    fn new(results_path: &Path, config: &Config) -> Result<Self> {
+        let power_states = config.settings.power_states().len() as u64;
+        let mut total = 0;
+        let mut remaining_ms = 0;
+        for experiment in &config.benches {
+            let bench_args = get_bench_args(&config.bench_args, &*experiment.bench);
+            let cmds = experiment
+                .bench
+                .cmds(&config.settings, &*bench_args, &experiment.name)?
+                .cmds;
+            let runs = power_states * experiment.repeat as u64;
+            total += runs * cmds.len() as u64;
+            for cmd in &cmds {
+                remaining_ms += runs * Self::run_estimate(&*cmd.bench_obj, &config.settings)?;
+            }
+        }
+        // the first run does not wait for the cooldown
+        if total > 0
+            && let Some(cooldown) = config.settings.cooldown_secs
+        {
+            remaining_ms = remaining_ms.saturating_sub(cooldown * 1000);
+        }
+
+        Ok(Self {
+            completed: 0,
+            total,
+            current_name: None,
+            started_at: Local::now().to_rfc3339(),
+            eta: String::new(),
+            remaining_ms,
+            path: results_path.join("progress.json"),
+        })
+    }
+
+    /// Expected duration of one run in ms, including the cooldown before and the sleeps after it
+    fn run_estimate(bench: &dyn Bench, settings: &Settings) -> Result<u64> {
+        let mut estimate = bench.runtime_estimate()?;
+        if let Some(cooldown) = settings.cooldown_secs {
+            estimate += cooldown * 1000;
+        }
+        if let Some(sleep) = settings.sleep_between_experiments {
+            estimate += sleep * 1000;
+        }
+        if let Some(sleep) = settings.sleep_after_writes
+            && bench.write_hint()
+        {
+            estimate += sleep * 1000;
+        }
+        Ok(estimate)
+    }
+
+    async fn complete(&mut self, bench: &dyn Bench, settings: &Settings) -> Result<()> {
+        self.completed += 1;
+        self.remaining_ms = self
+            .remaining_ms
+            .saturating_sub(Self::run_estimate(bench, settings)?);
+        self.write().await
+    }
+
+    /// Adds a run to repeat an outlier
+    fn add_run(&mut self, bench: &dyn Bench, settings: &Settings) -> Result<()> {
+        self.total += 1;
+        self.remaining_ms += Self::run_estimate(bench, settings)?;
+        Ok(())
+    }
+
+    /// Writes the progress to a temporary file next to `progress.json` and renames it, so that a
+    /// crash while writing leaves the previous progress intact
+    async fn write(&mut self) -> Result<()> {
+        self.eta =
+            (Local::now() + chrono::Duration::milliseconds(self.remaining_ms as i64)).to_rfc3339();
+        let tmp_path = self.path.with_extension("json.tmp");
+        write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .await
+            .context("Write progress.json")?;
+        rename(&tmp_path, &self.path)
+            .await
+            .context("Replace progress.json")
+    }
+}
+
 struct TimingTracker {
     start_time: Instant,
     last_completion_time: Instant,
@@ -697,6 +1456,61 @@ async fn fetch_nvme_power_states(device: &str) -> Result<Vec<(f64, String)>> {
     Ok(result.into_iter().map(|x| (x.1, x.2)).collect())
 }
 
+/// Maximum power of each NVMe power state from the controller's power state descriptors,
+/// falling back to [`common::config::Settings::device_power_states`] if they can not be read
+async fn resolve_device_power_states(
+    settings: &Settings,
+    device: &str,
+) -> Result<Vec<(f64, String)>> {
+    let err = match fetch_nvme_power_states(device).await {
+        Ok(states) if !states.is_empty() => return Ok(states),
+        Ok(_) => eyre!("No power state descriptors reported for {device}"),
+        Err(err) => err,
+    };
+
+    let Some(states) = &settings.device_power_states else {
+        return Err(err)
+            .context("Fetch NVMe power states, set device_power_states in the config instead");
+    };
+    warn!("Could not fetch NVMe power states, using device_power_states from the config: {err}");
+    Ok(states.iter().map(|x| (*x, format!("{x}W"))).collect())
+}
+
+/// Model and firmware of the controller from `nvme id-ctrl`, plots are not annotated with them if
+/// they can not be read
+async fn fetch_device_info(device: &str) -> Option<DeviceInfo> {
+    let output = match simple_command_with_output_no_dir("nvme", &["id-ctrl", device]).await {
+        Ok(output) => output,
+        Err(err) => {
+            warn!("Could not read the model and firmware of {device}: {err}");
+            return None;
+        }
+    };
+    let field = |name: &str| {
+        output.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_owned())
+        })
+    };
+    Some(DeviceInfo {
+        model: field("mn")?,
+        firmware: field("fr")?,
+    })
+}
+
+/// NVMe controllers of the devices, ie. `/dev/nvme0` for `/dev/nvme0n1` and `/dev/nvme0n2`, in
+/// the order of the devices
+fn nvme_controllers(devices: &Devices) -> Vec<String> {
+    let mut controllers = Vec::new();
+    for device in devices.all() {
+        let controller = strip_nvme_namespace(device);
+        if !controllers.contains(&controller) {
+            controllers.push(controller);
+        }
+    }
+    controllers
+}
+
 fn strip_nvme_namespace(device: &str) -> String {
     if let Some(captures) = device.strip_prefix("/dev/nvme") {
         if let Some((base, _partition)) = captures.split_once('n') {
@@ -710,14 +1524,17 @@ fn strip_nvme_namespace(device: &str) -> String {
 }
 
 pub async fn estimate_runtime(config_file: &str) -> Result<()> {
-    let config: Config = serde_yml::from_str(&read_to_string(&config_file).await?)
-        .context(format!("Failed reading {config_file}"))?;
+    let config = read_config(config_file).await?;
 
     let mut total = 0;
+    let mut runs = 0;
     for b in config.benches {
         let bench_args = get_bench_args(&config.bench_args, &*b.bench);
         let cmds = b.bench.cmds(&config.settings, &*bench_args, &b.name)?;
-        total += b.bench.runtime_estimate()? * cmds.cmds.len() as u64;
+        for cmd in &cmds.cmds {
+            total += cmd.bench_obj.runtime_estimate()?;
+        }
+        runs += cmds.cmds.len() as u64;
         if let Some(sleep) = &config.settings.sleep_between_experiments {
             total += sleep * 1000 * cmds.cmds.len() as u64;
         }
@@ -728,6 +1545,10 @@ pub async fn estimate_runtime(config_file: &str) -> Result<()> {
             total += sleep * 1000 * cmds.cmds.len() as u64;
         }
     }
+    // every run but the first waits for the cooldown
+    if let Some(cooldown) = &config.settings.cooldown_secs {
+        total += cooldown * 1000 * runs.saturating_sub(1);
+    }
 
     println!("Estimated runtime: {}", total as f64 / 1000.0);
     Ok(())
@@ -739,21 +1560,16 @@ pub async fn generate_info(
     cpu_freq_limits: Option<String>,
     cpu_topology: Option<String>,
 ) -> Result<()> {
-    let config_file = Path::new(results_dir).join("config.yaml");
-    let config: Config = serde_yml::from_str(&read_to_string(&config_file).await?)
-        .context(format!("Failed reading {:?}", config_file))?;
+    let config_file = results_config_path(Path::new(results_dir));
+    let config = read_config(&config_file).await?;
 
-    let nvme_power_states = match &config.settings.nvme_power_states {
-        Some(ps) => ps.iter().map(|x| *x as i32).collect(),
-        None => vec![],
-    };
-    let nvme_cli_device = strip_nvme_namespace(&config.settings.device);
+    let power_states = config.settings.power_states();
+    let nvme_controllers = nvme_controllers(&config.settings.device);
+    let nvme_cli_device = &nvme_controllers[0];
 
     let device_power_states = match device_power_states {
         Some(ps) => serde_json::from_str(&ps)?,
-        None => fetch_nvme_power_states(&nvme_cli_device)
-            .await
-            .context("Fetch NVMe power states")?,
+        None => resolve_device_power_states(&config.settings, nvme_cli_device).await?,
     };
     debug!("Fetched NVMe power states: {device_power_states:?}");
 
@@ -784,14 +1600,13 @@ pub async fn generate_info(
         device_power_states,
         cpu_freq_limits: (cpu_min_freq, cpu_max_freq),
         cpu_topology,
+        warmup: None,
+        device: fetch_device_info(nvme_cli_device).await,
     };
 
     let info_path = config_file.parent().unwrap().join("info.json");
     for experiment in &config.benches {
-        let mut ps = nvme_power_states.clone();
-        if ps.is_empty() {
-            ps.push(-1);
-        }
+        let ps = power_states.states();
 
         let bench_args = get_bench_args(&config.bench_args, &*experiment.bench);
         let CmdsResult { cmds, .. } =
@@ -799,7 +1614,7 @@ pub async fn generate_info(
                 .bench
                 .cmds(&config.settings, &*bench_args, &experiment.name)?;
 
-        for power_state in &ps {
+        for power_state in ps {
             for Cmd { idx, bench_obj, .. } in cmds.iter() {
                 let folder_name = format!("{}-ps{}-i0-{}", experiment.name, power_state, idx);
 
@@ -811,6 +1626,12 @@ pub async fn generate_info(
                         iteration: 0,
                         name: experiment.name.clone(),
                         idx: *idx,
+                        tags: experiment.tags.clone(),
+                        background: config
+                            .settings
+                            .background
+                            .as_ref()
+                            .map(|x| x.command_line(&config.settings.bench_settings().device)),
                     },
                 );
             }
@@ -819,3 +1640,30 @@ pub async fn generate_info(
     write(&info_path, serde_json::to_string_pretty(&bench_info)?).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn matches_runs_of_a_partially_failed_folder() {
+        let cmds = (0..4)
+            .map(|idx| (json!({ "io_depth": idx }), idx))
+            .collect::<Vec<_>>();
+        assert_eq!(match_run_indices(&[], &cmds), [0, 1, 2, 3]);
+
+        // the sweep stopped on a failure of the second command, which left no runs in the folder
+        let existing = [(json!({ "io_depth": 0 }), 0), (json!({ "io_depth": 2 }), 2)];
+        assert_eq!(match_run_indices(&existing, &cmds), [0, 3, 2, 4]);
+
+        // a variable added to the sweep gets an index after the existing runs
+        let cmds = [
+            (json!({ "io_depth": 8 }), 0),
+            (json!({ "io_depth": 0 }), 1),
+            (json!({ "io_depth": 2 }), 2),
+        ];
+        assert_eq!(match_run_indices(&existing, &cmds), [3, 0, 2]);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{collections::BTreeMap, path::Path};
+
+use common::{FAILED_MARKER, bench::BenchInfo, util::read_json_file};
+use eyre::{Context, Result, bail};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    comparison::compare_values,
+    config_file::{read_config, results_config_path},
+    export_db::run_metrics,
+};
+
+/// Metrics of [`run_metrics`] that are compared
+const METRICS: [&str; 4] = ["throughput", "ssd_power", "cpu_power", "efficiency"];
+
+/// A run is identified by its experiment, power state and command index, iterations are averaged
+type RunKey = (String, i32, usize);
+
+/// Mean value and unit of every metric of a run
+type RunValues = BTreeMap<&'static str, (f64, String)>;
+
+#[derive(Serialize)]
+struct CompareRow {
+    experiment: String,
+    power_state: i32,
+    idx: usize,
+    metric: &'static str,
+    unit: String,
+    base: f64,
+    candidate: f64,
+    /// Relative change from `base` to `candidate` in percent
+    delta: f64,
+}
+
+/// Mean metrics of every run of a benchmark folder, optionally only of the `bench` experiment
+async fn folder_metrics(folder: &str, bench: Option<&str>) -> Result<BTreeMap<RunKey, RunValues>> {
+    let base_path = Path::new(folder);
+    let config = read_config(results_config_path(base_path)).await?;
+    if let Some(bench) = bench
+        && !config.benches.iter().any(|x| x.name == bench)
+    {
+        bail!("{folder} has no experiment named {bench}");
+    }
+    let info_path = base_path.join("info.json");
+    let bench_info: BenchInfo = read_json_file(&info_path)
+        .await
+        .context(format!("Reading {}", info_path.display()))?;
+    let data_path = base_path.join("data");
+    let device_max_power = bench_info
+        .device_power_states
+        .first()
+        .map_or(f64::INFINITY, |x| x.0);
+
+    let mut sums: BTreeMap<RunKey, BTreeMap<&'static str, (f64, usize, String)>> = BTreeMap::new();
+    for (dir, info) in &bench_info.param_map {
+        if bench.is_some_and(|x| x != info.name) {
+            continue;
+        }
+        let run_path = data_path.join(dir);
+        if !run_path.exists() || run_path.join(FAILED_MARKER).exists() {
+            continue;
+        }
+        let metrics =
+            match run_metrics(&run_path, info.args.as_ref(), device_max_power, &config).await {
+                Ok(metrics) => metrics,
+                Err(err) => {
+                    warn!("Skipping {dir} of {folder}: {err:#}");
+                    continue;
+                }
+            };
+
+        let run = sums
+            .entry((info.name.clone(), info.power_state, info.idx))
+            .or_default();
+        for (metric, value, unit) in metrics {
+            if !METRICS.contains(&metric) || !value.is_finite() {
+                continue;
+            }
+            let entry = run.entry(metric).or_insert((0.0, 0, unit));
+            entry.0 += value;
+            entry.1 += 1;
+        }
+    }
+
+    Ok(sums
+        .into_iter()
+        .map(|(key, metrics)| {
+            let means = metrics
+                .into_iter()
+                .map(|(metric, (sum, count, unit))| (metric, (sum / count as f64, unit)))
+                .collect();
+            (key, means)
+        })
+        .collect())
+}
+
+/// Prints the relative change of throughput, power and efficiency of every run of `candidate`
+/// compared to the same run of `base`, as a table or as JSON
+pub async fn compare(base: &str, candidate: &str, bench: Option<&str>, json: bool) -> Result<()> {
+    let base_runs = folder_metrics(base, bench).await?;
+    let candidate_runs = folder_metrics(candidate, bench).await?;
+
+    for key in base_runs.keys() {
+        if !candidate_runs.contains_key(key) {
+            warn!(
+                "Skipping {} at power state {} ({}), not run in {candidate}",
+                key.0, key.1, key.2
+            );
+        }
+    }
+    // metrics are keyed by their index in METRICS to keep them in that order
+    let values = |runs: &BTreeMap<RunKey, RunValues>| {
+        runs.iter()
+            .flat_map(|(key, values)| {
+                METRICS
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, metric)| Some(((key.clone(), i), values.get(metric)?.0)))
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let rows = compare_values(&values(&base_runs), &values(&candidate_runs))
+        .into_iter()
+        .map(|x| {
+            let (run, metric) = (&x.key.0, METRICS[x.key.1]);
+            CompareRow {
+                experiment: run.0.clone(),
+                power_state: run.1,
+                idx: run.2,
+                metric,
+                unit: base_runs[run][metric].1.clone(),
+                base: x.base,
+                candidate: x.candidate,
+                delta: x.delta * 100.0,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        bail!("No runs of {base} and {candidate} to compare");
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<20} {:<12} {:<5} {:<12} {:>14} {:>14} {:>9}  unit",
+        "experiment", "power state", "idx", "metric", "base", "candidate", "delta"
+    );
+    for row in &rows {
+        println!(
+            "{:<20} {:<12} {:<5} {:<12} {:>14.3} {:>14.3} {:>8.1}%  {}",
+            row.experiment,
+            row.power_state,
+            row.idx,
+            row.metric,
+            row.base,
+            row.candidate,
+            row.delta,
+            row.unit
+        );
+    }
+    Ok(())
+}
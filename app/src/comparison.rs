@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+/// Value of the same metric in the base and candidate results
+#[derive(Debug, PartialEq)]
+pub struct Delta<K> {
+    pub key: K,
+    pub base: f64,
+    pub candidate: f64,
+    /// Relative change from `base` to `candidate`, ie. 0.05 for 5% more
+    pub delta: f64,
+}
+
+/// Matches the values of `base` and `candidate` by their key and computes the relative change of
+/// each, values only in one of them, that are not finite or whose base is 0 are skipped
+pub fn compare_values<K: Ord + Clone>(
+    base: &BTreeMap<K, f64>,
+    candidate: &BTreeMap<K, f64>,
+) -> Vec<Delta<K>> {
+    base.iter()
+        .filter_map(|(key, base)| {
+            let candidate = candidate.get(key)?;
+            if !base.is_finite() || !candidate.is_finite() || *base == 0.0 {
+                return None;
+            }
+            Some(Delta {
+                key: key.clone(),
+                base: *base,
+                candidate: *candidate,
+                delta: (candidate - base) / base.abs(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_matching_values() {
+        let base = BTreeMap::from([
+            ("a", 100.0),
+            ("b", -4.0),
+            ("only base", 1.0),
+            ("zero", 0.0),
+            ("nan", f64::NAN),
+        ]);
+        let candidate = BTreeMap::from([
+            ("a", 110.0),
+            ("b", -2.0),
+            ("only candidate", 1.0),
+            ("zero", 1.0),
+            ("nan", 1.0),
+        ]);
+        assert_eq!(
+            compare_values(&base, &candidate),
+            vec![
+                Delta {
+                    key: "a",
+                    base: 100.0,
+                    candidate: 110.0,
+                    delta: 0.1,
+                },
+                Delta {
+                    key: "b",
+                    base: -4.0,
+                    candidate: -2.0,
+                    delta: 0.5,
+                },
+            ]
+        );
+        assert!(compare_values(&base, &BTreeMap::new()).is_empty());
+    }
+}
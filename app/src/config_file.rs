@@ -0,0 +1,41 @@
+use std::path::Path;
+
+pub use common::config::results_config_path;
+use common::config::{Config, TOML_CONFIG, YAML_CONFIG};
+use eyre::{Context, Result};
+use tokio::fs::read_to_string;
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "toml")
+}
+
+/// Reads a config file, parsed as TOML if it has a `.toml` extension and as YAML otherwise
+pub async fn read_config(path: impl AsRef<Path>) -> Result<Config> {
+    let path = path.as_ref();
+    let content = read_to_string(path)
+        .await
+        .context(format!("Reading {}", path.display()))?;
+    if is_toml(path) {
+        toml::from_str(&content).context(format!("Parsing {}", path.display()))
+    } else {
+        serde_yml::from_str(&content).context(format!("Parsing {}", path.display()))
+    }
+}
+
+/// Serializes a config in the format [`read_config`] expects for `path`
+pub fn config_to_string(path: impl AsRef<Path>, config: &Config) -> Result<String> {
+    if is_toml(path.as_ref()) {
+        Ok(toml::to_string_pretty(config)?)
+    } else {
+        Ok(serde_yml::to_string(config)?)
+    }
+}
+
+/// Name of the copy of `config_file` in a results folder, keeping its format
+pub fn results_config_name(config_file: &Path) -> &'static str {
+    if is_toml(config_file) {
+        TOML_CONFIG
+    } else {
+        YAML_CONFIG
+    }
+}
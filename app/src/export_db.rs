@@ -0,0 +1,479 @@
+use std::path::Path;
+
+use common::{
+    FAILED_MARKER,
+    bench::{Bench, BenchInfo},
+    config::{Config, InnerBench},
+    sensor::{powersensor3_filename, rapl_filename},
+    summary::sensor_stats,
+    util::BarChartSpec,
+};
+use eyre::{Context, Result, bail};
+use rusqlite::{Connection, Transaction, params, types::Value};
+use tokio::fs::{read_dir, read_to_string};
+use tracing::warn;
+
+use crate::config_file::{read_config, results_config_path};
+
+/// Version of [`SCHEMA`], bumped on every incompatible change
+const SCHEMA_VERSION: i64 = 2;
+
+/// Tables of the exported database, see the README for a description of every column
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS schema_version (
+    version INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS benchmarks (
+    id INTEGER PRIMARY KEY,
+    folder TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    device_model TEXT,
+    device_firmware TEXT
+);
+CREATE TABLE IF NOT EXISTS experiments (
+    id INTEGER PRIMARY KEY,
+    benchmark_id INTEGER NOT NULL REFERENCES benchmarks(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    bench TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    UNIQUE (benchmark_id, name)
+);
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    experiment_id INTEGER NOT NULL REFERENCES experiments(id) ON DELETE CASCADE,
+    dir TEXT NOT NULL,
+    power_state INTEGER NOT NULL,
+    idx INTEGER NOT NULL,
+    iteration INTEGER NOT NULL,
+    UNIQUE (experiment_id, dir)
+);
+CREATE TABLE IF NOT EXISTS run_params (
+    run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    value NOT NULL,
+    PRIMARY KEY (run_id, name)
+);
+CREATE TABLE IF NOT EXISTS run_metrics (
+    run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    metric TEXT NOT NULL,
+    value REAL NOT NULL,
+    unit TEXT NOT NULL,
+    PRIMARY KEY (run_id, metric)
+);
+CREATE TABLE IF NOT EXISTS bar_metrics (
+    experiment_id INTEGER NOT NULL REFERENCES experiments(id) ON DELETE CASCADE,
+    chart TEXT NOT NULL,
+    power_state TEXT NOT NULL,
+    label TEXT NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (experiment_id, chart, power_state, label)
+);
+";
+
+/// A metric of a single run, `(metric, value, unit)`
+type RunMetric = (&'static str, f64, String);
+
+/// A run of an experiment as it is exported
+struct ExportedRun {
+    dir: String,
+    power_state: i32,
+    idx: usize,
+    iteration: usize,
+    params: Vec<(String, Value)>,
+    metrics: Vec<RunMetric>,
+}
+
+/// Exports the parameters and metrics of every run of a benchmark folder to a SQLite database,
+/// replacing an earlier export of the same folder, so that several folders can be queried
+/// together
+pub async fn export_db(folder: &str, db_path: &str) -> Result<()> {
+    let base_path = Path::new(folder);
+    let config = read_config(results_config_path(base_path)).await?;
+    let bench_info: BenchInfo = serde_json::from_str(
+        &read_to_string(base_path.join("info.json"))
+            .await
+            .context(format!("Reading {}", base_path.join("info.json").display()))?,
+    )?;
+    let data_path = base_path.join("data");
+    let device_max_power = bench_info
+        .device_power_states
+        .first()
+        .map_or(f64::INFINITY, |x| x.0);
+
+    let mut runs = Vec::new();
+    for (dir, info) in &bench_info.param_map {
+        let run_path = data_path.join(dir);
+        if !run_path.exists() || run_path.join(FAILED_MARKER).exists() {
+            continue;
+        }
+        let metrics =
+            match run_metrics(&run_path, info.args.as_ref(), device_max_power, &config).await {
+                Ok(metrics) => metrics,
+                Err(err) => {
+                    warn!("Exporting {dir} without metrics: {err:#}");
+                    Vec::new()
+                }
+            };
+        let run = ExportedRun {
+            dir: dir.clone(),
+            power_state: info.power_state,
+            idx: info.idx,
+            iteration: info.iteration,
+            params: run_params(&serde_json::to_value(&info.args)?),
+            metrics,
+        };
+        runs.push((info.name.as_str(), run));
+    }
+
+    let plot_path = base_path.join("plots");
+    let mut bars = Vec::new();
+    for experiment in &config.benches {
+        bars.extend(
+            read_bar_metrics(&plot_path, &experiment.name, &config.benches)
+                .await?
+                .into_iter()
+                .map(|x| (experiment.name.as_str(), x)),
+        );
+    }
+
+    let folder_key = base_path
+        .canonicalize()
+        .context(format!("Resolving {folder}"))?
+        .to_string_lossy()
+        .to_string();
+    let mut db = Connection::open(db_path).context(format!("Opening {db_path}"))?;
+    db.execute_batch("PRAGMA foreign_keys = ON;")?;
+    db.execute_batch(SCHEMA)?;
+    let version = db
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+            row.get::<_, Option<i64>>(0)
+        })?
+        .unwrap_or(SCHEMA_VERSION);
+    if version != SCHEMA_VERSION {
+        bail!("{db_path} has schema version {version}, expected {SCHEMA_VERSION}");
+    }
+
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM schema_version", [])?;
+    tx.execute(
+        "INSERT INTO schema_version (version) VALUES (?1)",
+        [SCHEMA_VERSION],
+    )?;
+    tx.execute("DELETE FROM benchmarks WHERE folder = ?1", [&folder_key])?;
+    tx.execute(
+        "INSERT INTO benchmarks (folder, name, device_model, device_firmware) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            folder_key,
+            config.name,
+            bench_info.device.as_ref().map(|x| &x.model),
+            bench_info.device.as_ref().map(|x| &x.firmware),
+        ],
+    )?;
+    let benchmark_id = tx.last_insert_rowid();
+
+    for experiment in &config.benches {
+        tx.execute(
+            "INSERT INTO experiments (benchmark_id, name, bench, tags) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                benchmark_id,
+                experiment.name,
+                experiment.bench.name(),
+                experiment.tags.clone().unwrap_or_default().join(","),
+            ],
+        )?;
+    }
+    let experiment_id = |name: &str| -> rusqlite::Result<i64> {
+        tx.query_row(
+            "SELECT id FROM experiments WHERE benchmark_id = ?1 AND name = ?2",
+            params![benchmark_id, name],
+            |row| row.get(0),
+        )
+    };
+
+    let mut exported_runs = 0;
+    for (experiment, run) in &runs {
+        let Ok(experiment_id) = experiment_id(experiment) else {
+            warn!(
+                "Skipping {}, experiment {experiment} is not in the config",
+                run.dir
+            );
+            continue;
+        };
+        insert_run(&tx, experiment_id, run)?;
+        exported_runs += 1;
+    }
+
+    for (experiment, (chart, power_state, label, value)) in &bars {
+        tx.execute(
+            "INSERT OR REPLACE INTO bar_metrics (experiment_id, chart, power_state, label, value) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![experiment_id(experiment)?, chart, power_state, label, value],
+        )?;
+    }
+    tx.commit()?;
+
+    println!(
+        "Exported {exported_runs} runs and {} bars of {folder} to {db_path}",
+        bars.len()
+    );
+    Ok(())
+}
+
+/// Inserts a run with its parameters and metrics
+fn insert_run(tx: &Transaction, experiment_id: i64, run: &ExportedRun) -> Result<()> {
+    tx.execute(
+        "INSERT INTO runs (experiment_id, dir, power_state, idx, iteration) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            experiment_id,
+            run.dir,
+            run.power_state,
+            run.idx,
+            run.iteration
+        ],
+    )?;
+    let run_id = tx.last_insert_rowid();
+    for (name, value) in &run.params {
+        tx.execute(
+            "INSERT INTO run_params (run_id, name, value) VALUES (?1, ?2, ?3)",
+            params![run_id, name, value],
+        )?;
+    }
+    for (metric, value, unit) in &run.metrics {
+        tx.execute(
+            "INSERT INTO run_metrics (run_id, metric, value, unit) VALUES (?1, ?2, ?3, ?4)",
+            params![run_id, metric, value, unit],
+        )?;
+    }
+    Ok(())
+}
+
+/// Parameters of a run from its serialized benchmark arguments, one per field with the names of
+/// nested fields joined by `.`, ie. `fs.type`. Lists are kept as JSON, unset fields and the
+/// benchmark type, which is the `bench` of the experiment, are left out.
+fn run_params(args: &serde_json::Value) -> Vec<(String, Value)> {
+    fn flatten(name: String, value: &serde_json::Value, params: &mut Vec<(String, Value)>) {
+        let value = match value {
+            serde_json::Value::Null => return,
+            serde_json::Value::Object(fields) => {
+                for (field, value) in fields {
+                    if name.is_empty() && field == "type" {
+                        continue;
+                    }
+                    let field = if name.is_empty() {
+                        field.clone()
+                    } else {
+                        format!("{name}.{field}")
+                    };
+                    flatten(field, value, params);
+                }
+                return;
+            }
+            serde_json::Value::Bool(x) => Value::Integer(*x as i64),
+            serde_json::Value::Number(x) => match x.as_i64() {
+                Some(x) => Value::Integer(x),
+                None => Value::Real(x.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(x) => Value::Text(x.clone()),
+            serde_json::Value::Array(_) => Value::Text(value.to_string()),
+        };
+        params.push((name, value));
+    }
+
+    let mut params = Vec::new();
+    flatten(String::new(), args, &mut params);
+    params
+}
+
+/// Throughput, power, energy and efficiency of a run, computed like the plots do, metrics of
+/// sensors that were not recorded are left out
+pub(crate) async fn run_metrics(
+    run_path: &Path,
+    bench: &dyn Bench,
+    device_max_power: f64,
+    config: &Config,
+) -> Result<Vec<RunMetric>> {
+    let mut metrics = Vec::new();
+    let throughput = bench.primary_metric(run_path).await?;
+    if let Some(throughput) = &throughput {
+        metrics.push(("throughput", throughput.value, throughput.unit.clone()));
+    }
+
+    let mut ssd_power = None;
+    for (sensor, power_metric, energy_metric, max_power) in [
+        (
+            powersensor3_filename(&config.sensors, None)?,
+            "ssd_power",
+            "ssd_energy",
+            device_max_power,
+        ),
+        (
+            rapl_filename(&config.sensors, None)?,
+            "cpu_power",
+            "cpu_energy",
+            config.settings.cpu_max_power_watts,
+        ),
+    ] {
+        let Some(stats) = sensor_stats(&run_path.join(sensor), max_power).await? else {
+            continue;
+        };
+        if let Some(power) = stats.power_mean {
+            metrics.push((power_metric, power, "W".to_owned()));
+            if power_metric == "ssd_power" {
+                ssd_power = Some(power);
+            }
+        }
+        if let Some(energy) = stats.energy {
+            metrics.push((energy_metric, energy, "J".to_owned()));
+        }
+    }
+
+    if let (Some(throughput), Some(ssd_power)) = (&throughput, ssd_power)
+        && ssd_power > 0.0
+    {
+        metrics.push((
+            "efficiency",
+            throughput.value / ssd_power,
+            format!("({})/W", throughput.unit),
+        ));
+    }
+    Ok(metrics)
+}
+
+/// `(chart, power state, label, value)` of every bar of the plotted bar charts of `experiment`,
+/// charts are named as in [`crate::rank::chart_spec_path`]
+async fn read_bar_metrics(
+    plot_path: &Path,
+    experiment: &str,
+    experiments: &[InnerBench],
+) -> Result<Vec<(String, String, String, f64)>> {
+    let mut bars = Vec::new();
+    let Ok(mut plot_dirs) = read_dir(plot_path).await else {
+        return Ok(bars);
+    };
+    while let Some(plot_dir) = plot_dirs.next_entry().await? {
+        let Ok(mut specs) = read_dir(plot_dir.path().join("plot_data")).await else {
+            continue;
+        };
+        let dir = plot_dir.file_name().to_string_lossy().to_string();
+        while let Some(spec_file) = specs.next_entry().await? {
+            let file_name = spec_file.file_name().to_string_lossy().to_string();
+            let Some(stem) = file_name.strip_suffix(".bar.json") else {
+                continue;
+            };
+            // charts of an experiment whose name starts with this one belong to that experiment
+            let owner = experiments
+                .iter()
+                .map(|x| x.name.as_str())
+                .filter(|name| stem == *name || stem.starts_with(&format!("{name}-")))
+                .max_by_key(|name| name.len());
+            if owner != Some(experiment) {
+                continue;
+            }
+            let chart = match stem.strip_prefix(&format!("{experiment}-")) {
+                Some(suffix) => format!("{dir}-{suffix}"),
+                None => dir.clone(),
+            };
+
+            let path = spec_file.path();
+            let spec: BarChartSpec = serde_json::from_str(&read_to_string(&path).await?)
+                .context(format!("Parsing {}", path.display()))?;
+            for (ps, row) in spec.data.iter().enumerate() {
+                let power_state = spec
+                    .nvme_power_states
+                    .get(ps)
+                    .cloned()
+                    .unwrap_or(ps.to_string());
+                for (label, value) in spec.labels.iter().zip(row) {
+                    if value.is_finite() {
+                        bars.push((chart.clone(), power_state.clone(), label.clone(), *value));
+                    }
+                }
+            }
+        }
+    }
+    Ok(bars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_runs_with_their_params() {
+        let mut db = Connection::open_in_memory().unwrap();
+        db.execute_batch(SCHEMA).unwrap();
+        let tx = db.transaction().unwrap();
+        tx.execute(
+            "INSERT INTO benchmarks (folder, name) VALUES ('/results/a', 'a')",
+            [],
+        )
+        .unwrap();
+        tx.execute(
+            "INSERT INTO experiments (benchmark_id, name, bench, tags) VALUES (1, 'randread', 'fio', '')",
+            [],
+        )
+        .unwrap();
+
+        let run = ExportedRun {
+            dir: "abc".to_owned(),
+            power_state: 2,
+            idx: 1,
+            iteration: 0,
+            params: run_params(&serde_json::json!({
+                "type": "Fio",
+                "direct": true,
+                "io_depths": [1, 32],
+                "num_jobs": 4,
+                "ramp_time": null,
+                "rw": "randread",
+                "fs": { "type": "Ext4", "block_size": 4096 },
+            })),
+            metrics: vec![("throughput", 512.5, "MiB/s".to_owned())],
+        };
+        insert_run(&tx, 1, &run).unwrap();
+        tx.commit().unwrap();
+
+        let params = db
+            .prepare("SELECT name, value FROM run_params WHERE run_id = 1 ORDER BY name")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Value>(1)?))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("direct".to_owned(), Value::Integer(1)),
+                ("fs.block_size".to_owned(), Value::Integer(4096)),
+                ("fs.type".to_owned(), Value::Text("Ext4".to_owned())),
+                ("io_depths".to_owned(), Value::Text("[1,32]".to_owned())),
+                ("num_jobs".to_owned(), Value::Integer(4)),
+                ("rw".to_owned(), Value::Text("randread".to_owned())),
+            ]
+        );
+        assert_eq!(
+            db.query_row(
+                "SELECT dir, power_state, idx, metric, value, unit FROM runs JOIN run_metrics ON run_id = id",
+                [],
+                |row| Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i32>(1)?,
+                    row.get::<_, usize>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, f64>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            )
+            .unwrap(),
+            (
+                "abc".to_owned(),
+                2,
+                1,
+                "throughput".to_owned(),
+                512.5,
+                "MiB/s".to_owned()
+            )
+        );
+    }
+}
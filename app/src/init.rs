@@ -0,0 +1,259 @@
+use std::io::{Write, stdin, stdout};
+
+use common::{
+    bench::{Bench, BenchArgs},
+    config::{Config, InnerBench, Sensor, Settings},
+    plot::Plot,
+    sensor::SensorArgs,
+};
+use console::style;
+use eyre::{Context, Result, bail};
+use serde_yml::Value;
+use tokio::fs::write;
+
+use crate::{config_file::config_to_string, validate};
+
+/// Interactively builds a config file with a single benchmark
+pub async fn init_config(config_file: &str) -> Result<()> {
+    if std::path::Path::new(config_file).exists()
+        && !prompt_bool(&format!("{config_file} already exists, overwrite?"), false)?
+    {
+        return Ok(());
+    }
+
+    println!("{}", style("Settings").bold());
+    let name = prompt("Name (prefix for the results folder)", Some("benchmark"))?;
+    let device = prompt("Device to benchmark", Some("/dev/nvme0n1"))?;
+    let nvme_power_states = prompt_list::<usize>("NVMe power states to test", "")?;
+    let cpu_max_power_watts = prompt_parse("CPU maximum rated power (Watts)", Some(200.0))?;
+    let max_repeat = prompt_parse::<usize>("Maximum repetitions (0 to disable)", Some(0))?;
+
+    let settings = Settings {
+        device: device.into(),
+        nvme_power_states: (!nvme_power_states.is_empty()).then_some(nvme_power_states),
+        max_repeat: (max_repeat > 0).then_some(max_repeat),
+        cpu_max_power_watts,
+        ..Default::default()
+    };
+
+    println!("\n{}", style("Sensors").bold());
+    let sensor_objects = default_sensors::SENSORS.get().unwrap();
+    let sensor_names = sensor_objects
+        .iter()
+        .map(|x| x.name().to_string())
+        .collect::<Vec<_>>();
+    let all = (0..sensor_names.len())
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut sensors = Vec::new();
+    for idx in select_many("Sensors to record", &sensor_names, &all)? {
+        let sensor = &sensor_objects[idx];
+        let default_args = default_sensors::SENSOR_ARGS
+            .get()
+            .unwrap()
+            .iter()
+            .find(|x| x.name() == sensor.name())
+            .unwrap();
+        let args = prompt_fields::<Box<dyn SensorArgs>>(&sensor_names[idx], default_args)?;
+        sensors.push(Sensor {
+            sensor: sensor.name(),
+            alias: None,
+            args: Some(args),
+        });
+    }
+
+    println!("\n{}", style("Benchmark").bold());
+    let benches = default_benches::default_benches();
+    let bench_names = benches.iter().map(|x| x.name()).collect::<Vec<_>>();
+    let bench = &benches[select_one("Benchmark to run", &bench_names)?];
+    let bench_name = prompt("Experiment name", Some(bench.name()))?;
+    let repeat = prompt_parse("Minimum repetitions", Some(1))?;
+    let bench_args =
+        prompt_fields::<Box<dyn BenchArgs>>(bench.name(), &bench.default_bench_args())?;
+    let bench = prompt_fields::<Box<dyn Bench>>(bench.name(), bench)?;
+
+    println!("\n{}", style("Plots").bold());
+    let recorded = sensors
+        .iter()
+        .map(|x| x.sensor.to_string())
+        .collect::<Vec<_>>();
+    let plots = default_plots::default_plots()
+        .into_iter()
+        .filter(|x| {
+            x.required_sensors()
+                .iter()
+                .all(|s| recorded.iter().any(|r| r == s))
+        })
+        .collect::<Vec<_>>();
+    let plot_names = plots
+        .iter()
+        .map(|x| {
+            serde_yml::to_value(x)
+                .ok()
+                .and_then(|x| x.get("type").and_then(|x| x.as_str()).map(str::to_owned))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let mut selected_plots = Vec::new();
+    for idx in select_many("Plots to generate", &plot_names, "")? {
+        selected_plots.push(prompt_fields::<Box<dyn Plot>>(
+            &plot_names[idx],
+            &plots[idx],
+        )?);
+    }
+
+    let config = Config {
+        name,
+        settings,
+        benches: vec![InnerBench {
+            name: bench_name,
+            repeat,
+            bench,
+            plots: (!selected_plots.is_empty()).then_some(selected_plots),
+            tags: None,
+            parser: None,
+        }],
+        bench_args: vec![bench_args],
+        sensors,
+    };
+
+    write(config_file, config_to_string(config_file, &config)?).await?;
+    validate(config_file)
+        .await
+        .context(format!("Generated {config_file} is invalid"))?;
+    println!("\nWrote {config_file}");
+    Ok(())
+}
+
+/// Prompts for every field of a typetag object, starting from its default values
+fn prompt_fields<T: serde::Serialize + serde::de::DeserializeOwned>(
+    name: &str,
+    default: &T,
+) -> Result<T> {
+    let Value::Mapping(mut fields) = serde_yml::to_value(default)? else {
+        bail!("Expected {name} to serialize to a mapping");
+    };
+
+    loop {
+        for (key, value) in fields.iter_mut() {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            if key == "type" {
+                continue;
+            }
+
+            let current = serde_json::to_string(&value)?;
+            loop {
+                let input = prompt(&format!("{name}.{key}"), Some(&current))?;
+                match serde_yml::from_str::<Value>(&input) {
+                    Ok(input) => {
+                        *value = input;
+                        break;
+                    }
+                    Err(err) => println!("{}", style(format!("Invalid value: {err}")).red()),
+                }
+            }
+        }
+
+        match serde_yml::from_value(Value::Mapping(fields.clone())) {
+            Ok(value) => return Ok(value),
+            Err(err) => println!(
+                "{}",
+                style(format!("Invalid values for {name}: {err}")).red()
+            ),
+        }
+    }
+}
+
+fn prompt(question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => print!("{question} [{}]: ", style(default).dim()),
+        None => print!("{question}: "),
+    }
+    stdout().flush()?;
+
+    let mut input = String::new();
+    if stdin().read_line(&mut input)? == 0 {
+        bail!("Unexpected end of input");
+    }
+    let input = input.trim();
+    Ok(match default {
+        Some(default) if input.is_empty() => default.to_owned(),
+        _ => input.to_owned(),
+    })
+}
+
+fn prompt_parse<T: std::str::FromStr + ToString>(question: &str, default: Option<T>) -> Result<T> {
+    let default = default.map(|x| x.to_string());
+    loop {
+        match prompt(question, default.as_deref())?.parse() {
+            Ok(value) => return Ok(value),
+            Err(_) => println!("{}", style("Invalid value").red()),
+        }
+    }
+}
+
+fn prompt_bool(question: &str, default: bool) -> Result<bool> {
+    loop {
+        match prompt(question, Some(if default { "y" } else { "n" }))?
+            .to_lowercase()
+            .as_str()
+        {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("{}", style("Expected y or n").red()),
+        }
+    }
+}
+
+fn prompt_list<T: std::str::FromStr>(question: &str, default: &str) -> Result<Vec<T>> {
+    loop {
+        let input = prompt(&format!("{question} (comma separated)"), Some(default))?;
+        match input
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse())
+            .collect::<Result<Vec<T>, _>>()
+        {
+            Ok(values) => return Ok(values),
+            Err(_) => println!("{}", style("Invalid value").red()),
+        }
+    }
+}
+
+fn select_one<S: AsRef<str>>(question: &str, items: &[S]) -> Result<usize> {
+    if items.is_empty() {
+        bail!("Nothing to select for: {question}");
+    }
+
+    for (idx, item) in items.iter().enumerate() {
+        println!("  {idx}: {}", item.as_ref());
+    }
+    loop {
+        let idx = prompt_parse::<usize>(question, Some(0))?;
+        if idx < items.len() {
+            return Ok(idx);
+        }
+        println!("{}", style("Invalid selection").red());
+    }
+}
+
+fn select_many<S: AsRef<str>>(question: &str, items: &[S], default: &str) -> Result<Vec<usize>> {
+    if items.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    for (idx, item) in items.iter().enumerate() {
+        println!("  {idx}: {}", item.as_ref());
+    }
+    loop {
+        let selected = prompt_list::<usize>(question, default)?;
+        if selected.iter().all(|x| *x < items.len()) {
+            return Ok(selected);
+        }
+        println!("{}", style("Invalid selection").red());
+    }
+}
@@ -1,7 +1,12 @@
 use std::{collections::HashSet, path::PathBuf};
 
 use clap::{Parser, Subcommand};
-use common::{bench::BenchInfo, config::Config, plot::PlotType};
+use common::{
+    bench::BenchInfo,
+    config::Config,
+    plot::PlotType,
+    util::{parse_data_size, parse_time},
+};
 use eyre::{Context, Result, bail};
 use regex::Regex;
 use tokio::fs::{create_dir_all, read_dir, read_to_string, remove_dir_all};
@@ -13,9 +18,26 @@ use tracing_subscriber::{
     util::SubscriberInitExt,
 };
 
-use crate::bench::*;
+use crate::{
+    bench::*,
+    compare::compare,
+    config_file::{read_config, results_config_path},
+    export_db::export_db,
+    init::init_config,
+    rank::rank,
+    regress::regress,
+    report::report,
+};
 
 mod bench;
+mod compare;
+mod comparison;
+mod config_file;
+mod export_db;
+mod init;
+mod rank;
+mod regress;
+mod report;
 
 #[derive(Parser)]
 struct Cli {
@@ -25,6 +47,9 @@ struct Cli {
     no_progress: bool,
     #[arg(short, long)]
     log: Vec<String>,
+    /// Log at info level unless `RUST_LOG` is set, ie. the achieved sample rate of every sensor
+    #[arg(short, long, default_value_t = false)]
+    verbose: bool,
 }
 
 #[derive(Subcommand)]
@@ -38,14 +63,74 @@ enum Commands {
         /// Do not generate plots
         #[arg(long, default_value_t = false)]
         skip_plot: bool,
+        /// Results folder to use, an existing folder is appended to if its config matches
         #[arg(long)]
         use_dir: Option<String>,
+        /// Overwrite an existing `use_dir` instead of appending to it
+        #[arg(long, default_value_t = false)]
+        force: bool,
     },
     /// Generate plots for benchmarks
     Plot {
         /// Benchmark folder
         #[arg(short, long)]
         folder: String,
+        /// Only plot experiments with any of these tags
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Generate an HTML report of all plots for a benchmark
+    Report {
+        /// Benchmark folder
+        #[arg(short, long)]
+        folder: String,
+    },
+    /// Rank experiments of a plotted benchmark by a weighted score
+    Rank {
+        /// Benchmark folder
+        #[arg(short, long)]
+        folder: String,
+        /// Scoring config with the metric weights, defaults to throughput - SSD power - p99 latency
+        #[arg(short, long)]
+        scoring: Option<String>,
+    },
+    /// Compare the plotted throughput, power and efficiency of a benchmark to a baseline, fails
+    /// if any of them regressed by more than the threshold
+    Regress {
+        /// Baseline benchmark folder
+        #[arg(short, long)]
+        baseline: String,
+        /// Benchmark folder compared to the baseline
+        #[arg(short, long)]
+        candidate: String,
+        /// Allowed relative regression of each metric, ie. 0.05 for 5%
+        #[arg(short, long, default_value_t = 0.05)]
+        threshold: f64,
+    },
+    /// Print the relative change of throughput, power and efficiency of every run of a benchmark
+    /// compared to the same run of another
+    Compare {
+        /// Benchmark folder compared against
+        #[arg(short, long)]
+        base: String,
+        /// Benchmark folder compared to the base
+        #[arg(short, long)]
+        candidate: String,
+        /// Only compare the runs of this experiment
+        #[arg(long)]
+        bench: Option<String>,
+        /// Print the deltas as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Export the parameters and metrics of every run of a benchmark to a SQLite database
+    ExportDb {
+        /// Benchmark folder
+        #[arg(short, long)]
+        folder: String,
+        /// SQLite database, created if it does not exist
+        #[arg(short, long)]
+        db_path: String,
     },
     /// Print generated benchmark commands
     Print {
@@ -57,6 +142,8 @@ enum Commands {
     },
     /// List available sensors
     ListSensors,
+    /// List the metrics of every available plot, with their units and plot files
+    ListMetrics,
     /// Validate config
     Validate {
         #[arg(short, long, default_value = "config.yaml")]
@@ -67,6 +154,11 @@ enum Commands {
         #[arg(short, long, default_value = "config.yaml")]
         config_file: String,
     },
+    /// Interactively create a config file
+    Init {
+        #[arg(short, long, default_value = "config.yaml")]
+        config_file: String,
+    },
     /// Generate info.json for ideal run
     GenerateInfo {
         #[arg(short, long)]
@@ -83,8 +175,9 @@ enum Commands {
 #[tokio::main]
 async fn main() -> Result<()> {
     let modules: &[&str] = macros::plugin_names_str!();
-    let log_level = std::env::var("RUST_LOG").unwrap_or("warn".to_owned());
     let args = Cli::parse();
+    let log_level =
+        std::env::var("RUST_LOG").unwrap_or(if args.verbose { "info" } else { "warn" }.to_owned());
     let file_appender = tracing_appender::rolling::never(".", "log.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
@@ -122,21 +215,39 @@ async fn main() -> Result<()> {
             config_file,
             skip_plot,
             use_dir,
+            force,
         } => {
-            if let Err(err) = run_benchmark(config_file, args.no_progress, skip_plot, use_dir).await
+            if let Err(err) =
+                run_benchmark(config_file, args.no_progress, skip_plot, use_dir, force).await
             {
                 error!("{err:#?}");
                 return Err(err);
             }
         }
-        Commands::Plot { folder } => plot(&folder).await?,
+        Commands::Plot { folder, tag } => plot(&folder, &tag).await?,
+        Commands::Report { folder } => report(&folder).await?,
+        Commands::Rank { folder, scoring } => rank(&folder, scoring.as_deref()).await?,
+        Commands::Regress {
+            baseline,
+            candidate,
+            threshold,
+        } => regress(&baseline, &candidate, threshold).await?,
+        Commands::Compare {
+            base,
+            candidate,
+            bench,
+            json,
+        } => compare(&base, &candidate, bench.as_deref(), json).await?,
+        Commands::ExportDb { folder, db_path } => export_db(&folder, &db_path).await?,
         Commands::Print { config, only_cli } => print_commands(&config, only_cli).await?,
         Commands::ListSensors => list_sensors().await?,
+        Commands::ListMetrics => list_metrics(),
         Commands::Validate { config_file } => match validate(&config_file).await {
             Ok(_) => println!("{config_file} is valid"),
             Err(err) => println!("{config_file}: {err:#?}"),
         },
         Commands::Estimate { config_file } => estimate_runtime(&config_file).await?,
+        Commands::Init { config_file } => init_config(&config_file).await?,
         Commands::GenerateInfo {
             folder,
             device_power_states,
@@ -163,9 +274,9 @@ async fn get_benchmarks() -> Result<Vec<(String, PathBuf)>> {
     let mut results = Vec::new();
     while let Ok(Some(entry)) = items.next_entry().await {
         if entry.file_type().await?.is_dir() {
-            let config_file = entry.path().join("config.yaml");
+            let config_file = results_config_path(&entry.path());
             if config_file.exists() {
-                let config: Config = serde_yml::from_str(&read_to_string(config_file).await?)?;
+                let config = read_config(config_file).await?;
                 results.push((config.name, entry.path()));
             }
         }
@@ -174,7 +285,7 @@ async fn get_benchmarks() -> Result<Vec<(String, PathBuf)>> {
 }
 
 async fn print_commands(config: &str, only_cli: bool) -> Result<()> {
-    let config: Config = serde_yml::from_str(&read_to_string(config).await?)?;
+    let config = read_config(config).await?;
 
     for experiment in &config.benches {
         fn get_bench_args(
@@ -204,20 +315,24 @@ async fn print_commands(config: &str, only_cli: bool) -> Result<()> {
     Ok(())
 }
 
-async fn plot(folder: &str) -> Result<()> {
+async fn plot(folder: &str, tags: &[String]) -> Result<()> {
     let base_path = PathBuf::from(folder);
     let plot_path = base_path.join("plots");
     _ = remove_dir_all(&plot_path).await;
     create_dir_all(&plot_path).await?;
-    let config: Config = serde_yml::from_str(&read_to_string(base_path.join("config.yaml")).await?)
-        .context(format!("Reading config.yaml: {}", base_path.display()))?;
+    let config = read_config(results_config_path(&base_path)).await?;
     let data_path = base_path.join("data");
 
-    let bench_info: BenchInfo = serde_json::from_str(
+    let mut bench_info: BenchInfo = serde_json::from_str(
         &read_to_string(base_path.join("info.json"))
             .await
             .context(format!("Reading {}", base_path.join("info.json").display()))?,
     )?;
+    if !tags.is_empty() {
+        bench_info
+            .param_map
+            .retain(|_, params| tags.iter().any(|tag| params.has_tag(tag)));
+    }
 
     for experiment in &config.benches {
         let dir_regex = Regex::new(&format!("^{}-ps(?:-1|[0-4])-\\S+$", experiment.name))?;
@@ -269,8 +384,35 @@ async fn list_sensors() -> Result<()> {
     Ok(())
 }
 
-async fn validate(config_file: &str) -> Result<()> {
-    let config: Config = serde_yml::from_str(&read_to_string(&config_file).await?)?;
+fn list_metrics() {
+    for plot in default_plots::default_plots() {
+        println!("{}", plot.typetag_name());
+        for metric in plot.metrics() {
+            println!("  {:<36} {:<12} {}", metric.name, metric.unit, metric.file);
+        }
+    }
+}
+
+/// Checks the plots of every experiment against its benchmark, see [`common::plot::Plot::validate`]
+fn validate_plots(config: &Config) -> Result<()> {
+    for bench in &config.benches {
+        for plot in bench.plots.iter().flatten() {
+            plot.validate(bench.bench.as_ref())
+                .context(format!("Invalid plot of experiment {}", bench.name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks a config for mistakes that can be found without running it, called before a benchmark
+/// starts and by the `validate` subcommand
+fn validate_config(config: &Config) -> Result<()> {
+    // names prefix the results and run folders, which would be hidden with a leading dot
+    for name in std::iter::once(&config.name).chain(config.benches.iter().map(|x| &x.name)) {
+        if name.trim().is_empty() || name.starts_with('.') {
+            bail!("Invalid name {name:?}, must be non-empty and not start with a dot");
+        }
+    }
     let unique_bench_names = config
         .benches
         .iter()
@@ -281,5 +423,95 @@ async fn validate(config_file: &str) -> Result<()> {
             "Bench names must be unique! Config file contains multiple benchmarks with the same name."
         );
     }
+    if let Some(warmup) = &config.settings.warmup
+        && !config.benches.iter().any(|x| &x.name == warmup)
+    {
+        bail!("Warmup experiment {warmup} does not exist");
+    }
+    if let Some(window) = config.settings.powersensor3_median_filter
+        && window % 2 == 0
+    {
+        bail!("powersensor3_median_filter must be odd, got {window}");
+    }
+    let unique_sensors = config
+        .sensors
+        .iter()
+        .map(|x| (x.sensor.to_string(), x.alias.as_deref()))
+        .collect::<HashSet<_>>();
+    if unique_sensors.len() != config.sensors.len() {
+        bail!("Multiple sensors of the same kind must have distinct aliases");
+    }
+    if config.settings.device.all().is_empty() {
+        bail!("device must not be an empty list");
+    }
+    if config.settings.device.is_multiple()
+        && (config.settings.dm_crypt.is_some() || config.settings.loop_device.is_some())
+    {
+        bail!("dm_crypt and loop_device support only a single device");
+    }
+    if config.settings.device.is_multiple() {
+        if let Some(bench) = config
+            .benches
+            .iter()
+            .find(|x| !x.bench.supports_multiple_devices())
+        {
+            bail!("{} supports only a single device", bench.name);
+        }
+        if config
+            .sensors
+            .iter()
+            .any(|x| x.sensor.to_string() == "Pcie")
+        {
+            bail!("The Pcie sensor supports only a single device");
+        }
+    }
+    if let Some(cpu_governor) = &config.settings.cpu_governor {
+        if config.settings.cpu_freq.is_some() {
+            bail!("cpu_governor can not be combined with cpu_freq");
+        }
+        if let (Some(min), Some(max)) = (cpu_governor.min_freq, cpu_governor.max_freq)
+            && min > max
+        {
+            bail!("min_freq of cpu_governor must not be greater than max_freq");
+        }
+    }
+    if config.settings.sensor_flush_rows == Some(0) {
+        bail!("sensor_flush_rows must be greater than 0");
+    }
+    if config.settings.sync_sample_interval == Some(0) {
+        bail!("sync_sample_interval must be greater than 0");
+    }
+    if let Some(url) = &config.settings.prometheus_push_gateway
+        && !url.starts_with("http://")
+    {
+        bail!("prometheus_push_gateway must be an http:// URL, got {url}");
+    }
+    if config
+        .settings
+        .timeout_multiplier
+        .is_some_and(|x| x.is_nan() || x <= 0.0)
+    {
+        bail!("timeout_multiplier must be greater than 0");
+    }
+    if let Some(csv_format) = &config.settings.csv_format {
+        csv_format.validate()?;
+    }
+    validate_plots(config)?;
+    if let Some(loop_device) = &config.settings.loop_device {
+        parse_data_size(&loop_device.size).context("Invalid loop_device.size")?;
+    }
+    if let Some(detection) = &config.settings.throttle_detection {
+        if detection.drop.is_some_and(|x| !(0.0..1.0).contains(&x)) {
+            bail!("throttle_detection.drop must be between 0 and 1");
+        }
+        if let Some(duration) = &detection.duration {
+            parse_time(duration).context("Invalid throttle_detection.duration")?;
+        }
+    }
     Ok(())
 }
+
+async fn validate(config_file: &str) -> Result<()> {
+    let config = read_config(config_file).await?;
+    validate_config(&config)
+}
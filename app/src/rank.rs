@@ -0,0 +1,174 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use common::util::BarChartSpec;
+use console::style;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs::read_to_string;
+use tracing::warn;
+
+use crate::config_file::{read_config, results_config_path};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScoringConfig {
+    pub metrics: Vec<ScoreMetric>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScoreMetric {
+    /// Bar chart to score on, ie. `throughput` reads `plots/throughput/plot_data/<experiment>.bar.json`
+    /// and `latency-p99` reads `plots/latency/plot_data/<experiment>-p99.bar.json`
+    pub chart: String,
+    /// Positive weights reward higher values, negative weights penalize them
+    pub weight: f64,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            metrics: vec![
+                ScoreMetric {
+                    chart: "throughput".to_owned(),
+                    weight: 1.0,
+                },
+                ScoreMetric {
+                    chart: "power-ssd".to_owned(),
+                    weight: -1.0,
+                },
+                ScoreMetric {
+                    chart: "latency-p99".to_owned(),
+                    weight: -1.0,
+                },
+            ],
+        }
+    }
+}
+
+/// Data of a bar chart of an experiment, ie. `throughput` is
+/// `plots/throughput/plot_data/<experiment>.bar.json` and `latency-p99` is
+/// `plots/latency/plot_data/<experiment>-p99.bar.json`
+pub fn chart_spec_path(plot_path: &Path, chart: &str, experiment: &str) -> PathBuf {
+    let (dir, file) = match chart.split_once('-') {
+        Some((dir, suffix)) => (dir, format!("{experiment}-{suffix}.bar.json")),
+        None => (chart, format!("{experiment}.bar.json")),
+    };
+    plot_path.join(dir).join("plot_data").join(file)
+}
+
+/// (power state, label) of a single bar
+type RankKey = (usize, String);
+
+/// Ranks the plotted results of every experiment by a weighted sum of min-max normalized metrics
+pub async fn rank(folder: &str, scoring: Option<&str>) -> Result<()> {
+    let scoring = match scoring {
+        Some(scoring) => serde_yml::from_str::<ScoringConfig>(
+            &read_to_string(scoring)
+                .await
+                .context(format!("Reading {scoring}"))?,
+        )?,
+        None => ScoringConfig::default(),
+    };
+
+    let base_path = Path::new(folder);
+    let config = read_config(results_config_path(base_path)).await?;
+    let plot_path = base_path.join("plots");
+
+    'experiments: for experiment in &config.benches {
+        let mut power_states = Vec::new();
+        let mut values: BTreeMap<RankKey, Vec<f64>> = BTreeMap::new();
+        for (metric_idx, metric) in scoring.metrics.iter().enumerate() {
+            let path = chart_spec_path(&plot_path, &metric.chart, &experiment.name);
+            let Ok(spec) = read_to_string(&path).await else {
+                warn!(
+                    "Skipping {}, no plot data for {} at {}",
+                    experiment.name,
+                    metric.chart,
+                    path.display()
+                );
+                continue 'experiments;
+            };
+            let spec: BarChartSpec =
+                serde_json::from_str(&spec).context(format!("Parsing {}", path.display()))?;
+            power_states = spec.nvme_power_states;
+
+            for (ps, row) in spec.data.iter().enumerate() {
+                for (label, value) in spec.labels.iter().zip(row) {
+                    let entry = values.entry((ps, label.clone())).or_default();
+                    if entry.len() == metric_idx {
+                        entry.push(*value);
+                    }
+                }
+            }
+        }
+
+        // only bars present in every chart can be scored
+        values.retain(|_, v| v.len() == scoring.metrics.len() && v.iter().all(|x| x.is_finite()));
+        if values.is_empty() {
+            continue;
+        }
+
+        let ranges = (0..scoring.metrics.len())
+            .map(|idx| {
+                values.values().fold((f64::MAX, f64::MIN), |(min, max), v| {
+                    (min.min(v[idx]), max.max(v[idx]))
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut ranking = values
+            .into_iter()
+            .map(|(key, v)| {
+                let score = scoring
+                    .metrics
+                    .iter()
+                    .zip(&ranges)
+                    .zip(&v)
+                    .map(|((metric, (min, max)), x)| {
+                        let norm = if max > min {
+                            (x - min) / (max - min)
+                        } else {
+                            0.0
+                        };
+                        metric.weight * norm
+                    })
+                    .sum::<f64>();
+                (key, v, score)
+            })
+            .collect::<Vec<_>>();
+        ranking.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        println!("{}", style(&experiment.name).bold());
+        println!(
+            "  {:>4}  {:<24} {:<12} {:>8}  {}",
+            "rank",
+            "label",
+            "power state",
+            "score",
+            scoring
+                .metrics
+                .iter()
+                .map(|x| format!("{:>14}", x.chart))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        for (idx, ((ps, label), v, score)) in ranking.iter().enumerate() {
+            println!(
+                "  {:>4}  {:<24} {:<12} {:>8.3}  {}",
+                idx + 1,
+                label,
+                power_states.get(*ps).cloned().unwrap_or(ps.to_string()),
+                score,
+                v.iter()
+                    .map(|x| format!("{x:>14.3}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+        println!();
+    }
+    Ok(())
+}
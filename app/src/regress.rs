@@ -0,0 +1,219 @@
+use std::{collections::BTreeMap, path::Path};
+
+use common::util::BarChartSpec;
+use console::style;
+use eyre::{Context, Result, bail};
+use tokio::fs::read_to_string;
+use tracing::warn;
+
+use crate::{
+    comparison::compare_values,
+    config_file::{read_config, results_config_path},
+    rank::chart_spec_path,
+};
+
+/// Bar values of a chart by (power state, label), power states by their `nvme_power_states` label
+/// so that charts of different power state lists are compared by the same power state
+type BarValues = BTreeMap<(String, String), f64>;
+
+enum Source {
+    /// Values of a bar chart, see [`chart_spec_path`]
+    Chart(&'static str),
+    /// Values of the first chart divided by those of the second
+    Ratio(&'static str, &'static str),
+}
+
+struct RegressMetric {
+    name: &'static str,
+    source: Source,
+    higher_is_better: bool,
+}
+
+const METRICS: [RegressMetric; 4] = [
+    RegressMetric {
+        name: "throughput",
+        source: Source::Chart("throughput"),
+        higher_is_better: true,
+    },
+    RegressMetric {
+        name: "power-ssd",
+        source: Source::Chart("power-ssd"),
+        higher_is_better: false,
+    },
+    RegressMetric {
+        name: "power-cpu",
+        source: Source::Chart("power-cpu"),
+        higher_is_better: false,
+    },
+    RegressMetric {
+        name: "efficiency",
+        source: Source::Ratio("throughput", "power-ssd"),
+        higher_is_better: true,
+    },
+];
+
+impl RegressMetric {
+    async fn values(&self, plot_path: &Path, experiment: &str) -> Result<Option<BarValues>> {
+        match self.source {
+            Source::Chart(chart) => read_bar_values(plot_path, chart, experiment).await,
+            Source::Ratio(numerator, denominator) => {
+                let (Some(numerator), Some(denominator)) = (
+                    read_bar_values(plot_path, numerator, experiment).await?,
+                    read_bar_values(plot_path, denominator, experiment).await?,
+                ) else {
+                    return Ok(None);
+                };
+                let values = numerator
+                    .into_iter()
+                    .filter_map(|(key, x)| {
+                        let y = denominator.get(&key)?;
+                        Some((key, x / y))
+                    })
+                    .collect();
+                Ok(Some(values))
+            }
+        }
+    }
+}
+
+/// Values of a bar chart written by a plot, `None` if the experiment has no such chart
+async fn read_bar_values(
+    plot_path: &Path,
+    chart: &str,
+    experiment: &str,
+) -> Result<Option<BarValues>> {
+    let path = chart_spec_path(plot_path, chart, experiment);
+    let Ok(spec) = read_to_string(&path).await else {
+        return Ok(None);
+    };
+    let spec: BarChartSpec =
+        serde_json::from_str(&spec).context(format!("Parsing {}", path.display()))?;
+    Ok(Some(bar_values(&spec)))
+}
+
+/// Values of the bars of a chart, power states without a label are named by their index
+fn bar_values(spec: &BarChartSpec) -> BarValues {
+    let mut values = BTreeMap::new();
+    for (ps, row) in spec.data.iter().enumerate() {
+        let power_state = spec
+            .nvme_power_states
+            .get(ps)
+            .cloned()
+            .unwrap_or(ps.to_string());
+        for (label, value) in spec.labels.iter().zip(row) {
+            values.insert((power_state.clone(), label.clone()), *value);
+        }
+    }
+    values
+}
+
+/// Compares the plotted metrics of every experiment of `candidate` to the same bars of `baseline`,
+/// fails if any of them is worse by more than `threshold`, relative to the baseline
+pub async fn regress(baseline: &str, candidate: &str, threshold: f64) -> Result<()> {
+    if threshold.is_nan() || threshold < 0.0 {
+        bail!("threshold must be a non-negative fraction, ie. 0.05 for 5%");
+    }
+    let baseline_plots = Path::new(baseline).join("plots");
+    let candidate_plots = Path::new(candidate).join("plots");
+    let config = read_config(results_config_path(Path::new(candidate))).await?;
+
+    println!(
+        "{:<20} {:<24} {:<12} {:<12} {:>14} {:>14} {:>9}  result",
+        "experiment", "label", "power state", "metric", "baseline", "candidate", "delta"
+    );
+    let mut compared = 0;
+    let mut regressed = 0;
+    for experiment in &config.benches {
+        for metric in &METRICS {
+            let (Some(base), Some(cand)) = (
+                metric.values(&baseline_plots, &experiment.name).await?,
+                metric.values(&candidate_plots, &experiment.name).await?,
+            ) else {
+                warn!(
+                    "Skipping {} of {}, not plotted in both folders",
+                    metric.name, experiment.name
+                );
+                continue;
+            };
+
+            for x in compare_values(&base, &cand) {
+                let (ps, label) = &x.key;
+                let failed = if metric.higher_is_better {
+                    x.delta < -threshold
+                } else {
+                    x.delta > threshold
+                };
+                compared += 1;
+                if failed {
+                    regressed += 1;
+                }
+
+                println!(
+                    "{:<20} {:<24} {:<12} {:<12} {:>14.3} {:>14.3} {:>8.1}%  {}",
+                    experiment.name,
+                    label,
+                    ps,
+                    metric.name,
+                    x.base,
+                    x.candidate,
+                    x.delta * 100.0,
+                    if failed {
+                        style("FAIL").red()
+                    } else {
+                        style("PASS").green()
+                    }
+                );
+            }
+        }
+    }
+
+    if compared == 0 {
+        bail!("No metrics to compare, plot both {baseline} and {candidate} first");
+    }
+    if regressed > 0 {
+        bail!(
+            "{regressed} of {compared} metrics regressed by more than {:.1}%",
+            threshold * 100.0
+        );
+    }
+    println!(
+        "All {compared} metrics are within {:.1}%",
+        threshold * 100.0
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(nvme_power_states: &[&str], data: Vec<Vec<f64>>) -> BarChartSpec {
+        serde_json::from_value(serde_json::json!({
+            "data": data,
+            "labels": ["4k", "128k"],
+            "title": "",
+            "x_label": "",
+            "y_label": "",
+            "output_path": "",
+            "nvme_power_states": nvme_power_states,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn compares_bars_of_the_same_power_state() {
+        let base = bar_values(&spec(&["0", "2"], vec![vec![1.0, 2.0], vec![3.0, 4.0]]));
+        let cand = bar_values(&spec(&["2"], vec![vec![6.0, 8.0]]));
+        let deltas = compare_values(&base, &cand)
+            .into_iter()
+            .map(|x| (x.key, x.delta))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deltas,
+            vec![
+                (("2".to_owned(), "128k".to_owned()), 1.0),
+                (("2".to_owned(), "4k".to_owned()), 1.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use common::{bench::BenchInfo, config::Config};
+use eyre::{Context, Result, bail};
+use tokio::fs::{read_dir, read_to_string, write};
+
+use crate::config_file::{read_config, results_config_path};
+
+const REPORT_FILENAME: &str = "report.html";
+/// Plot directories shown first, in this order, any other directory follows alphabetically
+const SECTION_ORDER: [&str; 4] = ["throughput", "latency", "power", "efficiency"];
+const OTHER_EXPERIMENTS: &str = "Other";
+
+/// Generates `report.html` in the benchmark folder, linking every generated plot grouped by
+/// experiment and plot directory
+pub async fn report(folder: &str) -> Result<()> {
+    let base_path = PathBuf::from(folder);
+    let plot_path = base_path.join("plots");
+    if !plot_path.exists() {
+        bail!(
+            "No plots found in {}, run the plot command first",
+            base_path.display()
+        );
+    }
+
+    let config = read_config(results_config_path(&base_path)).await?;
+    let bench_info: BenchInfo = serde_json::from_str(
+        &read_to_string(base_path.join("info.json"))
+            .await
+            .context(format!("Reading {}", base_path.join("info.json").display()))?,
+    )?;
+
+    let mut plots = Vec::new();
+    collect_plots(&plot_path, &mut plots).await?;
+    plots.sort();
+
+    // longest names first, so that an experiment named `a-1` is not matched by `a`
+    let mut experiments = config
+        .benches
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<Vec<_>>();
+    experiments.sort_by_key(|x| std::cmp::Reverse(x.len()));
+
+    // experiment -> section -> plots
+    let mut grouped: BTreeMap<&str, BTreeMap<String, Vec<PathBuf>>> = BTreeMap::new();
+    for plot in plots {
+        let relative = plot.strip_prefix(&base_path)?.to_path_buf();
+        let in_plots = plot.strip_prefix(&plot_path)?;
+        let section = match in_plots.components().count() {
+            1 => "misc".to_owned(),
+            _ => in_plots
+                .components()
+                .next()
+                .unwrap()
+                .as_os_str()
+                .to_string_lossy()
+                .to_string(),
+        };
+        let experiment = experiments
+            .iter()
+            .find(|name| {
+                in_plots.components().any(|x| x.as_os_str() == **name)
+                    || in_plots
+                        .file_stem()
+                        .is_some_and(|x| x.to_string_lossy().starts_with(**name))
+            })
+            .copied()
+            .unwrap_or(OTHER_EXPERIMENTS);
+        grouped
+            .entry(experiment)
+            .or_default()
+            .entry(section)
+            .or_default()
+            .push(relative);
+    }
+
+    // experiments in config order, unmatched plots last
+    let grouped = config
+        .benches
+        .iter()
+        .map(|x| x.name.as_str())
+        .chain([OTHER_EXPERIMENTS])
+        .filter_map(|name| grouped.get(name).map(|sections| (name, sections)))
+        .collect::<Vec<_>>();
+
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>",
+        escape(&config.name)
+    )?;
+    writeln!(
+        html,
+        "<style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse}}\
+         td,th{{border:1px solid #ccc;padding:4px 8px;text-align:left}}\
+         .plots{{display:flex;flex-wrap:wrap;gap:1em}}figure{{margin:0}}\
+         embed{{width:560px;height:420px}}</style>\n</head>\n<body>"
+    )?;
+    writeln!(html, "<h1>{}</h1>", escape(&config.name))?;
+    write_metadata(&mut html, &config, &bench_info)?;
+
+    writeln!(html, "<h2>Contents</h2>\n<ul>")?;
+    for (experiment, _) in &grouped {
+        writeln!(
+            html,
+            "<li><a href=\"#{0}\">{0}</a></li>",
+            escape(experiment)
+        )?;
+    }
+    writeln!(html, "</ul>")?;
+
+    for (experiment, sections) in &grouped {
+        writeln!(html, "<h2 id=\"{0}\">{0}</h2>", escape(experiment))?;
+        let mut sections = sections.iter().collect::<Vec<_>>();
+        sections.sort_by_key(|(name, _)| {
+            (
+                SECTION_ORDER
+                    .iter()
+                    .position(|x| x == name)
+                    .unwrap_or(SECTION_ORDER.len()),
+                name.to_string(),
+            )
+        });
+        for (section, plots) in sections {
+            writeln!(html, "<h3>{}</h3>\n<div class=\"plots\">", escape(section))?;
+            for plot in plots {
+                let src = escape(&plot.to_string_lossy());
+                let name = escape(&plot.file_stem().unwrap_or_default().to_string_lossy());
+                writeln!(
+                    html,
+                    "<figure><embed src=\"{src}\"><figcaption><a href=\"{src}\">{name}</a></figcaption></figure>"
+                )?;
+            }
+            writeln!(html, "</div>")?;
+        }
+    }
+    writeln!(html, "</body>\n</html>")?;
+
+    let report_path = base_path.join(REPORT_FILENAME);
+    write(&report_path, html).await?;
+    println!("Wrote {}", report_path.display());
+    Ok(())
+}
+
+fn write_metadata(html: &mut String, config: &Config, bench_info: &BenchInfo) -> Result<()> {
+    let mut topology = bench_info.cpu_topology.iter().collect::<Vec<_>>();
+    topology.sort();
+    let rows = [
+        (
+            "Device",
+            match &bench_info.device {
+                Some(device) => format!("{} ({device})", config.settings.device),
+                None => config.settings.device.to_string(),
+            },
+        ),
+        ("Experiments", config.benches.len().to_string()),
+        ("Runs", bench_info.param_map.len().to_string()),
+        (
+            "NVMe power states",
+            bench_info
+                .device_power_states
+                .iter()
+                .enumerate()
+                .map(|(idx, (watts, name))| format!("{idx}: {name} ({watts} W)"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        (
+            "CPU frequency limits",
+            format!(
+                "{} - {} MHz",
+                bench_info.cpu_freq_limits.0 / 1000,
+                bench_info.cpu_freq_limits.1 / 1000
+            ),
+        ),
+        (
+            "CPU topology",
+            topology
+                .iter()
+                .map(|(node, cores)| format!("node {node}: {cores} cores"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    ];
+
+    writeln!(html, "<table>")?;
+    for (name, value) in rows {
+        writeln!(
+            html,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape(name),
+            escape(&value)
+        )?;
+    }
+    writeln!(html, "</table>")?;
+    Ok(())
+}
+
+async fn collect_plots(dir: &Path, plots: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if entry.file_type().await?.is_dir() {
+            if entry.file_name() != "plot_data" {
+                Box::pin(collect_plots(&path, plots)).await?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|x| x == "pdf" || x == "png" || x == "svg")
+        {
+            plots.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,16 +1,17 @@
-use std::{
-    collections::HashMap,
-    path::Path,
-    pin::Pin,
-    process::Stdio,
-    time::{Duration, Instant},
-};
+use std::{collections::HashMap, path::Path, pin::Pin, process::Stdio, time::Duration};
 
 use common::{
-    bench::{Bench, BenchArgs, Cmd, CmdsResult, trace_nvme_calls},
+    bench::{
+        Bench, BenchArgs, Cmd, CmdsResult, MarkerWriter, OUTPUT_FILENAME, read_stdout, run_parser,
+        set_running_benchmark, trace_nvme_calls, write_output,
+    },
     config::{Config, Settings},
+    perf::{perf_stat_command, write_perf_json},
     sensor::SensorRequest,
-    util::{Filesystem, mount_fs, read_until_prompt, simple_command_with_output_no_dir},
+    summary::Throughput,
+    util::{
+        Filesystem, mount_fs, read_json_file, read_until_prompt, simple_command_with_output_no_dir,
+    },
 };
 use eyre::{Context, ContextCompat, Result, bail};
 use flume::Sender;
@@ -18,7 +19,7 @@ use itertools::iproduct;
 use result::{FilebenchSummary, parse_output};
 use serde::{Deserialize, Serialize};
 use tokio::{
-    fs::{File, OpenOptions, write},
+    fs::write,
     io::{AsyncReadExt, AsyncWriteExt},
     join,
     process::Command,
@@ -73,10 +74,19 @@ impl Bench for Filebench {
     fn runtime_estimate(&self) -> Result<u64> {
         let f = self.fs.len() as u64;
         let vars = self.vars.as_ref().unwrap_or(&vec![HashMap::new()]).len() as u64;
-        let runtime = self.runtime as u64;
+        let runtime = self.runtime as u64 * 1000;
         Ok(f * vars * runtime)
     }
 
+    async fn primary_metric(&self, final_results_dir: &Path) -> Result<Option<Throughput>> {
+        let results =
+            read_json_file::<FilebenchSummary>(final_results_dir.join("results.json")).await?;
+        Ok(Some(Throughput {
+            value: results.summary.ops_per_sec,
+            unit: "ops/s".to_owned(),
+        }))
+    }
+
     fn write_hint(&self) -> bool {
         let contents = std::fs::read_to_string(format!(
             "/usr/local/share/filebench/workloads/{}.f",
@@ -148,11 +158,7 @@ impl Bench for Filebench {
         _config: &Config,
         final_results_dir: &Path,
     ) -> Result<()> {
-        let marker_filename = final_results_dir.join("markers.csv");
-        let mut marker_file = File::create(marker_filename).await?;
-        marker_file
-            .write_all("time,marker_name\n".as_bytes())
-            .await?;
+        MarkerWriter::create(final_results_dir).await?;
 
         let mountpoint = std::env::current_dir()?.join("mountpoint");
 
@@ -160,9 +166,25 @@ impl Bench for Filebench {
         let should_format = !skip_format
             && !self.fs.is_empty()
             && !last_experiment_uses_same_fs(last_experiment, &self.fs[0])?;
+        #[cfg(feature = "prefill")]
+        let should_format = match &self.prefill {
+            Some(spec) => {
+                should_format
+                    && !fio::Fio::prefill_device(
+                        spec,
+                        settings.device.single()?,
+                        &self.fs[0],
+                        should_format,
+                        _config,
+                        settings,
+                    )
+                    .await?
+            }
+            None => should_format,
+        };
         mount_fs(
             &mountpoint,
-            &settings.device,
+            settings.device.single()?,
             &self.fs[0],
             should_format,
             None::<String>,
@@ -170,9 +192,18 @@ impl Bench for Filebench {
         .await?;
 
         #[cfg(feature = "prefill")]
-        if let Some(size) = &self.prefill {
+        if let Some(size) = &self.prefill
+            && fio::Fio::device_prefill_percent(size)?.is_none()
+        {
             let prefill_file = mountpoint.join("prefill");
-            fio::Fio::prefill(&prefill_file, size, _config, settings).await?;
+            fio::Fio::prefill(
+                &prefill_file,
+                settings.device.single()?,
+                size,
+                _config,
+                settings,
+            )
+            .await?;
         }
         Ok(())
     }
@@ -191,20 +222,18 @@ impl Bench for Filebench {
     ) -> Result<()> {
         let mountpoint = std::env::current_dir()?.join("mountpoint");
 
-        let marker_filename = final_results_dir.join("markers.csv");
-        let mut marker_file = OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open(marker_filename)
-            .await?;
-
-        let mut filebench = Command::new(program)
-            .args(args)
+        let (cmd_program, cmd_args) = match &settings.perf_events {
+            Some(events) => perf_stat_command(program, args, events, final_results_dir),
+            None => (program.to_owned(), args.to_vec()),
+        };
+        let mut filebench = Command::new(cmd_program)
+            .args(&cmd_args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
             .context("Running benchmark")?;
+        set_running_benchmark(filebench.id());
 
         let mut filebench_stdin = filebench.stdin.take().context("Could not take stdin")?;
         let mut filebench_stdout = filebench.stdout.take().context("Could not take stdout")?;
@@ -238,7 +267,7 @@ impl Bench for Filebench {
         )
         .await
         .context(format!("Setting $dir={}", mountpoint.to_str().unwrap()))?;
-        let start_time = Instant::now();
+        let mut markers = MarkerWriter::open(final_results_dir).await?;
         for sensor in sensors {
             sensor
                 .send_async(SensorRequest::StartRecording {
@@ -275,16 +304,7 @@ impl Bench for Filebench {
         .context("Drop caches")?;
 
         debug!("Fileset created");
-        marker_file
-            .write_all(
-                format!(
-                    "{},{}\n",
-                    start_time.elapsed().as_millis(),
-                    "create-fileset"
-                )
-                .as_bytes(),
-            )
-            .await?;
+        markers.mark("create-fileset").await?;
 
         debug!("Starting benchmark");
 
@@ -300,59 +320,60 @@ impl Bench for Filebench {
         let stderr = stderr?;
         debug!("Benchmark done");
 
-        marker_file
-            .write_all(
-                format!(
-                    "{},{}\n",
-                    start_time.elapsed().as_millis(),
-                    "benchmark-done"
-                )
-                .as_bytes(),
-            )
-            .await?;
+        markers.mark("benchmark-done").await?;
 
+        let output_file =
+            write_output(final_results_dir, stdout.as_bytes(), stderr.as_bytes()).await?;
         if !exit_status.success() {
             bail!(
-                "Process exitied with {}, err: {}",
+                "Process exitied with {}, see {}",
                 exit_status.code().unwrap_or_default(),
-                stderr
+                output_file.display()
             );
         }
 
-        write(final_results_dir.join("output.txt"), &stdout).await?;
-        let (summary, ops_stats) = match parse_output(&stdout) {
-            Ok(s) => s,
-            Err(err) => bail!(
-                "Failed to parse filebench output! err: {} stdout: {} stderr: {}",
-                err,
-                stdout,
-                stderr
-            ),
-        };
-
         sleep(Duration::from_secs(60)).await;
         debug!(
             "Disk sizes: {}",
-            simple_command_with_output_no_dir("df", &["-h", &settings.device]).await?
+            simple_command_with_output_no_dir("df", &["-h", settings.device.single()?]).await?
         );
 
-        marker_file
-            .write_all(format!("{},{}\n", start_time.elapsed().as_millis(), "unmount").as_bytes())
-            .await?;
-        _ = simple_command_with_output_no_dir("umount", &[&settings.device]).await?;
+        markers.mark("unmount").await?;
+        _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
 
         for sensor in sensors {
             sensor.send_async(SensorRequest::StopRecording).await?;
         }
         debug!("Sensors stopped");
 
+        if settings.perf_events.is_some() {
+            write_perf_json(final_results_dir).await?;
+        }
+
         if let Some(mut trace) = trace {
             trace.0.kill().await?;
             trace.1.await?;
         }
+        Ok(())
+    }
 
+    async fn parse_results(&self, final_results_dir: &Path, parser: Option<&str>) -> Result<()> {
+        let results_file = final_results_dir.join("results.json");
+        if let Some(parser) = parser {
+            run_parser(parser, final_results_dir).await?;
+            read_json_file::<FilebenchSummary>(&results_file)
+                .await
+                .context(format!("Invalid results.json written by {parser}"))?;
+            return Ok(());
+        }
+
+        let (summary, ops_stats) =
+            parse_output(&read_stdout(final_results_dir).await?).context(format!(
+                "Failed to parse filebench output, see {}",
+                final_results_dir.join(OUTPUT_FILENAME).display()
+            ))?;
         write(
-            final_results_dir.join("results.json"),
+            results_file,
             serde_json::to_string(&FilebenchSummary { summary, ops_stats })?,
         )
         .await?;
@@ -398,3 +419,19 @@ async fn read_output(mut stdout: Pin<&mut impl AsyncReadExt>) -> Result<String>
     }
     Ok(String::from_utf8(buffer)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn read_output_keeps_only_read_bytes() {
+        // every read of a chain returns at most the rest of its current part, less than the buffer
+        let stdout = b"IO Summary: 1000 ops".chain(&b" 100.000 ops/s\n"[..]);
+        let output = read_output(pin!(stdout)).await.unwrap();
+        assert_eq!(output, "IO Summary: 1000 ops 100.000 ops/s\n");
+        assert!(!output.contains('\0'));
+    }
+}
@@ -7,6 +7,7 @@ pub struct OpStat {
     pub name: String,
     pub ops: u64,
     pub ops_per_sec: f64,
+    /// filebench computes "mb/s" using 1024 * 1024 bytes, ie. this is MiB/s
     pub mb_per_sec: f64,
     pub ms_per_op: f64,
     pub us_per_op_cpu: f64,
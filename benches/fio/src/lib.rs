@@ -1,21 +1,28 @@
 use std::{collections::HashMap, path::Path};
 
 use common::{
-    bench::{Bench, BenchArgs, Cmd, CmdsResult},
+    bench::{Bench, BenchArgs, Cmd, CmdsResult, MARKERS_FILENAME, OUTPUT_FILENAME},
     config::{Config, Settings},
+    cpu_governor::{restore_cpu_governor, set_cpu_governor},
+    power_cap::{restore_rapl_power_limit, set_rapl_power_limit},
+    summary::Throughput,
     util::{
-        Filesystem, get_pcie_address, mount_fs, parse_time, read_json_file,
-        simple_command_with_output, simple_command_with_output_no_dir,
+        BYTES_PER_GIB, Filesystem, get_pcie_address, mount_fs, parse_data_size, parse_time,
+        read_json_file, simple_command_with_output, simple_command_with_output_no_dir, to_mib,
     },
 };
-use eyre::{ContextCompat, Result, bail};
-use itertools::iproduct;
+use eyre::{Context, ContextCompat, Result, bail, eyre};
+use itertools::{Itertools, iproduct};
 use serde::{Deserialize, Serialize};
 use tokio::fs::{create_dir_all, read_to_string, write};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 pub mod result;
 
+/// Prefix of a prefill of `Ycsb`, `Filebench` and `H5Bench` that conditions the whole device, see
+/// [`Fio::prefill_device`]
+pub const DEVICE_PREFILL_PREFIX: &str = "device:";
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Fio {
@@ -24,11 +31,55 @@ pub struct Fio {
     pub io_engines: Vec<String>,
     pub io_depths: Vec<usize>,
     pub direct: bool,
+    /// Passes `--bs_unaligned`, for block sizes that are not a multiple of the sector size, ie.
+    /// 4608 for 4k with metadata
+    #[serde(default)]
+    pub bs_unaligned: bool,
+    /// Passes `--thread`, jobs run as threads of one process instead of forked processes
+    pub use_threads: Option<bool>,
+    /// Passes `--norandommap`, random jobs do not track which blocks were written, which saves the
+    /// memory and startup time of the map on large devices but may hit blocks repeatedly
+    pub norandommap: Option<bool>,
+    /// Passes `--randrepeat`, whether the random offsets are the same in every run, fio's default
+    /// is to repeat them
+    pub randrepeat: Option<bool>,
     pub time_based: bool,
     pub runtime: Option<String>,
     pub ramp_time: Option<String>,
+    /// Time before the end of the job excluded from the steady state, written as a marker to
+    /// `markers.csv` after `ramp_time`
+    pub wind_down: Option<String>,
     pub size: Option<String>,
+    /// Total IO per job, independent of `size`
+    pub io_size: Option<String>,
     pub num_jobs: Option<Vec<usize>>,
+    /// `--fsync` intervals to sweep, fio syncs the file after this many writes
+    pub fsync: Option<Vec<usize>>,
+    /// `--fdatasync` intervals to sweep, swept after `fsync`, which is not set in the same run
+    pub fdatasync: Option<Vec<usize>>,
+    /// `--random_distribution` values to sweep, ie. `zipf:1.2` or `pareto:0.9`, see
+    /// [`validate_random_distribution`]
+    pub random_distribution: Option<Vec<String>>,
+    /// `--iodepth_batch_submit` values to sweep, the number of IOs submitted at once
+    pub iodepth_batch_submit: Option<Vec<usize>>,
+    /// `--iodepth_batch_complete` values to sweep, the minimum number of IOs reaped at once,
+    /// swept in combination with `iodepth_batch_submit`
+    pub iodepth_batch_complete: Option<Vec<usize>>,
+    /// `--dedupe_percentage`, percentage of the written buffers that are duplicates
+    pub dedupe_percentage: Option<u8>,
+    /// `--buffer_compress_percentage`, how compressible the written buffers are, by default fio
+    /// writes fully random, incompressible data
+    pub buffer_compress_percentage: Option<u8>,
+    /// RAPL power limits to sweep, in Watts per CPU package, set before and restored after every
+    /// run, see [`common::power_cap`]
+    pub rapl_power_limits: Option<Vec<f64>>,
+    /// CPU frequency governors to sweep, set on every CPU before and restored after every run,
+    /// see [`common::cpu_governor`]
+    pub cpu_governors: Option<Vec<String>>,
+    /// Minimum CPU frequency (kHz) set along with every governor of `cpu_governors`
+    pub cpu_min_freq: Option<usize>,
+    /// Maximum CPU frequency (kHz) set along with every governor of `cpu_governors`
+    pub cpu_max_freq: Option<usize>,
     pub extra_options: Option<Vec<Vec<String>>>,
     pub job_specific_extra_options: Option<Vec<Vec<String>>>,
     pub job_specific_extra_options_index: Option<usize>,
@@ -38,7 +89,7 @@ pub struct Fio {
     pub filename: Option<String>,
     pub directory: Option<String>,
     pub open_dir: Option<String>,
-    // TODO: placeholder so that old config files don't break, to be removed
+    /// Sequentially writes the target once before every run, see [`Fio::prefill_job`]
     pub prefill: Option<bool>,
 }
 
@@ -54,6 +105,7 @@ pub struct FioConfig {
     pub program: Option<String>,
     pub log_avg: Option<usize>,
     pub spdk_path: Option<String>,
+    pub diskstat_tolerance: Option<f64>,
 }
 
 #[typetag::serde]
@@ -74,8 +126,13 @@ pub struct FioTestTypeConfig {
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct FioTestTypeArgs {
+    #[serde(default)]
     pub read: u8,
+    #[serde(default)]
     pub write: u8,
+    /// Read percentages to sweep, one command per value with `write` set to the rest, overrides
+    /// `read` and `write`
+    pub read_sweep: Option<Vec<u8>>,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -121,8 +178,17 @@ impl Bench for Fio {
         true
     }
 
+    fn supports_multiple_devices(&self) -> bool {
+        // a filesystem and SPDK need a single block device
+        self.fs.is_none() && !self.io_engines.iter().any(|x| x == "spdk")
+    }
+
     fn runtime_estimate(&self) -> Result<u64> {
-        let runtime = parse_time(self.runtime.as_ref().unwrap_or(&"1s".to_owned()))?;
+        // jobs without a runtime run until their size is written, which can not be estimated
+        let Some(runtime) = &self.runtime else {
+            return Ok(0);
+        };
+        let runtime = parse_time(runtime)?;
         let ramp = parse_time(self.ramp_time.as_ref().unwrap_or(&"1s".to_owned()))?;
         let total = runtime + ramp;
         Ok(total as u64)
@@ -149,7 +215,17 @@ impl Bench for Fio {
         };
 
         let jobs = self.num_jobs.clone();
-        let jobs_vec = jobs.unwrap_or(vec![1]);
+        let jobs_vec = jobs
+            .unwrap_or(vec![1])
+            .into_iter()
+            .map(|jobs| {
+                if jobs == 0 {
+                    auto_num_jobs(settings)
+                } else {
+                    Ok(jobs)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
         if let Some(specific) = &self.job_specific_extra_options {
             if jobs_vec[0] != specific.len() {
                 bail!(
@@ -160,83 +236,169 @@ impl Bench for Fio {
             }
         }
 
-        if let Some(matched) = &self.matched_args {
-            for item in matched {
-                let match_str = item.key.split_once("[").unwrap();
-                let requested_idx = match_str.1.trim_end_matches("]").parse::<usize>()?;
-                match match_str.0 {
-                    "request_sizes" => {
-                        if requested_idx >= self.request_sizes.len() {
-                            bail!("Matched request_sizes index out of bounds");
-                        }
-                    }
-                    "io_engines" => {
-                        if requested_idx >= self.io_engines.len() {
-                            bail!("Matched io_engines index out of bounds");
-                        }
-                    }
-                    "io_depths" => {
-                        if requested_idx >= self.io_depths.len() {
-                            bail!("Matched io_depths index out of bounds");
-                        }
-                    }
-                    "num_jobs" => {
-                        if requested_idx >= jobs_vec.len() {
-                            bail!("Matched num_jobs index out of bounds");
-                        }
-                    }
-                    "extra_options" => {
-                        if let Some(extra_options) = &self.extra_options
-                            && requested_idx >= extra_options.len()
-                        {
-                            bail!("Matched extra_options index out of bounds");
-                        }
-                    }
-                    _ => bail!("Unknown matched key: {}", match_str.0),
+        self.validate_matched_args()?;
+
+        for size in &self.request_sizes {
+            parse_data_size(size).context(format!("Invalid request size {size}"))?;
+        }
+        if let Some(io_size) = &self.io_size {
+            parse_data_size(io_size).context(format!("Invalid io_size {io_size}"))?;
+            if self.time_based {
+                warn!("io_size is ignored by fio when time_based is set, runtime bounds the run");
+            }
+        }
+
+        let sync_sweep = self.sync_sweep()?;
+        let batch_sweep = self.batch_sweep()?;
+        let distributions = match &self.random_distribution {
+            Some(distributions) => {
+                if distributions.is_empty() {
+                    bail!("random_distribution requires at least one distribution");
+                }
+                for distribution in distributions {
+                    validate_random_distribution(distribution)?;
+                }
+                distributions.iter().map(|x| Some(x.clone())).collect()
+            }
+            None => vec![None],
+        };
+        let power_limits = match &self.rapl_power_limits {
+            Some(limits) => {
+                if limits.is_empty() || limits.iter().any(|x| x.is_nan() || *x <= 0.0) {
+                    bail!("rapl_power_limits must be a list of limits greater than 0");
+                }
+                limits.iter().map(|x| Some(*x)).collect()
+            }
+            None => vec![None],
+        };
+        let governors = match &self.cpu_governors {
+            Some(governors) => {
+                if governors.is_empty() {
+                    bail!("cpu_governors requires at least one governor");
+                }
+                if settings.cpu_freq.is_some() || settings.cpu_governor.is_some() {
+                    bail!(
+                        "cpu_governors can not be combined with the cpu_freq or cpu_governor settings"
+                    );
+                }
+                governors.iter().map(|x| Some(x.clone())).collect()
+            }
+            None => {
+                if self.cpu_min_freq.is_some() || self.cpu_max_freq.is_some() {
+                    bail!("cpu_min_freq and cpu_max_freq require cpu_governors");
+                }
+                vec![None]
+            }
+        };
+        let test_types = self.test_type.sweep()?;
+        for (name, value) in [
+            ("dedupe_percentage", self.dedupe_percentage),
+            (
+                "buffer_compress_percentage",
+                self.buffer_compress_percentage,
+            ),
+        ] {
+            if let Some(value) = value {
+                if value > 100 {
+                    bail!("{name} must be at most 100, got {value}");
+                }
+                if !self.write_hint() {
+                    warn!("{name} has no effect on a read only workload");
                 }
             }
         }
+        if self.prefill.unwrap_or(false) {
+            if self.directory.is_some() || self.open_dir.is_some() || spdk {
+                bail!("prefill requires a filename or the device as target");
+            }
+            if self.fs.is_some() && self.filename.is_none() {
+                bail!("prefill with a filesystem requires a filename");
+            }
+        }
 
         let extra_options = self.extra_options.clone();
         let extra_options_vec = extra_options.unwrap_or(vec![vec!["--unit_base=0".to_owned()]]);
-        let filename = self.filename.clone().unwrap_or(settings.device.clone());
+        // fio takes several files as one colon separated filename
+        let filename = self
+            .filename
+            .clone()
+            .unwrap_or(settings.device.all().join(":"));
         let cmds = iproduct!(
             0..self.request_sizes.len(),
             0..self.io_engines.len(),
             0..self.io_depths.len(),
             0..jobs_vec.len(),
             0..extra_options_vec.len(),
+            0..test_types.len(),
+            0..sync_sweep.len(),
+            0..power_limits.len(),
+            0..batch_sweep.len(),
+            0..governors.len(),
+            0..distributions.len(),
         )
-        .map(|(req_idx, eng_idx, depth_idx, job_idx, extra_idx)| {
-            let bench = Fio {
-                test_type: self.test_type.clone(),
-                request_sizes: vec![self.request_sizes[req_idx].clone()],
-                io_engines: vec![self.io_engines[eng_idx].clone()],
-                io_depths: vec![self.io_depths[depth_idx]],
-                direct: self.direct,
-                time_based: self.time_based,
-                runtime: self.runtime.clone(),
-                ramp_time: self.ramp_time.clone(),
-                size: self.size.clone(),
-                extra_options: Some(vec![extra_options_vec[extra_idx].clone()]),
-                num_jobs: Some(vec![jobs_vec[job_idx]]),
-                job_specific_extra_options: self.job_specific_extra_options.clone(),
-                job_specific_extra_options_index: self.job_specific_extra_options_index.clone(),
-                fs: self.fs.clone(),
-                skip_format: self.skip_format,
-                filename: if self.directory.is_some() || self.open_dir.is_some() {
-                    None
-                } else {
-                    Some(filename.clone())
-                },
-                matched_args: self.matched_args.clone(),
-                directory: self.directory.clone(),
-                open_dir: self.open_dir.clone(),
-                prefill: None,
-            };
+        .map(
+            |(
+                req_idx,
+                eng_idx,
+                depth_idx,
+                job_idx,
+                extra_idx,
+                mix_idx,
+                sync_idx,
+                limit_idx,
+                batch_idx,
+                governor_idx,
+                distribution_idx,
+            )| {
+                let (fsync, fdatasync) = sync_sweep[sync_idx];
+                let (batch_submit, batch_complete) = batch_sweep[batch_idx];
+                let bench = Fio {
+                    test_type: test_types[mix_idx].clone(),
+                    request_sizes: vec![self.request_sizes[req_idx].clone()],
+                    io_engines: vec![self.io_engines[eng_idx].clone()],
+                    io_depths: vec![self.io_depths[depth_idx]],
+                    direct: self.direct,
+                    bs_unaligned: self.bs_unaligned,
+                    use_threads: self.use_threads,
+                    norandommap: self.norandommap,
+                    randrepeat: self.randrepeat,
+                    time_based: self.time_based,
+                    runtime: self.runtime.clone(),
+                    ramp_time: self.ramp_time.clone(),
+                    wind_down: self.wind_down.clone(),
+                    size: self.size.clone(),
+                    io_size: self.io_size.clone(),
+                    extra_options: Some(vec![extra_options_vec[extra_idx].clone()]),
+                    num_jobs: Some(vec![jobs_vec[job_idx]]),
+                    fsync: fsync.map(|x| vec![x]),
+                    fdatasync: fdatasync.map(|x| vec![x]),
+                    random_distribution: distributions[distribution_idx].clone().map(|x| vec![x]),
+                    iodepth_batch_submit: batch_submit.map(|x| vec![x]),
+                    iodepth_batch_complete: batch_complete.map(|x| vec![x]),
+                    dedupe_percentage: self.dedupe_percentage,
+                    buffer_compress_percentage: self.buffer_compress_percentage,
+                    rapl_power_limits: power_limits[limit_idx].map(|x| vec![x]),
+                    cpu_governors: governors[governor_idx].clone().map(|x| vec![x]),
+                    cpu_min_freq: self.cpu_min_freq,
+                    cpu_max_freq: self.cpu_max_freq,
+                    job_specific_extra_options: self.job_specific_extra_options.clone(),
+                    job_specific_extra_options_index: self.job_specific_extra_options_index.clone(),
+                    fs: self.fs.clone(),
+                    skip_format: self.skip_format,
+                    filename: if self.directory.is_some() || self.open_dir.is_some() {
+                        None
+                    } else {
+                        Some(filename.clone())
+                    },
+                    matched_args: self.matched_args.clone(),
+                    directory: self.directory.clone(),
+                    open_dir: self.open_dir.clone(),
+                    prefill: self.prefill,
+                };
 
-            (req_idx, eng_idx, depth_idx, job_idx, extra_idx, bench)
-        })
+                (req_idx, eng_idx, depth_idx, job_idx, extra_idx, bench)
+            },
+        )
         .enumerate()
         .map(
             |(idx, (req_idx, eng_idx, depth_idx, job_idx, extra_idx, mut bench))| {
@@ -272,7 +434,11 @@ impl Bench for Fio {
                         open_dir.clone()
                     } else {
                         if bench.io_engines[0].eq("spdk") {
-                            let pcie_address = get_pcie_address(&settings.device)
+                            let pcie_address = settings
+                                .device
+                                .single()
+                                .ok()
+                                .and_then(get_pcie_address)
                                 .context("Get drive PCIe address")
                                 .unwrap_or("00:00.0".to_owned())
                                 .replace(":", ".");
@@ -291,6 +457,18 @@ impl Bench for Fio {
 
                 args.extend(temp);
                 args.push("--output-format=json+".to_owned());
+                if bench.bs_unaligned {
+                    args.push("--bs_unaligned".to_owned());
+                }
+                if bench.use_threads.unwrap_or(false) {
+                    args.push("--thread".to_owned());
+                }
+                if bench.norandommap.unwrap_or(false) {
+                    args.push("--norandommap".to_owned());
+                }
+                if let Some(randrepeat) = bench.randrepeat {
+                    args.push(format!("--randrepeat={}", randrepeat as u8));
+                }
 
                 let log_avg = bench_args.log_avg.unwrap_or(10);
                 if log_avg > 0 {
@@ -299,12 +477,38 @@ impl Bench for Fio {
                 if let Some(size) = &bench.size {
                     args.push(format!("--size={size}"));
                 }
+                if let Some(io_size) = &bench.io_size {
+                    args.push(format!("--io_size={io_size}"));
+                }
                 if let Some(runtime) = &bench.runtime {
                     args.push(format!("--runtime={runtime}"))
                 }
                 if let Some(ramp_time) = &bench.ramp_time {
                     args.push(format!("--ramp_time={ramp_time}"));
                 }
+                if let Some(fsync) = &bench.fsync {
+                    args.push(format!("--fsync={}", fsync[0]));
+                }
+                if let Some(fdatasync) = &bench.fdatasync {
+                    args.push(format!("--fdatasync={}", fdatasync[0]));
+                }
+                if let Some(distribution) = &bench.random_distribution {
+                    args.push(format!("--random_distribution={}", distribution[0]));
+                }
+                if let Some(batch_submit) = &bench.iodepth_batch_submit {
+                    args.push(format!("--iodepth_batch_submit={}", batch_submit[0]));
+                }
+                if let Some(batch_complete) = &bench.iodepth_batch_complete {
+                    args.push(format!("--iodepth_batch_complete={}", batch_complete[0]));
+                }
+                if let Some(dedupe_percentage) = bench.dedupe_percentage {
+                    args.push(format!("--dedupe_percentage={dedupe_percentage}"));
+                }
+                if let Some(buffer_compress_percentage) = bench.buffer_compress_percentage {
+                    args.push(format!(
+                        "--buffer_compress_percentage={buffer_compress_percentage}"
+                    ));
+                }
 
                 bench
                     .test_type
@@ -428,6 +632,15 @@ impl Bench for Fio {
         self.io_engines[0].eq("spdk")
     }
 
+    async fn primary_metric(&self, final_results_dir: &Path) -> Result<Option<Throughput>> {
+        let results: result::FioResult =
+            read_json_file(final_results_dir.join("results.json")).await?;
+        Ok(Some(Throughput {
+            value: results.total_throughput(),
+            unit: "MiB/s".to_owned(),
+        }))
+    }
+
     fn write_hint(&self) -> bool {
         matches!(
             &self.test_type._type,
@@ -447,6 +660,17 @@ impl Bench for Fio {
         _config: &Config,
         _final_results_dir: &Path,
     ) -> Result<()> {
+        if let Some(limits) = &self.rapl_power_limits {
+            set_rapl_power_limit(limits[0])
+                .await
+                .context("Set RAPL power limit")?;
+        }
+        if let Some(governors) = &self.cpu_governors {
+            set_cpu_governor(&governors[0], self.cpu_min_freq, self.cpu_max_freq)
+                .await
+                .context("Set CPU governor")?;
+        }
+
         if self.io_engines[0] == "spdk" {
             if self.fs.is_some() {
                 bail!("Filesystem not supported for SPDK");
@@ -456,7 +680,7 @@ impl Bench for Fio {
             let spdk_dir = args.spdk_path.as_ref().context("Missing SPDK path")?;
             let spdk_dir = Path::new(spdk_dir);
             let pcie_device =
-                get_pcie_address(&settings.device).context("Get drive PCIe address")?;
+                get_pcie_address(settings.device.single()?).context("Get drive PCIe address")?;
             write("spdk_device", &pcie_device).await?;
             let mut env = HashMap::from([("PCI_ALLOWED".to_owned(), pcie_device)]);
 
@@ -483,7 +707,7 @@ impl Bench for Fio {
             info!("Formatting: {should_format}");
             mount_fs(
                 &mountpoint,
-                &settings.device,
+                settings.device.single()?,
                 fs,
                 should_format,
                 None::<String>,
@@ -494,6 +718,19 @@ impl Bench for Fio {
                 _ = create_dir_all(dir).await;
             }
         }
+
+        if self.prefill.unwrap_or(false) {
+            info!(
+                "Prefilling {}",
+                self.filename.as_deref().unwrap_or_default()
+            );
+            let CmdsResult { cmds, program } =
+                self.prefill_job().cmds(settings, bench_args, "prefill")?;
+            let args = cmds[0].args.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+            simple_command_with_output_no_dir(&program, &args)
+                .await
+                .context("Prefill fio target")?;
+        }
         Ok(())
     }
 
@@ -504,19 +741,41 @@ impl Bench for Fio {
         settings: &Settings,
         bench_args: &dyn BenchArgs,
     ) -> Result<()> {
-        let results: result::FioResult =
-            read_json_file(final_results_dir.join("results.json")).await?;
-        let runtime = results.jobs[0].job_runtime as f64 / 1000.0;
-        debug!(
-            "bw_mean: ({}, {})",
-            ((results.jobs.iter().map(|x| x.read.io_bytes).sum::<i64>() as f64) / 1048576.0)
-                / runtime,
-            ((results.jobs.iter().map(|x| x.write.io_bytes).sum::<i64>() as f64) / 1048576.0)
-                / runtime
-        );
+        if self.rapl_power_limits.is_some() {
+            restore_rapl_power_limit()
+                .await
+                .context("Restore RAPL power limit")?;
+        }
+        if self.cpu_governors.is_some() {
+            restore_cpu_governor()
+                .await
+                .context("Restore CPU governor")?;
+        }
+
+        let results: result::FioResult = read_json_file(final_results_dir.join("results.json"))
+            .await
+            .context(format!(
+                "Parsing fio results.json, see {}",
+                final_results_dir.join(OUTPUT_FILENAME).display()
+            ))?;
+        debug!("bw_mean: {:?}", results.throughput());
+
+        if let Some(wind_down) = &self.wind_down {
+            self.write_steady_state_markers(final_results_dir, &results, wind_down)
+                .await?;
+        }
+
+        let args = bench_args.downcast_ref::<FioConfig>().unwrap();
+        if self.io_engines[0] != "spdk" {
+            self.verify_diskstat_bytes(
+                final_results_dir,
+                &results,
+                args.diskstat_tolerance.unwrap_or(0.1),
+            )
+            .await?;
+        }
 
         if self.io_engines[0] == "spdk" {
-            let args = bench_args.downcast_ref::<FioConfig>().unwrap();
             let spdk_dir = args.spdk_path.as_ref().context("Missing SPDK path")?;
             let spdk_dir = Path::new(spdk_dir);
             let pcie_device = read_to_string("spdk_device").await?;
@@ -531,12 +790,34 @@ impl Bench for Fio {
         }
 
         if self.fs.is_some() {
-            _ = simple_command_with_output_no_dir("umount", &[&settings.device]).await?;
+            _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
         }
         Ok(())
     }
 }
 
+/// Resolves `num_jobs: 0` to the number of CPUs fio can run on, ie. the cores of the
+/// bound NUMA node if set, otherwise all cores of the machine
+fn auto_num_jobs(settings: &Settings) -> Result<usize> {
+    let Some(numa) = &settings.numa else {
+        return Ok(num_cpus::get());
+    };
+
+    let cpulist = std::fs::read_to_string(format!(
+        "/sys/devices/system/node/node{}/cpulist",
+        numa.cpunodebind
+    ))
+    .context("Read NUMA node cpulist")?;
+    let mut cpus = 0;
+    for range in cpulist.trim().split(',').filter(|x| !x.is_empty()) {
+        cpus += match range.split_once('-') {
+            Some((start, end)) => end.parse::<usize>()? - start.parse::<usize>()? + 1,
+            None => 1,
+        };
+    }
+    Ok(cpus)
+}
+
 fn last_experiment_uses_same_fs(
     last_experiment: &Option<Box<dyn Bench>>,
     current_fs: &Filesystem,
@@ -556,6 +837,253 @@ fn last_experiment_uses_same_fs(
 }
 
 impl Fio {
+    /// `(fsync, fdatasync)` of each run, one of them is set per run, `(None, None)` if neither is
+    /// swept
+    fn sync_sweep(&self) -> Result<Vec<(Option<usize>, Option<usize>)>> {
+        let mut sweep = Vec::new();
+        for (name, values) in [("fsync", &self.fsync), ("fdatasync", &self.fdatasync)] {
+            if let Some(values) = values {
+                if values.is_empty() {
+                    bail!("{name} requires at least one value");
+                }
+                if !self.write_hint() {
+                    warn!("{name} has no effect on a read only workload");
+                }
+            }
+        }
+        sweep.extend(self.fsync.iter().flatten().map(|x| (Some(*x), None)));
+        sweep.extend(self.fdatasync.iter().flatten().map(|x| (None, Some(*x))));
+        if sweep.is_empty() {
+            sweep.push((None, None));
+        }
+        Ok(sweep)
+    }
+
+    /// Fields that `matched_args` can refer to, with their number of values
+    fn matched_fields(&self) -> [(&'static str, usize); 5] {
+        [
+            ("request_sizes", self.request_sizes.len()),
+            ("io_engines", self.io_engines.len()),
+            ("io_depths", self.io_depths.len()),
+            ("num_jobs", self.num_jobs.as_ref().map_or(1, Vec::len)),
+            (
+                "extra_options",
+                self.extra_options.as_ref().map_or(1, Vec::len),
+            ),
+        ]
+    }
+
+    /// Checks that every `matched_args` key is `<field>[<index>]` of an existing value
+    pub fn validate_matched_args(&self) -> Result<()> {
+        let fields = self.matched_fields();
+        for item in self.matched_args.iter().flatten() {
+            let Some((field, index)) = item
+                .key
+                .strip_suffix(']')
+                .and_then(|key| key.split_once('['))
+            else {
+                bail!(
+                    "Invalid matched_args key {}, expected <field>[<index>], ie. io_engines[0]",
+                    item.key
+                );
+            };
+            let index = index
+                .parse::<usize>()
+                .context(format!("Invalid index in matched_args key {}", item.key))?;
+            let Some((_, len)) = fields.iter().find(|(name, _)| *name == field) else {
+                bail!(
+                    "Unknown matched_args field {field}, expected one of {}",
+                    fields.map(|(name, _)| name).join(", ")
+                );
+            };
+            if index >= *len {
+                bail!(
+                    "Matched {} is out of bounds, {field} has {len} values",
+                    item.key
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Positions in `matched_args` of the arguments applied together to a run, for every
+    /// distinct combination that the sweep produces
+    pub fn matched_combinations(&self) -> Vec<Vec<usize>> {
+        let Some(matched) = &self.matched_args else {
+            return vec![Vec::new()];
+        };
+        let fields = self.matched_fields();
+        fields
+            .iter()
+            .map(|(_, len)| 0..*len)
+            .multi_cartesian_product()
+            .map(|indices| {
+                let keys = fields
+                    .iter()
+                    .zip(indices)
+                    .map(|((name, _), idx)| format!("{name}[{idx}]"))
+                    .collect::<Vec<_>>();
+                matched
+                    .iter()
+                    .positions(|item| keys.contains(&item.key))
+                    .collect::<Vec<_>>()
+            })
+            .unique()
+            .collect()
+    }
+
+    /// `(iodepth_batch_submit, iodepth_batch_complete)` of every run, all combinations of both
+    fn batch_sweep(&self) -> Result<Vec<(Option<usize>, Option<usize>)>> {
+        let mut values = Vec::new();
+        for (name, sweep) in [
+            ("iodepth_batch_submit", &self.iodepth_batch_submit),
+            ("iodepth_batch_complete", &self.iodepth_batch_complete),
+        ] {
+            match sweep {
+                Some(sweep) if sweep.is_empty() => bail!("{name} requires at least one value"),
+                Some(sweep) => values.push(sweep.iter().map(|x| Some(*x)).collect::<Vec<_>>()),
+                None => values.push(vec![None]),
+            }
+        }
+        Ok(iproduct!(values[0].iter(), values[1].iter())
+            .map(|(submit, complete)| (*submit, *complete))
+            .collect())
+    }
+
+    /// Writes the start (`ramp_time`) and end (`wind_down`) of the steady state section to
+    /// [`MARKERS_FILENAME`], relative to the start of the run
+    async fn write_steady_state_markers(
+        &self,
+        final_results_dir: &Path,
+        results: &result::FioResult,
+        wind_down: &str,
+    ) -> Result<()> {
+        let ramp_time = match &self.ramp_time {
+            Some(ramp_time) => parse_time(ramp_time)?,
+            None => 0,
+        };
+        let runtime = results
+            .jobs
+            .iter()
+            .map(|x| x.job_runtime)
+            .max()
+            .unwrap_or_default()
+            .max(0) as usize;
+        let wind_down_time = parse_time(wind_down).context("Parse wind_down")?;
+        if wind_down_time >= runtime {
+            warn!("wind_down {wind_down} is longer than the job runtime of {runtime}ms, ignoring");
+            return Ok(());
+        }
+
+        write(
+            final_results_dir.join(MARKERS_FILENAME),
+            format!(
+                "time,marker_name\n{ramp_time},ramp_time\n{},wind_down\n",
+                ramp_time + runtime - wind_down_time
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Compares the bytes fio reports to the bytes the kernel saw on the device (recorded by
+    /// the diskstat sensor, after the ramp time), and warns if they differ by more than
+    /// `tolerance` (fraction of fio bytes). A mismatch hints at page cache effects or
+    /// benchmarking the wrong device.
+    async fn verify_diskstat_bytes(
+        &self,
+        final_results_dir: &Path,
+        results: &result::FioResult,
+        tolerance: f64,
+    ) -> Result<()> {
+        let diskstat_file = final_results_dir.join("diskstat.csv");
+        if !diskstat_file.exists() {
+            return Ok(());
+        }
+
+        let diskstat = read_to_string(&diskstat_file).await?;
+        let mut lines = diskstat.lines();
+        let header = lines
+            .next()
+            .context("Empty diskstat.csv")?
+            .split(',')
+            .collect::<Vec<_>>();
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|x| *x == name)
+                .context(format!("Missing {name} column in diskstat.csv"))
+        };
+        let (time, read, write) = (
+            column("time")?,
+            column("bytes_read")?,
+            column("bytes_write")?,
+        );
+
+        let ramp_time = match &self.ramp_time {
+            Some(ramp_time) => parse_time(ramp_time)? as f64,
+            None => 0.0,
+        };
+        let rows = lines
+            .map(|line| {
+                let row = line
+                    .split(',')
+                    .map(|x| x.parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((row[time], row[read] + row[write]))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|(time, _)| *time >= ramp_time)
+            .collect::<Vec<_>>();
+        let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+            return Ok(());
+        };
+
+        // /sys/block/<dev>/stat always counts in 512 byte sectors
+        let diskstat_bytes = (last.1 - first.1) * 512.0;
+        let fio_bytes = results
+            .jobs
+            .iter()
+            .map(|x| x.read.io_bytes + x.write.io_bytes)
+            .sum::<i64>() as f64;
+        if fio_bytes == 0.0 {
+            return Ok(());
+        }
+
+        let deviation = (diskstat_bytes - fio_bytes).abs() / fio_bytes;
+        debug!("fio bytes={fio_bytes} diskstat bytes={diskstat_bytes} deviation={deviation}");
+        if deviation > tolerance {
+            warn!(
+                "fio and diskstat bytes differ by {:.1}% in {} (fio: {:.1} MiB, diskstat: {:.1} MiB), check for caching or the wrong device",
+                deviation * 100.0,
+                final_results_dir.display(),
+                to_mib(fio_bytes),
+                to_mib(diskstat_bytes)
+            );
+        }
+        Ok(())
+    }
+
+    /// Single job sequentially writing the target of the run once with large requests, run
+    /// before the run if `prefill` is set, so that the run does not measure an empty SSD
+    fn prefill_job(&self) -> Fio {
+        Fio {
+            test_type: FioTestTypeConfig {
+                _type: FioTestType::Write,
+                args: None,
+            },
+            request_sizes: vec!["128k".to_owned()],
+            io_engines: vec!["io_uring".to_owned()],
+            io_depths: vec![32],
+            num_jobs: Some(vec![1]),
+            direct: true,
+            size: self.size.clone(),
+            filename: self.filename.clone(),
+            ..Default::default()
+        }
+    }
+
     pub async fn prefill(
         prefill_file: &Path,
         device: &str,
@@ -577,11 +1105,28 @@ impl Fio {
             io_engines: vec!["io_uring".to_owned()],
             io_depths: vec![32],
             num_jobs: Some(vec![20]),
+            fsync: None,
+            fdatasync: None,
+            random_distribution: None,
+            iodepth_batch_submit: None,
+            iodepth_batch_complete: None,
+            dedupe_percentage: None,
+            buffer_compress_percentage: None,
+            rapl_power_limits: None,
+            cpu_governors: None,
+            cpu_min_freq: None,
+            cpu_max_freq: None,
             direct: true,
+            bs_unaligned: false,
+            use_threads: None,
+            norandommap: None,
+            randrepeat: None,
             time_based: false,
             runtime: None,
             ramp_time: None,
+            wind_down: None,
             size: Some(size.to_owned()),
+            io_size: None,
             extra_options: None,
             job_specific_extra_options: None,
             job_specific_extra_options_index: None,
@@ -594,26 +1139,150 @@ impl Fio {
             prefill: None,
         };
 
+        fio.run_once(device, config, settings).await
+    }
+
+    /// Percentage of a `device:<N>%` prefill of `Ycsb`, `Filebench` and `H5Bench`, which
+    /// conditions the whole device, or `None` for the size of a prefill file
+    pub fn device_prefill_percent(spec: &str) -> Result<Option<u64>> {
+        let Some(percent) = spec.strip_prefix(DEVICE_PREFILL_PREFIX) else {
+            return Ok(None);
+        };
+        let percent = percent
+            .strip_suffix('%')
+            .and_then(|x| x.parse::<u64>().ok())
+            .filter(|x| (1..=100).contains(x))
+            .context(format!(
+                "Invalid prefill {spec}, expected {DEVICE_PREFILL_PREFIX}<1-100>%"
+            ))?;
+        Ok(Some(percent))
+    }
+
+    /// Conditions the device for a `device:<N>%` prefill by sequentially writing N% of its
+    /// capacity, independent of the size of the benchmark's files, then formats it with `fs`
+    /// without discarding the written blocks. Only done if the filesystem is going to be
+    /// formatted, returns whether it was, in which case the caller only mounts it
+    pub async fn prefill_device(
+        spec: &str,
+        device: &str,
+        fs: &Filesystem,
+        should_format: bool,
+        config: &Config,
+        settings: &Settings,
+    ) -> Result<bool> {
+        let Some(percent) = Self::device_prefill_percent(spec)? else {
+            return Ok(false);
+        };
+        if !should_format {
+            return Ok(false);
+        }
+
+        _ = simple_command_with_output_no_dir("umount", &[device]).await;
+        let capacity = device_capacity(device).await?;
+        // whole MiB, the request size of the prefill job divides it
+        let size = capacity / 100 * percent / (1 << 20) * (1 << 20);
+        info!(
+            "Conditioning {percent}% of {device} ({:.1} GiB)",
+            size as f64 / BYTES_PER_GIB
+        );
+        Fio {
+            filename: Some(device.to_owned()),
+            size: Some(size.to_string()),
+            ..Default::default()
+        }
+        .prefill_job()
+        .run_once(device, config, settings)
+        .await
+        .context(format!("Conditioning {device}"))?;
+
+        simple_command_with_output_no_dir("bash", &["-c", &fs.nodiscard_cmd(device)?]).await?;
+        Ok(true)
+    }
+
+    /// Runs the single command of this job against `device`, outside of the measured runs
+    async fn run_once(&self, device: &str, config: &Config, settings: &Settings) -> Result<()> {
         let bench_args: Box<dyn BenchArgs> = 'outer: {
             for item in &config.bench_args {
                 if let Some(fio_args) = item.downcast_ref::<FioConfig>() {
                     break 'outer Box::new(fio_args.clone());
                 }
             }
-            fio.default_bench_args()
+            self.default_bench_args()
         };
         let mut prefill_settings = settings.clone();
-        prefill_settings.device = device.to_owned();
+        prefill_settings.device = device.to_owned().into();
         prefill_settings.numa = None;
         prefill_settings.nvme_power_states = None;
-        let CmdsResult { cmds, program } = fio.cmds(&prefill_settings, &*bench_args, "prefill")?;
+        let CmdsResult { cmds, program } = self.cmds(&prefill_settings, &*bench_args, "prefill")?;
         let args = cmds[0].args.iter().map(|x| x.as_str()).collect::<Vec<_>>();
         _ = simple_command_with_output_no_dir(&program, &args).await?;
         Ok(())
     }
 }
 
+/// Capacity of a block device in bytes, from its number of 512 byte sectors
+async fn device_capacity(device: &str) -> Result<u64> {
+    let resolved = Path::new(device)
+        .canonicalize()
+        .context(format!("Resolving {device}"))?;
+    let name = resolved
+        .file_name()
+        .context(format!("{device} is not a device"))?
+        .to_string_lossy()
+        .to_string();
+    let sectors = read_to_string(format!("/sys/class/block/{name}/size"))
+        .await
+        .context(format!("Reading the size of {device}"))?;
+    Ok(sectors.trim().parse::<u64>()? * 512)
+}
+
 impl FioTestTypeConfig {
+    /// Expands `read_sweep` into one test type per read percentage, checking that mixed test
+    /// types have valid `args`
+    fn sweep(&self) -> Result<Vec<FioTestTypeConfig>> {
+        if !matches!(
+            self._type,
+            FioTestType::ReadWrite | FioTestType::RandReadWrite
+        ) {
+            return Ok(vec![self.clone()]);
+        }
+
+        let args = self
+            .args
+            .as_ref()
+            .context("read_write and rand_read_write require test_type.args")?;
+        let Some(read_sweep) = &args.read_sweep else {
+            if args.read as u16 + args.write as u16 > 100 {
+                bail!(
+                    "test_type read + write must not exceed 100, got {} + {}",
+                    args.read,
+                    args.write
+                );
+            }
+            return Ok(vec![self.clone()]);
+        };
+        if read_sweep.is_empty() {
+            bail!("test_type read_sweep is empty");
+        }
+
+        read_sweep
+            .iter()
+            .map(|read| {
+                if *read > 100 {
+                    bail!("test_type read_sweep values must not exceed 100, got {read}");
+                }
+                Ok(FioTestTypeConfig {
+                    _type: self._type.clone(),
+                    args: Some(FioTestTypeArgs {
+                        read: *read,
+                        write: 100 - read,
+                        read_sweep: None,
+                    }),
+                })
+            })
+            .collect()
+    }
+
     fn cmds(&self, _: &Settings) -> Vec<String> {
         let mut cmds = match self._type {
             FioTestType::Read => vec!["read".to_owned()],
@@ -636,6 +1305,50 @@ impl FioTestTypeConfig {
     }
 }
 
+/// Checks a `--random_distribution` of fio, `random`, `zipf:<theta>`, `pareto:<h>`,
+/// `normal:<percent>` (or `gauss`), optionally followed by `:<center>`, or `zoned:` and
+/// `zoned_abs:` with `<access>/<size>` pairs separated by `:`
+fn validate_random_distribution(distribution: &str) -> Result<()> {
+    let invalid = || eyre!("Invalid random_distribution {distribution}");
+    let (kind, params) = distribution.split_once(':').unwrap_or((distribution, ""));
+    let numbers = || -> Result<Vec<f64>> {
+        params
+            .split(':')
+            .map(|x| x.parse::<f64>().map_err(|_| invalid()))
+            .collect()
+    };
+    match kind {
+        "random" if params.is_empty() => {}
+        "zipf" | "pareto" | "normal" | "gauss" => {
+            let numbers = numbers()?;
+            if numbers.len() > 2 {
+                return Err(invalid());
+            }
+            let valid = match kind {
+                "zipf" => numbers[0] > 0.0 && numbers[0] != 1.0,
+                "pareto" => numbers[0] > 0.0 && numbers[0] < 1.0,
+                _ => (0.0..=100.0).contains(&numbers[0]),
+            };
+            if !valid || numbers.get(1).is_some_and(|x| !(0.0..=1.0).contains(x)) {
+                return Err(invalid());
+            }
+        }
+        "zoned" | "zoned_abs" => {
+            for zone in params.split(':') {
+                let (access, size) = zone.split_once('/').ok_or_else(invalid)?;
+                access.parse::<f64>().map_err(|_| invalid())?;
+                if kind == "zoned" {
+                    size.parse::<f64>().map_err(|_| invalid())?;
+                } else {
+                    parse_data_size(size).map_err(|_| invalid())?;
+                }
+            }
+        }
+        _ => return Err(invalid()),
+    }
+    Ok(())
+}
+
 fn apply_matched_index(
     field: &str,
     index: usize,
@@ -651,3 +1364,160 @@ fn apply_matched_index(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefill_job_writes_target_of_run() {
+        let fio = Fio {
+            test_type: FioTestTypeConfig {
+                _type: FioTestType::Randread,
+                args: None,
+            },
+            request_sizes: vec!["4k".to_owned()],
+            io_engines: vec!["libaio".to_owned()],
+            io_depths: vec![1],
+            num_jobs: Some(vec![8]),
+            time_based: true,
+            runtime: Some("60s".to_owned()),
+            size: Some("10G".to_owned()),
+            filename: Some("/dev/nvme0n1".to_owned()),
+            prefill: Some(true),
+            ..Default::default()
+        };
+
+        let job = fio.prefill_job();
+        assert!(matches!(job.test_type._type, FioTestType::Write));
+        assert_eq!(job.size.as_deref(), Some("10G"));
+        assert_eq!(job.filename.as_deref(), Some("/dev/nvme0n1"));
+        assert_eq!(job.num_jobs, Some(vec![1]));
+        // the whole target is written once, not for the runtime of the run
+        assert!(!job.time_based && job.runtime.is_none());
+        assert!(job.prefill.is_none());
+    }
+
+    #[test]
+    fn device_prefill_percent() {
+        assert_eq!(Fio::device_prefill_percent("device:100%").unwrap(), Some(100));
+        assert_eq!(Fio::device_prefill_percent("200GB").unwrap(), None);
+        assert!(Fio::device_prefill_percent("device:0%").is_err());
+        assert!(Fio::device_prefill_percent("device:100").is_err());
+    }
+
+    #[test]
+    fn random_map_args() {
+        let mut fio = Fio {
+            test_type: FioTestTypeConfig {
+                _type: FioTestType::Randwrite,
+                args: None,
+            },
+            request_sizes: vec!["4k".to_owned()],
+            io_engines: vec!["libaio".to_owned()],
+            io_depths: vec![1],
+            filename: Some("/dev/nvme0n1".to_owned()),
+            ..Default::default()
+        };
+        let cmd_args = |fio: &Fio| {
+            fio.cmds(&Settings::default(), &FioConfig::default(), "fio")
+                .unwrap()
+                .cmds
+                .remove(0)
+                .args
+        };
+
+        let default = cmd_args(&fio);
+        assert!(!default.iter().any(|x| x.starts_with("--norandommap")));
+        assert!(!default.iter().any(|x| x.starts_with("--randrepeat")));
+
+        fio.norandommap = Some(true);
+        fio.randrepeat = Some(false);
+        let args = cmd_args(&fio);
+        assert!(args.contains(&"--norandommap".to_owned()));
+        assert!(args.contains(&"--randrepeat=0".to_owned()));
+    }
+
+    fn matched(keys: &[&str]) -> Option<Vec<MatchedKv>> {
+        Some(
+            keys.iter()
+                .map(|key| MatchedKv {
+                    key: key.to_string(),
+                    value: vec!["--hipri".to_owned()],
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn validates_matched_args_keys() {
+        let mut fio = Fio {
+            io_engines: vec!["libaio".to_owned(), "io_uring".to_owned()],
+            request_sizes: vec!["4k".to_owned()],
+            ..Default::default()
+        };
+        fio.matched_args = matched(&["io_engines[1]", "request_sizes[0]"]);
+        assert!(fio.validate_matched_args().is_ok());
+        fio.matched_args = None;
+        assert!(fio.validate_matched_args().is_ok());
+
+        for key in ["io_engines[2]", "io_engines", "io_engines[x]", "runtime[0]"] {
+            fio.matched_args = matched(&[key]);
+            assert!(fio.validate_matched_args().is_err(), "{key}");
+        }
+    }
+
+    #[test]
+    fn matched_combinations_of_the_sweep() {
+        let mut fio = Fio {
+            io_engines: vec!["libaio".to_owned(), "io_uring".to_owned()],
+            io_depths: vec![1, 32],
+            request_sizes: vec!["4k".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(fio.matched_combinations(), vec![Vec::<usize>::new()]);
+
+        fio.matched_args = matched(&["io_engines[1]", "io_depths[1]"]);
+        let mut combinations = fio.matched_combinations();
+        combinations.sort();
+        assert_eq!(combinations, vec![vec![], vec![0], vec![0, 1], vec![1]]);
+    }
+
+    #[test]
+    fn validates_random_distributions() {
+        for distribution in [
+            "random",
+            "zipf:1.2",
+            "zipf:0.8:0.5",
+            "pareto:0.9",
+            "normal:20",
+            "gauss:5:0.2",
+            "zoned:60/10:30/20:10/70",
+            "zoned_abs:80/1G:20/10G",
+        ] {
+            assert!(
+                validate_random_distribution(distribution).is_ok(),
+                "{distribution}"
+            );
+        }
+        for distribution in [
+            "",
+            "uniform",
+            "random:1",
+            "zipf",
+            "zipf:1",
+            "zipf:1.2:1.5",
+            "zipf:1.2:0.5:1",
+            "pareto:1",
+            "normal:150",
+            "zoned:60",
+            "zoned:60/x",
+            "zoned_abs:80/1G:20/big",
+        ] {
+            assert!(
+                validate_random_distribution(distribution).is_err(),
+                "{distribution}"
+            );
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use common::util::to_mib;
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -96,6 +97,8 @@ pub struct Read {
     pub total_ios: i64,
     pub short_ios: i64,
     pub drop_ios: i64,
+    /// Missing for synchronous io engines
+    #[serde(default)]
     pub slat_ns: SlatNs,
     pub clat_ns: ClatNs,
     pub lat_ns: LatNs,
@@ -143,6 +146,8 @@ pub struct Write {
     pub total_ios: i64,
     pub short_ios: i64,
     pub drop_ios: i64,
+    /// Missing for synchronous io engines
+    #[serde(default)]
     pub slat_ns: SlatNs,
     pub clat_ns: ClatNs,
     pub lat_ns: LatNs,
@@ -171,6 +176,72 @@ pub struct ClatNs {
     pub bins: Option<HashMap<String, i64>>,
 }
 
+impl FioResult {
+    /// Read and write throughput summed over all jobs in MiB/s
+    ///
+    /// Every job's throughput is taken over its own runtime, jobs can finish at different times,
+    /// ie. with `size` or `io_size` limits, so the runtime of the first job does not apply to all
+    pub fn throughput(&self) -> (f64, f64) {
+        self.jobs.iter().fold((0.0, 0.0), |(read, write), job| {
+            (
+                read + job.throughput(job.read.io_bytes),
+                write + job.throughput(job.write.io_bytes),
+            )
+        })
+    }
+
+    /// Total read + write throughput over all jobs in MiB/s, see [`FioResult::throughput`]
+    pub fn total_throughput(&self) -> f64 {
+        let (read, write) = self.throughput();
+        read + write
+    }
+}
+
+impl Job {
+    /// Throughput of `io_bytes` over the runtime of this job in MiB/s, 0 if the job did not run
+    fn throughput(&self, io_bytes: i64) -> f64 {
+        if self.job_runtime <= 0 {
+            return 0.0;
+        }
+        to_mib(io_bytes as f64) / (self.job_runtime as f64 / 1000.0)
+    }
+}
+
+impl ClatNs {
+    /// Number of IOs with a completion latency above `threshold_ns`
+    ///
+    /// Counted from the latency histogram if fio reported it (`--output-format=json+`), otherwise
+    /// estimated from the percentiles as the IOs above the highest percentile within the threshold,
+    /// ie. an upper bound with at least one IO if `max` is above the threshold
+    pub fn count_above(&self, threshold_ns: f64) -> f64 {
+        if self.n == 0 || self.max as f64 <= threshold_ns {
+            return 0.0;
+        }
+
+        if let Some(bins) = &self.bins {
+            return bins
+                .iter()
+                .filter_map(|(latency, count)| Some((latency.parse::<f64>().ok()?, *count)))
+                .filter(|(latency, _)| *latency > threshold_ns)
+                .map(|(_, count)| count as f64)
+                .sum();
+        }
+
+        let below = self
+            .percentile
+            .as_ref()
+            .map(|x| {
+                x.values()
+                    .into_iter()
+                    .filter(|(_, latency)| *latency as f64 <= threshold_ns)
+                    .map(|(percentile, _)| percentile)
+                    .fold(0.0, f64::max)
+            })
+            .unwrap_or(0.0);
+        (self.n as f64 * (100.0 - below) / 100.0).max(1.0)
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Percentile {
     #[serde(rename = "1.000000")]
@@ -209,6 +280,31 @@ pub struct Percentile {
     pub n99_990000: i64,
 }
 
+impl Percentile {
+    /// Pairs of (percentile, latency)
+    pub fn values(&self) -> [(f64, i64); 17] {
+        [
+            (1.0, self.n1_000000),
+            (5.0, self.n5_000000),
+            (10.0, self.n10_000000),
+            (20.0, self.n20_000000),
+            (30.0, self.n30_000000),
+            (40.0, self.n40_000000),
+            (50.0, self.n50_000000),
+            (60.0, self.n60_000000),
+            (70.0, self.n70_000000),
+            (80.0, self.n80_000000),
+            (90.0, self.n90_000000),
+            (95.0, self.n95_000000),
+            (99.0, self.n99_000000),
+            (99.5, self.n99_500000),
+            (99.9, self.n99_900000),
+            (99.95, self.n99_950000),
+            (99.99, self.n99_990000),
+        ]
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Trim {
     pub io_bytes: i64,
@@ -220,6 +316,8 @@ pub struct Trim {
     pub total_ios: i64,
     pub short_ios: i64,
     pub drop_ios: i64,
+    /// Missing for synchronous io engines
+    #[serde(default)]
     pub slat_ns: SlatNs,
     pub clat_ns: ClatNs,
     pub lat_ns: LatNs,
@@ -386,3 +484,94 @@ pub struct DiskUtil {
     pub in_queue: i64,
     pub util: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(runtime_ms: i64, read_bytes: i64, write_bytes: i64) -> Job {
+        Job {
+            job_runtime: runtime_ms,
+            read: Read {
+                io_bytes: read_bytes,
+                ..Default::default()
+            },
+            write: Write {
+                io_bytes: write_bytes,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn throughput_uses_runtime_of_each_job() {
+        const MIB: i64 = 1024 * 1024;
+        // the second job finished its fixed size after half the runtime of the first one
+        let result = FioResult {
+            jobs: vec![job(10_000, 1000 * MIB, 0), job(5_000, 0, 1000 * MIB)],
+            ..Default::default()
+        };
+
+        let (read, write) = result.throughput();
+        assert!((read - 100.0).abs() < 1e-9);
+        assert!((write - 200.0).abs() < 1e-9);
+        assert!((result.total_throughput() - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn throughput_skips_jobs_without_runtime() {
+        let result = FioResult {
+            jobs: vec![job(0, 1024, 1024), job(1000, 1024 * 1024, 0)],
+            ..Default::default()
+        };
+        assert!((result.total_throughput() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn counts_ios_above_threshold() {
+        let histogram = ClatNs {
+            max: 300,
+            n: 10,
+            bins: Some(HashMap::from([
+                ("100".to_owned(), 5),
+                ("200".to_owned(), 3),
+                ("300".to_owned(), 2),
+            ])),
+            ..Default::default()
+        };
+        // IOs at exactly the threshold are not above it
+        assert_eq!(histogram.count_above(200.0), 2.0);
+        assert_eq!(histogram.count_above(199.0), 5.0);
+        assert_eq!(histogram.count_above(300.0), 0.0);
+
+        let percentiles = ClatNs {
+            max: 600,
+            n: 1000,
+            percentile: Some(Percentile {
+                n1_000000: 100,
+                n5_000000: 100,
+                n10_000000: 100,
+                n20_000000: 100,
+                n30_000000: 100,
+                n40_000000: 100,
+                n50_000000: 100,
+                n60_000000: 100,
+                n70_000000: 100,
+                n80_000000: 100,
+                n90_000000: 100,
+                n95_000000: 100,
+                n99_000000: 500,
+                n99_500000: 500,
+                n99_900000: 500,
+                n99_950000: 500,
+                n99_990000: 500,
+            }),
+            ..Default::default()
+        };
+        assert!((percentiles.count_above(100.0) - 50.0).abs() < 1e-9);
+        // at least one IO is above the threshold if the max is
+        assert_eq!(percentiles.count_above(500.0), 1.0);
+        assert_eq!(percentiles.count_above(600.0), 0.0);
+    }
+}
@@ -121,11 +121,28 @@ impl Bench for H5Bench {
     ) -> Result<()> {
         let mountpoint = data_dir.join("mountpoint");
 
+        let should_format = !mountpoint.exists();
+        #[cfg(feature = "prefill")]
+        let should_format = match &self.prefill {
+            Some(spec) => {
+                should_format
+                    && !fio::Fio::prefill_device(
+                        spec,
+                        settings.device.single()?,
+                        &self.base_fs,
+                        should_format,
+                        config,
+                        settings,
+                    )
+                    .await?
+            }
+            None => should_format,
+        };
         mount_fs(
             &mountpoint,
-            &settings.device,
+            settings.device.single()?,
             &self.base_fs,
-            !mountpoint.exists(),
+            should_format,
             None::<String>,
         )
         .await?;
@@ -170,9 +187,18 @@ impl Bench for H5Bench {
         chown_user(data_dir).await?;
 
         #[cfg(feature = "prefill")]
-        if let Some(size) = &self.prefill {
+        if let Some(size) = &self.prefill
+            && fio::Fio::device_prefill_percent(size)?.is_none()
+        {
             let prefill_file = mountpoint.join("prefill");
-            fio::Fio::prefill(&prefill_file, size, config, settings).await?;
+            fio::Fio::prefill(
+                &prefill_file,
+                settings.device.single()?,
+                size,
+                config,
+                settings,
+            )
+            .await?;
         }
         Ok(())
     }
@@ -197,7 +223,11 @@ impl Bench for H5Bench {
         }
         drop(dir);
 
-        _ = simple_command_with_output_no_dir("umount", &[&settings.device]).await?;
+        _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
         Ok(())
     }
+
+    fn write_hint(&self) -> bool {
+        true
+    }
 }
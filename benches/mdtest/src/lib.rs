@@ -0,0 +1,200 @@
+use std::path::Path;
+
+use common::{
+    RUN_NONROOT,
+    bench::{Bench, BenchArgs, Cmd, CmdsResult, OUTPUT_FILENAME, read_stdout, run_parser},
+    config::{Config, Settings},
+    summary::Throughput,
+    util::{Filesystem, chown_user, mount_fs, read_json_file, simple_command_with_output_no_dir},
+};
+use eyre::{Context, ContextCompat, Result, bail};
+use result::{MdtestResult, parse_output};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{create_dir_all, write};
+
+pub mod result;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mdtest {
+    /// Number of files/directories created by each rank
+    pub items: usize,
+    /// Number of iterations mdtest repeats the test for
+    pub iterations: usize,
+    /// MPI ranks to sweep over
+    pub ranks: Vec<usize>,
+    /// Filesystem to create on the SSD
+    pub fs: Filesystem,
+    /// Additional arguments passed to mdtest as is
+    pub extra_options: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MdtestConfig {
+    /// Path to the mdtest binary
+    pub mdtest: String,
+}
+
+#[typetag::serde]
+impl BenchArgs for MdtestConfig {
+    fn name(&self) -> &'static str {
+        "mdtest"
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Bench for Mdtest {
+    fn name(&self) -> &'static str {
+        "mdtest"
+    }
+
+    fn default_bench() -> Box<dyn Bench> {
+        Box::new(Self::default())
+    }
+
+    fn default_bench_args(&self) -> Box<dyn BenchArgs> {
+        Box::new(MdtestConfig::default())
+    }
+
+    fn runtime_estimate(&self) -> Result<u64> {
+        Ok(60_000 * self.ranks.len() as u64)
+    }
+
+    fn cmds(
+        &self,
+        settings: &Settings,
+        bench_args: &dyn BenchArgs,
+        _name: &str,
+    ) -> Result<CmdsResult> {
+        let bench_args = bench_args
+            .downcast_ref::<MdtestConfig>()
+            .context("Invalid bench args, expected args for mdtest")?;
+        if self.ranks.is_empty() {
+            bail!("mdtest requires at least one rank");
+        }
+
+        let cmds = self
+            .ranks
+            .iter()
+            .enumerate()
+            .map(|(idx, rank)| {
+                let mut args = vec!["mpirun".to_owned(), "-np".to_owned(), rank.to_string()];
+                if let Some(numa) = &settings.numa {
+                    args.extend([
+                        "numactl".to_owned(),
+                        format!("--cpunodebind={}", numa.cpunodebind),
+                        format!("--membind={}", numa.membind),
+                    ]);
+                }
+                args.extend([
+                    bench_args.mdtest.clone(),
+                    "-n".to_owned(),
+                    self.items.to_string(),
+                    "-i".to_owned(),
+                    self.iterations.to_string(),
+                ]);
+                if let Some(extra_options) = &self.extra_options {
+                    args.extend(extra_options.iter().cloned());
+                }
+
+                Cmd {
+                    args,
+                    idx,
+                    bench_obj: Box::new(Mdtest {
+                        ranks: vec![*rank],
+                        ..self.clone()
+                    }),
+                }
+            })
+            .collect();
+
+        Ok(CmdsResult {
+            program: RUN_NONROOT.to_owned(),
+            cmds,
+        })
+    }
+
+    fn add_path_args(&self, args: &mut Vec<String>, final_results_dir: &Path) {
+        let test_dir = final_results_dir
+            .parent()
+            .unwrap()
+            .join("mountpoint")
+            .join("mdtest")
+            .to_string_lossy()
+            .to_string();
+        args.push("-d".to_owned());
+        args.push(test_dir);
+    }
+
+    async fn experiment_init(
+        &self,
+        data_dir: &Path,
+        settings: &Settings,
+        _bench_args: &dyn BenchArgs,
+        _last_experiment: &Option<Box<dyn Bench>>,
+        _config: &Config,
+        _final_results_dir: &Path,
+    ) -> Result<()> {
+        // metadata performance depends on the state of the filesystem, so every experiment
+        // starts from a freshly formatted one
+        let mountpoint = data_dir.join("mountpoint");
+        mount_fs(
+            &mountpoint,
+            settings.device.single()?,
+            &self.fs,
+            true,
+            None::<String>,
+        )
+        .await?;
+
+        let test_dir = mountpoint.join("mdtest");
+        create_dir_all(&test_dir).await?;
+        chown_user(&mountpoint).await?;
+        chown_user(&test_dir).await?;
+        Ok(())
+    }
+
+    async fn parse_results(&self, final_results_dir: &Path, parser: Option<&str>) -> Result<()> {
+        let results_file = final_results_dir.join("results.json");
+        if let Some(parser) = parser {
+            run_parser(parser, final_results_dir).await?;
+            read_json_file::<MdtestResult>(&results_file)
+                .await
+                .context(format!("Invalid results.json written by {parser}"))?;
+            return Ok(());
+        }
+
+        let data = parse_output(&read_stdout(final_results_dir).await?).context(format!(
+            "Failed to parse mdtest output, see {}",
+            final_results_dir.join(OUTPUT_FILENAME).display()
+        ))?;
+        write(results_file, serde_json::to_string(&data)?).await?;
+        Ok(())
+    }
+
+    async fn post_experiment(
+        &self,
+        _data_dir: &Path,
+        _final_results_dir: &Path,
+        settings: &Settings,
+        _bench_args: &dyn BenchArgs,
+    ) -> Result<()> {
+        _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
+        Ok(())
+    }
+
+    async fn primary_metric(&self, final_results_dir: &Path) -> Result<Option<Throughput>> {
+        let results =
+            read_json_file::<MdtestResult>(final_results_dir.join("results.json")).await?;
+        Ok(results.rate("File creation").map(|value| Throughput {
+            value,
+            unit: "file creations/s".to_owned(),
+        }))
+    }
+
+    fn write_hint(&self) -> bool {
+        true
+    }
+}
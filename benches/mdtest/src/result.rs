@@ -0,0 +1,86 @@
+use eyre::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MdtestRate {
+    /// As printed by mdtest, ie. `File creation`
+    pub operation: String,
+    pub max: f64,
+    pub min: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MdtestResult {
+    /// Operations per second, over all ranks
+    pub rates: Vec<MdtestRate>,
+}
+
+impl MdtestResult {
+    /// Mean rate of an operation, in operations per second
+    pub fn rate(&self, operation: &str) -> Option<f64> {
+        self.rates
+            .iter()
+            .find(|x| x.operation == operation)
+            .map(|x| x.mean)
+    }
+}
+
+impl MdtestRate {
+    /// Parses a row of the summary table, ie. `File creation : 1.000 1.000 1.000 0.000`
+    pub fn from_line(line: &str) -> Option<Self> {
+        let (operation, values) = line.split_once(':')?;
+        let values = values
+            .split_whitespace()
+            .map(|x| x.parse::<f64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        if values.len() != 4 {
+            return None;
+        }
+
+        Some(Self {
+            operation: operation.trim().to_owned(),
+            max: values[0],
+            min: values[1],
+            mean: values[2],
+            std_dev: values[3],
+        })
+    }
+}
+
+/// Parses the `SUMMARY rate` table of the mdtest output
+pub fn parse_output(output: &str) -> Result<MdtestResult> {
+    let mut rates = Vec::new();
+    let mut in_summary = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("SUMMARY rate") {
+            in_summary = true;
+            continue;
+        }
+        if !in_summary {
+            continue;
+        }
+        if line.starts_with("SUMMARY") || (line.starts_with("--") && line.contains("finished")) {
+            break;
+        }
+        if line.is_empty() {
+            if rates.is_empty() {
+                continue;
+            }
+            break;
+        }
+
+        if let Some(rate) = MdtestRate::from_line(line) {
+            rates.push(rate);
+        }
+    }
+
+    if rates.is_empty() {
+        bail!("No SUMMARY rate table found in mdtest output");
+    }
+    Ok(MdtestResult { rates })
+}
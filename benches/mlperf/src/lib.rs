@@ -170,7 +170,7 @@ impl Bench for Mlperf {
 
         mount_fs(
             &mlperf_mount,
-            &settings.device,
+            settings.device.single()?,
             &self.fs,
             !should_format,
             None::<String>,
@@ -216,7 +216,7 @@ impl Bench for Mlperf {
         settings: &Settings,
         _bench_args: &dyn BenchArgs,
     ) -> Result<()> {
-        _ = simple_command_with_output_no_dir("umount", &[&settings.device]).await?;
+        _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
         Ok(())
     }
 }
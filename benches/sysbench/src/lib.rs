@@ -0,0 +1,306 @@
+use std::path::Path;
+
+use common::{
+    RUN_NONROOT,
+    bench::{Bench, BenchArgs, Cmd, CmdsResult, OUTPUT_FILENAME, read_stdout, run_parser},
+    config::{Config, Settings},
+    summary::Throughput,
+    util::{Filesystem, chown_user, mount_fs, read_json_file, simple_command_with_output_no_dir},
+};
+use eyre::{Context, ContextCompat, Result, bail};
+use result::{SysbenchResult, parse_output};
+use serde::{Deserialize, Serialize};
+use tokio::fs::write;
+use tracing::info;
+
+pub mod result;
+
+/// Directory of the postgres socket, the path of a socket on the mountpoint would be too long
+const SOCKET_DIR: &str = "/tmp";
+/// Port of the postgres server, differs from the default so an installed server is not used
+const PORT: &str = "54329";
+/// Name of the database, and of the user sysbench connects as
+const DATABASE: &str = "sbtest";
+const DEFAULT_TIME: u64 = 60;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sysbench {
+    /// Number of tables sysbench creates
+    pub tables: usize,
+    /// Number of rows of each table
+    pub table_size: usize,
+    /// Client threads to sweep over
+    pub threads: Vec<usize>,
+    /// Duration of each run in seconds, defaults to 60
+    pub time: Option<u64>,
+    /// Filesystem to create on the SSD, the database is kept between experiments with the same
+    /// tables and table size
+    pub fs: Filesystem,
+    pub fs_mount_opts: Option<String>,
+    /// Additional arguments passed to sysbench as is
+    pub extra_options: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SysbenchConfig {
+    /// Path to the sysbench binary
+    pub sysbench: String,
+    /// Directory of the postgres binaries, ie. `initdb` and `pg_ctl`
+    pub pg_bin: String,
+}
+
+#[typetag::serde]
+impl BenchArgs for SysbenchConfig {
+    fn name(&self) -> &'static str {
+        "sysbench"
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Bench for Sysbench {
+    fn name(&self) -> &'static str {
+        "sysbench"
+    }
+
+    fn default_bench() -> Box<dyn Bench> {
+        Box::new(Self::default())
+    }
+
+    fn default_bench_args(&self) -> Box<dyn BenchArgs> {
+        Box::new(SysbenchConfig::default())
+    }
+
+    fn runtime_estimate(&self) -> Result<u64> {
+        Ok(self.time.unwrap_or(DEFAULT_TIME) * 1000 * self.threads.len() as u64)
+    }
+
+    fn cmds(
+        &self,
+        settings: &Settings,
+        bench_args: &dyn BenchArgs,
+        _name: &str,
+    ) -> Result<CmdsResult> {
+        let bench_args = bench_args
+            .downcast_ref::<SysbenchConfig>()
+            .context("Invalid bench args, expected args for sysbench")?;
+        if self.threads.is_empty() {
+            bail!("sysbench requires at least one thread count");
+        }
+
+        let cmds = self
+            .threads
+            .iter()
+            .enumerate()
+            .map(|(idx, threads)| {
+                let mut args = Vec::new();
+                if let Some(numa) = &settings.numa {
+                    args.extend([
+                        "numactl".to_owned(),
+                        format!("--cpunodebind={}", numa.cpunodebind),
+                        format!("--membind={}", numa.membind),
+                    ]);
+                }
+                args.extend(self.sysbench_args(&bench_args.sysbench));
+                args.extend([
+                    format!("--threads={threads}"),
+                    format!("--time={}", self.time.unwrap_or(DEFAULT_TIME)),
+                ]);
+                if let Some(extra_options) = &self.extra_options {
+                    args.extend(extra_options.iter().cloned());
+                }
+                args.push("run".to_owned());
+
+                Cmd {
+                    args,
+                    idx,
+                    bench_obj: Box::new(Sysbench {
+                        threads: vec![*threads],
+                        ..self.clone()
+                    }),
+                }
+            })
+            .collect();
+
+        Ok(CmdsResult {
+            program: RUN_NONROOT.to_owned(),
+            cmds,
+        })
+    }
+
+    async fn experiment_init(
+        &self,
+        data_dir: &Path,
+        settings: &Settings,
+        bench_args: &dyn BenchArgs,
+        last_experiment: &Option<Box<dyn Bench>>,
+        _config: &Config,
+        _final_results_dir: &Path,
+    ) -> Result<()> {
+        let bench_args = bench_args
+            .downcast_ref::<SysbenchConfig>()
+            .context("Invalid bench args, expected args for sysbench")?;
+        let mountpoint = data_dir.join("mountpoint");
+        let pgdata = mountpoint.join("pgdata");
+
+        // the database is on the SSD, which is unmounted after every experiment
+        let should_prepare = !mountpoint.exists() || !self.is_same_database(last_experiment);
+        mount_fs(
+            &mountpoint,
+            settings.device.single()?,
+            &self.fs,
+            should_prepare,
+            self.fs_mount_opts.clone(),
+        )
+        .await?;
+        chown_user(&mountpoint).await?;
+
+        let pg_bin = Path::new(&bench_args.pg_bin);
+        if should_prepare {
+            run_nonroot(&[
+                pg_bin.join("initdb").to_str().unwrap(),
+                "-D",
+                pgdata.to_str().unwrap(),
+                "-U",
+                DATABASE,
+                "--auth=trust",
+            ])
+            .await
+            .context("Initializing postgres database")?;
+        }
+
+        run_nonroot(&[
+            pg_bin.join("pg_ctl").to_str().unwrap(),
+            "-D",
+            pgdata.to_str().unwrap(),
+            "-l",
+            mountpoint.join("postgres.log").to_str().unwrap(),
+            "-o",
+            &format!("-k {SOCKET_DIR} -p {PORT} -c listen_addresses=''"),
+            "-w",
+            "start",
+        ])
+        .await
+        .context("Starting postgres")?;
+
+        if should_prepare {
+            run_nonroot(&[
+                pg_bin.join("createdb").to_str().unwrap(),
+                "-h",
+                SOCKET_DIR,
+                "-p",
+                PORT,
+                "-U",
+                DATABASE,
+                DATABASE,
+            ])
+            .await
+            .context("Creating sysbench database")?;
+
+            info!(
+                "Preparing {} tables of {} rows",
+                self.tables, self.table_size
+            );
+            let mut args = self.sysbench_args(&bench_args.sysbench);
+            args.push("prepare".to_owned());
+            run_nonroot(&args.iter().map(String::as_str).collect::<Vec<_>>())
+                .await
+                .context("Preparing sysbench tables")?;
+        }
+        Ok(())
+    }
+
+    async fn parse_results(&self, final_results_dir: &Path, parser: Option<&str>) -> Result<()> {
+        let results_file = final_results_dir.join("results.json");
+        if let Some(parser) = parser {
+            run_parser(parser, final_results_dir).await?;
+            read_json_file::<SysbenchResult>(&results_file)
+                .await
+                .context(format!("Invalid results.json written by {parser}"))?;
+            return Ok(());
+        }
+
+        let data = parse_output(&read_stdout(final_results_dir).await?).context(format!(
+            "Failed to parse sysbench output, see {}",
+            final_results_dir.join(OUTPUT_FILENAME).display()
+        ))?;
+        write(results_file, serde_json::to_string(&data)?).await?;
+        Ok(())
+    }
+
+    async fn post_experiment(
+        &self,
+        data_dir: &Path,
+        _final_results_dir: &Path,
+        settings: &Settings,
+        bench_args: &dyn BenchArgs,
+    ) -> Result<()> {
+        let bench_args = bench_args
+            .downcast_ref::<SysbenchConfig>()
+            .context("Invalid bench args, expected args for sysbench")?;
+        run_nonroot(&[
+            Path::new(&bench_args.pg_bin)
+                .join("pg_ctl")
+                .to_str()
+                .unwrap(),
+            "-D",
+            data_dir.join("mountpoint/pgdata").to_str().unwrap(),
+            "-m",
+            "fast",
+            "-w",
+            "stop",
+        ])
+        .await
+        .context("Stopping postgres")?;
+        _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
+        Ok(())
+    }
+
+    async fn primary_metric(&self, final_results_dir: &Path) -> Result<Option<Throughput>> {
+        let results =
+            read_json_file::<SysbenchResult>(final_results_dir.join("results.json")).await?;
+        Ok(Some(Throughput {
+            value: results.tps,
+            unit: "transactions/s".to_owned(),
+        }))
+    }
+
+    fn write_hint(&self) -> bool {
+        true
+    }
+}
+
+impl Sysbench {
+    /// Arguments of the `oltp_read_write` test shared by `prepare` and `run`
+    fn sysbench_args(&self, sysbench: &str) -> Vec<String> {
+        vec![
+            sysbench.to_owned(),
+            "oltp_read_write".to_owned(),
+            "--db-driver=pgsql".to_owned(),
+            format!("--pgsql-host={SOCKET_DIR}"),
+            format!("--pgsql-port={PORT}"),
+            format!("--pgsql-user={DATABASE}"),
+            format!("--pgsql-db={DATABASE}"),
+            format!("--tables={}", self.tables),
+            format!("--table-size={}", self.table_size),
+        ]
+    }
+
+    /// Whether the last experiment used the same database, so it does not need to be prepared again
+    fn is_same_database(&self, last_experiment: &Option<Box<dyn Bench>>) -> bool {
+        last_experiment
+            .as_ref()
+            .and_then(|x| x.downcast_ref::<Sysbench>())
+            .is_some_and(|x| {
+                x.tables == self.tables && x.table_size == self.table_size && x.fs == self.fs
+            })
+    }
+}
+
+/// Runs a command as the user that started nvme-energy-bench, postgres refuses to run as root
+async fn run_nonroot(args: &[&str]) -> Result<()> {
+    _ = simple_command_with_output_no_dir(RUN_NONROOT, args).await?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use eyre::{ContextCompat, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SysbenchResult {
+    pub transactions: u64,
+    /// Transactions per second
+    pub tps: f64,
+    pub queries: u64,
+    /// Queries per second
+    pub qps: f64,
+    pub ignored_errors: u64,
+    pub latency_min_ms: f64,
+    pub latency_avg_ms: f64,
+    pub latency_max_ms: f64,
+    pub latency_p95_ms: f64,
+}
+
+/// Value of a summary row, ie. `transactions: 10000 (166.60 per sec.)`, and its rate if it has one
+fn field<'a>(output: &'a str, name: &str) -> Option<(&'a str, Option<&'a str>)> {
+    let line = output
+        .lines()
+        .map(str::trim)
+        .find(|x| x.strip_prefix(name).is_some_and(|x| x.starts_with(':')))?;
+    let mut values = line[name.len() + 1..].split_whitespace();
+    let value = values.next()?;
+    let rate = values.next().map(|x| x.trim_start_matches('('));
+    Some((value, rate))
+}
+
+fn parse<T: std::str::FromStr>(output: &str, name: &str) -> Result<T> {
+    field(output, name)
+        .and_then(|(value, _)| value.parse().ok())
+        .context(format!("Missing {name} in sysbench output"))
+}
+
+fn parse_rate(output: &str, name: &str) -> Result<f64> {
+    field(output, name)
+        .and_then(|(_, rate)| rate?.parse().ok())
+        .context(format!("Missing {name} per second in sysbench output"))
+}
+
+/// Parses the summary sysbench prints after a run
+pub fn parse_output(output: &str) -> Result<SysbenchResult> {
+    // the latency rows are only unique after the latency header
+    let latency = output
+        .split_once("Latency (ms):")
+        .context("No latency statistics found in sysbench output")?
+        .1;
+
+    Ok(SysbenchResult {
+        transactions: parse(output, "transactions")?,
+        tps: parse_rate(output, "transactions")?,
+        queries: parse(output, "queries")?,
+        qps: parse_rate(output, "queries")?,
+        ignored_errors: parse(output, "ignored errors")?,
+        latency_min_ms: parse(latency, "min")?,
+        latency_avg_ms: parse(latency, "avg")?,
+        latency_max_ms: parse(latency, "max")?,
+        latency_p95_ms: parse(latency, "95th percentile")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "SQL statistics:
+    queries performed:
+        read:                            140000
+        write:                           40000
+        other:                           20000
+        total:                           200000
+    transactions:                        10000  (166.60 per sec.)
+    queries:                             200000 (3332.03 per sec.)
+    ignored errors:                      0      (0.00 per sec.)
+    reconnects:                          0      (0.00 per sec.)
+
+General statistics:
+    total time:                          60.0180s
+    total number of events:              10000
+
+Latency (ms):
+         min:                                    2.30
+         avg:                                    6.00
+         max:                                   42.21
+         95th percentile:                        8.43
+         sum:                                59979.38
+";
+
+    #[test]
+    fn parses_summary() {
+        let result = parse_output(OUTPUT).unwrap();
+        assert_eq!(result.transactions, 10000);
+        assert_eq!(result.tps, 166.60);
+        assert_eq!(result.queries, 200000);
+        assert_eq!(result.qps, 3332.03);
+        assert_eq!(result.ignored_errors, 0);
+        assert_eq!(result.latency_min_ms, 2.30);
+        assert_eq!(result.latency_max_ms, 42.21);
+        assert_eq!(result.latency_p95_ms, 8.43);
+        assert!(parse_output("FATAL: unable to connect").is_err());
+    }
+}
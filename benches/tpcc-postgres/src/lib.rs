@@ -1,7 +1,7 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use common::{
@@ -9,15 +9,23 @@ use common::{
     config::{Config, Settings},
     util::{Filesystem, mount_fs, simple_command_with_output, simple_command_with_output_no_dir},
 };
-use eyre::{ContextCompat, Result, bail};
+use eyre::{Context, ContextCompat, Result, bail};
 use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
-use tokio::fs::{DirEntry, copy, read_dir, remove_file, write};
-use tracing::debug;
+use tokio::{
+    fs::{DirEntry, copy, read_dir, read_to_string, remove_file, write},
+    time::sleep,
+};
+use tracing::{debug, info, warn};
 
 pub mod result;
 
 const DOCKER: &str = "docker";
+/// Attempts of every step of the SSH key exchange between the containers
+const KEY_EXCHANGE_ATTEMPTS: usize = 3;
+/// Attempts to connect to postgres after the containers started, [`RETRY_DELAY`] apart
+const POSTGRES_READY_ATTEMPTS: usize = 30;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -138,7 +146,7 @@ impl Bench for TpccPostgres {
 
         mount_fs(
             &postgres_mount,
-            &settings.device,
+            settings.device.single()?,
             &self.filesystem,
             should_load,
             self.fs_mount_opts.clone(),
@@ -180,28 +188,72 @@ impl Bench for TpccPostgres {
         )
         .await?;
 
-        let helper = InitHelper {
+        let helper = &InitHelper {
             dir: common_dir.clone(),
         };
-        helper.exec("tpcc-host", &["/gen-key.sh"]).await?;
-        helper
-            .cp("tpcc-host:/root/.ssh/id_rsa.pub", "tpcc-host.pub")
-            .await?;
+        retry(
+            "Generate SSH key in tpcc-host",
+            KEY_EXCHANGE_ATTEMPTS,
+            move || async move {
+                helper.exec("tpcc-host", &["/gen-key.sh"]).await?;
+                helper
+                    .cp("tpcc-host:/root/.ssh/id_rsa.pub", "tpcc-host.pub")
+                    .await
+            },
+        )
+        .await?;
+        let public_key = read_to_string(common_dir.join("tpcc-host.pub")).await?;
+        let public_key = public_key.trim();
 
         for i in 1..=self.num_clients[0] {
-            helper
-                .cp("tpcc-host.pub", &format!("tpcc-{i}:/tmp/id_rsa.pub"))
-                .await?;
-            helper.exec(&format!("tpcc-{i}"), &["/add-key.sh"]).await?;
+            let client = &format!("tpcc-{i}");
+            retry(
+                &format!("Add SSH key to {client}"),
+                KEY_EXCHANGE_ATTEMPTS,
+                move || async move {
+                    helper
+                        .cp("tpcc-host.pub", &format!("{client}:/tmp/id_rsa.pub"))
+                        .await?;
+                    helper.exec(client, &["/add-key.sh"]).await?;
+                    helper
+                        .exec(
+                            client,
+                            &["grep", "-qxF", public_key, "/root/.ssh/authorized_keys"],
+                        )
+                        .await
+                        .context("Key is missing from authorized_keys")
+                },
+            )
+            .await?;
         }
 
         remove_file(common_dir.join("tpcc-host.pub")).await?;
-        helper
-            .exec(
-                "tpcc-host",
-                &["/set-keys.sh", &self.num_clients[0].to_string()],
+        retry(
+            "Scan SSH host keys of the clients",
+            KEY_EXCHANGE_ATTEMPTS,
+            move || async move {
+                helper
+                    .exec(
+                        "tpcc-host",
+                        &["/set-keys.sh", &self.num_clients[0].to_string()],
+                    )
+                    .await
+            },
+        )
+        .await?;
+        for i in 1..=self.num_clients[0] {
+            let client = &format!("tpcc-{i}");
+            retry(
+                &format!("SSH from tpcc-host to {client}"),
+                KEY_EXCHANGE_ATTEMPTS,
+                move || async move {
+                    helper
+                        .exec("tpcc-host", &["ssh", "-o", "BatchMode=yes", client, "true"])
+                        .await
+                },
             )
             .await?;
+        }
 
         helper
             .exec(
@@ -218,7 +270,30 @@ impl Bench for TpccPostgres {
             )
             .await?;
 
+        retry(
+            "Connect to postgres",
+            POSTGRES_READY_ATTEMPTS,
+            move || async move {
+                helper
+                    .exec(
+                        "postgres",
+                        &[
+                            "pg_isready",
+                            "-h",
+                            "postgres",
+                            "-U",
+                            "admin",
+                            "-d",
+                            "benchbase",
+                        ],
+                    )
+                    .await
+            },
+        )
+        .await?;
+
         if should_load {
+            info!("Loading {} warehouses", self.warehouses);
             helper
                 .exec(
                     "tpcc-host",
@@ -264,7 +339,7 @@ impl Bench for TpccPostgres {
             }
             None => bail!("No tpcc-postgres logs found"),
         }
-        _ = simple_command_with_output_no_dir("umount", &[&settings.device]).await?;
+        _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
         Ok(())
     }
 }
@@ -291,6 +366,31 @@ impl InitHelper {
     }
 }
 
+/// Runs a setup step up to `attempts` times, [`RETRY_DELAY`] apart, logging the outcome of
+/// every attempt
+async fn retry<Fut: Future<Output = Result<()>>>(
+    step: &str,
+    attempts: usize,
+    mut f: impl FnMut() -> Fut,
+) -> Result<()> {
+    for attempt in 1..=attempts {
+        match f().await {
+            Ok(()) => {
+                info!("{step}: done");
+                return Ok(());
+            }
+            Err(err) if attempt < attempts => {
+                warn!("{step}: attempt {attempt}/{attempts} failed: {err:#}");
+                sleep(RETRY_DELAY).await;
+            }
+            Err(err) => {
+                return Err(err).context(format!("{step} failed after {attempts} attempts"));
+            }
+        }
+    }
+    bail!("{step} was not attempted")
+}
+
 async fn is_dir(entry: &DirEntry) -> Result<bool> {
     let ft = entry.file_type().await?;
     Ok(ft.is_dir())
@@ -1,26 +1,21 @@
-use std::{
-    collections::HashMap,
-    path::Path,
-    process::Stdio,
-    time::{Duration, Instant},
-};
+use std::{collections::HashMap, path::Path, process::Stdio, time::Duration};
 
 use common::{
-    bench::{Bench, BenchArgs, Cmd, CmdsResult, trace_nvme_calls},
+    bench::{
+        Bench, BenchArgs, Cmd, CmdsResult, MarkerWriter, OUTPUT_FILENAME, read_stdout, run_parser,
+        set_running_benchmark, trace_nvme_calls, write_output,
+    },
     config::{Config, Settings},
+    perf::{perf_stat_command, write_perf_json},
     sensor::SensorRequest,
-    util::{Filesystem, mount_fs, simple_command_with_output_no_dir},
+    summary::Throughput,
+    util::{Filesystem, mount_fs, read_json_file, simple_command_with_output_no_dir},
 };
 use eyre::{Context, ContextCompat, Result, bail};
 use flume::Sender;
-use result::parse_output;
+use result::{YcsbMetrics, parse_output};
 use serde::{Deserialize, Serialize};
-use tokio::{
-    fs::{File, write},
-    io::AsyncWriteExt,
-    process::Command,
-    time::sleep,
-};
+use tokio::{fs::write, process::Command, time::sleep};
 use tracing::debug;
 
 pub mod result;
@@ -28,7 +23,12 @@ pub mod result;
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Ycsb {
+    /// Workload of the load and run commands, set to the workload of the command if
+    /// `workload_files` is used
+    #[serde(default)]
     pub workload_file: String,
+    /// Workloads to sweep instead of `workload_file`, one load and run command per workload
+    pub workload_files: Option<Vec<String>>,
     pub data_var_name: String,
     pub vars: Option<HashMap<String, String>>,
     pub db: String,
@@ -107,42 +107,50 @@ impl Bench for Ycsb {
             ]);
         }
 
-        let mut continued_args = vec![self.db.clone(), "-P".to_owned(), self.workload_file.clone()];
+        let workload_files = match &self.workload_files {
+            Some(files) => {
+                if files.is_empty() {
+                    bail!("workload_files requires at least one workload");
+                }
+                if !self.workload_file.is_empty() {
+                    bail!("Set either workload_file or workload_files, not both");
+                }
+                files.clone()
+            }
+            None if self.workload_file.is_empty() => bail!("Missing workload_file"),
+            None => vec![self.workload_file.clone()],
+        };
 
-        if let Some(threads) = self.threads {
-            continued_args.push("-threads".to_owned());
-            continued_args.push(threads.to_string());
-        }
+        let mut cmds = Vec::new();
+        for workload_file in workload_files {
+            let mut continued_args = vec![self.db.clone(), "-P".to_owned(), workload_file.clone()];
 
-        for (k, v) in self.vars.as_ref().unwrap_or(&HashMap::new()).iter() {
-            continued_args.push("-p".to_owned());
-            continued_args.push(format!("{k}={v}"));
-        }
+            if let Some(threads) = self.threads {
+                continued_args.push("-threads".to_owned());
+                continued_args.push(threads.to_string());
+            }
 
-        let mut load = args.clone();
-        load.push("load".to_owned());
-        load.extend(continued_args.clone());
-
-        let mut run = args.clone();
-        run.push("run".to_owned());
-        run.extend(continued_args.clone());
-
-        let mut load_obj = self.clone();
-        load_obj._ycsb_op_type = Some(OpType::Load);
-        let mut run_obj = self.clone();
-        run_obj._ycsb_op_type = Some(OpType::Run);
-        let cmds = vec![
-            Cmd {
-                args: load,
-                idx: 0,
-                bench_obj: Box::new(load_obj),
-            },
-            Cmd {
-                args: run,
-                idx: 1,
-                bench_obj: Box::new(run_obj),
-            },
-        ];
+            for (k, v) in self.vars.as_ref().unwrap_or(&HashMap::new()).iter() {
+                continued_args.push("-p".to_owned());
+                continued_args.push(format!("{k}={v}"));
+            }
+
+            for (op_type, op) in [(OpType::Load, "load"), (OpType::Run, "run")] {
+                let mut op_args = args.clone();
+                op_args.push(op.to_owned());
+                op_args.extend(continued_args.clone());
+
+                let mut bench_obj = self.clone();
+                bench_obj.workload_file = workload_file.clone();
+                bench_obj.workload_files = None;
+                bench_obj._ycsb_op_type = Some(op_type);
+                cmds.push(Cmd {
+                    args: op_args,
+                    idx: cmds.len(),
+                    bench_obj: Box::new(bench_obj),
+                });
+            }
+        }
 
         Ok(CmdsResult { program, cmds })
     }
@@ -160,25 +168,45 @@ impl Bench for Ycsb {
         last_experiment: &Option<Box<dyn Bench>>,
     ) -> Result<()> {
         let ycsb_mount = final_results_dir.join("ycsb-mount");
+        let should_format = !self.is_same_experiment(last_experiment)?;
+        #[cfg(feature = "prefill")]
+        let should_format = match &self.prefill {
+            Some(spec) => {
+                should_format
+                    && !fio::Fio::prefill_device(
+                        spec,
+                        settings.device.single()?,
+                        &self.fs,
+                        should_format,
+                        config,
+                        settings,
+                    )
+                    .await?
+            }
+            None => should_format,
+        };
         mount_fs(
             &ycsb_mount,
-            &settings.device,
+            settings.device.single()?,
             &self.fs,
-            !self.is_same_experiment(last_experiment)?,
+            should_format,
             self.fs_mount_opts.clone(),
         )
         .await?;
 
-        let marker_filename = final_results_dir.join("markers.csv");
-        let mut marker_file = File::create(marker_filename).await?;
-        marker_file
-            .write_all("time,marker_name\n".as_bytes())
-            .await?;
-
         #[cfg(feature = "prefill")]
-        if let Some(size) = &self.prefill {
+        if let Some(size) = &self.prefill
+            && fio::Fio::device_prefill_percent(size)?.is_none()
+        {
             let prefill_file = ycsb_mount.join("prefill");
-            fio::Fio::prefill(&prefill_file, size, config, settings).await?;
+            fio::Fio::prefill(
+                &prefill_file,
+                settings.device.single()?,
+                size,
+                config,
+                settings,
+            )
+            .await?;
         }
 
         let bench_args = 'inner: {
@@ -200,8 +228,12 @@ impl Bench for Ycsb {
             trace.replace(trace_nvme_calls(final_results_dir).await?);
         }
 
-        let child = Command::new(program)
-            .args(args)
+        let (cmd_program, cmd_args) = match &settings.perf_events {
+            Some(events) => perf_stat_command(program, args, events, final_results_dir),
+            None => (program.to_owned(), args.to_vec()),
+        };
+        let child = Command::new(cmd_program)
+            .args(&cmd_args)
             .arg("-p")
             .arg(format!(
                 "{}={}",
@@ -213,9 +245,10 @@ impl Bench for Ycsb {
             .stderr(Stdio::piped())
             .spawn()
             .context("Running benchmark")?;
+        set_running_benchmark(child.id());
         debug!("Benchmark started");
 
-        let start_time = Instant::now();
+        let mut markers = MarkerWriter::create(final_results_dir).await?;
         for sensor in sensors {
             sensor
                 .send_async(SensorRequest::StartRecording {
@@ -232,66 +265,71 @@ impl Bench for Ycsb {
         let output = child.wait_with_output().await?;
         debug!("Benchmark done");
 
-        marker_file
-            .write_all(
-                format!(
-                    "{},{}\n",
-                    start_time.elapsed().as_millis(),
-                    "benchmark-done"
-                )
-                .as_bytes(),
-            )
-            .await?;
+        markers.mark("benchmark-done").await?;
 
+        let output_file = write_output(final_results_dir, &output.stdout, &output.stderr).await?;
         if !output.status.success() {
             bail!(
-                "Process exitied with {}, err: {}",
+                "Process exitied with {}, see {}",
                 output.status.code().unwrap_or_default(),
-                String::from_utf8(output.stderr)?
+                output_file.display()
             );
         }
 
         sleep(Duration::from_secs(60)).await;
         debug!(
             "Disk sizes: {}",
-            simple_command_with_output_no_dir("df", &["-h", &settings.device]).await?
+            simple_command_with_output_no_dir("df", &["-h", settings.device.single()?]).await?
         );
 
-        marker_file
-            .write_all(format!("{},{}\n", start_time.elapsed().as_millis(), "unmount").as_bytes())
-            .await?;
-        _ = simple_command_with_output_no_dir("umount", &[&settings.device]).await?;
+        markers.mark("unmount").await?;
+        _ = simple_command_with_output_no_dir("umount", &[settings.device.single()?]).await?;
 
         for sensor in sensors {
             sensor.send_async(SensorRequest::StopRecording).await?;
         }
         debug!("Sensors stopped");
 
+        if settings.perf_events.is_some() {
+            write_perf_json(final_results_dir).await?;
+        }
+
         if let Some(mut trace) = trace {
             trace.0.kill().await?;
             trace.1.await?;
         }
+        Ok(())
+    }
+
+    async fn parse_results(&self, final_results_dir: &Path, parser: Option<&str>) -> Result<()> {
+        let results_file = final_results_dir.join("results.json");
+        if let Some(parser) = parser {
+            run_parser(parser, final_results_dir).await?;
+            read_json_file::<YcsbMetrics>(&results_file)
+                .await
+                .context(format!("Invalid results.json written by {parser}"))?;
+            return Ok(());
+        }
 
-        let stdout = String::from_utf8(output.stdout)?;
-        write(final_results_dir.join("output.txt"), &stdout).await?;
-        let data = match parse_output(&stdout) {
-            Ok(s) => s,
-            Err(err) => {
-                bail!(
-                    "Failed to parse filebench output! err: {} stdout: {} stderr: {}",
-                    err,
-                    stdout,
-                    String::from_utf8(output.stderr)?
-                );
-            }
-        };
-        write(
-            final_results_dir.join("results.json"),
-            serde_json::to_string(&data)?,
-        )
-        .await?;
+        let data = parse_output(&read_stdout(final_results_dir).await?).context(format!(
+            "Failed to parse ycsb output, see {}",
+            final_results_dir.join(OUTPUT_FILENAME).display()
+        ))?;
+        write(results_file, serde_json::to_string(&data)?).await?;
         Ok(())
     }
+
+    async fn primary_metric(&self, final_results_dir: &Path) -> Result<Option<Throughput>> {
+        let results = read_json_file::<YcsbMetrics>(final_results_dir.join("results.json")).await?;
+        Ok(results.throughput_ops_sec.map(|value| Throughput {
+            value,
+            unit: "ops/s".to_owned(),
+        }))
+    }
+
+    fn write_hint(&self) -> bool {
+        true
+    }
 }
 
 impl Ycsb {
@@ -316,3 +354,56 @@ impl Ycsb {
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(workload_file: &str, op_type: OpType) -> Ycsb {
+        Ycsb {
+            workload_file: workload_file.to_owned(),
+            _ycsb_op_type: Some(op_type),
+            ..Default::default()
+        }
+    }
+
+    fn last(workload_file: &str, op_type: OpType) -> Option<Box<dyn Bench>> {
+        Some(Box::new(op(workload_file, op_type)))
+    }
+
+    #[test]
+    fn runs_on_the_data_of_the_same_workload() {
+        let run = op("workloada", OpType::Run);
+        assert!(
+            run.is_same_experiment(&last("workloada", OpType::Load))
+                .unwrap()
+        );
+        assert!(
+            !run.is_same_experiment(&last("workloadb", OpType::Load))
+                .unwrap()
+        );
+        assert!(
+            !run.is_same_experiment(&last("workloada", OpType::Run))
+                .unwrap()
+        );
+        assert!(!run.is_same_experiment(&None).unwrap());
+    }
+
+    #[test]
+    fn loads_after_a_run() {
+        // the same workload is loaded again into the database of its run
+        assert!(
+            op("workloada", OpType::Load)
+                .is_same_experiment(&last("workloada", OpType::Run))
+                .unwrap()
+        );
+        // another workload is loaded on a fresh filesystem
+        let load = op("workloadb", OpType::Load);
+        assert!(
+            !load
+                .is_same_experiment(&last("workloada", OpType::Run))
+                .unwrap()
+        );
+        assert!(!load.is_same_experiment(&None).unwrap());
+    }
+}
@@ -12,6 +12,10 @@ pub struct OpMetrics {
     pub max_latency_us: u64,
     pub p95_latency_us: f64,
     pub p99_latency_us: f64,
+    /// Number of operations per 1 ms latency bucket, as (lower bound in us, count), only reported
+    /// with `measurementtype=histogram`, the overflow bucket is at `histogram.buckets` ms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<Vec<(u64, u64)>>,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -32,6 +36,7 @@ struct PartialOp {
     max: Option<u64>,
     p95: Option<f64>,
     p99: Option<f64>,
+    histogram: Option<Vec<(u64, u64)>>,
 }
 
 impl PartialOp {
@@ -43,6 +48,7 @@ impl PartialOp {
             max_latency_us: self.max?,
             p95_latency_us: self.p95?,
             p99_latency_us: self.p99?,
+            histogram: self.histogram,
         })
     }
 }
@@ -75,6 +81,7 @@ pub fn parse_output(output: &str) -> Result<YcsbMetrics> {
         Regex::new(r"\[([A-Z]+)\],\s*95thPercentileLatency\(us\)\s*,\s*([0-9]+\.?[0-9]*)")?;
     let re_p99 =
         Regex::new(r"\[([A-Z]+)\],\s*99thPercentileLatency\(us\)\s*,\s*([0-9]+\.?[0-9]*)")?;
+    let re_bucket = Regex::new(r"\[([A-Z]+)\],\s*>?(\d+)\s*,\s*(\d+)")?;
 
     let mut total_ops = None;
     let mut throughput = None;
@@ -93,6 +100,19 @@ pub fn parse_output(output: &str) -> Result<YcsbMetrics> {
     parse_field!(map, re_max, output, max);
     parse_field!(map, re_p95, output, p95);
     parse_field!(map, re_p99, output, p99);
+    for cap in re_bucket.captures_iter(output) {
+        let (Some(op), Some(ms), Some(count)) = (cap.get(1), cap.get(2), cap.get(3)) else {
+            continue;
+        };
+        let (Ok(ms), Ok(count)) = (ms.as_str().parse::<u64>(), count.as_str().parse()) else {
+            continue;
+        };
+        map.entry(op.as_str())
+            .or_default()
+            .histogram
+            .get_or_insert_default()
+            .push((ms * 1000, count));
+    }
 
     Ok(YcsbMetrics {
         total_operations: total_ops,
@@ -0,0 +1,117 @@
+use std::{ffi::CString, fs::File, path::Path, process::Stdio, time::Duration};
+
+use eyre::{Context, ContextCompat, Result};
+use nix::{
+    fcntl::{OFlag, open},
+    sys::{
+        signal::{Signal, kill},
+        stat::Mode,
+    },
+    unistd::{Pid, write},
+};
+use tokio::{
+    fs::{create_dir_all, remove_dir},
+    process::{Child, Command},
+    time::timeout,
+};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{BackgroundLoad, Devices},
+    util::block_device_numbers,
+};
+
+/// Output of the background load, written to the run's directory while it runs
+pub const BACKGROUND_OUTPUT_FILENAME: &str = "background.txt";
+
+const BACKGROUND_CGROUP_PATH: &str = "/sys/fs/cgroup/nvme-energy-bench-background";
+
+/// Time the background load has to exit after `SIGINT` before it is killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl BackgroundLoad {
+    /// `{device}` is replaced by the devices joined with `:`, as fio expects them
+    fn args(&self, device: &Devices) -> Vec<String> {
+        let device = device.all().join(":");
+        self.args
+            .iter()
+            .map(|x| x.replace("{device}", &device))
+            .collect()
+    }
+
+    /// Command line of the background load, recorded in the params of every run
+    pub fn command_line(&self, device: &Devices) -> String {
+        std::iter::once(self.program.clone())
+            .chain(self.args(device))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Starts the background load against `device` with its output written to
+    /// [`BACKGROUND_OUTPUT_FILENAME`] in `run_dir` and waits [`BackgroundLoad::delay_secs`], it
+    /// is killed if the returned process is dropped
+    pub async fn start(&self, device: &Devices, run_dir: &Path) -> Result<Child> {
+        let output_path = run_dir.join(BACKGROUND_OUTPUT_FILENAME);
+        let output =
+            File::create(&output_path).context(format!("Create {}", output_path.display()))?;
+        let mut cmd = Command::new(&self.program);
+        cmd.args(self.args(device))
+            .stdout(Stdio::from(output.try_clone()?))
+            .stderr(Stdio::from(output))
+            .kill_on_drop(true);
+
+        if let Some(cgroup) = &self.cgroup {
+            _ = remove_dir(BACKGROUND_CGROUP_PATH).await;
+            create_dir_all(BACKGROUND_CGROUP_PATH).await?;
+            // the io.cost model is shared with the benchmark's cgroup, which applies it
+            for device in device.all() {
+                cgroup
+                    .apply(
+                        BACKGROUND_CGROUP_PATH,
+                        block_device_numbers(device).await?,
+                        false,
+                    )
+                    .await?;
+            }
+            // only async-signal-safe calls are allowed between fork and exec, so the path is built
+            // beforehand and "0", which moves the writing process, is written instead of its pid
+            let procs = CString::new(format!("{BACKGROUND_CGROUP_PATH}/cgroup.procs"))?;
+            unsafe {
+                cmd.pre_exec(move || {
+                    let fd = open(procs.as_c_str(), OFlag::O_WRONLY, Mode::empty())?;
+                    write(&fd, b"0")?;
+                    Ok(())
+                });
+            }
+        }
+
+        debug!("Starting background load: {}", self.command_line(device));
+        let child = cmd
+            .spawn()
+            .context(format!("Starting background load {}", self.program))?;
+        if let Some(delay) = self.delay_secs {
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+        }
+        Ok(child)
+    }
+}
+
+/// Stops the background load and waits for it to exit
+pub async fn stop_background_load(mut child: Child) -> Result<()> {
+    if let Some(status) = child.try_wait()? {
+        warn!("Background load exited before the run finished ({status})");
+    } else {
+        let pid = child
+            .id()
+            .context("Could not get background load process id")?;
+        // SIGINT lets programs like fio print their results before exiting
+        _ = kill(Pid::from_raw(pid as i32), Signal::SIGINT);
+    }
+
+    if timeout(STOP_TIMEOUT, child.wait()).await.is_err() {
+        warn!("Background load did not exit after SIGINT, killing it");
+        child.kill().await?;
+    }
+    _ = remove_dir(BACKGROUND_CGROUP_PATH).await;
+    Ok(())
+}
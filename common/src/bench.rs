@@ -1,5 +1,12 @@
 use core::fmt::Debug;
-use std::{collections::HashMap, path::Path, pin::Pin, process::Stdio, time::Instant};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    pin::Pin,
+    process::Stdio,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Instant,
+};
 
 use downcast_rs::{Downcast, impl_downcast};
 use dyn_clone::{DynClone, clone_trait_object};
@@ -7,7 +14,7 @@ use eyre::{Context, ContextCompat, Result, bail};
 use flume::Sender;
 use serde::{Deserialize, Serialize};
 use tokio::{
-    fs::{File, create_dir_all, read_to_string, remove_dir},
+    fs::{File, OpenOptions, create_dir_all, read_to_string, remove_dir},
     io::{AsyncReadExt, AsyncWriteExt},
     process::{Child, Command},
     spawn,
@@ -17,8 +24,10 @@ use tracing::debug;
 
 use crate::{
     config::{Config, Settings},
+    perf::{perf_stat_command, write_perf_json},
     sensor::SensorRequest,
-    util::read_until_prompt,
+    summary::Throughput,
+    util::{block_device_numbers, read_until_prompt, simple_command_with_output_no_dir},
 };
 
 #[derive(Debug)]
@@ -41,10 +50,17 @@ pub trait Bench: Debug + DynClone + Downcast + Send + Sync {
     fn internal_cgroup(&self) -> bool {
         false
     }
-    /// Return an estimate of how long the benchmark will take to run
+    /// Indicates if the benchmark can run against several devices at once, see
+    /// [`crate::config::Devices`]
+    fn supports_multiple_devices(&self) -> bool {
+        false
+    }
+    /// Return an estimate of how long the benchmark will take to run, called on the `bench_obj` of
+    /// every [`Cmd`] for the estimate of that run
     ///
     /// Returns:
-    /// * An estimate in milliseconds of how long the benchmark will take to run
+    /// * An estimate in milliseconds of how long the benchmark will take to run, 0 if it can not
+    ///   be estimated, ie. it depends on the workload, in which case the run never times out
     fn runtime_estimate(&self) -> Result<u64>;
     /// Generates the commands to run the experiment with each argument combination to test
     ///
@@ -143,27 +159,29 @@ pub trait Bench: Debug + DynClone + Downcast + Send + Sync {
             trace.replace(trace_nvme_calls(final_results_dir).await?);
         }
 
-        let mut cmd = Command::new(program);
+        let (cmd_program, cmd_args) = match &settings.perf_events {
+            Some(events) => perf_stat_command(program, args, events, final_results_dir),
+            None => (program.to_owned(), args.to_vec()),
+        };
+        let mut cmd = Command::new(cmd_program);
         let cgroup_path = "/sys/fs/cgroup/nvme-energy-bench";
         if let Some(cgroup) = &settings.cgroup {
             _ = remove_dir(cgroup_path).await;
             create_dir_all(cgroup_path).await?;
-            let device = settings
-                .device
-                .strip_prefix("/dev/")
-                .context("Device does not include /dev")?;
-            let device = read_to_string(format!("/sys/block/{device}/dev")).await?;
-            cgroup
-                .apply(
-                    cgroup_path,
-                    device.trim(),
-                    if _last_experiment.is_none() {
-                        true
-                    } else {
-                        false
-                    },
-                )
-                .await?;
+            for device in settings.device.all() {
+                let device = block_device_numbers(device).await?;
+                cgroup
+                    .apply(
+                        cgroup_path,
+                        &device,
+                        if _last_experiment.is_none() {
+                            true
+                        } else {
+                            false
+                        },
+                    )
+                    .await?;
+            }
         }
 
         if settings.cgroup.is_some() && !self.internal_cgroup() {
@@ -180,12 +198,13 @@ pub trait Bench: Debug + DynClone + Downcast + Send + Sync {
         }
 
         let child = cmd
-            .args(args)
+            .args(&cmd_args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .envs(env)
             .spawn()
             .context("Running benchmark")?;
+        set_running_benchmark(child.id());
         debug!("Benchmark started");
 
         for sensor in sensors {
@@ -203,12 +222,12 @@ pub trait Bench: Debug + DynClone + Downcast + Send + Sync {
 
         let output = child.wait_with_output().await?;
         debug!("Benchmark done");
+        let output_file = write_output(final_results_dir, &output.stdout, &output.stderr).await?;
         if !output.status.success() {
             bail!(
-                "Process exited with {}, stdout: {}, stderr: {}",
+                "Process exited with {}, see {}",
                 output.status.code().unwrap_or_default(),
-                String::from_utf8(output.stdout).unwrap(),
-                String::from_utf8(output.stderr).unwrap()
+                output_file.display()
             );
         }
 
@@ -218,6 +237,9 @@ pub trait Bench: Debug + DynClone + Downcast + Send + Sync {
         debug!("Sensors stopped");
 
         _ = remove_dir(cgroup_path).await;
+        if settings.perf_events.is_some() {
+            write_perf_json(final_results_dir).await?;
+        }
 
         if let Some(mut trace) = trace {
             trace.0.kill().await?;
@@ -241,6 +263,27 @@ pub trait Bench: Debug + DynClone + Downcast + Send + Sync {
     ) -> Result<()> {
         Ok(())
     }
+    /// Parses the output of a successful run into `results.json`, called before
+    /// [`Bench::post_experiment`]. Override for benchmarks whose output needs parsing, the
+    /// default only runs the alternate parser if one is set.
+    ///
+    /// Arguments:
+    /// * `final_results_dir` - Directory of final results
+    /// * `parser` - Alternate parser of the experiment, ie. [`crate::config::InnerBench::parser`]
+    async fn parse_results(&self, final_results_dir: &Path, parser: Option<&str>) -> Result<()> {
+        if let Some(parser) = parser {
+            run_parser(parser, final_results_dir).await?;
+        }
+        Ok(())
+    }
+
+    /// Primary throughput metric of a run, logged and recorded in its [`crate::summary::RunSummary`]
+    ///
+    /// Arguments:
+    /// * `_final_results_dir` - Directory of final results, after [`Bench::parse_results`]
+    async fn primary_metric(&self, _final_results_dir: &Path) -> Result<Option<Throughput>> {
+        Ok(None)
+    }
 
     /// Returns true if the benchmark will perform write operations
     /// This is used to determine if nvme-energy-bench should sleep based on [`Settings::sleep_after_writes`]
@@ -274,6 +317,41 @@ pub struct BenchInfo {
     pub device_power_states: Vec<(f64, String)>,
     pub cpu_freq_limits: (usize, usize), // (min, max)
     pub cpu_topology: HashMap<u32, u32>, // (numa domain, cores)
+    /// Name of the experiment run as a warmup, if any
+    pub warmup: Option<String>,
+    /// Model and firmware of the SSD, shown as a footnote on the bar charts and heatmaps
+    pub device: Option<DeviceInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceInfo {
+    /// Model number, `mn` of `nvme id-ctrl`
+    pub model: String,
+    /// Firmware revision, `fr` of `nvme id-ctrl`
+    pub firmware: String,
+}
+
+impl BenchInfo {
+    /// Label of a power state in the plots, with its maximum power if known, ie. `ps0 (8.50W)`
+    pub fn power_state_label(&self, power_state: i32) -> String {
+        if power_state == -1 {
+            return "Default".to_owned();
+        }
+        match usize::try_from(power_state)
+            .ok()
+            .and_then(|x| self.device_power_states.get(x))
+        {
+            Some((_, max_power)) => format!("ps{power_state} ({max_power})"),
+            None => format!("ps{power_state}"),
+        }
+    }
+}
+
+impl std::fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (FW {})", self.model, self.firmware)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -285,6 +363,19 @@ pub struct BenchParams {
     pub name: String,
     pub idx: usize,
     pub args: Box<dyn Bench>,
+    /// Tags of the experiment, ie. [`crate::config::InnerBench::tags`]
+    pub tags: Option<Vec<String>>,
+    /// Command line of the background load that ran alongside, see
+    /// [`crate::config::Settings::background`]
+    pub background: Option<String>,
+}
+
+impl BenchParams {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .as_ref()
+            .is_some_and(|tags| tags.iter().any(|x| x == tag))
+    }
 }
 
 impl PartialEq for BenchParams {
@@ -298,6 +389,132 @@ impl PartialEq for BenchParams {
 
 impl Eq for BenchParams {}
 
+/// Raw output of the benchmark process, kept in the results directory of every run
+pub const OUTPUT_FILENAME: &str = "output.txt";
+/// Sub-directory of a run with the sensor data recorded during [`Bench::experiment_init`], if
+/// `measure_prepare` is set
+pub const PREPARE_DIRNAME: &str = "prepare";
+/// Sub-directory of a run with the sensor data recorded during the cooldown before it, if
+/// `record_cooldown` is set
+pub const COOLDOWN_DIRNAME: &str = "cooldown";
+
+/// Process id of the running benchmark, 0 if none is running, see [`set_running_benchmark`]
+static RUNNING_BENCHMARK: AtomicU32 = AtomicU32::new(0);
+
+/// Records the process of the benchmark started by [`Bench::run`], so that a hung run can be
+/// killed without the other processes of the app, ie. the background load or tracing. Every
+/// implementation of [`Bench::run`] that spawns the benchmark sets it, `None` clears it.
+pub fn set_running_benchmark(pid: Option<u32>) {
+    RUNNING_BENCHMARK.store(pid.unwrap_or_default(), Ordering::SeqCst);
+}
+
+/// Process id of the running benchmark, see [`set_running_benchmark`]
+pub fn running_benchmark() -> Option<u32> {
+    Some(RUNNING_BENCHMARK.load(Ordering::SeqCst)).filter(|x| *x != 0)
+}
+
+/// Writes the combined stdout and stderr of a benchmark process to [`OUTPUT_FILENAME`],
+/// returning the path of the written file
+pub async fn write_output(
+    final_results_dir: &Path,
+    stdout: &[u8],
+    stderr: &[u8],
+) -> Result<PathBuf> {
+    let path = final_results_dir.join(OUTPUT_FILENAME);
+    let mut file = File::create(&path)
+        .await
+        .context(format!("Creating {}", path.display()))?;
+    file.write_all(stdout).await?;
+    if !stderr.is_empty() {
+        file.write_all(b"\n--- stderr ---\n").await?;
+        file.write_all(stderr).await?;
+    }
+    file.flush().await?;
+    Ok(path)
+}
+
+/// Stdout of the benchmark process, as written by [`write_output`]
+pub async fn read_stdout(final_results_dir: &Path) -> Result<String> {
+    let path = final_results_dir.join(OUTPUT_FILENAME);
+    let output = read_to_string(&path)
+        .await
+        .context(format!("Reading {}", path.display()))?;
+    Ok(match output.split_once("\n--- stderr ---\n") {
+        Some((stdout, _)) => stdout.to_owned(),
+        None => output,
+    })
+}
+
+/// Markers of a run, used to section its sensor readings, see
+/// [`crate::util::calculate_named_sections`]
+pub const MARKERS_FILENAME: &str = "markers.csv";
+
+/// Appends named markers to [`MARKERS_FILENAME`] of a run, at the time (ms) since the writer was
+/// opened, open it right when the sensors start recording
+pub struct MarkerWriter {
+    file: File,
+    start: Instant,
+}
+
+impl MarkerWriter {
+    /// Creates [`MARKERS_FILENAME`] in `final_results_dir` with only its header, replacing the
+    /// markers of a previous run
+    pub async fn create(final_results_dir: &Path) -> Result<Self> {
+        let path = final_results_dir.join(MARKERS_FILENAME);
+        let mut file = File::create(&path)
+            .await
+            .context(format!("Creating {}", path.display()))?;
+        file.write_all(b"time,marker_name\n").await?;
+        Ok(Self {
+            file,
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends to the markers in `final_results_dir`, ie. created in [`Bench::experiment_init`],
+    /// creates them if there are none yet
+    pub async fn open(final_results_dir: &Path) -> Result<Self> {
+        let path = final_results_dir.join(MARKERS_FILENAME);
+        if !path.exists() {
+            return Self::create(final_results_dir).await;
+        }
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .context(format!("Opening {}", path.display()))?;
+        Ok(Self {
+            file,
+            start: Instant::now(),
+        })
+    }
+
+    /// Marks the start of a section named `name`
+    pub async fn mark(&mut self, name: &str) -> Result<()> {
+        if name.is_empty() || name.contains([',', '\n', '\r']) {
+            bail!("Invalid marker name {name:?}, must be non-empty without commas or newlines");
+        }
+        self.file
+            .write_all(format!("{},{name}\n", self.start.elapsed().as_millis()).as_bytes())
+            .await?;
+        self.file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Runs an alternate result parser with the results directory of a run as its only argument,
+/// the parser has to write `results.json` in the format of the benchmark's built-in parser
+pub async fn run_parser(parser: &str, final_results_dir: &Path) -> Result<()> {
+    let dir = final_results_dir.to_string_lossy();
+    simple_command_with_output_no_dir(parser, &[&dir])
+        .await
+        .context(format!("Running result parser {parser}"))?;
+    if !final_results_dir.join("results.json").exists() {
+        bail!("Result parser {parser} did not write results.json in {dir}");
+    }
+    Ok(())
+}
+
 pub async fn trace_nvme_calls(trace_out_dir: &Path) -> Result<(Child, JoinHandle<()>, Instant)> {
     let trace_start_time = Instant::now();
     let mut bpftrace = Command::new("bpftrace")
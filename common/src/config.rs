@@ -1,18 +1,40 @@
 use std::{
+    collections::HashMap,
     fmt::{Debug, Write},
-    path::Path,
+    fs::Permissions,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
 };
 
-use eyre::{Context, Result};
+use eyre::{Context, ContextCompat, Result, bail};
 use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{File, read_dir, remove_file, set_permissions, write},
+    io::AsyncReadExt,
+};
 
 use crate::{
     bench::{Bench, BenchArgs},
     plot::Plot,
     sensor::SensorArgs,
-    util::write_one_line,
+    util::{DEFAULT_PRECISION, parse_data_size, simple_command_with_output_no_dir, write_one_line},
 };
 
+/// Name of the copy of a YAML config in a results folder
+pub const YAML_CONFIG: &str = "config.yaml";
+/// Name of the copy of a TOML config in a results folder
+pub const TOML_CONFIG: &str = "config.toml";
+
+/// Config of a results folder, `config.toml` if the benchmark was run with a TOML config
+pub fn results_config_path(results_dir: &Path) -> PathBuf {
+    let toml = results_dir.join(TOML_CONFIG);
+    if toml.exists() {
+        toml
+    } else {
+        results_dir.join(YAML_CONFIG)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
@@ -27,23 +49,297 @@ pub struct Config {
 #[serde(deny_unknown_fields)]
 pub struct Sensor {
     pub sensor: sensor_common::SensorKind,
+    /// Distinguishes multiple instances of the same sensor, data is written to `<sensor>-<alias>.csv`
+    pub alias: Option<String>,
     pub args: Option<Box<dyn SensorArgs>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Settings {
     pub numa: Option<NumaConfig>,
-    pub device: String,
+    /// Device or devices under test, see [`Devices`]
+    pub device: Devices,
     pub nvme_power_states: Option<Vec<usize>>,
     pub max_repeat: Option<usize>,
     pub should_trace: Option<bool>,
     pub cpu_freq: Option<CpuFreq>,
+    /// Frequency governor of every online CPU during all experiments, see [`CpuGovernor`]
+    pub cpu_governor: Option<CpuGovernor>,
     pub cpu_max_power_watts: f64,
     pub cgroup: Option<Cgroup>,
     pub sleep_between_experiments: Option<u64>,
     pub sleep_after_writes: Option<u64>,
+    /// Seconds to wait before the init of every run but the first, so that the SSD cools down and
+    /// background work of the previous run, ie. garbage collection, settles
+    pub cooldown_secs: Option<u64>,
+    /// Records the sensors during [`Settings::cooldown_secs`] to [`crate::bench::COOLDOWN_DIRNAME`]
+    /// in the run's directory, ie. to measure idle power
+    pub record_cooldown: Option<bool>,
     pub scheduler: Option<String>,
+    pub on_failure: Option<FailurePolicy>,
+    /// Fails a run that takes longer than the benchmark's runtime estimate times this factor, its
+    /// processes are killed and the run is handled according to [`Settings::on_failure`]
+    pub timeout_multiplier: Option<f64>,
+    /// Window size of a median filter applied to Powersensor3 readings before smoothing, must be odd.
+    /// Only the time series plots are filtered, the power statistics of the bar charts, heatmaps
+    /// and `summary.json` are calculated from the raw readings.
+    pub powersensor3_median_filter: Option<usize>,
+    /// Name of an experiment in `benches` to run once before all experiments, its results are discarded
+    pub warmup: Option<String>,
+    /// Encrypts the device with dm-crypt, benchmarks run against the mapped device
+    pub dm_crypt: Option<DmCrypt>,
+    /// Backs [`Settings::device`], a loop device, with a sparse file instead of an SSD
+    pub loop_device: Option<LoopDevice>,
+    /// Number of readings sensors buffer before writing them to their CSV file, by default
+    /// readings are kept in memory until recording stops
+    pub sensor_flush_rows: Option<usize>,
+    /// Core the sensor readers are pinned to, see [`crate::sensor::sensor_runtime`]
+    pub sensor_cpu: Option<usize>,
+    /// Interval in ms of a shared ticker that triggers every sensor reading, so that the readings of
+    /// different sensors are taken at the same timestamps, by default every sensor samples at its
+    /// own pace, see [`crate::sensor::start_sample_ticker`]
+    pub sync_sample_interval: Option<u64>,
+    /// URL of a Prometheus pushgateway the latest reading of every sensor is pushed to once a
+    /// second while recording, see [`crate::sensor::start_prometheus_push`]
+    pub prometheus_push_gateway: Option<String>,
+    /// Events counted with `perf stat` while the benchmark runs, written to `perf.json` in the run directory
+    pub perf_events: Option<Vec<String>>,
+    /// Maximum power in Watts of each NVMe power state, only used if the power state descriptors
+    /// can not be read with `nvme id-ctrl`
+    pub device_power_states: Option<Vec<f64>>,
+    /// Records the sensors while a run is prepared (format, prefill) to [`crate::bench::PREPARE_DIRNAME`]
+    /// in the run's directory
+    pub measure_prepare: Option<bool>,
+    /// Format of the exported CSV files, see [`CsvFormat`]
+    pub csv_format: Option<CsvFormat>,
+    /// Checks every run for sustained CPU frequency or SSD power drops, see [`ThrottleDetection`]
+    pub throttle_detection: Option<ThrottleDetection>,
+    /// New names of sensor CSV columns, ie. `cpu-0-freq: cpu0_freq_mhz`, applied to copies of the
+    /// sensor CSVs, see [`crate::sensor::export_sensor_csvs`]
+    pub sensor_column_names: Option<HashMap<String, String>>,
+    /// Workload that runs alongside every run, to measure experiments under contention, see
+    /// [`BackgroundLoad`]
+    pub background: Option<BackgroundLoad>,
+    /// Significant figures of the heatmap cell labels, exported plot data and run summaries,
+    /// defaults to [`DEFAULT_PRECISION`]
+    pub precision: Option<usize>,
+}
+
+impl Settings {
+    /// Settings as seen by benchmarks, ie. with [`Settings::device`] pointing to the dm-crypt
+    /// mapping if enabled
+    pub fn bench_settings(&self) -> Settings {
+        let mut settings = self.clone();
+        if let Some(dm_crypt) = &self.dm_crypt {
+            settings.device = dm_crypt.mapped_device().into();
+        }
+        settings
+    }
+
+    /// [`Settings::csv_format`], or the default format if it is not set
+    pub fn csv_format_or_default(&self) -> CsvFormat {
+        self.csv_format.clone().unwrap_or_default()
+    }
+
+    pub fn precision(&self) -> usize {
+        self.precision.unwrap_or(DEFAULT_PRECISION)
+    }
+
+    /// Power states the experiments run at, see [`PowerStates`]
+    pub fn power_states(&self) -> PowerStates {
+        match &self.nvme_power_states {
+            Some(ps) if !ps.is_empty() => PowerStates(ps.iter().map(|x| *x as i32).collect()),
+            _ => PowerStates(vec![-1]),
+        }
+    }
+}
+
+/// Power states of [`Settings::nvme_power_states`], or only `-1` (power state left unchanged) if
+/// none are set. Plots index their power state rows and series by the position in this list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerStates(Vec<i32>);
+
+impl PowerStates {
+    pub fn states(&self) -> &[i32] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Position of a run's power state, `None` if the power state is not configured, ie. results
+    /// of a run with a different config
+    pub fn index(&self, power_state: i32) -> Option<usize> {
+        self.0.iter().position(|x| *x == power_state)
+    }
+}
+
+/// A single device under test, or several, ie. the members of a RAID or the namespaces of an
+/// NVMe SSD. Benchmarks that support only one device are rejected with several when the config is
+/// validated, see [`crate::bench::Bench::supports_multiple_devices`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Devices {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Devices {
+    pub fn all(&self) -> &[String] {
+        match self {
+            Devices::Single(device) => std::slice::from_ref(device),
+            Devices::Multiple(devices) => devices,
+        }
+    }
+
+    pub fn is_multiple(&self) -> bool {
+        self.all().len() > 1
+    }
+
+    /// The device for everything that supports only one, ie. a filesystem or the PCIe link, an
+    /// error with several devices
+    pub fn single(&self) -> Result<&str> {
+        match self.all() {
+            [device] => Ok(device),
+            _ => bail!("Only a single device is supported, got {self}"),
+        }
+    }
+}
+
+impl std::fmt::Display for Devices {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.all().join(", "))
+    }
+}
+
+impl Default for Devices {
+    fn default() -> Self {
+        Devices::Single(String::new())
+    }
+}
+
+impl From<String> for Devices {
+    fn from(device: String) -> Self {
+        Devices::Single(device)
+    }
+}
+
+/// What to do when an experiment fails to run
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FailurePolicy {
+    /// Stop the whole benchmark
+    #[default]
+    Abort,
+    /// Mark the run directory as failed and move on to the next iteration
+    Continue,
+    /// Re-run the experiment up to N times before aborting
+    Retry(usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DmCrypt {
+    /// Defaults to [`DmCryptMode::Plain`]
+    pub mode: Option<DmCryptMode>,
+    /// Defaults to `aes-xts-plain64`
+    pub cipher: Option<String>,
+    /// Key size in bits, defaults to 512
+    pub key_size: Option<usize>,
+    /// Name of the mapping in `/dev/mapper`, defaults to `nvme-energy-bench`
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum DmCryptMode {
+    /// No on-disk header, the mapping covers the whole device
+    #[default]
+    Plain,
+    /// LUKS2 header, the device is formatted once before the benchmark starts
+    Luks,
+}
+
+/// Sparse file attached to the loop device in [`Settings::device`] for the whole benchmark, to run
+/// the pipeline without an SSD, ie. in CI
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoopDevice {
+    /// Path of the backing file, created if it does not exist
+    pub file: String,
+    /// Size of the backing file, ie. `8G`
+    pub size: String,
+}
+
+/// Program started before every run and stopped after it, ie. a low priority fio job, its output
+/// is written to [`crate::background::BACKGROUND_OUTPUT_FILENAME`] in the run's directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackgroundLoad {
+    /// Program to run, ie. `fio`
+    pub program: String,
+    /// Arguments of the program, `{device}` is replaced by the benchmarked device
+    pub args: Vec<String>,
+    /// Cgroup the program runs in, ie. with a low `io.weight` so it yields to the experiment
+    pub cgroup: Option<Cgroup>,
+    /// Seconds the program runs before the experiment starts, defaults to 0
+    pub delay_secs: Option<u64>,
+}
+
+/// Thresholds of the throttling check, a run is flagged if the CPU frequency or SSD power stays
+/// below its median by more than `drop` for at least `duration`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThrottleDetection {
+    /// Relative drop below the median, defaults to 0.2
+    pub drop: Option<f64>,
+    /// Minimum length of a drop, ie. `5s`, defaults to 5s
+    pub duration: Option<String>,
+}
+
+/// Delimiter and decimal separator of exported CSV files, ie. `;` and `,` for spreadsheets in
+/// European locales
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvFormat {
+    /// Defaults to `,`
+    pub delimiter: Option<char>,
+    /// Defaults to `.`
+    pub decimal_separator: Option<char>,
+}
+
+impl CsvFormat {
+    pub fn validate(&self) -> Result<()> {
+        if !self.delimiter().is_ascii() {
+            bail!("CSV delimiter must be an ASCII character");
+        }
+        if self.delimiter() == self.decimal_separator() {
+            bail!("CSV delimiter and decimal separator must differ");
+        }
+        Ok(())
+    }
+
+    pub fn delimiter(&self) -> char {
+        self.delimiter.unwrap_or(',')
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        self.decimal_separator.unwrap_or('.')
+    }
+
+    /// Replaces the decimal point of a numeric field with [`CsvFormat::decimal_separator`]
+    pub fn localize(&self, field: &str) -> String {
+        if self.decimal_separator() != '.' && field.parse::<f64>().is_ok() {
+            field.replace('.', &self.decimal_separator().to_string())
+        } else {
+            field.to_owned()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +433,16 @@ pub struct CpuFreq {
     pub default_governor: String,
 }
 
+/// Governor and optional frequency range (kHz) set on every online CPU before the first experiment,
+/// the original settings are restored after the last one, see [`crate::cpu_governor`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuGovernor {
+    pub governor: String,
+    pub min_freq: Option<usize>,
+    pub max_freq: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct NumaConfig {
@@ -151,6 +457,11 @@ pub struct InnerBench {
     pub repeat: usize,
     pub bench: Box<dyn Bench>,
     pub plots: Option<Vec<Box<dyn Plot>>>,
+    /// Labels to categorize the experiment, used to filter plots
+    pub tags: Option<Vec<String>>,
+    /// Alternate result parser, a program that is run with the results directory of every run
+    /// and writes `results.json`, see [`crate::bench::Bench::parse_results`]
+    pub parser: Option<String>,
 }
 
 impl RwIos {
@@ -317,3 +628,297 @@ impl CgroupIo {
         Ok(())
     }
 }
+
+const DM_CRYPT_DEFAULT_NAME: &str = "nvme-energy-bench";
+const DM_CRYPT_DEFAULT_CIPHER: &str = "aes-xts-plain64";
+const DM_CRYPT_DEFAULT_KEY_SIZE: usize = 512;
+
+impl DmCrypt {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(DM_CRYPT_DEFAULT_NAME)
+    }
+
+    pub fn mapped_device(&self) -> String {
+        format!("/dev/mapper/{}", self.name())
+    }
+
+    fn key_file(&self) -> PathBuf {
+        std::env::temp_dir().join(format!("{}.key", self.name()))
+    }
+
+    /// Checks that cryptsetup is installed and that neither the device nor the mapping name is in use
+    pub async fn validate(&self, device: &str) -> Result<()> {
+        simple_command_with_output_no_dir("cryptsetup", &["--version"])
+            .await
+            .context("cryptsetup is required for dm_crypt")?;
+
+        if Path::new(&self.mapped_device()).exists() {
+            bail!("{} already exists", self.mapped_device());
+        }
+
+        let block = Path::new(device)
+            .canonicalize()
+            .context(format!("Resolving {device}"))?;
+        let block = block
+            .file_name()
+            .context("Invalid device")?
+            .to_string_lossy();
+        let mut holders = read_dir(format!("/sys/class/block/{block}/holders"))
+            .await
+            .context(format!("Reading holders of {device}"))?;
+        if let Some(holder) = holders.next_entry().await? {
+            bail!(
+                "{device} is already mapped by {}",
+                holder.file_name().to_string_lossy()
+            );
+        }
+        Ok(())
+    }
+
+    /// Generates the key used for every mapping of this benchmark, and writes the LUKS header
+    pub async fn setup(&self, device: &str) -> Result<()> {
+        let mut key = vec![0u8; self.key_size() / 8];
+        File::open("/dev/urandom")
+            .await?
+            .read_exact(&mut key)
+            .await
+            .context("Generating dm-crypt key")?;
+        let key_file = self.key_file();
+        write(&key_file, key).await?;
+        set_permissions(&key_file, Permissions::from_mode(0o600)).await?;
+
+        if self.mode.clone().unwrap_or_default() == DmCryptMode::Luks {
+            let key_size = self.key_size().to_string();
+            simple_command_with_output_no_dir(
+                "cryptsetup",
+                &[
+                    "luksFormat",
+                    "--batch-mode",
+                    "--type",
+                    "luks2",
+                    "--cipher",
+                    self.cipher(),
+                    "--key-size",
+                    &key_size,
+                    "--key-file",
+                    key_file.to_str().unwrap(),
+                    device,
+                ],
+            )
+            .await
+            .context(format!("Formatting {device} with LUKS"))?;
+        }
+        Ok(())
+    }
+
+    /// Maps the device to [`DmCrypt::mapped_device`]
+    pub async fn open(&self, device: &str) -> Result<()> {
+        let key_file = self.key_file();
+        let key_file = key_file.to_str().unwrap();
+        let key_size = self.key_size().to_string();
+        let mut args = vec!["open"];
+        match self.mode.clone().unwrap_or_default() {
+            DmCryptMode::Plain => args.extend([
+                "--type",
+                "plain",
+                "--cipher",
+                self.cipher(),
+                "--key-size",
+                &key_size,
+            ]),
+            DmCryptMode::Luks => args.extend(["--type", "luks2"]),
+        }
+        args.extend(["--key-file", key_file, device, self.name()]);
+        simple_command_with_output_no_dir("cryptsetup", &args)
+            .await
+            .context(format!("Opening dm-crypt mapping of {device}"))?;
+        Ok(())
+    }
+
+    /// Unmounts and removes the mapping
+    pub async fn close(&self) -> Result<()> {
+        _ = simple_command_with_output_no_dir("umount", &[&self.mapped_device()]).await;
+        simple_command_with_output_no_dir("cryptsetup", &["close", self.name()])
+            .await
+            .context(format!("Closing {}", self.mapped_device()))?;
+        Ok(())
+    }
+
+    /// Removes the key, the data on the device can not be read anymore
+    pub async fn teardown(&self) -> Result<()> {
+        _ = remove_file(self.key_file()).await;
+        Ok(())
+    }
+
+    fn cipher(&self) -> &str {
+        self.cipher.as_deref().unwrap_or(DM_CRYPT_DEFAULT_CIPHER)
+    }
+
+    fn key_size(&self) -> usize {
+        self.key_size.unwrap_or(DM_CRYPT_DEFAULT_KEY_SIZE)
+    }
+}
+
+impl LoopDevice {
+    /// Creates the sparse backing file and attaches it to `device`, which must be a free loop device
+    pub async fn setup(&self, device: &str) -> Result<()> {
+        simple_command_with_output_no_dir("losetup", &["--version"])
+            .await
+            .context("losetup is required for loop_device")?;
+        let name = device
+            .strip_prefix("/dev/")
+            .filter(|x| x.starts_with("loop"))
+            .context(format!("{device} is not a loop device"))?;
+        if Path::new(&format!("/sys/block/{name}/loop/backing_file")).exists() {
+            bail!("{device} is already in use");
+        }
+
+        let size = parse_data_size(&self.size).context("Invalid loop_device.size")?;
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.file)
+            .await
+            .context(format!("Creating {}", self.file))?;
+        // extending the file does not allocate blocks, so it stays sparse
+        file.set_len(size).await?;
+
+        simple_command_with_output_no_dir("losetup", &[device, &self.file])
+            .await
+            .context(format!("Attaching {} to {device}", self.file))?;
+        Ok(())
+    }
+
+    /// Detaches the backing file from `device`, the file is kept
+    pub async fn teardown(&self, device: &str) -> Result<()> {
+        _ = simple_command_with_output_no_dir("umount", &[device]).await;
+        simple_command_with_output_no_dir("losetup", &["-d", device])
+            .await
+            .context(format!("Detaching {device}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::CmdsResult;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TestBench {
+        program: String,
+    }
+
+    #[async_trait::async_trait]
+    #[typetag::serde]
+    impl Bench for TestBench {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        fn default_bench() -> Box<dyn Bench> {
+            Box::new(Self {
+                program: String::new(),
+            })
+        }
+
+        fn default_bench_args(&self) -> Box<dyn BenchArgs> {
+            Box::new(TestBenchArgs { jobs: 1 })
+        }
+
+        fn runtime_estimate(&self) -> Result<u64> {
+            Ok(0)
+        }
+
+        fn write_hint(&self) -> bool {
+            false
+        }
+
+        fn cmds(&self, _: &Settings, _: &dyn BenchArgs, _: &str) -> Result<CmdsResult> {
+            bail!("not run")
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TestBenchArgs {
+        jobs: usize,
+    }
+
+    #[typetag::serde]
+    impl BenchArgs for TestBenchArgs {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TestSensorArgs {
+        interval: u64,
+    }
+
+    #[typetag::serde]
+    impl SensorArgs for TestSensorArgs {
+        fn name(&self) -> sensor_common::SensorKind {
+            // the variants depend on setup.toml, only the args are serialized here
+            unreachable!()
+        }
+    }
+
+    /// The plugin fields of [`Config`]
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Plugins {
+        benches: Vec<InnerBench>,
+        bench_args: Vec<Box<dyn BenchArgs>>,
+        sensor_args: Vec<Option<Box<dyn SensorArgs>>>,
+    }
+
+    #[test]
+    fn plugins_round_trip_through_toml() {
+        let plugins: Plugins = toml::from_str(
+            r#"
+            bench_args = [{ type = "TestBenchArgs", jobs = 4 }]
+            sensor_args = [{ type = "TestSensorArgs", interval = 100 }]
+
+            [[benches]]
+            name = "seq-read"
+            repeat = 2
+            bench = { type = "TestBench", program = "fio" }
+            "#,
+        )
+        .unwrap();
+        let plugins: Plugins = toml::from_str(&toml::to_string_pretty(&plugins).unwrap()).unwrap();
+
+        assert_eq!(plugins.benches.len(), 1);
+        assert_eq!(plugins.benches[0].name, "seq-read");
+        assert_eq!(
+            plugins.benches[0].bench.downcast_ref::<TestBench>(),
+            Some(&TestBench {
+                program: "fio".to_owned()
+            })
+        );
+        assert_eq!(
+            plugins.bench_args[0].downcast_ref::<TestBenchArgs>(),
+            Some(&TestBenchArgs { jobs: 4 })
+        );
+        assert_eq!(
+            plugins.sensor_args[0]
+                .as_ref()
+                .and_then(|x| x.downcast_ref::<TestSensorArgs>()),
+            Some(&TestSensorArgs { interval: 100 })
+        );
+    }
+
+    #[test]
+    fn device_is_one_or_several() {
+        let single = serde_json::from_str::<Devices>(r#""/dev/nvme0n1""#).unwrap();
+        assert_eq!(single.single().unwrap(), "/dev/nvme0n1");
+        assert!(!single.is_multiple());
+        assert_eq!(serde_json::to_string(&single).unwrap(), r#""/dev/nvme0n1""#);
+
+        let multiple =
+            serde_json::from_str::<Devices>(r#"["/dev/nvme0n1", "/dev/nvme1n1"]"#).unwrap();
+        assert!(multiple.single().is_err());
+        assert_eq!(multiple.all().join(":"), "/dev/nvme0n1:/dev/nvme1n1");
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use eyre::{Context, Result, bail};
+use tokio::fs::{read_dir, read_to_string, write};
+use tracing::debug;
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+/// Settings of a CPU's `cpufreq` directory
+#[derive(Debug)]
+struct CpufreqSettings {
+    dir: PathBuf,
+    governor: String,
+    min_freq: String,
+    max_freq: String,
+}
+
+/// Settings of every CPU before the first governor was set, restored by [`restore_cpu_governor`]
+static ORIGINAL_SETTINGS: Mutex<Option<Vec<CpufreqSettings>>> = Mutex::new(None);
+
+/// `cpufreq` directory of every online CPU in `cpu_dir`, ie. `cpu0/cpufreq`, CPUs without an
+/// `online` file (usually cpu0) can not be taken offline
+async fn cpufreq_dirs(cpu_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dir = read_dir(cpu_dir)
+        .await
+        .context(format!("Read {}", cpu_dir.display()))?;
+    let mut dirs = Vec::new();
+    while let Some(entry) = dir.next_entry().await? {
+        let name = entry.file_name();
+        let Some(cpu) = name.to_str().and_then(|x| x.strip_prefix("cpu")) else {
+            continue;
+        };
+        if cpu.is_empty() || !cpu.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        if let Ok(online) = read_to_string(entry.path().join("online")).await
+            && online.trim() == "0"
+        {
+            debug!("Skipping offline CPU {cpu}");
+            continue;
+        }
+        dirs.push(entry.path().join("cpufreq"));
+    }
+    if dirs.is_empty() {
+        bail!("No online CPUs found in {}", cpu_dir.display());
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+async fn read_setting(dir: &Path, setting: &str) -> Result<String> {
+    let file = dir.join(setting);
+    Ok(read_to_string(&file)
+        .await
+        .context(format!("Read {}", file.display()))?
+        .trim()
+        .to_owned())
+}
+
+async fn write_setting(dir: &Path, setting: &str, value: &str) -> Result<()> {
+    let file = dir.join(setting);
+    debug!("Setting {} to {value}", file.display());
+    write(&file, value)
+        .await
+        .context(format!("Write {}", file.display()))
+}
+
+/// Whether `scaling_max_freq` has to be written before `scaling_min_freq`, the kernel rejects a
+/// minimum above the current maximum and a maximum below the current minimum, so the maximum
+/// goes first unless it is below the current minimum
+fn max_freq_first(current_min: usize, max_freq: Option<usize>) -> bool {
+    max_freq.is_some_and(|max| max >= current_min)
+}
+
+/// Writes the frequency range (kHz) of a CPU in an order the kernel accepts, see [`max_freq_first`]
+async fn write_freq_range(
+    dir: &Path,
+    min_freq: Option<usize>,
+    max_freq: Option<usize>,
+) -> Result<()> {
+    let current_min = read_setting(dir, "scaling_min_freq")
+        .await?
+        .parse()
+        .context("Parse scaling_min_freq")?;
+    let mut writes = [
+        ("scaling_min_freq", min_freq),
+        ("scaling_max_freq", max_freq),
+    ];
+    if max_freq_first(current_min, max_freq) {
+        writes.reverse();
+    }
+    for (setting, value) in writes {
+        if let Some(value) = value {
+            write_setting(dir, setting, &value.to_string()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets the frequency governor of every online CPU, and optionally its frequency range in kHz, the
+/// original settings are kept until [`restore_cpu_governor`]
+pub async fn set_cpu_governor(
+    governor: &str,
+    min_freq: Option<usize>,
+    max_freq: Option<usize>,
+) -> Result<()> {
+    let dirs = cpufreq_dirs(Path::new(CPU_DIR)).await?;
+    let available = read_setting(&dirs[0], "scaling_available_governors").await?;
+    if !available.split_whitespace().any(|x| x == governor) {
+        bail!("CPU governor {governor} is not available, available governors are: {available}");
+    }
+
+    if ORIGINAL_SETTINGS.lock().unwrap().is_none() {
+        let mut original = Vec::new();
+        for dir in &dirs {
+            original.push(CpufreqSettings {
+                dir: dir.clone(),
+                governor: read_setting(dir, "scaling_governor").await?,
+                min_freq: read_setting(dir, "scaling_min_freq").await?,
+                max_freq: read_setting(dir, "scaling_max_freq").await?,
+            });
+        }
+        *ORIGINAL_SETTINGS.lock().unwrap() = Some(original);
+    }
+
+    // the governor first, as changing it can reset the frequency range
+    for dir in &dirs {
+        write_setting(dir, "scaling_governor", governor).await?;
+        write_freq_range(dir, min_freq, max_freq).await?;
+    }
+    Ok(())
+}
+
+/// Restores the settings from before the first [`set_cpu_governor`], if any were changed
+pub async fn restore_cpu_governor() -> Result<()> {
+    let Some(original) = ORIGINAL_SETTINGS.lock().unwrap().take() else {
+        return Ok(());
+    };
+    for settings in original {
+        write_setting(&settings.dir, "scaling_governor", &settings.governor).await?;
+        write_freq_range(
+            &settings.dir,
+            Some(
+                settings
+                    .min_freq
+                    .parse()
+                    .context("Parse scaling_min_freq")?,
+            ),
+            Some(
+                settings
+                    .max_freq
+                    .parse()
+                    .context("Parse scaling_max_freq")?,
+            ),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use super::*;
+
+    #[test]
+    fn writes_max_freq_first_unless_below_current_min() {
+        // raising the range, ie. 1-2 GHz to 3-4 GHz
+        assert!(max_freq_first(1_000_000, Some(4_000_000)));
+        // lowering the range, ie. 3-4 GHz to 1-2 GHz
+        assert!(!max_freq_first(3_000_000, Some(2_000_000)));
+        assert!(max_freq_first(2_000_000, Some(2_000_000)));
+        assert!(!max_freq_first(1_000_000, None));
+    }
+
+    #[tokio::test]
+    async fn lists_online_cpus() {
+        let cpu_dir = std::env::temp_dir().join("nvme-energy-bench-cpufreq-dirs");
+        _ = remove_dir_all(&cpu_dir);
+        for (cpu, online) in [("cpu0", None), ("cpu1", Some("1\n")), ("cpu2", Some("0\n"))] {
+            create_dir_all(cpu_dir.join(cpu).join("cpufreq")).unwrap();
+            if let Some(online) = online {
+                write(cpu_dir.join(cpu).join("online"), online).unwrap();
+            }
+        }
+        create_dir_all(cpu_dir.join("cpufreq")).unwrap();
+        create_dir_all(cpu_dir.join("cpuidle")).unwrap();
+
+        assert_eq!(
+            cpufreq_dirs(&cpu_dir).await.unwrap(),
+            vec![
+                cpu_dir.join("cpu0").join("cpufreq"),
+                cpu_dir.join("cpu1").join("cpufreq"),
+            ]
+        );
+        remove_dir_all(&cpu_dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use eyre::{Context, Result, bail};
+use tokio::{fs::write, process::Command};
+use tracing::warn;
+
+/// Kernel errors logged during a run, written to the run's directory if there were any
+pub const KERNEL_ERRORS_FILENAME: &str = "kernel_errors.txt";
+
+/// Log levels of `dmesg` that are checked
+const LEVELS: &str = "emerg,alert,crit,err";
+
+/// Error messages of the kernel log, `(seconds since boot, message)`
+async fn kernel_errors() -> Result<Vec<(f64, String)>> {
+    let output = Command::new("dmesg")
+        .arg(format!("--level={LEVELS}"))
+        .output()
+        .await
+        .context("Running dmesg")?;
+    if !output.status.success() {
+        bail!(
+            "dmesg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_line)
+        .collect())
+}
+
+/// Splits a line like `[ 1234.567890] nvme nvme0: controller is down` into its timestamp and
+/// message, lines without a timestamp can not be ordered and are skipped
+fn parse_line(line: &str) -> Option<(f64, String)> {
+    let (time, message) = line.strip_prefix('[')?.split_once(']')?;
+    Some((time.trim().parse().ok()?, message.trim().to_owned()))
+}
+
+/// Timestamp of the last kernel error, errors after it were logged during the run, see
+/// [`check_kernel_errors`]
+pub async fn kernel_log_mark() -> Result<f64> {
+    Ok(kernel_errors().await?.last().map_or(0.0, |x| x.0))
+}
+
+/// Names of the devices that kernel messages about them mention, ie. `nvme0n1` and its
+/// controller `nvme0`, device mapper symlinks are resolved to `dm-N`
+fn device_names(devices: &[&str]) -> Vec<String> {
+    let mut names = Vec::new();
+    for device in devices {
+        let resolved = Path::new(device)
+            .canonicalize()
+            .unwrap_or(Path::new(device).to_path_buf());
+        let Some(name) = resolved
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+        else {
+            continue;
+        };
+        if let Some((controller, _)) = name.strip_prefix("nvme").and_then(|x| x.split_once('n')) {
+            names.push(format!("nvme{controller}"));
+        }
+        names.push(name);
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Whether `message` mentions the device `name` as a whole word, so that `nvme0n1` does not match
+/// `nvme0n10`
+fn mentions_device(message: &str, name: &str) -> bool {
+    message
+        .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .any(|x| x == name)
+}
+
+/// Writes the kernel errors that mention one of `devices` and were logged after `mark` to
+/// [`KERNEL_ERRORS_FILENAME`] in the run's directory and warns about them, ie. controller resets
+/// or filesystem errors, which make the results of the run unreliable
+pub async fn check_kernel_errors(
+    final_results_dir: &Path,
+    devices: &[&str],
+    mark: f64,
+) -> Result<()> {
+    let names = device_names(devices);
+    let errors = kernel_errors()
+        .await?
+        .into_iter()
+        .filter(|(time, message)| *time > mark && names.iter().any(|x| mentions_device(message, x)))
+        .map(|(time, message)| format!("[{time:.6}] {message}"))
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    warn!(
+        "{} kernel errors about the device during {}, see {KERNEL_ERRORS_FILENAME}: {}",
+        errors.len(),
+        final_results_dir.display(),
+        errors[0]
+    );
+    write(
+        final_results_dir.join(KERNEL_ERRORS_FILENAME),
+        errors.join("\n") + "\n",
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dmesg_lines() {
+        assert_eq!(
+            parse_line("[ 1234.567890] nvme nvme0: controller is down; will reset"),
+            Some((
+                1234.56789,
+                "nvme nvme0: controller is down; will reset".to_owned()
+            ))
+        );
+        assert_eq!(parse_line("no timestamp"), None);
+    }
+
+    #[test]
+    fn nvme_namespace_includes_controller() {
+        assert_eq!(
+            device_names(&["/nonexistent/nvme1n1"]),
+            vec!["nvme1".to_owned(), "nvme1n1".to_owned()]
+        );
+        assert_eq!(
+            device_names(&["/nonexistent/loop7"]),
+            vec!["loop7".to_owned()]
+        );
+    }
+
+    #[test]
+    fn matches_exact_device_names() {
+        let message = "I/O error, dev nvme0n10, sector 2048 op 0x1:(WRITE)";
+        assert!(mentions_device(message, "nvme0n10"));
+        assert!(!mentions_device(message, "nvme0n1"));
+        assert!(!mentions_device(message, "nvme0"));
+        assert!(mentions_device("nvme nvme0: controller is down", "nvme0"));
+        assert!(mentions_device("device-mapper: dm-3: error", "dm-3"));
+    }
+}
@@ -1,9 +1,19 @@
+pub mod background;
 pub mod bench;
 pub mod config;
+pub mod cpu_governor;
+pub mod kernel_log;
+pub mod perf;
 pub mod plot;
+pub mod power_cap;
 pub mod sensor;
+pub mod summary;
+pub mod throttling;
 pub mod util;
 
-pub const MB_TO_MIB: f64 = 1_000_000.0 / 1_048_576.0;
+pub const MB_TO_MIB: f64 = util::BYTES_PER_MB / util::BYTES_PER_MIB;
 
 pub const RUN_NONROOT: &str = "common/src/run-nonroot.sh";
+
+/// Written into a run directory when the experiment failed and [`config::FailurePolicy::Continue`] is set
+pub const FAILED_MARKER: &str = "FAILED";
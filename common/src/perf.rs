@@ -0,0 +1,167 @@
+use std::{collections::BTreeMap, path::Path};
+
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{read_to_string, write};
+
+use crate::util::simple_command_with_output_no_dir;
+
+/// Raw `perf stat` output, written next to the run's results
+pub const PERF_OUTPUT_FILENAME: &str = "perf.txt";
+/// Parsed counters, see [`PerfCounter`]
+pub const PERF_FILENAME: &str = "perf.json";
+/// Field separator passed to `perf stat -x`, not a comma as event names and localized values can
+/// contain commas
+const PERF_SEPARATOR: &str = ";";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerfCounter {
+    /// `None` if the event was not counted or is not supported
+    pub value: Option<f64>,
+    pub unit: String,
+    /// Percentage of the runtime the counter was running, below 100 if events were multiplexed
+    pub running: Option<f64>,
+}
+
+/// Checks that `perf` is installed
+pub async fn check_perf() -> Result<()> {
+    simple_command_with_output_no_dir("perf", &["--version"])
+        .await
+        .context("perf is required for perf_events")?;
+    Ok(())
+}
+
+/// Wraps a benchmark command with `perf stat`, counting `events` into [`PERF_OUTPUT_FILENAME`]
+pub fn perf_stat_command(
+    program: &str,
+    args: &[String],
+    events: &[String],
+    final_results_dir: &Path,
+) -> (String, Vec<String>) {
+    let mut perf_args = vec![
+        "stat".to_owned(),
+        "-x".to_owned(),
+        PERF_SEPARATOR.to_owned(),
+        "-o".to_owned(),
+        final_results_dir
+            .join(PERF_OUTPUT_FILENAME)
+            .to_string_lossy()
+            .to_string(),
+    ];
+    // One `-e` per event, as events with modifiers like `cpu/event=0x3c,umask=0x0/` contain commas
+    for event in events {
+        perf_args.push("-e".to_owned());
+        perf_args.push(event.clone());
+    }
+    perf_args.push("--".to_owned());
+    perf_args.push(program.to_owned());
+    perf_args.extend_from_slice(args);
+    ("perf".to_owned(), perf_args)
+}
+
+/// Parses [`PERF_OUTPUT_FILENAME`] into [`PERF_FILENAME`]
+pub async fn write_perf_json(final_results_dir: &Path) -> Result<()> {
+    let path = final_results_dir.join(PERF_OUTPUT_FILENAME);
+    let output = read_to_string(&path)
+        .await
+        .context(format!("Reading {}", path.display()))?;
+    let counters = parse_perf_stat(&output);
+    write(
+        final_results_dir.join(PERF_FILENAME),
+        serde_json::to_string_pretty(&counters)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Parses the output of `perf stat -x ";"`, ie. `value;unit;event;run time;running %;...`, values
+/// with a decimal comma are accepted
+pub fn parse_perf_stat(output: &str) -> BTreeMap<String, PerfCounter> {
+    let parse_number = |x: &str| x.trim().replace(',', ".").parse().ok();
+    output
+        .lines()
+        .filter(|x| !x.trim().is_empty() && !x.starts_with('#'))
+        .filter_map(|line| {
+            let fields = line.split(PERF_SEPARATOR).collect::<Vec<_>>();
+            let event = fields.get(2)?.trim();
+            if event.is_empty() {
+                return None;
+            }
+            Some((
+                event.to_owned(),
+                PerfCounter {
+                    value: parse_number(fields[0]),
+                    unit: fields[1].trim().to_owned(),
+                    running: fields.get(4).and_then(|x| parse_number(x)),
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_perf_stat_output() {
+        let output = "# started on Thu Jan  1 00:00:00 2026
+
+1234;;cycles;1000000;100,00;;
+56,5;msec;task-clock;56500000;100.00;0.998;CPUs utilized
+789;;cpu/event=0x3c,umask=0x0/;500000;50.00;;
+<not counted>;;instructions;0;0.00;;
+<not supported>;;branch-misses;0;0.00;;
+";
+        let counters = parse_perf_stat(output);
+        let counter = |value, unit: &str, running| PerfCounter {
+            value,
+            unit: unit.to_owned(),
+            running,
+        };
+        assert_eq!(
+            counters,
+            BTreeMap::from([
+                ("cycles".to_owned(), counter(Some(1234.0), "", Some(100.0))),
+                (
+                    "task-clock".to_owned(),
+                    counter(Some(56.5), "msec", Some(100.0))
+                ),
+                (
+                    "cpu/event=0x3c,umask=0x0/".to_owned(),
+                    counter(Some(789.0), "", Some(50.0))
+                ),
+                ("instructions".to_owned(), counter(None, "", Some(0.0))),
+                ("branch-misses".to_owned(), counter(None, "", Some(0.0))),
+            ])
+        );
+    }
+
+    #[test]
+    fn passes_each_event_separately() {
+        let (program, args) = perf_stat_command(
+            "fio",
+            &["job.fio".to_owned()],
+            &["cycles".to_owned(), "cpu/event=0x3c,umask=0x0/".to_owned()],
+            Path::new("results"),
+        );
+        assert_eq!(program, "perf");
+        assert_eq!(
+            args,
+            [
+                "stat",
+                "-x",
+                ";",
+                "-o",
+                "results/perf.txt",
+                "-e",
+                "cycles",
+                "-e",
+                "cpu/event=0x3c,umask=0x0/",
+                "--",
+                "fio",
+                "job.fio"
+            ]
+        );
+    }
+}
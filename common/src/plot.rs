@@ -1,22 +1,23 @@
 use core::fmt::Debug;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
     path::{Path, PathBuf},
 };
 
 use downcast_rs::{Downcast, impl_downcast};
 use dyn_clone::{DynClone, clone_trait_object};
-use eyre::{Result, eyre};
+use eyre::{Context, Result, eyre};
 use futures::future::join_all;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, info, warn};
 
 use crate::{
-    bench::{BenchInfo, BenchParams},
+    FAILED_MARKER,
+    bench::{Bench, BenchInfo, BenchParams},
     config::{Config, Settings},
-    util::plot_python,
+    util::{plot_python, round_significant, write_table_csv},
 };
 use tokio::fs::create_dir_all;
 
@@ -33,6 +34,15 @@ pub enum PlotType {
 pub trait Plot: Debug + DynClone + Downcast + Send + Sync {
     /// The names of the sensors that this plot requires
     fn required_sensors(&self) -> &'static [&'static str];
+    /// Metrics this plot emits, listed by the `list-metrics` command
+    fn metrics(&self) -> &'static [PlotMetric] {
+        &[]
+    }
+    /// Checks the plot arguments against the benchmark of its experiment, run by `validate` and
+    /// before a benchmark starts, so that config errors are not only found while plotting
+    fn validate(&self, _bench: &dyn Bench) -> Result<()> {
+        Ok(())
+    }
     /// Plots the data
     ///
     /// Arguments:
@@ -59,6 +69,22 @@ pub trait Plot: Debug + DynClone + Downcast + Send + Sync {
 clone_trait_object!(Plot);
 impl_downcast!(Plot);
 
+/// Metric emitted by a plot, see [`Plot::metrics`]
+#[derive(Debug, Clone, Copy)]
+pub struct PlotMetric {
+    pub name: &'static str,
+    pub unit: &'static str,
+    /// Plot file the metric is written to, relative to the plot folder, `<name>` stands for the
+    /// experiment name
+    pub file: &'static str,
+}
+
+impl PlotMetric {
+    pub const fn new(name: &'static str, unit: &'static str, file: &'static str) -> Self {
+        Self { name, unit, file }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RunGroup {
     pub dir: String,
@@ -112,10 +138,13 @@ pub fn render_heatmaps(
     labels: &[String],
     plot_dir: &Path,
     jobs: &[HeatmapJob<'_>],
+    bench_info: &BenchInfo,
+    settings: &Settings,
 ) -> Result<()> {
     if jobs.is_empty() {
         return Ok(());
     }
+    let csv_format = &settings.csv_format_or_default();
 
     if !plot_dir.exists() {
         fs::create_dir_all(plot_dir)?;
@@ -126,6 +155,13 @@ pub fn render_heatmaps(
     }
 
     let labels_joined = labels.join(",");
+    // rows are in the order of the configured power states
+    let power_states = settings
+        .power_states()
+        .states()
+        .iter()
+        .map(|x| bench_info.power_state_label(*x))
+        .collect::<Vec<_>>();
 
     let jobs: Result<Vec<Vec<(String, String)>>> = jobs
         .iter()
@@ -143,8 +179,22 @@ pub fn render_heatmaps(
                 .ok_or_else(|| eyre!("Invalid filepath for heatmap: {:?}", job.filepath))?;
             let data_path = plot_data_dir.join(format!("{stem}.json"));
             fs::write(&data_path, serde_json::to_string(&job.data)?)?;
+            let num_power_states = job.data.first().map(|x| x.len()).unwrap_or_default();
+            let row_labels = (0..num_power_states)
+                .map(|x| power_states.get(x).cloned().unwrap_or(format!("ps{x}")))
+                .collect::<Vec<_>>();
+            write_table_csv(
+                &plot_data_dir.join(format!("{stem}.csv")),
+                std::iter::once(job.x_label.to_owned())
+                    .chain(row_labels.iter().cloned())
+                    .collect(),
+                labels,
+                &transpose(&job.data),
+                csv_format,
+                settings.precision(),
+            )?;
 
-            let args = vec![
+            let mut args = vec![
                 ("--data".to_owned(), data_path.to_str().unwrap().to_owned()),
                 (
                     "--filepath".to_owned(),
@@ -154,6 +204,7 @@ pub fn render_heatmaps(
                         .to_owned(),
                 ),
                 ("--col_labels".to_owned(), labels_joined.clone()),
+                ("--power_states".to_owned(), row_labels.join(",")),
                 ("--x_label".to_owned(), job.x_label.to_owned()),
                 ("--experiment_name".to_owned(), experiment_name.to_owned()),
                 ("--title".to_owned(), job.title.to_owned()),
@@ -162,6 +213,10 @@ pub fn render_heatmaps(
                     if job.reverse { "1" } else { "0" }.to_string(),
                 ),
             ];
+            if let Some(device) = &bench_info.device {
+                args.push(("--footnote".to_owned(), device.to_string()));
+            }
+            args.push(("--precision".to_owned(), settings.precision().to_string()));
             Ok(args)
         })
         .collect();
@@ -173,6 +228,355 @@ pub fn render_heatmaps(
     Ok(())
 }
 
+/// Divides every power state of a heatmap by PS0 of the same column, so that values above 1 are
+/// more efficient than PS0. For heatmaps where lower is better (`reverse`) PS0 is divided by the
+/// power state instead, cells without a PS0 value are `NaN`.
+pub fn normalize_to_ps0(data: &mut [Vec<f64>], reverse: bool) {
+    for column in data {
+        let base = column.first().copied().unwrap_or_default();
+        for value in column.iter_mut() {
+            *value = if base == 0.0 || *value == 0.0 {
+                f64::NAN
+            } else if reverse {
+                base / *value
+            } else {
+                *value / base
+            };
+        }
+    }
+}
+
+/// Heatmap over the grid of two variables, instead of over the power states like [`HeatmapJob`]
+pub struct GridHeatmapJob<'a> {
+    pub filepath: PathBuf,
+    /// Indexed by `[x][y]`, `NaN` for missing cells
+    pub data: Vec<Vec<f64>>,
+    pub title: &'a str,
+    pub x_label: &'a str,
+    pub x_ticks: &'a [String],
+    pub y_label: &'a str,
+    pub y_ticks: &'a [String],
+    pub reverse: bool,
+}
+
+pub fn render_grid_heatmap(
+    experiment_name: &str,
+    job: &GridHeatmapJob<'_>,
+    settings: &Settings,
+) -> Result<()> {
+    let plot_dir = job
+        .filepath
+        .parent()
+        .ok_or_else(|| eyre!("Invalid filepath for heatmap: {:?}", job.filepath))?;
+    let plot_data_dir = plot_dir.join("plot_data");
+    if !plot_data_dir.exists() {
+        fs::create_dir_all(&plot_data_dir)?;
+    }
+
+    let stem = job
+        .filepath
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| eyre!("Invalid filepath for heatmap: {:?}", job.filepath))?;
+    let data_path = plot_data_dir.join(format!("{stem}.json"));
+    fs::write(&data_path, serde_json::to_string(&job.data)?)?;
+    write_table_csv(
+        &plot_data_dir.join(format!("{stem}.csv")),
+        std::iter::once(format!("{}/{}", job.x_label, job.y_label))
+            .chain(job.y_ticks.iter().cloned())
+            .collect(),
+        job.x_ticks,
+        &transpose(&job.data),
+        &settings.csv_format_or_default(),
+        settings.precision(),
+    )?;
+
+    let x_ticks = job.x_ticks.join(",");
+    let y_ticks = job.y_ticks.join(",");
+    let precision = settings.precision().to_string();
+    plot_python(
+        "efficiency",
+        &[
+            ("--data", data_path.to_str().unwrap()),
+            (
+                "--filepath",
+                job.filepath
+                    .to_str()
+                    .ok_or_else(|| eyre!("Invalid filepath for heatmap"))?,
+            ),
+            ("--col_labels", x_ticks.as_str()),
+            ("--row_labels", y_ticks.as_str()),
+            ("--x_label", job.x_label),
+            ("--y_label", job.y_label),
+            ("--experiment_name", experiment_name),
+            ("--title", job.title),
+            ("--reverse", if job.reverse { "1" } else { "0" }),
+            ("--precision", precision.as_str()),
+        ],
+    )
+}
+
+/// Turns `[label][series]` into `[series][label]`, the layout of [`write_table_csv`]
+fn transpose(data: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let num_series = data.iter().map(|x| x.len()).max().unwrap_or_default();
+    (0..num_series)
+        .map(|idx| {
+            data.iter()
+                .map(|x| x.get(idx).copied().unwrap_or(f64::NAN))
+                .collect()
+        })
+        .collect()
+}
+
+/// Headlines of the experiments in the `efficiency` plot folder, see [`write_headline`]
+pub const HEADLINE_FILENAME: &str = "summary.json";
+
+/// Operating point of an experiment, a cell of its efficiency heatmaps
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatingPoint {
+    /// Label of the heatmap column, ie. the request size
+    pub label: String,
+    pub power_state: i32,
+    /// Primary throughput metric of the benchmark, ie. IOPS
+    pub throughput: f64,
+    /// Mean SSD power in W
+    pub ssd_power: f64,
+}
+
+impl OperatingPoint {
+    fn efficiency(&self) -> f64 {
+        if self.ssd_power > 0.0 {
+            self.throughput / self.ssd_power
+        } else {
+            f64::NAN
+        }
+    }
+}
+
+/// Single efficiency figure of an experiment, the operating point selected by [`select_headline`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Headline {
+    /// How the operating point was selected, ie. `max IO/J` or `max IO/s at <= 5 W`
+    pub criterion: String,
+    pub label: String,
+    pub power_state: i32,
+    pub throughput: f64,
+    pub throughput_unit: String,
+    pub ssd_power_w: f64,
+    pub efficiency: f64,
+    /// Unit of `efficiency`, ie. `IO/J`
+    pub efficiency_unit: String,
+}
+
+/// The operating point with the most work per Joule, or with the highest throughput at an SSD
+/// power of at most `power_cap` W if set. Points without a positive power are skipped.
+fn select_headline(points: &[OperatingPoint], power_cap: Option<f64>) -> Option<&OperatingPoint> {
+    let candidates = points
+        .iter()
+        .filter(|x| x.efficiency().is_finite() && power_cap.is_none_or(|cap| x.ssd_power <= cap));
+    match power_cap {
+        Some(_) => candidates.max_by(|a, b| a.throughput.total_cmp(&b.throughput)),
+        None => candidates.max_by(|a, b| a.efficiency().total_cmp(&b.efficiency())),
+    }
+}
+
+/// Selects the headline of an experiment from its operating points, logs it and adds it to
+/// [`HEADLINE_FILENAME`] in `plot_path`, keyed by the experiment name
+pub fn write_headline(
+    plot_path: &Path,
+    experiment_name: &str,
+    points: &[OperatingPoint],
+    (throughput_unit, efficiency_unit): (&str, &str),
+    power_cap: Option<f64>,
+    settings: &Settings,
+) -> Result<()> {
+    let criterion = match power_cap {
+        Some(cap) => format!("max {throughput_unit} at <= {cap} W"),
+        None => format!("max {efficiency_unit}"),
+    };
+    let Some(point) = select_headline(points, power_cap) else {
+        warn!("No operating point of {experiment_name} matches {criterion}, no headline");
+        return Ok(());
+    };
+
+    let precision = settings.precision();
+    let round = |x: f64| round_significant(x, precision);
+    let headline = Headline {
+        criterion,
+        label: point.label.clone(),
+        power_state: point.power_state,
+        throughput: round(point.throughput),
+        throughput_unit: throughput_unit.to_owned(),
+        ssd_power_w: round(point.ssd_power),
+        efficiency: round(point.efficiency()),
+        efficiency_unit: efficiency_unit.to_owned(),
+    };
+    info!(
+        "Headline of {experiment_name}: {} {} at {} in power state {} ({} {}, {} W, {})",
+        headline.efficiency,
+        headline.efficiency_unit,
+        headline.label,
+        headline.power_state,
+        headline.throughput,
+        headline.throughput_unit,
+        headline.ssd_power_w,
+        headline.criterion
+    );
+
+    let path = plot_path.join(HEADLINE_FILENAME);
+    let mut headlines = match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str::<BTreeMap<String, Headline>>(&data)
+            .context(format!("Parsing {}", path.display()))?,
+        Err(_) => BTreeMap::new(),
+    };
+    headlines.insert(experiment_name.to_owned(), headline);
+    fs::write(&path, serde_json::to_string_pretty(&headlines)?)?;
+    Ok(())
+}
+
+/// Scatter of the throughput over the SSD power of every operating point, the non-dominated points
+/// of [`pareto_front`] are highlighted and connected
+pub struct ParetoJob<'a> {
+    pub filepath: PathBuf,
+    pub points: Vec<OperatingPoint>,
+    pub title: &'a str,
+    /// Label of the power axis
+    pub x_label: &'a str,
+    /// Label of the throughput axis
+    pub y_label: &'a str,
+}
+
+/// Point of [`ParetoSpec`], a run of the experiment
+#[derive(Serialize)]
+struct ParetoPoint<'a> {
+    label: &'a str,
+    /// Name of the power state, the legend entry of the point
+    power_state: String,
+    ssd_power: f64,
+    throughput: f64,
+    pareto: bool,
+}
+
+/// Data behind a Pareto plot, written to `plot_data/<name>.pareto.json` next to the plot
+#[derive(Serialize)]
+struct ParetoSpec<'a> {
+    points: Vec<ParetoPoint<'a>>,
+    title: &'a str,
+    x_label: &'a str,
+    y_label: &'a str,
+    output_path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    footnote: Option<String>,
+}
+
+/// Indices of the points that no other point beats in both throughput and power, ordered by
+/// power. Points without a positive power or a finite throughput are never on the front.
+pub fn pareto_front(points: &[OperatingPoint]) -> Vec<usize> {
+    let mut order = (0..points.len())
+        .filter(|x| {
+            let point = &points[*x];
+            point.ssd_power > 0.0 && point.ssd_power.is_finite() && point.throughput.is_finite()
+        })
+        .collect::<Vec<_>>();
+    // among points of equal power only the fastest can be on the front
+    order.sort_by(|a, b| {
+        points[*a]
+            .ssd_power
+            .total_cmp(&points[*b].ssd_power)
+            .then(points[*b].throughput.total_cmp(&points[*a].throughput))
+    });
+
+    let mut front = Vec::new();
+    let mut best = f64::NEG_INFINITY;
+    for idx in order {
+        if points[idx].throughput > best {
+            best = points[idx].throughput;
+            front.push(idx);
+        }
+    }
+    front
+}
+
+pub fn render_pareto(
+    experiment_name: &str,
+    job: &ParetoJob<'_>,
+    bench_info: &BenchInfo,
+    settings: &Settings,
+) -> Result<()> {
+    if job.points.is_empty() {
+        return Ok(());
+    }
+
+    let plot_dir = job
+        .filepath
+        .parent()
+        .ok_or_else(|| eyre!("Invalid filepath for Pareto plot: {:?}", job.filepath))?;
+    let plot_data_dir = plot_dir.join("plot_data");
+    if !plot_data_dir.exists() {
+        fs::create_dir_all(&plot_data_dir)?;
+    }
+    let stem = job
+        .filepath
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| eyre!("Invalid filepath for Pareto plot: {:?}", job.filepath))?;
+
+    let front = pareto_front(&job.points);
+    if front.is_empty() {
+        warn!("No run of {experiment_name} has a positive SSD power, no Pareto front");
+    }
+    let points = job
+        .points
+        .iter()
+        .enumerate()
+        .map(|(idx, point)| ParetoPoint {
+            label: &point.label,
+            power_state: usize::try_from(point.power_state)
+                .ok()
+                .and_then(|x| bench_info.device_power_states.get(x))
+                .map_or_else(|| "Default".to_owned(), |x| x.1.clone()),
+            ssd_power: point.ssd_power,
+            throughput: point.throughput,
+            pareto: front.contains(&idx),
+        })
+        .collect::<Vec<_>>();
+    write_table_csv(
+        &plot_data_dir.join(format!("{stem}.csv")),
+        ["label", "ssd_power", "throughput", "pareto"]
+            .map(|x| x.to_owned())
+            .to_vec(),
+        &points
+            .iter()
+            .map(|x| format!("{} {}", x.label, x.power_state))
+            .collect::<Vec<_>>(),
+        &[
+            points.iter().map(|x| x.ssd_power).collect(),
+            points.iter().map(|x| x.throughput).collect(),
+            points
+                .iter()
+                .map(|x| if x.pareto { 1.0 } else { 0.0 })
+                .collect(),
+        ],
+        &settings.csv_format_or_default(),
+        settings.precision(),
+    )?;
+
+    let spec_path = plot_data_dir.join(format!("{stem}.pareto.json"));
+    let spec = ParetoSpec {
+        points,
+        title: job.title,
+        x_label: job.x_label,
+        y_label: job.y_label,
+        output_path: job
+            .filepath
+            .to_str()
+            .ok_or_else(|| eyre!("Invalid filepath for Pareto plot"))?,
+        footnote: bench_info.device.as_ref().map(|x| x.to_string()),
+    };
+    fs::write(&spec_path, serde_json::to_string(&spec)?)?;
+    plot_python("pareto", &[("--spec", spec_path.to_str().unwrap())])
+}
+
 pub async fn plot(
     plots: &Option<Vec<Box<dyn Plot>>>,
     plot_type: PlotType,
@@ -189,6 +593,10 @@ pub async fn plot(
         return Ok(());
     }
 
+    let dirs = dirs
+        .into_iter()
+        .filter(|dir| !data_path.join(dir).join(FAILED_MARKER).exists())
+        .collect::<Vec<_>>();
     let plots = plots.as_ref().unwrap();
     for plot in plots {
         plot.plot(
@@ -205,3 +613,44 @@ pub async fn plot(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(label: &str, power_state: i32, throughput: f64, ssd_power: f64) -> OperatingPoint {
+        OperatingPoint {
+            label: label.to_owned(),
+            power_state,
+            throughput,
+            ssd_power,
+        }
+    }
+
+    #[test]
+    fn headline_is_most_efficient_or_fastest_under_cap() {
+        let points = [
+            point("4k", 0, 1000.0, 8.0),
+            point("4k", 2, 600.0, 3.0),
+            point("128k", 0, 1500.0, 10.0),
+            point("128k", 1, 0.0, 0.0),
+        ];
+        assert_eq!(select_headline(&points, None), Some(&points[1]));
+        assert_eq!(select_headline(&points, Some(9.0)), Some(&points[0]));
+        assert_eq!(select_headline(&points, Some(1.0)), None);
+    }
+
+    #[test]
+    fn pareto_front_is_not_dominated() {
+        let points = [
+            point("4k", 0, 1000.0, 8.0),
+            point("4k", 2, 600.0, 3.0),
+            point("128k", 0, 900.0, 9.0),
+            point("128k", 1, 600.0, 4.0),
+            point("128k", 2, 1500.0, 10.0),
+            point("256k", 0, 2000.0, 0.0),
+        ];
+        assert_eq!(pareto_front(&points), [1, 0, 4]);
+        assert!(pareto_front(&[]).is_empty());
+    }
+}
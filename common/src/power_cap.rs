@@ -0,0 +1,77 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use eyre::{Context, Result, bail};
+use tokio::fs::{read_dir, read_to_string, write};
+use tracing::debug;
+
+const POWERCAP_DIR: &str = "/sys/class/powercap";
+const LIMIT_FILENAME: &str = "constraint_0_power_limit_uw";
+
+/// Limits of every package before the first cap was set, restored by [`restore_rapl_power_limit`]
+static ORIGINAL_LIMITS: Mutex<Option<Vec<(PathBuf, u64)>>> = Mutex::new(None);
+
+/// `constraint_0_power_limit_uw` of every package zone, ie. `intel-rapl:0`, without subzones
+async fn package_limit_files() -> Result<Vec<PathBuf>> {
+    let mut dir = read_dir(POWERCAP_DIR)
+        .await
+        .context(format!("Read {POWERCAP_DIR}"))?;
+    let mut files = Vec::new();
+    while let Some(entry) = dir.next_entry().await? {
+        let name = entry.file_name();
+        let Some(package) = name.to_str().and_then(|x| x.strip_prefix("intel-rapl:")) else {
+            continue;
+        };
+        if package.chars().all(|c| c.is_ascii_digit()) {
+            files.push(entry.path().join(LIMIT_FILENAME));
+        }
+    }
+    if files.is_empty() {
+        bail!("No RAPL packages found in {POWERCAP_DIR}");
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Caps the long term power limit of every CPU package to `watts`, the original limits are kept
+/// until [`restore_rapl_power_limit`]
+pub async fn set_rapl_power_limit(watts: f64) -> Result<()> {
+    if watts.is_nan() || watts <= 0.0 {
+        bail!("RAPL power limit must be greater than 0, got {watts}");
+    }
+
+    let files = package_limit_files().await?;
+    if ORIGINAL_LIMITS.lock().unwrap().is_none() {
+        let mut original = Vec::new();
+        for file in &files {
+            let limit = read_to_string(file)
+                .await
+                .context(format!("Read {}", file.display()))?
+                .trim()
+                .parse()?;
+            original.push((file.clone(), limit));
+        }
+        *ORIGINAL_LIMITS.lock().unwrap() = Some(original);
+    }
+
+    let limit = ((watts * 1e6) as u64).to_string();
+    for file in &files {
+        debug!("Setting {} to {limit}", file.display());
+        write(file, &limit)
+            .await
+            .context(format!("Write {}", file.display()))?;
+    }
+    Ok(())
+}
+
+/// Restores the limits from before the first [`set_rapl_power_limit`], if any were changed
+pub async fn restore_rapl_power_limit() -> Result<()> {
+    let Some(original) = ORIGINAL_LIMITS.lock().unwrap().take() else {
+        return Ok(());
+    };
+    for (file, limit) in original {
+        write(&file, limit.to_string())
+            .await
+            .context(format!("Write {}", file.display()))?;
+    }
+    Ok(())
+}
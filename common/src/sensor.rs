@@ -1,13 +1,30 @@
 use core::fmt::Debug;
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
 
 use downcast_rs::{Downcast, impl_downcast};
 use dyn_clone::{DynClone, clone_trait_object};
-use eyre::Result;
+use eyre::{Context, Result, bail};
 use flume::{Receiver, Sender};
-use tokio::task::JoinHandle;
+use futures::future::join_all;
+use nix::{
+    sched::{CpuSet, sched_setaffinity},
+    unistd::Pid,
+};
+use tokio::{
+    fs::{create_dir_all, read_to_string, write},
+    runtime::{Builder, Runtime},
+    sync::watch,
+    task::JoinHandle,
+    time::{MissedTickBehavior, interval},
+};
+use tracing::{error, warn};
 
-use crate::config::Settings;
+use crate::config::{Sensor as SensorConfig, Settings};
 
 #[derive(Debug)]
 pub enum SensorRequest {
@@ -37,12 +54,14 @@ pub trait Sensor: Debug + Send + Sync {
     ///
     /// Arguments:
     /// * `args` - Specific arguments to the sensor
+    /// * `filename` - Filename to write sensor data to, see [`sensor_filename`]
     /// * `rx` - Requests to the sensor to start/stop recording
     /// * `tx` - Replies from the sensor when its done flushing data to disk, after [`SensorRequest::StopRecording`] is received
     fn start(
         &self,
         args: &dyn SensorArgs,
         settings: &Settings,
+        filename: &str,
         rx: Receiver<SensorRequest>,
         tx: Sender<SensorReply>,
     ) -> Result<JoinHandle<Result<()>>>;
@@ -52,6 +71,382 @@ pub trait Sensor: Debug + Send + Sync {
 /// All [`SensorArgs`] implementations are expected to implement [`Default`]
 pub trait SensorArgs: Debug + DynClone + Downcast + Send + Sync {
     fn name(&self) -> sensor_common::SensorKind;
+    /// Intended time between readings, a warning is logged when a recording misses it, `None` if
+    /// the sensor reads as fast as it can
+    fn sample_interval(&self) -> Option<Duration> {
+        None
+    }
 }
 clone_trait_object!(SensorArgs);
 impl_downcast!(SensorArgs);
+
+static SENSOR_RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Runtime to start the sensors on when `sensor_cpu` is set, all of its threads (including
+/// blocking ones) are pinned to `cpu` so that sensor readers do not compete with the benchmark
+pub fn sensor_runtime(cpu: usize) -> Result<&'static Runtime> {
+    if let Some(runtime) = SENSOR_RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    // fail early on an invalid core, errors in `on_thread_start` can only be logged
+    std::thread::spawn(move || pin_thread(cpu))
+        .join()
+        .map_err(|_| eyre::eyre!("Pinning thread panicked"))?
+        .context(format!("Pin sensors to CPU {cpu}"))?;
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("sensor")
+        .on_thread_start(move || {
+            if let Err(err) = pin_thread(cpu) {
+                error!("Could not pin sensor thread to CPU {cpu}: {err}");
+            }
+        })
+        .enable_all()
+        .build()?;
+    Ok(SENSOR_RUNTIME.get_or_init(|| runtime))
+}
+
+static SAMPLE_TICKER: OnceLock<SampleTicker> = OnceLock::new();
+
+/// Shared ticker of [`Settings::sync_sample_interval`]
+#[derive(Debug, Clone)]
+pub struct SampleTicker {
+    pub period: Duration,
+    /// Instant of the last tick
+    pub rx: watch::Receiver<Instant>,
+}
+
+impl SampleTicker {
+    /// Interval a sensor with `interval` between readings is expected to achieve when reading on
+    /// ticks, its interval rounded up to whole ticks
+    pub fn target_interval(&self, interval: Option<Duration>) -> Duration {
+        match interval {
+            Some(interval) => {
+                self.period * interval.as_nanos().div_ceil(self.period.as_nanos()).max(1) as u32
+            }
+            None => self.period,
+        }
+    }
+}
+
+/// Starts the ticker of [`Settings::sync_sample_interval`] on the current runtime, it broadcasts
+/// the instant of every tick to the sensor readers, which then all read at the same instants
+pub fn start_sample_ticker(period: Duration) {
+    let (tx, rx) = watch::channel(Instant::now());
+    if SAMPLE_TICKER.set(SampleTicker { period, rx }).is_err() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            let tick = ticker.tick().await;
+            if tx.send(tick.into_std()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Receiver of the shared ticker, `None` if every sensor samples at its own pace
+pub fn sample_ticker() -> Option<SampleTicker> {
+    SAMPLE_TICKER.get().cloned()
+}
+
+/// Latest reading of every sensor by its filename, waiting to be pushed to
+/// [`Settings::prometheus_push_gateway`], see [`ReadingPusher`]
+static PUSH_LATEST: OnceLock<std::sync::Mutex<HashMap<String, PushedReading>>> = OnceLock::new();
+
+/// Interval at which the latest reading of every sensor is pushed
+const PUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// Name of the gauge of every sensor column, see [`push_body`]
+const PUSH_METRIC: &str = "nvme_energy_bench_sensor";
+
+/// A reading of a sensor, stored by [`ReadingPusher::push`]
+#[derive(Debug)]
+struct PushedReading {
+    columns: Vec<String>,
+    values: Vec<String>,
+}
+
+/// Starts pushing the readings of the sensors to the Prometheus pushgateway at `url` on the current
+/// runtime, see [`Settings::prometheus_push_gateway`]
+pub fn start_prometheus_push(url: &str) -> Result<()> {
+    if PUSH_LATEST.set(Default::default()).is_err() {
+        return Ok(());
+    }
+    let client = reqwest::Client::builder().timeout(PUSH_INTERVAL).build()?;
+    let url = url.trim_end_matches('/').to_owned();
+
+    tokio::spawn(async move {
+        let mut ticker = interval(PUSH_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut failing = false;
+        loop {
+            ticker.tick().await;
+            let latest = match PUSH_LATEST.get().map(|x| x.lock()) {
+                Some(Ok(mut latest)) => std::mem::take(&mut *latest),
+                _ => break,
+            };
+            // every sensor is pushed at once, so that a slow gateway delays them by at most one
+            // timeout
+            let pushes = latest.iter().map(|(filename, reading)| {
+                client
+                    .put(format!(
+                        "{url}/metrics/job/nvme-energy-bench/sensor/{filename}"
+                    ))
+                    .body(push_body(reading))
+                    .send()
+            });
+            for result in join_all(pushes).await {
+                match result.and_then(|x| x.error_for_status()) {
+                    Ok(_) => failing = false,
+                    // logged once until a push succeeds again
+                    Err(err) if !failing => {
+                        failing = true;
+                        warn!("Could not push sensor readings to {url}: {err}");
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Stores the readings of the sensor writing to `filename` to be pushed, if
+/// [`Settings::prometheus_push_gateway`] is set. Only the latest reading is pushed, so readings
+/// are only stored every [`PUSH_INTERVAL`], before they are formatted.
+pub struct ReadingPusher {
+    filename: String,
+    columns: Vec<String>,
+    stored: Option<Instant>,
+}
+
+impl ReadingPusher {
+    pub fn new(filename: &str, columns: &[String]) -> Self {
+        Self {
+            filename: filename.to_owned(),
+            columns: columns.to_vec(),
+            stored: None,
+        }
+    }
+
+    pub fn push<T>(&mut self, values: &T)
+    where
+        T: IntoIterator + Clone,
+        T::Item: ToString,
+    {
+        let Some(latest) = PUSH_LATEST.get() else {
+            return;
+        };
+        if self.stored.is_some_and(|x| x.elapsed() < PUSH_INTERVAL) {
+            return;
+        }
+        self.stored = Some(Instant::now());
+        let reading = PushedReading {
+            columns: self.columns.clone(),
+            values: values.clone().into_iter().map(|x| x.to_string()).collect(),
+        };
+        if let Ok(mut latest) = latest.lock() {
+            latest.insert(self.filename.clone(), reading);
+        }
+    }
+}
+
+/// Reading in the Prometheus text format, one sample of [`PUSH_METRIC`] per column, ie.
+/// `nvme_energy_bench_sensor{column="Total"} 6.2`, the sensor's filename is the `sensor` label of
+/// the pushgateway group
+fn push_body(reading: &PushedReading) -> String {
+    let mut body = format!("# TYPE {PUSH_METRIC} gauge\n");
+    for (column, value) in reading.columns.iter().zip(&reading.values) {
+        let column = column
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        let value = match value.as_str() {
+            "inf" => "+Inf",
+            "-inf" => "-Inf",
+            value => value,
+        };
+        body.push_str(&format!("{PUSH_METRIC}{{column=\"{column}\"}} {value}\n"));
+    }
+    body
+}
+
+fn pin_thread(cpu: usize) -> nix::Result<()> {
+    let mut set = CpuSet::new();
+    set.set(cpu)?;
+    sched_setaffinity(Pid::from_raw(0), &set)
+}
+
+/// Data filename of a sensor instance, ie. `powersensor3-<alias>.csv` for an instance with an alias
+/// and `powersensor3.csv` otherwise
+pub fn sensor_filename(filename: &str, alias: Option<&str>) -> String {
+    match (alias, filename.rsplit_once('.')) {
+        (Some(alias), Some((stem, ext))) => format!("{stem}-{alias}.{ext}"),
+        (Some(alias), None) => format!("{filename}-{alias}"),
+        (None, _) => filename.to_owned(),
+    }
+}
+
+/// Data filename of the Powersensor3 sensor, see [`powersensor3_filename`]
+pub const POWERSENSOR3_FILENAME: &str = "powersensor3.csv";
+
+/// Data filename of the configured instance of a sensor kind with `alias`, or the one without an
+/// alias if `alias` is None. The filename of a sensor kind that is not configured at all is
+/// returned as is, as its data is simply missing from the run.
+pub fn instance_filename(
+    sensors: &[SensorConfig],
+    kind: &str,
+    filename: &str,
+    alias: Option<&str>,
+) -> Result<String> {
+    let configured = sensors
+        .iter()
+        .filter(|x| x.sensor.to_string() == kind)
+        .map(|x| x.alias.as_deref())
+        .collect::<Vec<_>>();
+    check_instance(&configured, kind, alias)?;
+    Ok(sensor_filename(filename, alias))
+}
+
+fn check_instance(configured: &[Option<&str>], kind: &str, alias: Option<&str>) -> Result<()> {
+    if configured.is_empty() || configured.contains(&alias) {
+        return Ok(());
+    }
+    match alias {
+        Some(alias) => bail!("No {kind} sensor has the alias {alias}"),
+        None => bail!("Every {kind} sensor has an alias, select the one to use by its alias"),
+    }
+}
+
+/// Data filename of the Rapl sensor, see [`rapl_filename`]
+pub const RAPL_FILENAME: &str = "rapl.csv";
+
+/// Data filename of the Powersensor3 instance measuring the SSD, see [`instance_filename`]
+pub fn powersensor3_filename(sensors: &[SensorConfig], alias: Option<&str>) -> Result<String> {
+    instance_filename(sensors, "Powersensor3", POWERSENSOR3_FILENAME, alias)
+}
+
+/// Data filename of the Rapl instance, see [`instance_filename`]
+pub fn rapl_filename(sensors: &[SensorConfig], alias: Option<&str>) -> Result<String> {
+    instance_filename(sensors, "Rapl", RAPL_FILENAME, alias)
+}
+
+/// Directory in a run's directory with the sensor CSVs whose columns are renamed, see
+/// [`Settings::sensor_column_names`]
+pub const SENSOR_EXPORT_DIRNAME: &str = "export";
+
+/// Copies the sensor CSVs of a run to [`SENSOR_EXPORT_DIRNAME`] with their columns renamed, the
+/// CSVs in the run's directory keep the names the plots read
+pub async fn export_sensor_csvs(
+    run_dir: &Path,
+    filenames: &[String],
+    names: &HashMap<String, String>,
+) -> Result<()> {
+    let export_dir = run_dir.join(SENSOR_EXPORT_DIRNAME);
+    create_dir_all(&export_dir).await?;
+    for filename in filenames {
+        let Ok(csv) = read_to_string(run_dir.join(filename)).await else {
+            continue;
+        };
+        let (header, rows) = csv.split_once('\n').unwrap_or((&csv, ""));
+        write(
+            export_dir.join(filename),
+            format!("{}\n{rows}", rename_columns(header, names)),
+        )
+        .await
+        .context(format!("Export {filename}"))?;
+    }
+    Ok(())
+}
+
+/// Renames the columns of a CSV header, columns without a new name are kept
+fn rename_columns(header: &str, names: &HashMap<String, String>) -> String {
+    header
+        .split(',')
+        .map(|x| names.get(x).map_or(x, |x| x.as_str()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Aliases of the sensor instances recorded in a run directory, see [`sensor_filename`]
+pub fn recorded_aliases(run_dir: &Path, filename: &str) -> Vec<String> {
+    let (stem, ext) = filename.rsplit_once('.').unwrap_or((filename, ""));
+    let prefix = format!("{stem}-");
+    let suffix = format!(".{ext}");
+    let Ok(entries) = std::fs::read_dir(run_dir) else {
+        return Vec::new();
+    };
+    let mut aliases = entries
+        .filter_map(|x| x.ok())
+        .filter_map(|x| {
+            x.file_name()
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .strip_suffix(&suffix)
+                .map(str::to_owned)
+        })
+        .collect::<Vec<_>>();
+    aliases.sort();
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_columns_keeps_unmapped_columns() {
+        let names = HashMap::from([
+            ("cpu-0-freq".to_owned(), "cpu0_freq_mhz".to_owned()),
+            ("Total".to_owned(), "power_w".to_owned()),
+        ]);
+        assert_eq!(
+            rename_columns("time,cpu-0-freq,cpu-0-load", &names),
+            "time,cpu0_freq_mhz,cpu-0-load"
+        );
+        assert_eq!(rename_columns("time,Total", &names), "time,power_w");
+    }
+
+    #[test]
+    fn check_instance_requires_a_configured_alias() {
+        assert!(check_instance(&[], "Powersensor3", None).is_ok());
+        assert!(check_instance(&[], "Powersensor3", Some("ssd")).is_ok());
+        assert!(check_instance(&[None, Some("ssd")], "Powersensor3", None).is_ok());
+        assert!(check_instance(&[None, Some("ssd")], "Powersensor3", Some("ssd")).is_ok());
+        assert!(check_instance(&[Some("ssd1"), Some("ssd2")], "Powersensor3", None).is_err());
+        assert!(check_instance(&[None], "Powersensor3", Some("ssd")).is_err());
+    }
+
+    #[test]
+    fn pushes_one_reading_per_interval() {
+        let latest = PUSH_LATEST.get_or_init(Default::default);
+        let mut pusher = ReadingPusher::new("pusher.csv", &["Total".to_owned()]);
+        pusher.push(&vec![1.5]);
+        pusher.push(&vec![2.5]);
+        assert_eq!(latest.lock().unwrap()["pusher.csv"].values, ["1.5"]);
+    }
+
+    #[test]
+    fn push_body_is_prometheus_text() {
+        let reading = PushedReading {
+            columns: vec![
+                "package-0".to_owned(),
+                "a\"b".to_owned(),
+                "Total".to_owned(),
+            ],
+            values: vec!["12.5".to_owned(), "NaN".to_owned(), "inf".to_owned()],
+        };
+        assert_eq!(
+            push_body(&reading),
+            "# TYPE nvme_energy_bench_sensor gauge\n\
+             nvme_energy_bench_sensor{column=\"package-0\"} 12.5\n\
+             nvme_energy_bench_sensor{column=\"a\\\"b\"} NaN\n\
+             nvme_energy_bench_sensor{column=\"Total\"} +Inf\n"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::{path::Path, time::Duration};
+
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{read_to_string, write};
+use tracing::info;
+
+use crate::{
+    bench::MARKERS_FILENAME,
+    config::Config,
+    sensor::{powersensor3_filename, rapl_filename},
+    throttling::ThrottleEvent,
+    util::{
+        SectionStats, calculate_named_sections, calculate_sectioned, power_energy_calculator,
+        round_significant,
+    },
+};
+
+/// Post-processing results of a single run, kept in its results directory
+pub const SUMMARY_FILENAME: &str = "summary.json";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    /// Sustained drops of CPU frequency or SSD power during the run, see
+    /// [`crate::config::ThrottleDetection`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttling: Option<Vec<ThrottleEvent>>,
+    /// Wall-clock time of the benchmark program in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_time_s: Option<f64>,
+    /// Energy consumed by the SSD during the run in Joules, from Powersensor3
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssd_energy_j: Option<f64>,
+    /// Energy consumed by the CPU during the run in Joules, from Rapl
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_energy_j: Option<f64>,
+    /// Primary throughput metric of the benchmark, see [`crate::bench::Bench::primary_metric`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput: Option<Throughput>,
+    /// SSD power and energy between the markers of a run whose benchmark wrote them, see
+    /// [`crate::bench::MarkerWriter`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sections: Option<Vec<SectionSummary>>,
+}
+
+/// SSD power and energy of a section of a run, from a marker to the next one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SectionSummary {
+    /// Name of the marker the section starts at, see [`crate::util::NamedSection`]
+    pub name: String,
+    pub start_ms: usize,
+    pub end_ms: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssd_power_w: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssd_energy_j: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Throughput {
+    pub value: f64,
+    /// Unit of `value`, ie. `MiB/s`
+    pub unit: String,
+}
+
+impl RunSummary {
+    /// Reads the summary of a run, or an empty summary if none was written yet
+    pub async fn read(final_results_dir: &Path) -> Result<Self> {
+        let path = final_results_dir.join(SUMMARY_FILENAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&read_to_string(&path).await?)
+            .context(format!("Parsing {}", path.display()))
+    }
+
+    /// Records the wall time, SSD and CPU energy and throughput of a run and logs them in a single
+    /// line, energy is only recorded if the sensor was used
+    pub async fn record_run(
+        final_results_dir: &Path,
+        wall_time: Duration,
+        throughput: Option<Throughput>,
+        config: &Config,
+        device_max_power: f64,
+    ) -> Result<()> {
+        let ssd_csv = final_results_dir.join(powersensor3_filename(&config.sensors, None)?);
+        let ssd_energy_j = sensor_stats(&ssd_csv, device_max_power)
+            .await?
+            .and_then(|x| x.energy);
+        let sections = match (
+            read_to_string(final_results_dir.join(MARKERS_FILENAME)).await,
+            read_to_string(&ssd_csv).await,
+        ) {
+            (Ok(markers), Ok(csv)) => Some(section_summaries(&markers, &csv, device_max_power)?),
+            _ => None,
+        };
+        let cpu_energy_j = sensor_stats(
+            &final_results_dir.join(rapl_filename(&config.sensors, None)?),
+            config.settings.cpu_max_power_watts,
+        )
+        .await?
+        .and_then(|x| x.energy);
+
+        let fmt_energy = |energy: Option<f64>| {
+            energy
+                .map(|x| format!("{x:.1} J"))
+                .unwrap_or_else(|| "-".to_owned())
+        };
+        info!(
+            "Run {}: wall time {:.1}s, SSD energy {}, CPU energy {}, throughput {}",
+            final_results_dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            wall_time.as_secs_f64(),
+            fmt_energy(ssd_energy_j),
+            fmt_energy(cpu_energy_j),
+            throughput
+                .as_ref()
+                .map(|x| format!("{:.2} {}", x.value, x.unit))
+                .unwrap_or_else(|| "-".to_owned())
+        );
+
+        let precision = config.settings.precision();
+        let round = |x: f64| round_significant(x, precision);
+        let mut summary = Self::read(final_results_dir).await?;
+        summary.wall_time_s = Some(round(wall_time.as_secs_f64()));
+        summary.ssd_energy_j = ssd_energy_j.map(round);
+        summary.cpu_energy_j = cpu_energy_j.map(round);
+        summary.throughput = throughput.map(|x| Throughput {
+            value: round(x.value),
+            unit: x.unit,
+        });
+        summary.sections = sections.map(|sections| {
+            sections
+                .into_iter()
+                .map(|x| SectionSummary {
+                    ssd_power_w: x.ssd_power_w.map(round),
+                    ssd_energy_j: x.ssd_energy_j.map(round),
+                    ..x
+                })
+                .collect()
+        });
+        summary.write(final_results_dir).await
+    }
+
+    pub async fn write(&self, final_results_dir: &Path) -> Result<()> {
+        write(
+            final_results_dir.join(SUMMARY_FILENAME),
+            serde_json::to_string_pretty(self)?,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Power and energy of the `Total` column of a sensor CSV over the whole run, computed like the
+/// plots do, or None if the sensor was not recorded
+pub async fn sensor_stats(csv: &Path, max_power: f64) -> Result<Option<SectionStats>> {
+    let Ok(csv) = read_to_string(csv).await else {
+        return Ok(None);
+    };
+    let (_, overall, _) = calculate_sectioned::<_, 0>(
+        None,
+        &csv,
+        &["Total"],
+        &[(0.0, max_power)],
+        power_energy_calculator,
+        None,
+    )?;
+    Ok(Some(overall))
+}
+
+/// SSD power and energy of every section between the markers of a run
+fn section_summaries(markers: &str, ssd_csv: &str, max_power: f64) -> Result<Vec<SectionSummary>> {
+    let (sections, _) = calculate_named_sections(
+        markers,
+        ssd_csv,
+        &["Total"],
+        &[(0.0, max_power)],
+        &[],
+        power_energy_calculator,
+    )?;
+    Ok(sections
+        .into_iter()
+        .map(|x| SectionSummary {
+            name: x.name,
+            start_ms: x.start,
+            end_ms: x.end,
+            ssd_power_w: x.stats.power_mean,
+            ssd_energy_j: x.stats.energy,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_sections_between_markers() {
+        let sections = section_summaries(
+            "time,marker_name\n0,create-fileset\n2000,benchmark-done\n",
+            "time,Total\n0,2\n1000,2\n2000,4\n3000,4\n",
+            10.0,
+        )
+        .unwrap();
+        assert_eq!(
+            sections,
+            [
+                SectionSummary {
+                    name: "create-fileset".to_owned(),
+                    start_ms: 0,
+                    end_ms: 2000,
+                    ssd_power_w: Some(2.0),
+                    ssd_energy_j: Some(2.0),
+                },
+                SectionSummary {
+                    name: "benchmark-done".to_owned(),
+                    start_ms: 2000,
+                    end_ms: 3000,
+                    ssd_power_w: Some(4.0),
+                    ssd_energy_j: Some(4.0),
+                },
+            ]
+        );
+    }
+}
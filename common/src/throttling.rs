@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use csv::ReaderBuilder;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::fs::read_to_string;
+use tracing::warn;
+
+use crate::{
+    config::{Sensor, ThrottleDetection},
+    sensor::powersensor3_filename,
+    summary::RunSummary,
+    util::parse_time,
+};
+
+const DEFAULT_DROP: f64 = 0.2;
+const DEFAULT_DURATION: &str = "5s";
+/// Readings are averaged over buckets of this many ms before looking for drops
+const BUCKET_MS: usize = 1000;
+/// Fraction of the run at its start and end that is not checked, sensors start before and stop
+/// after the benchmark
+const EDGE_FRACTION: f64 = 0.1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleEvent {
+    /// Description of the series that dropped, ie. `CPU frequency`
+    pub series: String,
+    /// Start of the drop, in ms since the sensor started recording
+    pub start: usize,
+    /// End of the drop, in ms since the sensor started recording
+    pub end: usize,
+    /// Median of the series over the run
+    pub baseline: f64,
+    /// Lowest bucket mean during the drop
+    pub min: f64,
+}
+
+/// Looks for sustained drops of CPU frequency and SSD power in the middle of a run, warns and
+/// records them in the run's [`RunSummary`]
+pub async fn check_throttling(
+    final_results_dir: &Path,
+    detection: &ThrottleDetection,
+    sensors: &[Sensor],
+) -> Result<()> {
+    let drop = detection.drop.unwrap_or(DEFAULT_DROP);
+    let duration = parse_time(detection.duration.as_deref().unwrap_or(DEFAULT_DURATION))?;
+
+    // series checked for throttling, (sensor file, column regex, description)
+    let series = [
+        (
+            "sysinfo.csv".to_owned(),
+            "^cpu-[0-9]+-freq$",
+            "CPU frequency",
+        ),
+        (
+            powersensor3_filename(sensors, None)?,
+            "^Total$",
+            "SSD power",
+        ),
+    ];
+    let mut events = Vec::new();
+    for (filename, column, description) in series {
+        let Ok(csv) = read_to_string(final_results_dir.join(&filename)).await else {
+            continue;
+        };
+        let series = read_series(&csv, column)?;
+        events.extend(find_drops(&series, drop, duration, description));
+    }
+
+    for event in &events {
+        warn!(
+            "Possible throttling in {}: {} dropped to {:.2} from {:.2} between {:.1}s and {:.1}s",
+            final_results_dir.display(),
+            event.series,
+            event.min,
+            event.baseline,
+            event.start as f64 / 1000.0,
+            event.end as f64 / 1000.0
+        );
+    }
+
+    let mut summary = RunSummary::read(final_results_dir).await?;
+    summary.throttling = Some(events);
+    summary.write(final_results_dir).await
+}
+
+/// Mean of the columns matching `column` per reading
+fn read_series(csv: &str, column: &str) -> Result<Vec<(usize, f64)>> {
+    let column = regex::Regex::new(column)?;
+    let mut rdr = ReaderBuilder::new().from_reader(csv.as_bytes());
+    let headers = rdr.headers()?.clone();
+    let Some(time_idx) = headers.iter().position(|x| x == "time") else {
+        return Ok(Vec::new());
+    };
+    let columns = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| column.is_match(x))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    if columns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(rdr
+        .records()
+        .filter_map(Result::ok)
+        .filter_map(|record| {
+            let time = record.get(time_idx)?.parse().ok()?;
+            let values = columns
+                .iter()
+                .filter_map(|idx| record.get(*idx)?.parse::<f64>().ok())
+                .filter(|x| x.is_finite())
+                .collect::<Vec<_>>();
+            (!values.is_empty()).then(|| (time, values.iter().sum::<f64>() / values.len() as f64))
+        })
+        .collect())
+}
+
+/// Spans of at least `duration` ms where the bucketed series stays more than `drop` below its
+/// median, ignoring the start and end of the run
+fn find_drops(
+    series: &[(usize, f64)],
+    drop: f64,
+    duration: usize,
+    description: &str,
+) -> Vec<ThrottleEvent> {
+    let (Some(first), Some(last)) = (series.first(), series.last()) else {
+        return Vec::new();
+    };
+    let edge = ((last.0 - first.0) as f64 * EDGE_FRACTION) as usize;
+    let (from, to) = (first.0 + edge, last.0 - edge);
+
+    let mut buckets: Vec<(usize, f64, usize)> = Vec::new();
+    for (time, value) in series.iter().filter(|x| x.0 >= from && x.0 <= to) {
+        let start = time - (time - from) % BUCKET_MS;
+        match buckets.last_mut() {
+            Some(bucket) if bucket.0 == start => {
+                bucket.1 += value;
+                bucket.2 += 1;
+            }
+            _ => buckets.push((start, *value, 1)),
+        }
+    }
+    let buckets = buckets
+        .into_iter()
+        .map(|(start, sum, count)| (start, sum / count as f64))
+        .collect::<Vec<_>>();
+    if buckets.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sorted = buckets.iter().map(|x| x.1).collect::<Vec<_>>();
+    sorted.sort_by(f64::total_cmp);
+    let baseline = sorted[sorted.len() / 2];
+    let threshold = baseline * (1.0 - drop);
+
+    let mut events = Vec::new();
+    let mut current: Option<(usize, usize, f64)> = None;
+    for (start, mean) in buckets.iter().copied().chain([(usize::MAX, f64::MAX)]) {
+        if mean < threshold {
+            current = Some(match current {
+                Some((from, _, min)) => (from, start + BUCKET_MS, min.min(mean)),
+                None => (start, start + BUCKET_MS, mean),
+            });
+        } else if let Some((from, to, min)) = current.take()
+            && to - from >= duration
+        {
+            events.push(ThrottleEvent {
+                series: description.to_owned(),
+                start: from,
+                end: to,
+                baseline,
+                min,
+            });
+        }
+    }
+    events
+}
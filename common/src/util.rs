@@ -1,6 +1,6 @@
 use std::{
     collections::{HashMap, HashSet},
-    fmt::Debug,
+    fmt::{Debug, Display, Formatter},
     fs,
     hash::Hash,
     ops::AddAssign,
@@ -8,10 +8,10 @@ use std::{
     pin::Pin,
     process::Stdio,
     string::FromUtf8Error,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
-use csv::{ReaderBuilder, StringRecord, Writer};
+use csv::{ReaderBuilder, StringRecord, Writer, WriterBuilder};
 use eyre::{Context, ContextCompat, Result, bail};
 use flume::{Receiver, Sender};
 use rayon::{
@@ -27,23 +27,32 @@ use tokio::{
     },
     io::{self, AsyncReadExt, AsyncWriteExt},
     process::Command,
+    runtime::Handle,
+    sync::watch,
 };
 use tracing::{debug, error, info, warn};
 
 use crate::{
     bench::BenchInfo,
-    sensor::{SensorArgs, SensorReply, SensorRequest},
+    config::{CsvFormat, Settings, results_config_path},
+    sensor::{ReadingPusher, SensorArgs, SensorReply, SensorRequest, sample_ticker},
 };
 
+/// Indices of the values further than `allowed_deviation` population standard deviations from
+/// the mean, none if all values are equal
 pub fn find_outliers_by_stddev(data: &[f64], allowed_deviation: f64) -> Vec<usize> {
     if data.is_empty() {
         return Vec::new();
     }
     let mean: f64 = data.iter().sum::<f64>() / data.len() as f64;
+    let stddev = (data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / data.len() as f64).sqrt();
+    if stddev == 0.0 {
+        return Vec::new();
+    }
     data.iter()
         .enumerate()
         .filter_map(|(i, &x)| {
-            if (x - mean).abs() > allowed_deviation {
+            if (x - mean).abs() > allowed_deviation * stddev {
                 Some(i)
             } else {
                 None
@@ -67,24 +76,89 @@ pub fn remove_indices<T>(vec: &mut Vec<T>, indices: &[usize]) {
     }
 }
 
+/// Parses a size in bytes as fio accepts it, ie. `4608`, `4k`, `4.5k`, `4KiB` or `10G`, units are
+/// powers of 1024
 pub fn parse_data_size(request_size: &str) -> Result<u64> {
-    let request_size = request_size.to_lowercase();
-    if request_size.contains("k") {
-        Ok(request_size
-            .replace("k", "")
-            .parse::<u64>()
+    let size = request_size.trim().to_lowercase();
+    let size = size
+        .strip_suffix("ib")
+        .or_else(|| size.strip_suffix('b'))
+        .unwrap_or(&size);
+    let (number, multiplier) = match size.char_indices().last() {
+        Some((idx, 'k')) => (&size[..idx], 1u64 << 10),
+        Some((idx, 'm')) => (&size[..idx], 1 << 20),
+        Some((idx, 'g')) => (&size[..idx], 1 << 30),
+        Some((idx, 't')) => (&size[..idx], 1 << 40),
+        Some((idx, 'p')) => (&size[..idx], 1 << 50),
+        _ => (size, 1),
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        bail!("Unsupported request size {request_size}")
+    }
+
+    if number.contains('.') {
+        let bytes = number
+            .parse::<f64>()
             .context(format!("Parse request size: {request_size}"))?
-            * 1024)
-    } else if request_size.contains("m") {
-        Ok(request_size
-            .replace("m", "")
+            * multiplier as f64;
+        if bytes.fract() != 0.0 {
+            bail!("Request size {request_size} is not a whole number of bytes");
+        }
+        if bytes >= u64::MAX as f64 {
+            bail!("Request size {request_size} is too large");
+        }
+        Ok(bytes as u64)
+    } else {
+        number
             .parse::<u64>()
             .context(format!("Parse request size: {request_size}"))?
-            * 1024
-            * 1024)
-    } else {
-        bail!("Unsupported request size {request_size}")
+            .checked_mul(multiplier)
+            .context(format!("Request size {request_size} is too large"))
+    }
+}
+
+pub const BYTES_PER_MIB: f64 = 1_048_576.0;
+pub const BYTES_PER_MB: f64 = 1_000_000.0;
+pub const BYTES_PER_GIB: f64 = 1_073_741_824.0;
+
+/// Converts a byte count to mebibytes (2^20 bytes), the unit used for all throughput plots
+pub fn to_mib(bytes: f64) -> f64 {
+    bytes / BYTES_PER_MIB
+}
+
+/// Converts a byte count to megabytes (10^6 bytes)
+pub fn to_mb(bytes: f64) -> f64 {
+    bytes / BYTES_PER_MB
+}
+
+/// Default of [`crate::config::Settings::precision`]
+pub const DEFAULT_PRECISION: usize = 4;
+
+/// Rounds `value` to `digits` significant figures, ie. 1234.5678 to 1235 with 4
+pub fn round_significant(value: f64, digits: usize) -> f64 {
+    if value == 0.0 || !value.is_finite() || digits == 0 {
+        return value;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let factor = 10f64.powi(digits as i32 - 1 - magnitude);
+    (value * factor).round() / factor
+}
+
+/// Formats `value` with `digits` significant figures, without an exponent, ie. `0.01235` or
+/// `12350`
+pub fn format_significant(value: f64, digits: usize) -> String {
+    let rounded = round_significant(value, digits);
+    if rounded == 0.0 || !rounded.is_finite() || digits == 0 {
+        return rounded.to_string();
     }
+    let magnitude = rounded.abs().log10().floor() as i32;
+    let decimals = (digits as i32 - 1 - magnitude).max(0) as usize;
+    format!("{rounded:.decimals$}")
+}
+
+/// Converts a value reported by a benchmark in megabytes to mebibytes
+pub fn mb_to_mib(mb: f64) -> f64 {
+    to_mib(mb * BYTES_PER_MB)
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -96,17 +170,22 @@ pub enum SensorError {
 }
 
 /// Utility function to perform sensor recordings in a conventional manner
+///
+/// Readings are written to `filename` every `flush_rows` readings, or only once recording
+/// stops if `None`, and are also pushed to [`crate::config::Settings::prometheus_push_gateway`]
+/// if it is set
 pub async fn sensor_reader<Args, Sensor, InitSensor, InitSensorFut, ReadSensorData, SensorData>(
     rx: Receiver<SensorRequest>,
     tx: Sender<SensorReply>,
     filename: &str,
+    flush_rows: Option<usize>,
     args: Args,
     init: InitSensor,
     read: ReadSensorData,
 ) -> Result<()>
 where
     Args: SensorArgs + Clone,
-    SensorData: IntoIterator + Debug,
+    SensorData: IntoIterator + Clone + Debug,
     SensorData::Item: ToString,
     Sensor: Send + 'static,
     InitSensor: Fn(Args) -> InitSensorFut,
@@ -123,15 +202,19 @@ where
     debug!("Spawning {} reader", args.name());
     let args_copy = args.clone();
     let (mut s, sensor_names) = init(args_copy).await?;
+    let mut pusher = ReadingPusher::new(filename, &sensor_names);
 
-    let mut readings = Vec::with_capacity(45_000);
+    let flush_rows = flush_rows.unwrap_or(usize::MAX);
+    let mut readings = Vec::with_capacity(flush_rows.min(45_000));
+    let mut file = None;
     let mut is_running = false;
-    let mut dir = PathBuf::new();
     let mut req = SensorRequest::StopRecording;
     let mut start_time = Instant::now();
     let mut read_time = Instant::now();
     let mut last_time = Instant::now();
     let mut error_count = 0;
+    let mut stats = SampleStats::default();
+    let mut ticker = sample_ticker();
     loop {
         if !is_running {
             if let Ok(request) = rx.recv_async().await {
@@ -145,8 +228,13 @@ where
                     } => {
                         debug!("Starting {} reader", args.name());
                         is_running = true;
-                        dir = _dir.clone();
                         error_count = 0;
+                        stats = SampleStats::default();
+
+                        let mut f = File::create(_dir.join(filename)).await?;
+                        f.write_all(format!("time,{}\n", sensor_names.join(",")).as_bytes())
+                            .await?;
+                        file = Some(f);
 
                         req = SensorRequest::StartRecording {
                             dir: _dir,
@@ -155,7 +243,10 @@ where
                             pid,
                             bench,
                         };
-                        start_time = Instant::now();
+                        start_time = match &ticker {
+                            Some(ticker) => *ticker.rx.borrow(),
+                            None => Instant::now(),
+                        };
                         read_time = Instant::now();
                     }
                     SensorRequest::Quit => break,
@@ -166,9 +257,25 @@ where
                 }
             }
         } else {
+            if readings.len() >= flush_rows
+                && let Some(file) = &mut file
+            {
+                file.write_all(sensor_rows(&mut readings).as_bytes())
+                    .await?;
+            }
+
+            let tick = match &mut ticker {
+                Some(ticker) => Some(next_tick(&mut ticker.rx).await?),
+                None => None,
+            };
             if error_count < 500 {
                 match read(&args, &mut s, &req, last_time).await {
-                    Ok(t) => readings.push((start_time.elapsed().as_millis(), t)),
+                    Ok(t) => {
+                        let time = reading_time(start_time, tick);
+                        stats.push(time);
+                        pusher.push(&t);
+                        readings.push((time.as_millis(), t))
+                    }
                     Err(err) => match err {
                         SensorError::MajorFailure(err) => {
                             error_count += 1;
@@ -192,29 +299,31 @@ where
                     SensorRequest::StopRecording => {
                         debug!("Stopping {} reader", args.name());
                         is_running = false;
-                        let filename = dir.join(filename);
-                        let mut file = File::create(filename).await?;
-
-                        file.write_all(format!("time,{}\n", sensor_names.join(",")).as_bytes())
-                            .await?;
-                        for row in readings.drain(..) {
-                            file.write_all(
-                                format!(
-                                    "{},{}\n",
-                                    row.0,
-                                    row.1
-                                        .into_iter()
-                                        .map(|x| x.to_string())
-                                        .collect::<Vec<_>>()
-                                        .join(",")
-                                )
-                                .as_bytes(),
-                            )
-                            .await?;
+                        stats.report(
+                            filename,
+                            match &ticker {
+                                Some(ticker) => {
+                                    Some(ticker.target_interval(args.sample_interval()))
+                                }
+                                None => args.sample_interval(),
+                            },
+                        );
+                        if let Some(mut file) = file.take() {
+                            file.write_all(sensor_rows(&mut readings).as_bytes())
+                                .await?;
+                            file.flush().await?;
                         }
-                        file.flush().await?;
                         tx.send_async(SensorReply::FileDumpComplete).await?;
                     }
+                    SensorRequest::Quit => {
+                        warn!("{} reader quit while recording", args.name());
+                        if let Some(mut file) = file.take() {
+                            file.write_all(sensor_rows(&mut readings).as_bytes())
+                                .await?;
+                            file.flush().await?;
+                        }
+                        break;
+                    }
                     request => {
                         warn!(
                             "Got unexpected sensor request {request:#?} for {}",
@@ -229,17 +338,102 @@ where
     Ok(())
 }
 
+/// Largest relative deviation of the average sample interval from [`SensorArgs::sample_interval`]
+const MAX_INTERVAL_DEVIATION: f64 = 0.1;
+
+/// Achieved intervals between the readings of a single recording
+#[derive(Debug, Default)]
+struct SampleStats {
+    count: usize,
+    last: Option<Duration>,
+    min: Option<Duration>,
+    max: Duration,
+    total: Duration,
+}
+
+impl SampleStats {
+    fn push(&mut self, time: Duration) {
+        if let Some(last) = self.last {
+            let interval = time.saturating_sub(last);
+            self.min = Some(self.min.map_or(interval, |x| x.min(interval)));
+            self.max = self.max.max(interval);
+            self.total += interval;
+        }
+        self.last = Some(time);
+        self.count += 1;
+    }
+
+    /// Logs the achieved sample rate, warns if it is too far from `target`, which signals the
+    /// sensor was starved on a loaded system
+    fn report(&self, filename: &str, target: Option<Duration>) {
+        let Some(min) = self.min else {
+            warn!("{filename}: only {} samples recorded", self.count);
+            return;
+        };
+        let avg = self.total / (self.count - 1) as u32;
+        info!(
+            "{filename}: {} samples, interval avg {avg:?}, min {min:?}, max {:?}",
+            self.count, self.max
+        );
+        if let Some(target) = target {
+            let deviation = (avg.as_secs_f64() - target.as_secs_f64()).abs() / target.as_secs_f64();
+            if deviation > MAX_INTERVAL_DEVIATION {
+                warn!(
+                    "{filename}: average sample interval {avg:?} is {:.0}% off the target of {target:?}, the sensor may have been starved",
+                    deviation * 100.0
+                );
+            }
+        }
+    }
+}
+
+/// Drains buffered sensor readings into CSV rows
+fn sensor_rows<T>(readings: &mut Vec<(u128, T)>) -> String
+where
+    T: IntoIterator,
+    T::Item: ToString,
+{
+    let mut rows = String::new();
+    for (time, row) in readings.drain(..) {
+        rows.push_str(&time.to_string());
+        for value in row {
+            rows.push(',');
+            rows.push_str(&value.to_string());
+        }
+        rows.push('\n');
+    }
+    rows
+}
+
+/// Waits for the next tick of the shared ticker, ticks that passed while the sensor was reading
+/// are skipped so that readings are never attributed to an earlier tick
+async fn next_tick(ticker: &mut watch::Receiver<Instant>) -> Result<Instant> {
+    ticker.borrow_and_update();
+    ticker.changed().await?;
+    Ok(*ticker.borrow_and_update())
+}
+
+/// Time of a reading since the recording started, the tick it was triggered by if sensors are
+/// synchronized
+fn reading_time(start_time: Instant, tick: Option<Instant>) -> Duration {
+    match tick {
+        Some(tick) => tick.saturating_duration_since(start_time),
+        None => start_time.elapsed(),
+    }
+}
+
 pub fn blocking_sensor_reader<Args, Sensor, InitSensor, ReadSensorData, SensorData>(
     rx: Receiver<SensorRequest>,
     tx: Sender<SensorReply>,
     filename: &str,
+    flush_rows: Option<usize>,
     args: Args,
     init: InitSensor,
     read: ReadSensorData,
 ) -> Result<()>
 where
     Args: SensorArgs + Clone,
-    SensorData: IntoIterator + Debug,
+    SensorData: IntoIterator + Clone + Debug,
     SensorData::Item: ToString,
     Sensor: Send + 'static,
     InitSensor: Fn(Args) -> Result<(Sensor, Vec<String>)> + Send + 'static,
@@ -250,18 +444,24 @@ where
         Instant,
     ) -> Result<SensorData, SensorError>,
 {
+    use std::io::Write;
+
     debug!("Spawning {} reader", args.name());
     let args_copy = args.clone();
     let (mut s, sensor_names) = init(args_copy)?;
+    let mut pusher = ReadingPusher::new(filename, &sensor_names);
 
-    let mut readings = Vec::with_capacity(45_000);
+    let flush_rows = flush_rows.unwrap_or(usize::MAX);
+    let mut readings = Vec::with_capacity(flush_rows.min(45_000));
+    let mut file = None;
     let mut is_running = false;
-    let mut dir = PathBuf::new();
     let mut req = SensorRequest::StopRecording;
     let mut start_time = Instant::now();
     let mut read_time = Instant::now();
     let mut last_time = Instant::now();
     let mut error_count = 0;
+    let mut stats = SampleStats::default();
+    let mut ticker = sample_ticker();
     loop {
         if !is_running {
             if let Ok(request) = rx.recv() {
@@ -275,8 +475,12 @@ where
                     } => {
                         debug!("Starting {} reader", args.name());
                         is_running = true;
-                        dir = _dir.clone();
                         error_count = 0;
+                        stats = SampleStats::default();
+
+                        let mut f = std::fs::File::create(_dir.join(filename))?;
+                        f.write_all(format!("time,{}\n", sensor_names.join(",")).as_bytes())?;
+                        file = Some(f);
 
                         req = SensorRequest::StartRecording {
                             dir: _dir,
@@ -285,7 +489,10 @@ where
                             pid,
                             bench,
                         };
-                        start_time = Instant::now();
+                        start_time = match &ticker {
+                            Some(ticker) => *ticker.rx.borrow(),
+                            None => Instant::now(),
+                        };
                         read_time = Instant::now();
                     }
                     SensorRequest::Quit => break,
@@ -296,9 +503,24 @@ where
                 }
             }
         } else {
+            if readings.len() >= flush_rows
+                && let Some(file) = &mut file
+            {
+                file.write_all(sensor_rows(&mut readings).as_bytes())?;
+            }
+
+            let tick = match &mut ticker {
+                Some(ticker) => Some(Handle::current().block_on(next_tick(&mut ticker.rx))?),
+                None => None,
+            };
             if error_count < 500 {
                 match read(&args, &mut s, &req, last_time) {
-                    Ok(t) => readings.push((start_time.elapsed().as_millis(), t)),
+                    Ok(t) => {
+                        let time = reading_time(start_time, tick);
+                        stats.push(time);
+                        pusher.push(&t);
+                        readings.push((time.as_millis(), t))
+                    }
                     Err(err) => match err {
                         SensorError::MajorFailure(err) => {
                             error_count += 1;
@@ -320,30 +542,31 @@ where
             {
                 match request {
                     SensorRequest::StopRecording => {
-                        use std::io::Write;
                         debug!("Stopping {} reader", args.name());
                         is_running = false;
-                        let filename = dir.join(filename);
-                        let mut file = std::fs::File::create(filename)?;
-
-                        file.write_all(format!("time,{}\n", sensor_names.join(",")).as_bytes())?;
-                        for row in readings.drain(..) {
-                            file.write_all(
-                                format!(
-                                    "{},{}\n",
-                                    row.0,
-                                    row.1
-                                        .into_iter()
-                                        .map(|x| x.to_string())
-                                        .collect::<Vec<_>>()
-                                        .join(",")
-                                )
-                                .as_bytes(),
-                            )?;
+                        stats.report(
+                            filename,
+                            match &ticker {
+                                Some(ticker) => {
+                                    Some(ticker.target_interval(args.sample_interval()))
+                                }
+                                None => args.sample_interval(),
+                            },
+                        );
+                        if let Some(mut file) = file.take() {
+                            file.write_all(sensor_rows(&mut readings).as_bytes())?;
+                            file.flush()?;
                         }
-                        file.flush()?;
                         tx.send(SensorReply::FileDumpComplete)?;
                     }
+                    SensorRequest::Quit => {
+                        warn!("{} reader quit while recording", args.name());
+                        if let Some(mut file) = file.take() {
+                            file.write_all(sensor_rows(&mut readings).as_bytes())?;
+                            file.flush()?;
+                        }
+                        break;
+                    }
                     request => {
                         warn!(
                             "Got unexpected sensor request {request:#?} for {}",
@@ -399,23 +622,41 @@ where
     Ok(serde_json::from_str(&data)?)
 }
 
-#[derive(Serialize)]
-struct BarChartSpec {
-    data: Vec<Vec<f64>>,
-    labels: Vec<String>,
-    title: String,
-    x_label: String,
-    y_label: String,
-    output_path: String,
+/// Data behind a bar chart, written to `plot_data/<name>.bar.json` next to the chart
+#[derive(Serialize, Deserialize)]
+pub struct BarChartSpec {
+    /// Values per power state, per label
+    pub data: Vec<Vec<f64>>,
+    pub labels: Vec<String>,
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub output_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legend_labels: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    legend_labels: Option<Vec<String>>,
+    pub tick_rotation_deg: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    tick_rotation_deg: Option<f64>,
+    pub tick_horizontal_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bar_width: Option<f64>,
+    pub nvme_power_states: Vec<String>,
+    /// Model and firmware of the SSD, see [`crate::bench::BenchInfo::device`]
     #[serde(skip_serializing_if = "Option::is_none")]
-    tick_horizontal_align: Option<String>,
+    pub footnote: Option<String>,
+    /// Text above every bar, per power state, per label like `data`
     #[serde(skip_serializing_if = "Option::is_none")]
-    bar_width: Option<f64>,
-    nvme_power_states: Vec<String>,
+    pub annotations: Option<Vec<Vec<String>>>,
+    /// Upper limit of the y axis, ie. shared by several charts, by default fit to the data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y_max: Option<f64>,
+    /// Value of a dashed horizontal reference line, ie. 100% of a budget
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y_reference: Option<f64>,
+    /// Min, p25, p50, p75, p99 and max of every box, per power state, per label like `data`,
+    /// see [`plot_box_chart`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boxes: Option<Vec<Vec<[f64; 6]>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -428,6 +669,9 @@ pub struct BarChartConfig {
     pub tick_horizontal_align: Option<String>,
     pub bar_width: Option<f64>,
     pub y_scale: Option<String>,
+    pub annotations: Option<Vec<Vec<String>>>,
+    pub y_max: Option<f64>,
+    pub y_reference: Option<f64>,
 }
 
 impl BarChartConfig {
@@ -445,6 +689,9 @@ impl BarChartConfig {
             tick_horizontal_align: None,
             bar_width: None,
             y_scale: None,
+            annotations: None,
+            y_max: None,
+            y_reference: None,
         }
     }
 }
@@ -457,6 +704,18 @@ pub enum BarChartKind {
     NormalizedPower,
     Freq,
     Load,
+    Energy,
+    Count,
+    Variation,
+    /// Total GiB read or written by a run, to compare fixed time runs
+    Data,
+    /// Total operations done by a run, to compare fixed time runs
+    Operations,
+    /// Measured power in % of the rated maximum power of the power state, with a reference line
+    /// at 100%
+    RatedPower,
+    /// Distribution of the latency of every run, see [`plot_box_chart`]
+    BoxPlot,
 }
 
 pub fn make_power_state_bar_config(
@@ -548,6 +807,98 @@ pub fn make_power_state_bar_config(
             config.tick_horizontal_align = Some("right".to_owned());
             config
         }
+        BarChartKind::Energy => {
+            let title = match clean_prefix {
+                Some(prefix) => format!("{} energy vs. {}", prefix, x_label.to_lowercase()),
+                None => format!("Energy vs. {}", x_label.to_lowercase()),
+            };
+            let mut config = BarChartConfig::new(title, x_label.to_owned(), "Energy (Joules)");
+            config.tick_rotation_deg = Some(45.0);
+            config.tick_horizontal_align = Some("right".to_owned());
+            config
+        }
+        BarChartKind::Count => {
+            let title = match clean_prefix {
+                Some(prefix) => format!("{} count vs. {}", prefix, x_label.to_lowercase()),
+                None => format!("Count vs. {}", x_label.to_lowercase()),
+            };
+            let mut config = BarChartConfig::new(title, x_label.to_owned(), "Count");
+            config.tick_rotation_deg = Some(45.0);
+            config.tick_horizontal_align = Some("right".to_owned());
+            config
+        }
+        BarChartKind::Variation => {
+            let title = match clean_prefix {
+                Some(prefix) => format!(
+                    "{} coefficient of variation vs. {}",
+                    prefix,
+                    x_label.to_lowercase()
+                ),
+                None => format!("Coefficient of variation vs. {}", x_label.to_lowercase()),
+            };
+            let mut config =
+                BarChartConfig::new(title, x_label.to_owned(), "Coefficient of variation");
+            config.tick_rotation_deg = Some(45.0);
+            config.tick_horizontal_align = Some("right".to_owned());
+            config
+        }
+        BarChartKind::Data => {
+            let title = match clean_prefix {
+                Some(prefix) => format!("{} data vs. {}", prefix, x_label.to_lowercase()),
+                None => format!("Data vs. {}", x_label.to_lowercase()),
+            };
+            let mut config = BarChartConfig::new(title, x_label.to_owned(), "Data (GiB)");
+            config.tick_rotation_deg = Some(45.0);
+            config.tick_horizontal_align = Some("right".to_owned());
+            config
+        }
+        BarChartKind::Operations => {
+            let title = match clean_prefix {
+                Some(prefix) => format!("{} operations vs. {}", prefix, x_label.to_lowercase()),
+                None => format!("Operations vs. {}", x_label.to_lowercase()),
+            };
+            let mut config = BarChartConfig::new(title, x_label.to_owned(), "Operations");
+            config.tick_rotation_deg = Some(45.0);
+            config.tick_horizontal_align = Some("right".to_owned());
+            config
+        }
+        BarChartKind::RatedPower => {
+            let title = match clean_prefix {
+                Some(prefix) => format!(
+                    "{} power of rated maximum vs. {}",
+                    prefix,
+                    x_label.to_lowercase()
+                ),
+                None => format!("Power of rated maximum vs. {}", x_label.to_lowercase()),
+            };
+            let mut config =
+                BarChartConfig::new(title, x_label.to_owned(), "Power of rated maximum (%)");
+            config.tick_rotation_deg = Some(45.0);
+            config.tick_horizontal_align = Some("right".to_owned());
+            config.y_reference = Some(100.0);
+            config
+        }
+        BarChartKind::BoxPlot => {
+            let title = match clean_prefix {
+                Some(prefix) => format!(
+                    "{} latency distribution for {} vs. power state",
+                    prefix,
+                    x_label.to_lowercase()
+                ),
+                None => format!(
+                    "Latency distribution for {} vs. power state",
+                    x_label.to_lowercase()
+                ),
+            };
+            let mut config = BarChartConfig::new(
+                title,
+                format!("{} {}", x_label, experiment_name),
+                "Latency (ms)",
+            );
+            config.tick_rotation_deg = Some(45.0);
+            config.tick_horizontal_align = Some("right".to_owned());
+            config
+        }
     }
 }
 
@@ -557,6 +908,70 @@ pub fn plot_bar_chart(
     labels: Vec<String>,
     config: BarChartConfig,
     bench_info: &BenchInfo,
+    settings: &Settings,
+) -> Result<()> {
+    plot_power_state_chart(
+        "bar", filepath, data, labels, None, config, bench_info, settings,
+    )
+}
+
+/// Like [`plot_bar_chart`], but draws one line per power state over the labels, for a variable
+/// whose values are ordered, ie. request sizes
+pub fn plot_line_chart(
+    filepath: &Path,
+    data: Vec<Vec<f64>>,
+    labels: Vec<String>,
+    config: BarChartConfig,
+    bench_info: &BenchInfo,
+    settings: &Settings,
+) -> Result<()> {
+    plot_power_state_chart(
+        "line", filepath, data, labels, None, config, bench_info, settings,
+    )
+}
+
+/// Statistics of every box of [`plot_box_chart`], in the order of [`BarChartSpec::boxes`]
+const BOX_STATS: [&str; 6] = ["min", "p25", "p50", "p75", "p99", "max"];
+
+/// Like [`plot_bar_chart`], but draws a box per power state and label from the min, p25, p50,
+/// p75, p99 and max in `boxes`, the whiskers span from the min to the p99 and the max is a marker
+/// above them
+pub fn plot_box_chart(
+    filepath: &Path,
+    boxes: Vec<Vec<[f64; 6]>>,
+    labels: Vec<String>,
+    config: BarChartConfig,
+    bench_info: &BenchInfo,
+    settings: &Settings,
+) -> Result<()> {
+    let medians = boxes
+        .iter()
+        .map(|x| x.iter().map(|x| x[2]).collect())
+        .collect();
+    plot_power_state_chart(
+        "box",
+        filepath,
+        medians,
+        labels,
+        Some(boxes),
+        config,
+        bench_info,
+        settings,
+    )
+}
+
+/// Writes the data of a chart with one series per power state to `plot_data/<name>.<chart>.json`
+/// and plots it with `plots/<chart>_chart.py`
+#[allow(clippy::too_many_arguments)]
+fn plot_power_state_chart(
+    chart: &str,
+    filepath: &Path,
+    data: Vec<Vec<f64>>,
+    labels: Vec<String>,
+    boxes: Option<Vec<Vec<[f64; 6]>>>,
+    config: BarChartConfig,
+    bench_info: &BenchInfo,
+    settings: &Settings,
 ) -> Result<()> {
     if data.is_empty() {
         return Ok(());
@@ -574,7 +989,49 @@ pub fn plot_bar_chart(
         .file_stem()
         .and_then(|s| s.to_str())
         .context("Failed to derive bar chart file stem")?;
-    let spec_path = plot_data_dir.join(format!("{stem}.bar.json"));
+    let spec_path = plot_data_dir.join(format!("{stem}.{chart}.json"));
+    let csv_format = settings.csv_format_or_default();
+    // series are in the order of the configured power states
+    let power_states = settings
+        .power_states()
+        .states()
+        .iter()
+        .map(|x| bench_info.power_state_label(*x))
+        .collect::<Vec<_>>();
+    let legends = (0..data.len()).map(|idx| {
+        config
+            .legend_labels
+            .as_ref()
+            .and_then(|x| x.get(idx).cloned())
+            .or(power_states.get(idx).cloned())
+            .unwrap_or(format!("ps{idx}"))
+    });
+    match &boxes {
+        // a column per statistic of every power state
+        Some(boxes) => write_table_csv(
+            &plot_data_dir.join(format!("{stem}.csv")),
+            std::iter::once("label".to_owned())
+                .chain(legends.flat_map(|x| BOX_STATS.map(|stat| format!("{x} {stat}"))))
+                .collect(),
+            &labels,
+            &boxes
+                .iter()
+                .flat_map(|x| {
+                    (0..BOX_STATS.len()).map(move |stat| x.iter().map(|x| x[stat]).collect())
+                })
+                .collect::<Vec<_>>(),
+            &csv_format,
+            settings.precision(),
+        )?,
+        None => write_table_csv(
+            &plot_data_dir.join(format!("{stem}.csv")),
+            std::iter::once("label".to_owned()).chain(legends).collect(),
+            &labels,
+            &data,
+            &csv_format,
+            settings.precision(),
+        )?,
+    }
 
     let output_path = filepath
         .to_str()
@@ -592,11 +1049,12 @@ pub fn plot_bar_chart(
         tick_rotation_deg: config.tick_rotation_deg,
         tick_horizontal_align: config.tick_horizontal_align,
         bar_width: config.bar_width,
-        nvme_power_states: bench_info
-            .device_power_states
-            .iter()
-            .map(|x| x.1.clone())
-            .collect(),
+        nvme_power_states: power_states,
+        footnote: bench_info.device.as_ref().map(|x| x.to_string()),
+        annotations: config.annotations,
+        y_max: config.y_max,
+        y_reference: config.y_reference,
+        boxes,
     };
 
     let spec_serialized = serde_json::to_string(&spec)?;
@@ -607,7 +1065,7 @@ pub fn plot_bar_chart(
         .context("Bar chart spec path is not valid UTF-8")?
         .to_owned();
     let args = vec![("--spec".to_owned(), spec_path_str)];
-    plot_python("bar_chart", &args)
+    plot_python(format!("{chart}_chart"), &args)
 }
 
 fn sanitize_filename(input: &str) -> String {
@@ -641,6 +1099,10 @@ pub struct TimeSeriesAxis {
     pub dataset_field: String,
     pub plot_label: String,
     pub axis_label: String,
+    /// Label of the [`TimeSeriesSpec::overlay_runs`] run the dataset is read from, the run of
+    /// [`TimeSeriesSpec::results_dir`] if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
 }
 
 impl TimeSeriesAxis {
@@ -656,6 +1118,7 @@ impl TimeSeriesAxis {
             dataset_field: dataset_field.into(),
             plot_label: plot_label.into(),
             axis_label: axis_label.into(),
+            run: None,
         }
     }
 
@@ -670,6 +1133,7 @@ impl TimeSeriesAxis {
             dataset_field: dataset_field.into(),
             plot_label: plot_label.into(),
             axis_label: axis_label.into(),
+            run: None,
         }
     }
 
@@ -680,6 +1144,13 @@ impl TimeSeriesAxis {
     pub fn bench_time() -> Self {
         Self::bench("time", "Time", "Time (s)")
     }
+
+    /// Reads the sensor dataset from an overlaid run, see [`TimeSeriesSpec::with_overlay_run`],
+    /// every series of another run is plotted over its own time
+    pub fn with_run(mut self, run: impl Into<String>) -> Self {
+        self.run = Some(run.into());
+        self
+    }
 }
 
 impl TimeSeriesPlot {
@@ -729,7 +1200,8 @@ pub struct TimeSeriesSpec {
     pub bench_type: String,
     pub plot_dir: PathBuf,
     pub results_dir: PathBuf,
-    pub config_yaml: PathBuf,
+    /// Copy of the config in the results folder, YAML or TOML
+    pub config_file: PathBuf,
     pub info_json: PathBuf,
     pub name: String,
     pub plots: Vec<TimeSeriesPlot>,
@@ -739,6 +1211,12 @@ pub struct TimeSeriesSpec {
     pub trim_end: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub width: Option<usize>,
+    /// Further runs whose sensors can be plotted, as `(label, results dir)`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overlay_runs: Vec<(String, PathBuf)>,
+    /// Markers drawn along with those of the run's `markers.csv`, as `(time in ms, name)`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<(usize, String)>,
 }
 
 impl TimeSeriesSpec {
@@ -763,13 +1241,15 @@ impl TimeSeriesSpec {
             bench_type: bench_type.into(),
             plot_dir: plot_dir.into(),
             results_dir,
-            config_yaml: base_dir.join("config.yaml"),
+            config_file: results_config_path(&base_dir),
             info_json: base_dir.join("info.json"),
             name,
             plots,
             offset: None,
             trim_end: None,
             width: None,
+            overlay_runs: Vec::new(),
+            markers: Vec::new(),
         }
     }
 
@@ -793,6 +1273,22 @@ impl TimeSeriesSpec {
         self
     }
 
+    pub fn with_overlay_run(
+        mut self,
+        label: impl Into<String>,
+        results_dir: impl Into<PathBuf>,
+    ) -> Self {
+        self.overlay_runs.push((label.into(), results_dir.into()));
+        self
+    }
+
+    /// Draws a marker at `time` (ms) that is not in the run's `markers.csv`, ie. a detected
+    /// steady state
+    pub fn with_marker(mut self, time: usize, name: impl Into<String>) -> Self {
+        self.markers.push((time, name.into()));
+        self
+    }
+
     fn plot_dir(&self) -> &Path {
         &self.plot_dir
     }
@@ -895,6 +1391,55 @@ pub async fn simple_command_with_output_no_dir(
     .await
 }
 
+/// Kills the process `pid` and all its descendants with SIGKILL, ie. a hung benchmark and
+/// everything it spawned. The whole tree is collected from `/proc` before any process is killed,
+/// so that the children of a killed process are still found through it. Descendants whose parent
+/// exited earlier were reparented to init and are not found. Returns the number of processes
+/// signalled.
+pub fn kill_process_tree(pid: u32) -> Result<usize> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for entry in fs::read_dir("/proc")? {
+        let entry = entry?;
+        let Some(child) = entry
+            .file_name()
+            .to_str()
+            .and_then(|x| x.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        // the process may have exited in the meantime
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        // the name in the second field can contain spaces and parentheses
+        let Some(parent) = stat
+            .rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().nth(1))
+            .and_then(|x| x.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        children.entry(parent).or_default().push(child);
+    }
+
+    let mut tree = vec![pid];
+    let mut queue = vec![pid];
+    while let Some(parent) = queue.pop() {
+        for child in children.get(&parent).into_iter().flatten() {
+            tree.push(*child);
+            queue.push(*child);
+        }
+    }
+
+    for child in &tree {
+        _ = nix::sys::signal::kill(
+            nix::unistd::Pid::from_raw(*child as i32),
+            nix::sys::signal::Signal::SIGKILL,
+        );
+    }
+    Ok(tree.len())
+}
+
 #[derive(Debug, Default, Clone, PartialOrd, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub enum Filesystem {
@@ -917,6 +1462,18 @@ impl Filesystem {
         })
     }
 
+    /// Like [`Filesystem::cmd`], but keeps the written blocks of the device instead of discarding
+    /// them, ie. after the device was conditioned
+    pub fn nodiscard_cmd(&self, device: &str) -> Result<String> {
+        Ok(match self {
+            Filesystem::None => bail!("No filesystem specified"),
+            Filesystem::Ext4 => format!("sudo mkfs.ext4 -F -E nodiscard -L ext4_bench {device}"),
+            Filesystem::Xfs => format!("sudo mkfs.xfs -f -K -L xfs_bench {device}"),
+            Filesystem::Btrfs => format!("sudo mkfs.btrfs -f -K -L btrfs_bench {device}"),
+            Filesystem::F2fs => format!("sudo mkfs.f2fs -f -t 0 -l f2fs_bench {device}"),
+        })
+    }
+
     pub fn ord(&self) -> usize {
         match self {
             Filesystem::None => 0,
@@ -957,16 +1514,105 @@ pub async fn read_until_prompt(
 #[derive(Debug, Deserialize)]
 struct Marker {
     time: usize,
+    #[serde(default)]
+    marker_name: String,
+}
+
+pub type SectionCalculator<T> = fn(data: &[(usize, Vec<f64>)]) -> T;
+
+/// Readings to leave out of the sections, see [`find_outliers_by_stddev`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierConfig {
+    /// Readings whose summed columns are further than this many standard deviations from the mean
+    /// of the CSV are dropped
+    pub allowed_deviation: f64,
+}
+
+/// Stats of each section, overall stats, end of each section and the shortfall of the CSV
+pub type CheckedSections<T, const N: usize> = ([T; N], T, [usize; N], Option<SensorShortfall>);
+
+/// Readings missing at the end of a sensor CSV, ie. its sensor thread crashed mid-run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorShortfall {
+    /// Time (ms) of the last valid reading, `None` if the CSV has none
+    pub last_reading: Option<usize>,
+    /// Index of the section the readings end in, it and all later sections are incomplete
+    pub first_incomplete_section: usize,
+    /// The last row of the CSV was cut off mid-row and skipped
+    pub truncated_row: bool,
 }
 
-/// `runtime` Required for fallback to old csv format, in milliseconds
+impl SensorShortfall {
+    pub fn is_complete(&self, section: usize) -> bool {
+        section < self.first_incomplete_section
+    }
+}
+
+impl Display for SensorShortfall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.last_reading {
+            Some(time) => write!(
+                f,
+                "readings end at {time} ms, sections from {} on are incomplete",
+                self.first_incomplete_section
+            )?,
+            None => write!(f, "no valid readings")?,
+        }
+        if self.truncated_row {
+            write!(f, ", last row is truncated")?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits the readings of a sensor CSV into the sections between the markers and calculates the
+/// stats of each section and of the whole CSV
+///
+/// - `marker_csv` markers of the run, `N - 1` of them split the readings into `N` sections, with
+///   `None` only the overall stats are calculated
+/// - `csv_to_section` the sensor CSV
+/// - `columns` regexes of the columns passed to `calculator`
+/// - `limits` valid range of each of `columns`, rows with a value outside of it are skipped
+/// - `calculator` calculates the stats of the readings of a section
+/// - `outliers` drops outlying readings before sectioning, `None` keeps all readings
+///
+/// A sensor CSV that ends early is logged and sectioned as far as it goes, use
+/// [`calculate_sectioned_checked`] to handle the shortfall instead.
 pub fn calculate_sectioned<CalculatedData: Debug + Default + Copy, const N: usize>(
     marker_csv: Option<&str>,
     csv_to_section: &str,
     columns: &[&str],
     limits: &[(f64, f64)],
-    calculator: fn(data: &[(usize, Vec<f64>)]) -> CalculatedData,
+    calculator: SectionCalculator<CalculatedData>,
+    outliers: Option<OutlierConfig>,
 ) -> Result<([CalculatedData; N], CalculatedData, [usize; N])> {
+    let (stats, overall, markers, shortfall) = calculate_sectioned_checked(
+        marker_csv,
+        csv_to_section,
+        columns,
+        limits,
+        calculator,
+        outliers,
+    )?;
+    if let Some(shortfall) = shortfall {
+        warn!("Sensor CSV ends early, {shortfall}");
+    }
+    Ok((stats, overall, markers))
+}
+
+/// [`calculate_sectioned`] that also returns whether the CSV has fewer readings than the markers
+/// expect, ie. the sensor stopped before the last marker or its last row is truncated
+///
+/// Sections without readings are calculated from no data, for the built-in calculators their
+/// stats are `None`.
+pub fn calculate_sectioned_checked<CalculatedData: Debug + Default + Copy, const N: usize>(
+    marker_csv: Option<&str>,
+    csv_to_section: &str,
+    columns: &[&str],
+    limits: &[(f64, f64)],
+    calculator: SectionCalculator<CalculatedData>,
+    outliers: Option<OutlierConfig>,
+) -> Result<CheckedSections<CalculatedData, N>> {
     assert_eq!(columns.len(), limits.len());
     let markers = match marker_csv {
         Some(marker_csv) => {
@@ -983,6 +1629,80 @@ pub fn calculate_sectioned<CalculatedData: Debug + Default + Copy, const N: usiz
         None => vec![],
     };
 
+    let (mut data, truncated_row) = read_sensor_readings(csv_to_section, columns, limits)?;
+    if let Some(outliers) = outliers {
+        let sums = data
+            .iter()
+            .map(|(_, v)| v.iter().sum())
+            .collect::<Vec<f64>>();
+        remove_indices(
+            &mut data,
+            &find_outliers_by_stddev(&sums, outliers.allowed_deviation),
+        );
+    }
+    let mut prev = 0;
+    let mut stats = [CalculatedData::default(); N];
+    let mut markers_final = [0; N];
+    for (i, bound) in markers.iter().enumerate() {
+        let section_data: Vec<(usize, Vec<f64>)> = data
+            .iter()
+            .filter(|&&(t, _)| t >= prev && t < *bound)
+            .cloned()
+            .collect();
+
+        prev = *bound;
+        stats[i] = calculator(&section_data);
+        markers_final[i] = *bound;
+    }
+
+    if let Some(last_marker) = markers.last() {
+        let tail_data: Vec<_> = data
+            .iter()
+            .filter(|&&(t, _)| t >= *last_marker)
+            .cloned()
+            .collect();
+
+        stats[N - 1] = calculator(&tail_data);
+        markers_final[N - 1] = data.last().map(|(t, _)| *t).unwrap_or(prev);
+    }
+
+    let last_reading = data.last().map(|(t, _)| *t);
+    let ends_early = match (last_reading, markers.last()) {
+        (Some(time), Some(last_marker)) => time < *last_marker,
+        (None, _) => true,
+        _ => false,
+    };
+    let shortfall = (truncated_row || ends_early).then(|| SensorShortfall {
+        last_reading,
+        first_incomplete_section: last_reading
+            .map_or(0, |time| markers.iter().filter(|x| **x <= time).count()),
+        truncated_row,
+    });
+
+    let overall = calculator(&data);
+    Ok((stats, overall, markers_final, shortfall))
+}
+
+/// Time (ms) and values of the selected columns of every row of a sensor CSV
+type Readings = Vec<(usize, Vec<f64>)>;
+
+/// Readings of `columns` with their time, skipping rows with a value outside of `limits`, and
+/// whether the last row of the CSV was truncated and skipped
+fn read_sensor_readings(
+    csv_to_section: &str,
+    columns: &[&str],
+    limits: &[(f64, f64)],
+) -> Result<(Readings, bool)> {
+    // rows are always terminated, so an unterminated last row was cut off while being written
+    let truncated_row = !csv_to_section.is_empty()
+        && !csv_to_section.ends_with('\n')
+        && csv_to_section.contains('\n');
+    let csv_to_section = if truncated_row {
+        &csv_to_section[..=csv_to_section.rfind('\n').unwrap()]
+    } else {
+        csv_to_section
+    };
+
     let mut rdr = ReaderBuilder::new()
         .has_headers(true)
         .from_reader(csv_to_section.as_bytes());
@@ -1008,7 +1728,10 @@ pub fn calculate_sectioned<CalculatedData: Debug + Default + Copy, const N: usiz
         );
     }
 
-    let time_idx = headers.iter().position(|h| h == "time").unwrap();
+    let time_idx = headers
+        .iter()
+        .position(|h| h == "time")
+        .context("The csv has no time column")?;
 
     let parse = |rec: &StringRecord| -> Option<(usize, Vec<f64>)> {
         let time = rec.get(time_idx)?.parse().ok()?;
@@ -1034,35 +1757,156 @@ pub fn calculate_sectioned<CalculatedData: Debug + Default + Copy, const N: usiz
         Some((time, values))
     };
 
-    let data: Vec<(usize, Vec<f64>)> = records.iter().filter_map(parse).collect();
-    let mut prev = 0;
-    let mut stats = [CalculatedData::default(); N];
-    let mut markers_final = [0; N];
-    for (i, bound) in markers.iter().enumerate() {
-        let section_data: Vec<(usize, Vec<f64>)> = data
+    Ok((records.iter().filter_map(parse).collect(), truncated_row))
+}
+
+/// Readings of the rolling mean of [`SteadyStateDetection`] by default
+const DEFAULT_STEADY_STATE_WINDOW: usize = 100;
+/// Relative tolerance of [`SteadyStateDetection`] by default
+const DEFAULT_STEADY_STATE_TOLERANCE: f64 = 0.05;
+
+/// Finds the start of the steady state of a sensor in runs without markers delimiting it, see
+/// [`detect_steady_state`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SteadyStateDetection {
+    /// Readings in the rolling mean, defaults to 100
+    pub window: Option<usize>,
+    /// Allowed deviation of the rolling mean from the mean of the steady state, relative to that
+    /// mean, defaults to 0.05
+    pub tolerance: Option<f64>,
+}
+
+impl SteadyStateDetection {
+    /// Time (ms) of the reading the steady state of the summed `columns` of a sensor CSV starts
+    /// at, `None` if the readings do not settle
+    pub fn start_time(
+        &self,
+        csv: &str,
+        columns: &[&str],
+        limits: &[(f64, f64)],
+    ) -> Result<Option<usize>> {
+        let (data, _) = read_sensor_readings(csv, columns, limits)?;
+        let series = data
             .iter()
-            .filter(|&&(t, _)| t >= prev && t < *bound)
-            .cloned()
-            .collect();
+            .map(|(_, v)| v.iter().sum())
+            .collect::<Vec<f64>>();
+        Ok(detect_steady_state(
+            &series,
+            self.window.unwrap_or(DEFAULT_STEADY_STATE_WINDOW),
+            self.tolerance.unwrap_or(DEFAULT_STEADY_STATE_TOLERANCE),
+        )
+        .map(|idx| data[idx].0))
+    }
+}
 
-        prev = *bound;
-        stats[i] = calculator(&section_data);
-        markers_final[i] = *bound;
+/// Index of the first reading from which on the rolling mean over `window` readings stays within
+/// `tolerance` of the mean of all readings from there on, relative to that mean. `None` if less
+/// than two windows of readings are steady.
+pub fn detect_steady_state(series: &[f64], window: usize, tolerance: f64) -> Option<usize> {
+    if window == 0 || series.len() < 2 * window {
+        return None;
     }
 
-    if let Some(last_marker) = markers.last() {
-        let tail_data: Vec<_> = data
-            .iter()
-            .filter(|&&(t, _)| t >= *last_marker)
-            .cloned()
-            .collect();
+    let mut prefix = Vec::with_capacity(series.len() + 1);
+    prefix.push(0.0);
+    for x in series {
+        prefix.push(prefix.last().unwrap() + x);
+    }
+    let rolling = (0..=series.len() - window)
+        .map(|start| (prefix[start + window] - prefix[start]) / window as f64)
+        .collect::<Vec<_>>();
+    // min and max of the rolling means of every window from there on
+    let mut bounds = vec![(f64::INFINITY, f64::NEG_INFINITY); rolling.len() + 1];
+    for idx in (0..rolling.len()).rev() {
+        bounds[idx] = (
+            bounds[idx + 1].0.min(rolling[idx]),
+            bounds[idx + 1].1.max(rolling[idx]),
+        );
+    }
 
-        stats[N - 1] = calculator(&tail_data);
-        markers_final[N - 1] = data.last().map(|(t, _)| *t).unwrap_or(prev);
+    (0..=series.len() - 2 * window).find(|start| {
+        let mean = (prefix[series.len()] - prefix[*start]) / (series.len() - start) as f64;
+        let allowed = tolerance * mean.abs();
+        let (min, max) = bounds[*start];
+        mean - min <= allowed && max - mean <= allowed
+    })
+}
+
+/// Name of the section before the first marker in [`calculate_named_sections`]
+pub const FIRST_SECTION: &str = "start";
+
+/// A section of the sensor readings, from a marker to the next one
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedSection<T> {
+    /// Name of the marker the section starts at, [`FIRST_SECTION`] before the first marker
+    pub name: String,
+    /// Start of the section (ms)
+    pub start: usize,
+    /// End of the section (ms), the next marker or the last reading
+    pub end: usize,
+    pub stats: T,
+}
+
+/// Sections the readings at the markers named in `names`, or at every marker if `names` is
+/// empty, returning the sections in the order of their markers and the overall stats
+///
+/// Unlike [`calculate_sectioned`] the number of sections does not need to be known up front, so
+/// a benchmark can write whichever markers it needs with [`crate::bench::MarkerWriter`]. The
+/// section before the first marker is left out if that marker is at 0 ms.
+pub fn calculate_named_sections<CalculatedData: Debug + Default + Copy>(
+    marker_csv: &str,
+    csv_to_section: &str,
+    columns: &[&str],
+    limits: &[(f64, f64)],
+    names: &[&str],
+    calculator: SectionCalculator<CalculatedData>,
+) -> Result<(Vec<NamedSection<CalculatedData>>, CalculatedData)> {
+    assert_eq!(columns.len(), limits.len());
+    let mut marker_reader = ReaderBuilder::new()
+        .has_headers(true)
+        .from_reader(marker_csv.as_bytes());
+    let mut markers: Vec<Marker> = marker_reader.deserialize().collect::<Result<_, _>>()?;
+    if let Some(name) = names
+        .iter()
+        .find(|name| !markers.iter().any(|x| x.marker_name == **name))
+    {
+        bail!("Marker {name} does not exist");
     }
+    markers.retain(|x| names.is_empty() || names.contains(&x.marker_name.as_str()));
+    markers.sort_by_key(|x| x.time);
 
-    let overall = calculator(&data);
-    Ok((stats, overall, markers_final))
+    let (data, truncated_row) = read_sensor_readings(csv_to_section, columns, limits)?;
+    if truncated_row {
+        warn!("Sensor CSV ends early, last row is truncated");
+    }
+    let last_reading = data.last().map_or(0, |(t, _)| *t);
+
+    let mut bounds = Vec::with_capacity(markers.len() + 1);
+    if markers.first().is_none_or(|x| x.time > 0) {
+        bounds.push((FIRST_SECTION.to_owned(), 0));
+    }
+    bounds.extend(markers.into_iter().map(|x| (x.marker_name, x.time)));
+
+    let sections = bounds
+        .iter()
+        .enumerate()
+        .map(|(i, (name, start))| {
+            let end = bounds.get(i + 1).map(|x| x.1);
+            let section_data = data
+                .iter()
+                .filter(|(t, _)| t >= start && end.is_none_or(|end| *t < end))
+                .cloned()
+                .collect::<Vec<_>>();
+            NamedSection {
+                name: name.clone(),
+                start: *start,
+                end: end.unwrap_or(last_reading.max(*start)),
+                stats: calculator(&section_data),
+            }
+        })
+        .collect();
+    Ok((sections, calculator(&data)))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1168,25 +2012,106 @@ pub fn parse_trace<R: std::io::Read>(reader: R, fs: &Filesystem) -> Result<Vec<T
     Ok(records)
 }
 
-pub fn write_csv<T: Serialize>(filename: &PathBuf, records: &[T]) -> Result<()> {
-    let mut wtr = Writer::from_path(filename)?;
-    for record in records {
-        wtr.serialize(record)?;
+/// Writes `records` as CSV in `format`, the time series plotter reads the `plot_data` CSVs it uses
+/// in the format of [`crate::config::Settings::csv_format`]
+pub fn write_csv<T: Serialize>(
+    filename: &PathBuf,
+    records: &[T],
+    format: &CsvFormat,
+) -> Result<()> {
+    let mut wtr = WriterBuilder::new()
+        .delimiter(format.delimiter() as u8)
+        .from_path(filename)?;
+    if *format == CsvFormat::default() {
+        for record in records {
+            wtr.serialize(record)?;
+        }
+    } else {
+        let mut buf = Writer::from_writer(Vec::new());
+        for record in records {
+            buf.serialize(record)?;
+        }
+        let buf = buf.into_inner().map_err(|x| eyre::eyre!("{x}"))?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(buf.as_slice());
+        for record in rdr.records() {
+            wtr.write_record(record?.iter().map(|x| format.localize(x)))?;
+        }
     }
     wtr.flush()?;
     Ok(())
 }
 
+/// Exports the values of a plot as a table, with one row per label and one column per series,
+/// rounded to `precision` significant figures
+pub fn write_table_csv(
+    filename: &PathBuf,
+    header: Vec<String>,
+    labels: &[String],
+    series: &[Vec<f64>],
+    format: &CsvFormat,
+    precision: usize,
+) -> Result<()> {
+    let mut rows = vec![header];
+    for (idx, label) in labels.iter().enumerate() {
+        let mut row = vec![label.clone()];
+        row.extend(series.iter().map(|x| {
+            x.get(idx)
+                .map(|x| format_significant(*x, precision))
+                .unwrap_or_default()
+        }));
+        rows.push(row);
+    }
+    write_csv(filename, &rows, format)
+}
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct SectionStats {
     pub power_mean: Option<f64>,
     pub power_stddev: Option<f64>,
     pub power_stddev_rolling_100ms: Option<f64>,
-    pub power_percentiles: Option<[f64; 6]>,
+    /// p10, p25, p50, p75, p90, p95, p99
+    pub power_percentiles: Option<[f64; 7]>,
+    pub power_max: Option<f64>,
     pub energy: Option<f64>,
 }
 
-fn percentile(sorted: &[f64], q: f64) -> f64 {
+/// Statistic used to summarize the power samples of a section
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PowerStatistic {
+    #[default]
+    Mean,
+    P95,
+    P99,
+    Max,
+}
+
+impl PowerStatistic {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PowerStatistic::Mean => "mean",
+            PowerStatistic::P95 => "p95",
+            PowerStatistic::P99 => "p99",
+            PowerStatistic::Max => "max",
+        }
+    }
+}
+
+impl SectionStats {
+    pub fn power(&self, statistic: PowerStatistic) -> Option<f64> {
+        match statistic {
+            PowerStatistic::Mean => self.power_mean,
+            PowerStatistic::P95 => self.power_percentiles.map(|x| x[5]),
+            PowerStatistic::P99 => self.power_percentiles.map(|x| x[6]),
+            PowerStatistic::Max => self.power_max,
+        }
+    }
+}
+
+/// Linearly interpolated percentile of sorted values, `q` between 0 and 1, NaN if empty
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
     let n = sorted.len();
     if n == 0 {
         return f64::NAN;
@@ -1301,19 +2226,30 @@ pub fn power_energy_calculator(data: &[(usize, Vec<f64>)]) -> SectionStats {
     let p50 = percentile(&sorted, 0.50);
     let p75 = percentile(&sorted, 0.75);
     let p90 = percentile(&sorted, 0.90);
+    let p95 = percentile(&sorted, 0.95);
     let p99 = percentile(&sorted, 0.99);
 
-    let power_percentiles = Some([p10, p25, p50, p75, p90, p99]);
+    let power_percentiles = Some([p10, p25, p50, p75, p90, p95, p99]);
 
     SectionStats {
         power_mean: mean,
         power_stddev: stddev,
         power_stddev_rolling_100ms,
         power_percentiles,
+        power_max: sorted.last().copied(),
         energy,
     }
 }
 
+/// [`power_energy_calculator`] over the sum of all selected columns, eg. `package-N` + `dram-N`
+pub fn summed_power_energy_calculator(data: &[(usize, Vec<f64>)]) -> SectionStats {
+    let summed = data
+        .iter()
+        .map(|(t, v)| (*t, vec![v.iter().sum::<f64>()]))
+        .collect::<Vec<_>>();
+    power_energy_calculator(&summed)
+}
+
 pub fn sysinfo_average_calculator(data: &[(usize, Vec<f64>)]) -> (f64, f64) {
     let (sum_freq, sum_load, n) = data
         .par_iter()
@@ -1398,6 +2334,21 @@ pub fn parse_time(time: &str) -> Result<usize> {
     })
 }
 
+/// `major:minor` numbers of a block device, device mapper symlinks are resolved, ie.
+/// `/dev/mapper/name` -> `/dev/dm-0`
+pub async fn block_device_numbers(device: &str) -> Result<String> {
+    let resolved = Path::new(device)
+        .canonicalize()
+        .context(format!("Resolving {device}"))?;
+    let name = resolved
+        .strip_prefix("/dev/")
+        .context("Device does not include /dev")?
+        .to_string_lossy()
+        .to_string();
+    let numbers = tokio_read_to_string(format!("/sys/block/{name}/dev")).await?;
+    Ok(numbers.trim().to_owned())
+}
+
 pub fn get_pcie_address(dev: &str) -> Option<String> {
     let dev_name = dev.trim_start_matches("/dev/");
     let sys_block = format!("/sys/block/{}", dev_name);
@@ -1477,3 +2428,240 @@ pub async fn clean_directory_except_prefill<P: AsRef<Path>>(dir: P) -> io::Resul
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKERS: &str = "time,marker_name\n100,start\n400,end\n";
+
+    #[test]
+    fn parses_data_sizes() {
+        assert_eq!(parse_data_size("4608").unwrap(), 4608);
+        assert_eq!(parse_data_size("512b").unwrap(), 512);
+        assert_eq!(parse_data_size("4k").unwrap(), 4 << 10);
+        assert_eq!(parse_data_size("4KiB").unwrap(), 4 << 10);
+        assert_eq!(parse_data_size("4.5k").unwrap(), 4608);
+        assert_eq!(parse_data_size("128M").unwrap(), 128 << 20);
+        assert_eq!(parse_data_size("10G").unwrap(), 10 << 30);
+        assert_eq!(parse_data_size("2tb").unwrap(), 2 << 40);
+        assert_eq!(parse_data_size(" 1P ").unwrap(), 1 << 50);
+    }
+
+    #[test]
+    fn rejects_invalid_data_sizes() {
+        for size in ["", "k", "4x", "4kk", "-4k", "4 k", "1.1b", "4.5.1k"] {
+            assert!(parse_data_size(size).is_err(), "{size}");
+        }
+        // u64::MAX is 16 EiB
+        assert!(parse_data_size("16384p").is_err());
+        assert!(parse_data_size("16384.0p").is_err());
+        assert!(parse_data_size("18446744073709551616").is_err());
+        assert_eq!(parse_data_size("16383p").unwrap(), 16383 << 50);
+    }
+
+    #[test]
+    fn rounds_to_significant_figures() {
+        assert_eq!(round_significant(1234.5678, 4), 1235.0);
+        assert_eq!(round_significant(-1234.5678, 4), -1235.0);
+        assert_eq!(round_significant(0.000_012_345_67, 3), 0.000_012_3);
+        assert_eq!(round_significant(9.999, 2), 10.0);
+        assert_eq!(round_significant(0.0, 4), 0.0);
+        assert!(round_significant(f64::NAN, 4).is_nan());
+        assert_eq!(round_significant(1.5, 0), 1.5);
+    }
+
+    #[test]
+    fn formats_significant_figures_without_exponent() {
+        assert_eq!(format_significant(1234.5678, 4), "1235");
+        assert_eq!(format_significant(123_456.0, 2), "120000");
+        assert_eq!(format_significant(-0.012_345, 3), "-0.0123");
+        assert_eq!(format_significant(0.000_001_234_5, 2), "0.0000012");
+        assert_eq!(format_significant(9.999, 2), "10");
+        assert_eq!(format_significant(1.0, 3), "1.00");
+        assert_eq!(format_significant(0.0, 4), "0");
+        assert_eq!(format_significant(f64::NAN, 4), "NaN");
+    }
+
+    fn sectioned(csv: &str) -> ([SectionStats; 3], Option<SensorShortfall>) {
+        let (stats, _, _, shortfall) = calculate_sectioned_checked::<_, 3>(
+            Some(MARKERS),
+            csv,
+            &["Total"],
+            &[(0.0, 100.0)],
+            power_energy_calculator,
+            None,
+        )
+        .unwrap();
+        (stats, shortfall)
+    }
+
+    #[test]
+    fn complete_csv_has_no_shortfall() {
+        let (stats, shortfall) = sectioned("time,Total\n0,1\n200,2\n300,3\n500,4\n");
+        assert_eq!(shortfall, None);
+        assert_eq!(stats[1].power_mean, Some(2.5));
+        assert_eq!(stats[2].power_mean, Some(4.0));
+    }
+
+    #[test]
+    fn csv_truncated_mid_row() {
+        for csv in [
+            "time,Total\n0,1\n200,2\n300,3\n450,4",
+            "time,Total\n0,1\n200,2\n300,3\n45",
+        ] {
+            let (stats, shortfall) = sectioned(csv);
+            assert_eq!(
+                shortfall,
+                Some(SensorShortfall {
+                    last_reading: Some(300),
+                    first_incomplete_section: 1,
+                    truncated_row: true,
+                })
+            );
+            assert_eq!(stats[0].power_mean, Some(1.0));
+            assert_eq!(stats[1].power_mean, Some(2.5));
+            assert_eq!(stats[2].power_mean, None);
+        }
+    }
+
+    #[test]
+    fn named_sections_of_selected_markers() {
+        let markers = "time,marker_name\n100,load\n200,loop-1\n400,loop-2\n";
+        let csv = "time,Total\n0,1\n150,2\n250,3\n350,5\n450,6\n";
+        let (sections, overall) = calculate_named_sections(
+            markers,
+            csv,
+            &["Total"],
+            &[(0.0, 100.0)],
+            &["loop-1", "loop-2"],
+            power_energy_calculator,
+        )
+        .unwrap();
+
+        let names = sections.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, [FIRST_SECTION, "loop-1", "loop-2"]);
+        assert_eq!((sections[1].start, sections[1].end), (200, 400));
+        assert_eq!((sections[2].start, sections[2].end), (400, 450));
+        assert_eq!(sections[0].stats.power_mean, Some(1.5));
+        assert_eq!(sections[1].stats.power_mean, Some(4.0));
+        assert_eq!(sections[2].stats.power_mean, Some(6.0));
+        assert_eq!(overall.power_mean, Some(3.4));
+
+        assert!(
+            calculate_named_sections(
+                markers,
+                csv,
+                &["Total"],
+                &[(0.0, 100.0)],
+                &["unknown"],
+                power_energy_calculator,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn csv_without_readings() {
+        let (stats, shortfall) = sectioned("time,Total\n");
+        assert!(!shortfall.unwrap().is_complete(0));
+        assert!(stats.iter().all(|x| x.power_mean.is_none()));
+    }
+
+    #[test]
+    fn outliers_are_dropped_only_if_configured() {
+        let csv = (0..10)
+            .map(|x| format!("{},10\n", x * 100))
+            .chain(std::iter::once("1000,100\n".to_owned()))
+            .fold("time,Total\n".to_owned(), |csv, row| csv + &row);
+        let mean = |outliers| {
+            calculate_sectioned::<_, 0>(
+                None,
+                &csv,
+                &["Total"],
+                &[(0.0, 1000.0)],
+                power_energy_calculator,
+                outliers,
+            )
+            .unwrap()
+            .1
+            .power_mean
+            .unwrap()
+        };
+        assert!(mean(None) > 18.0);
+        assert_eq!(
+            mean(Some(OutlierConfig {
+                allowed_deviation: 2.0
+            })),
+            10.0
+        );
+    }
+    #[test]
+    fn outliers_are_multiples_of_stddev() {
+        // mean 5, population stddev 2
+        let data = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(find_outliers_by_stddev(&data, 1.0), vec![0, 7]);
+        assert_eq!(find_outliers_by_stddev(&data, 1.5), vec![7]);
+        assert_eq!(find_outliers_by_stddev(&data, 2.0), Vec::<usize>::new());
+        assert_eq!(find_outliers_by_stddev(&data, 0.5), vec![0, 6, 7]);
+        assert!(find_outliers_by_stddev(&[3.0; 4], 0.0).is_empty());
+        assert!(find_outliers_by_stddev(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn binary_and_decimal_units() {
+        assert_eq!(to_mib(1048576.0), 1.0);
+        assert_eq!(to_mb(1000000.0), 1.0);
+        // 10^6 / 2^20 is exact in binary
+        assert_eq!(mb_to_mib(1.0), 0.95367431640625);
+        assert_eq!(format!("{:.6}", mb_to_mib(1.0)), "0.953674");
+    }
+
+    #[test]
+    fn steady_state_after_ramp() {
+        // ramps up to 5 W over 50 readings, then noisy around 5 W
+        let series = (0..50)
+            .map(|x| x as f64 * 0.1)
+            .chain((0..200).map(|x| if x % 2 == 0 { 5.1 } else { 4.9 }))
+            .collect::<Vec<_>>();
+        let start = detect_steady_state(&series, 10, 0.05).unwrap();
+        assert!((40..=50).contains(&start), "{start}");
+
+        // flat from the start
+        assert_eq!(detect_steady_state(&[3.0; 40], 10, 0.01), Some(0));
+        // ramps down to the steady state
+        let series = (0..30)
+            .map(|x| 10.0 - x as f64 * 0.2)
+            .chain(std::iter::repeat_n(4.0, 100))
+            .collect::<Vec<_>>();
+        let start = detect_steady_state(&series, 10, 0.02).unwrap();
+        assert!((25..=30).contains(&start), "{start}");
+    }
+
+    #[test]
+    fn no_steady_state_without_settling() {
+        // alternates between 1 and 10 W every 20 readings up to the end
+        let series = (0..210)
+            .map(|x| if (x / 20) % 2 == 0 { 1.0 } else { 10.0 })
+            .collect::<Vec<_>>();
+        assert_eq!(detect_steady_state(&series, 10, 0.1), None);
+        assert_eq!(detect_steady_state(&[1.0; 15], 10, 0.1), None);
+        assert_eq!(detect_steady_state(&[1.0; 15], 0, 0.1), None);
+    }
+
+    #[test]
+    fn steady_state_start_time_of_csv() {
+        let csv = (0..60)
+            .map(|x| format!("{},{}\n", x * 10, if x < 20 { x as f64 } else { 20.0 }))
+            .collect::<String>();
+        let detection = SteadyStateDetection {
+            window: Some(5),
+            tolerance: Some(0.05),
+        };
+        let time = detection
+            .start_time(&format!("time,Total\n{csv}"), &["Total"], &[(0.0, 100.0)])
+            .unwrap()
+            .unwrap();
+        assert!((180..=200).contains(&time), "{time}");
+    }
+}
@@ -38,6 +38,12 @@ pub fn include_benches(_: TokenStream) -> TokenStream {
             )*
         }
 
+        /// Default instance of every registered benchmark
+        pub fn default_benches() -> Vec<Box<dyn common::bench::Bench>> {
+            use common::bench::Bench;
+            vec![#(#benches::#benches_caps::default_bench(),)*]
+        }
+
         impl BenchKind {
             pub fn name(&self) -> &'static str {
                 use common::bench::Bench;
@@ -193,6 +199,11 @@ pub fn include_plots(_: TokenStream) -> TokenStream {
                 serde_json::to_string(&#plots::#plots_caps::default()).unwrap();
             )*
         }
+
+        /// Default instance of every registered plotter
+        pub fn default_plots() -> Vec<Box<dyn common::plot::Plot>> {
+            vec![#(Box::new(#plots::#plots_caps::default()),)*]
+        }
     }
     .into()
 }
@@ -44,12 +44,18 @@ if __name__ == "__main__":
     x = np.arange(len(labels))
     offsets = np.linspace(-(series_count - 1) / 2, (series_count - 1) / 2, series_count) * width
 
+    annotations = spec.get("annotations")
     _, ax = plt.subplots()
     for idx, series in enumerate(data):
         offset = offsets[idx] if series_count > 1 else 0
         positions = x + offset
         color = common.colors[idx % len(common.colors)]
-        ax.bar(positions, series, width, label=legends[idx], color=color)
+        bars = ax.bar(positions, series, width, label=legends[idx], color=color)
+        if annotations:
+            ax.bar_label(bars, labels=annotations[idx], rotation=90, fontsize=7, padding=2)
+    if annotations:
+        # room for the annotations above the highest bars
+        ax.margins(y=0.15)
 
     ax.set_ylabel(spec["y_label"])
     ax.set_xlabel(spec["x_label"])
@@ -61,6 +67,11 @@ if __name__ == "__main__":
         y_min = min(min(series) for series in data)
         y_max = max(max(series) for series in data)
         ax.set_ylim(y_min * 0.98, y_max * 1.02)
+    elif spec.get("y_max") is not None:
+        ax.set_ylim(top=spec["y_max"])
+
+    if spec.get("y_reference") is not None:
+        ax.axhline(spec["y_reference"], color="black", linestyle="--", linewidth=1)
 
     rotation = spec.get("tick_rotation_deg")
     if rotation is not None:
@@ -68,6 +79,6 @@ if __name__ == "__main__":
         plt.setp(ax.get_xticklabels(), rotation=rotation, ha=align)
 
     ax.legend()
-    plt.tight_layout()
+    common.add_footnote(spec.get("footnote"))
     plt.savefig(spec["output_path"], format=spec.get("format", "pdf"))
     plt.close()
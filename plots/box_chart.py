@@ -0,0 +1,78 @@
+import json
+import argparse
+import math
+
+import numpy as np
+import matplotlib.pyplot as plt
+from matplotlib.patches import Patch
+
+import common
+from bar_chart import get_plot_legends
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--spec", required=True, help="Path to JSON box chart specification, a bar chart with boxes")
+    args = parser.parse_args()
+
+    with open(args.spec, "r", encoding="utf-8") as f:
+        spec = json.load(f)
+
+    boxes = spec.get("boxes")
+    if not boxes:
+        raise ValueError("No data provided")
+
+    labels = spec["labels"]
+    series_count = len(boxes)
+    width = spec.get("bar_width")
+    if width is None:
+        width = 0.8 / max(1, series_count)
+
+    legends = get_plot_legends(boxes, spec.get("legend_labels"), spec.get("nvme_power_states"))
+
+    x = np.arange(len(labels))
+    offsets = np.linspace(-(series_count - 1) / 2, (series_count - 1) / 2, series_count) * width
+
+    _, ax = plt.subplots()
+    handles = []
+    for idx, series in enumerate(boxes):
+        offset = offsets[idx] if series_count > 1 else 0
+        color = common.colors[idx % len(common.colors)]
+        stats = []
+        positions = []
+        for position, (low, q1, med, q3, p99, high) in zip(x + offset, series):
+            # runs that are missing for this power state
+            if any(v is None or math.isnan(v) for v in (low, q1, med, q3, p99, high)):
+                continue
+            # whiskers end at the p99, the max is drawn as an outlier above them
+            stats.append({"whislo": low, "q1": q1, "med": med, "q3": q3, "whishi": p99, "fliers": [high]})
+            positions.append(position)
+        if stats:
+            ax.bxp(
+                stats,
+                positions=positions,
+                widths=width * 0.9,
+                patch_artist=True,
+                boxprops={"facecolor": color},
+                medianprops={"color": "black"},
+                flierprops={"marker": "x", "markeredgecolor": color},
+            )
+        handles.append(Patch(facecolor=color, label=legends[idx]))
+
+    ax.set_ylabel(spec["y_label"])
+    ax.set_xlabel(spec["x_label"])
+    ax.set_title(spec["title"])
+    ax.set_xticks(x)
+    ax.set_xticklabels(labels)
+
+    if spec.get("y_max") is not None:
+        ax.set_ylim(top=spec["y_max"])
+
+    rotation = spec.get("tick_rotation_deg")
+    if rotation is not None:
+        align = spec.get("tick_horizontal_align", "center")
+        plt.setp(ax.get_xticklabels(), rotation=rotation, ha=align)
+
+    ax.legend(handles=handles)
+    common.add_footnote(spec.get("footnote"))
+    plt.savefig(spec["output_path"], format=spec.get("format", "pdf"))
+    plt.close()
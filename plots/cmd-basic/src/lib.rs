@@ -4,7 +4,8 @@ use cmd::Cmd;
 use common::{
     bench::{BenchInfo, BenchParams},
     config::{Config, Settings},
-    plot::{Plot, PlotType, collect_run_groups, ensure_dirs},
+    plot::{Plot, PlotMetric, PlotType, collect_run_groups, ensure_dirs},
+    sensor::powersensor3_filename,
     util::{
         BarChartKind, Filesystem, SectionStats, calculate_sectioned, make_power_state_bar_config,
         plot_bar_chart, power_energy_calculator, sysinfo_average_calculator,
@@ -18,6 +19,16 @@ use serde::{Deserialize, Serialize};
 use tokio::fs::read_to_string;
 use tracing::debug;
 
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new("SSD power", "W", "power/<name>-ssd.pdf"),
+    PlotMetric::new("SSD power stddev", "W", "power/<name>-stdev-ssd.pdf"),
+    PlotMetric::new("CPU power", "W", "power/<name>-cpu.pdf"),
+    PlotMetric::new("Server power", "W", "power/<name>-server.pdf"),
+    PlotMetric::new("CPU frequency", "MHz", "power/<name>-freq.pdf"),
+    PlotMetric::new("CPU load", "%", "power/<name>-load.pdf"),
+];
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct CmdBasic;
@@ -39,12 +50,16 @@ impl Plot for CmdBasic {
         &["Powersensor3", "Rapl"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
         data_path: &Path,
         plot_path: &Path,
-        _config: &Config,
+        config: &Config,
         bench_info: &BenchInfo,
         dirs: Vec<String>,
         settings: &Settings,
@@ -60,13 +75,15 @@ impl Plot for CmdBasic {
             return Ok(());
         }
 
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
         let entries = join_all(groups.iter().map(|group| {
             let run_dir = data_path.join(&group.dir);
+            let powersensor3_path = run_dir.join(&powersensor3_filename);
             let dir = group.dir.clone();
             let info = group.info.clone();
             async move {
                 (
-                    read_to_string(run_dir.join("powersensor3.csv")).await,
+                    read_to_string(powersensor3_path).await,
                     read_to_string(run_dir.join("rapl.csv")).await,
                     read_to_string(run_dir.join("netio-http.csv")).await,
                     read_to_string(run_dir.join("sysinfo.csv")).await,
@@ -91,6 +108,7 @@ impl Plot for CmdBasic {
                     &["Total"],
                     &[(0.0, settings.cpu_max_power_watts)],
                     power_energy_calculator,
+                    None,
                 )
                 .context("Calculate rapl means")
                 .unwrap();
@@ -100,6 +118,7 @@ impl Plot for CmdBasic {
                     &["Total"],
                     &[(0.0, bench_info.device_power_states[0].0)],
                     power_energy_calculator,
+                    None,
                 )
                 .context("Calculate powersensor3 means")
                 .unwrap();
@@ -110,6 +129,7 @@ impl Plot for CmdBasic {
                     &[r#"load-\S+"#],
                     &[(0.0, settings.cpu_max_power_watts * 2.0)],
                     power_energy_calculator,
+                    None,
                 )
                 .context("Calculate system power means")
                 .unwrap();
@@ -126,6 +146,7 @@ impl Plot for CmdBasic {
                         (0.0, f64::MAX),
                     ],
                     sysinfo_average_calculator,
+                    None,
                 )
                 .context("Calculate sysinfo means")
                 .unwrap();
@@ -226,19 +247,18 @@ impl CmdBasic {
         get_value: fn(&PlotEntry) -> f64,
         bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut results = vec![vec![]; num_power_states];
 
         let experiment_name = ready_entries[0].info.name.clone();
 
         for item in ready_entries {
             let mean = get_value(&item);
-            let ps = if item.info.power_state == -1 {
-                0
-            } else {
-                item.info.power_state
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
             };
-            results[ps as usize].push((item, mean));
+            results[ps].push((item, mean));
         }
 
         let results = results
@@ -260,6 +280,7 @@ impl CmdBasic {
             vec!["Result".to_owned()],
             config,
             bench_info,
+            settings,
         )
     }
 }
@@ -1,5 +1,6 @@
 import os
 import re
+import math
 import numpy as np
 import pandas as pd
 import seaborn as sns
@@ -70,4 +71,22 @@ def parse_time_string(time_str):
     elif unit == "h":
         return value * 60 * 60 * 1000
     else:
-        return 0
\ No newline at end of file
+        return 0
+
+def format_significant(value, digits):
+    # like format_significant in common/src/util.rs, rounded without switching to an exponent
+    if value is None or not math.isfinite(value):
+        return ""
+    if value == 0:
+        return "0"
+    rounded = float(f"{value:.{digits}g}")
+    decimals = max(0, digits - 1 - math.floor(math.log10(abs(rounded))))
+    return f"{rounded:.{decimals}f}"
+
+def add_footnote(footnote):
+    # reserves space below the axes so that the footnote does not overlap the x label
+    if footnote:
+        plt.figtext(0.99, 0.01, footnote, ha="right", va="bottom", fontsize=8, color="#555555")
+        plt.tight_layout(rect=(0, 0.04, 1, 1))
+    else:
+        plt.tight_layout()
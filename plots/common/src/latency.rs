@@ -0,0 +1,123 @@
+use std::collections::{BTreeMap, HashMap};
+
+use common::{bench::BenchParams, plot::RunGroup};
+
+/// Histogram of raw per-IO latencies, as the number of IOs per latency bucket
+///
+/// Percentiles of several iterations can not be averaged, the average of the p99 of a run with a
+/// tail and a run without one is not the p99 of both. Histograms of the same buckets, ie. the
+/// fio `json+` bins, can be merged exactly instead, and the percentile taken of the merged one.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    buckets: BTreeMap<u64, u64>,
+}
+
+impl LatencyHistogram {
+    /// Adds `count` IOs with a latency of `latency`
+    pub fn add(&mut self, latency: u64, count: u64) {
+        if count > 0 {
+            *self.buckets.entry(latency).or_default() += count;
+        }
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (latency, count) in &other.buckets {
+            self.add(*latency, *count);
+        }
+    }
+
+    /// Number of IOs in the histogram
+    pub fn count(&self) -> u64 {
+        self.buckets.values().sum()
+    }
+
+    /// Latency below or at which `percentile` percent of the IOs completed (nearest rank)
+    pub fn percentile(&self, percentile: f64) -> Option<u64> {
+        let count = self.count();
+        if count == 0 || !(0.0..=100.0).contains(&percentile) {
+            return None;
+        }
+
+        let rank = ((percentile / 100.0 * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.buckets.iter().find_map(|(latency, n)| {
+            seen += n;
+            (seen >= rank).then_some(*latency)
+        })
+    }
+}
+
+impl FromIterator<(u64, u64)> for LatencyHistogram {
+    fn from_iter<T: IntoIterator<Item = (u64, u64)>>(iter: T) -> Self {
+        let mut histogram = LatencyHistogram::default();
+        for (latency, count) in iter {
+            histogram.add(latency, count);
+        }
+        histogram
+    }
+}
+
+/// Percentile of the merged histograms of all iterations, `None` if they hold no IOs
+pub fn merged_percentile<'a>(
+    histograms: impl IntoIterator<Item = &'a LatencyHistogram>,
+    percentile: f64,
+) -> Option<u64> {
+    let mut merged = LatencyHistogram::default();
+    for histogram in histograms {
+        merged.merge(histogram);
+    }
+    merged.percentile(percentile)
+}
+
+/// Directories of all iterations of the run of `group` among `dirs`, including its own
+///
+/// [`common::plot::collect_run_groups`] keeps only one iteration of every run, use this to
+/// aggregate over the rest.
+pub fn iteration_dirs(
+    group: &RunGroup,
+    dirs: &[String],
+    param_map: &HashMap<String, BenchParams>,
+) -> Vec<String> {
+    let mut iterations = dirs
+        .iter()
+        .filter(|dir| {
+            param_map.get(*dir).is_some_and(|info| {
+                info.name == group.info.name
+                    && info.power_state == group.info.power_state
+                    && info.idx == group.info.idx
+            })
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    if !iterations.contains(&group.dir) {
+        iterations.push(group.dir.clone());
+    }
+    iterations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_p99_differs_from_averaged_p99_on_skewed_iterations() {
+        // one iteration with a slow tail in 5% of its IOs, and one larger iteration without
+        let tail = LatencyHistogram::from_iter([(100, 950), (10_000, 50)]);
+        let no_tail = LatencyHistogram::from_iter([(100, 9_000)]);
+
+        let averaged = (tail.percentile(99.0).unwrap() + no_tail.percentile(99.0).unwrap()) / 2;
+        let merged = merged_percentile([&tail, &no_tail], 99.0).unwrap();
+
+        // the tail is 0.5% of all IOs, so the merged p99 is not affected by it, while the
+        // averaged p99 is dominated by it
+        assert_eq!(averaged, 5_050);
+        assert_eq!(merged, 100);
+        assert_eq!(merged_percentile([&tail, &no_tail], 99.9).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn percentile_of_empty_histogram() {
+        assert_eq!(LatencyHistogram::default().percentile(99.0), None);
+        assert_eq!(merged_percentile([], 50.0), None);
+    }
+}
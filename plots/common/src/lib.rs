@@ -2,13 +2,55 @@ use std::path::PathBuf;
 
 use common::{
     bench::BenchInfo,
-    sensor::Sensor,
+    plot::PlotMetric,
+    sensor::{Sensor, recorded_aliases, sensor_filename},
     util::{TimeSeriesAxis, TimeSeriesPlot, TimeSeriesSpec},
 };
 use default_benches::BenchKind;
 use macros::if_sensor;
 use sensor_common::SensorKind;
 
+pub mod latency;
+
+/// Metrics of the power over time plots, one plot per run, see [`default_timeseries_plot`]
+pub const POWER_TIME_METRICS: &[PlotMetric] = &[
+    PlotMetric::new(
+        "SSD Power",
+        "W",
+        "<bench>_time/<name>/<name>-ps<power state>-<run>-ssd.pdf",
+    ),
+    PlotMetric::new(
+        "CPU Power",
+        "W",
+        "<bench>_time/<name>/<name>-ps<power state>-<run>-cpu.pdf",
+    ),
+    PlotMetric::new(
+        "GPU Power",
+        "W",
+        "<bench>_time/<name>/<name>-ps<power state>-<run>-gpu.pdf",
+    ),
+    PlotMetric::new(
+        "CPU frequency",
+        "MHz",
+        "<bench>_time/<name>/<name>-ps<power state>-<run>-cpu-freq.pdf",
+    ),
+    PlotMetric::new(
+        "CPU load",
+        "%",
+        "<bench>_time/<name>/<name>-ps<power state>-<run>-cpu-load.pdf",
+    ),
+    PlotMetric::new(
+        "CPU C-state residency",
+        "%",
+        "<bench>_time/<name>/<name>-ps<power state>-<run>-cpu-cstate.pdf",
+    ),
+    PlotMetric::new(
+        "SSD temperature",
+        "°C",
+        "<bench>_time/<name>/<name>-ps<power state>-<run>-temp.pdf",
+    ),
+];
+
 pub fn default_timeseries_plot(
     kind: BenchKind,
     plot_path: PathBuf,
@@ -42,6 +84,35 @@ pub fn default_timeseries_plot(
         );
     }
 
+    if let Some(ps3) = SensorKind::get("Powersensor3")
+        && !diskstat.is_empty()
+    {
+        let ps3_filename = get_sensor(ps3).filename();
+        for alias in recorded_aliases(&data_path, ps3_filename) {
+            let filename = sensor_filename(ps3_filename, Some(&alias));
+            plots.push(
+                TimeSeriesPlot::new(
+                    None,
+                    format!("{name}-ssd-{alias}"),
+                    format!("SSD Power ({alias})"),
+                    TimeSeriesAxis::sensor_time(&filename),
+                    if_sensor!(
+                        "Powersensor3",
+                        powersensor3::POWERSENSOR_PLOT_AXIS
+                            .iter()
+                            .map(|x| TimeSeriesAxis {
+                                dataset_name: filename.clone(),
+                                ..x.clone()
+                            })
+                            .collect(),
+                        Vec::new()
+                    ),
+                )
+                .with_secondary(diskstat.clone()),
+            );
+        }
+    }
+
     if let Some(rapl) = SensorKind::get("Rapl")
         && !diskstat.is_empty()
     {
@@ -57,6 +128,23 @@ pub fn default_timeseries_plot(
         );
     }
 
+    let gpu = if_sensor!("Nvml", nvml::nvml_plot_axis(&data_path), Vec::new());
+    if let Some(nvml) = SensorKind::get("Nvml")
+        && !gpu.is_empty()
+        && !diskstat.is_empty()
+    {
+        plots.push(
+            TimeSeriesPlot::new(
+                None,
+                format!("{name}-gpu"),
+                "GPU Power",
+                TimeSeriesAxis::sensor_time(get_sensor(nvml).filename()),
+                gpu,
+            )
+            .with_secondary(diskstat.clone()),
+        );
+    }
+
     if let Some(sysinfo) = SensorKind::get("Sysinfo")
         && !diskstat.is_empty()
     {
@@ -91,8 +179,105 @@ pub fn default_timeseries_plot(
                     Vec::new()
                 ),
             )
-            .with_secondary(diskstat),
+            .with_secondary(diskstat.clone()),
+        );
+    }
+
+    if let Some(sysinfo) = SensorKind::get("Sysinfo") {
+        let cstates = if_sensor!(
+            "Sysinfo",
+            sysinfo::sysinfo_cstate_plot_axis(&data_path),
+            Vec::new()
+        );
+        // the throughput is only drawn alongside if diskstat was recorded
+        if !cstates.is_empty() {
+            plots.push(
+                TimeSeriesPlot::new(
+                    None,
+                    format!("{name}-cpu-cstate"),
+                    "CPU C-state residency",
+                    TimeSeriesAxis::sensor_time(get_sensor(sysinfo).filename()),
+                    cstates,
+                )
+                .with_secondary(diskstat.clone()),
+            );
+        }
+    }
+
+    let temp = if_sensor!(
+        "Diskstat",
+        diskstat::diskstat_temp_plot_axis(&data_path),
+        Vec::new()
+    );
+    if let Some(kind) = SensorKind::get("Diskstat")
+        && !temp.is_empty()
+    {
+        plots.push(
+            TimeSeriesPlot::new(
+                None,
+                format!("{name}-temp"),
+                "Throughput & SSD temperature",
+                TimeSeriesAxis::sensor_time(get_sensor(kind).filename()),
+                diskstat.clone(),
+            )
+            .with_secondary(temp),
+        );
+    }
+
+    if let Some(ps3) = SensorKind::get("Powersensor3")
+        && SensorKind::get("Pcie").is_some()
+    {
+        let time = TimeSeriesAxis::sensor_time(get_sensor(ps3).filename());
+        let pcie = if_sensor!("Pcie", pcie::pcie_plot_axis(&data_path), Vec::new());
+        for axis in pcie {
+            plots.push(
+                TimeSeriesPlot::new(
+                    None,
+                    format!("{name}-{}", axis.dataset_field.replace('_', "-")),
+                    format!("SSD Power & {}", axis.plot_label),
+                    time.clone(),
+                    if_sensor!(
+                        "Powersensor3",
+                        powersensor3::POWERSENSOR_PLOT_AXIS.to_vec(),
+                        Vec::new()
+                    ),
+                )
+                .with_secondary(vec![axis]),
+            );
+        }
+    }
+
+    if let Some(ps3) = SensorKind::get("Powersensor3")
+        && SensorKind::get("Cooling").is_some()
+    {
+        let time = TimeSeriesAxis::sensor_time(get_sensor(ps3).filename());
+        let (fans, fan_power) = if_sensor!(
+            "Cooling",
+            cooling::cooling_plot_axis(&data_path),
+            (Vec::new(), Vec::new())
         );
+        for (suffix, title, axis) in [
+            ("cooling-fans", "SSD Power & Fan speed", fans),
+            ("cooling-power", "SSD Power & Fan power", fan_power),
+        ] {
+            if axis.is_empty() {
+                continue;
+            }
+            plots.push(
+                TimeSeriesPlot::new(
+                    None,
+                    format!("{name}-{suffix}"),
+                    title,
+                    time.clone(),
+                    if_sensor!(
+                        "Powersensor3",
+                        powersensor3::POWERSENSOR_PLOT_AXIS.to_vec(),
+                        Vec::new()
+                    ),
+                )
+                .with_secondary(axis),
+            );
+        }
     }
 
     TimeSeriesSpec::new(
@@ -124,6 +309,10 @@ macro_rules! impl_power_time_plot {
                 &["Powersensor3", "Rapl", "Sysinfo"]
             }
 
+            fn metrics(&self) -> &'static [common::plot::PlotMetric] {
+                $crate::POWER_TIME_METRICS
+            }
+
             async fn plot(
                 &self,
                 plot_type: &PlotType,
@@ -132,7 +321,7 @@ macro_rules! impl_power_time_plot {
                 _config_yaml: &Config,
                 info: &BenchInfo,
                 dirs: Vec<String>,
-                _: &Settings,
+                settings: &Settings,
                 completed_dirs: &mut Vec<String>,
             ) -> Result<()> {
                 if *plot_type == PlotType::Total {
@@ -156,6 +345,7 @@ macro_rules! impl_power_time_plot {
                         &inner_dir,
                         &group.info,
                         info,
+                        settings,
                     )?;
                 }
                 Ok(())
@@ -169,6 +359,7 @@ macro_rules! impl_power_time_plot {
                 plot_path: &Path,
                 info: &BenchParams,
                 bench_info: &BenchInfo,
+                settings: &Settings,
             ) -> Result<()> {
                 let config = info.args.downcast_ref::<$bench>().unwrap();
                 let extra = ($dir_accessor)(config);
@@ -181,6 +372,7 @@ macro_rules! impl_power_time_plot {
                     common::util::write_csv(
                         &plot_path.join("plot_data").join(format!("{name}.csv")),
                         &trace,
+                        &settings.csv_format_or_default(),
                     )?;
                 }
 
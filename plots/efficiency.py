@@ -8,28 +8,40 @@ import matplotlib.pyplot as plt
 from matplotlib import rcParams
 rcParams['font.size'] = 12
 
-def gen_plots(matrix, filepath, col_labels, x_label, experiment_name, title, reverse=False):
+def gen_plots(matrix, filepath, col_labels, x_label, experiment_name, title, reverse=False, row_labels=None, y_label=None, footnote=None, precision=4, power_states=None):
     if reverse:
         r = "_r"
     else:
         r = ""
-    df = pd.DataFrame(matrix, index=col_labels, columns=['ps0 (8.5W)', 'ps1 (4.5W)', 'ps2 (3.2W)'][0:len(matrix[0])])
+    # rows are the power states, unless the heatmap is over the grid of two variables
+    if row_labels is None:
+        if power_states is None:
+            power_states = [f"ps{x}" for x in range(len(matrix[0]))]
+        df = pd.DataFrame(matrix, index=col_labels, columns=power_states[0:len(matrix[0])])
+    else:
+        df = pd.DataFrame(matrix, index=col_labels, columns=row_labels, dtype=float)
     df = df.T
+    annot = df.apply(lambda column: column.map(lambda x: common.format_significant(x, precision)))
     plt.figure(figsize=(12, 4.8))
     g = sns.heatmap(
         df,
         cmap=f"viridis{r}",
-        annot=True,
-        fmt=".2f",
+        annot=annot,
+        fmt="",
         linewidths=0.5,
         linecolor="white"
     )
     g.set_yticklabels(g.get_yticklabels())
-    plt.title(f"{x_label} {experiment_name} {title} efficiency")
-    plt.ylabel("Power state")
+    if row_labels is None:
+        plt.title(f"{x_label} {experiment_name} {title} efficiency")
+        plt.ylabel("Power state")
+    else:
+        g.invert_yaxis()
+        plt.title(f"{experiment_name} {title}")
+        plt.ylabel(y_label)
     plt.xlabel(x_label)
 
-    plt.tight_layout()
+    common.add_footnote(footnote)
     plt.savefig(filepath, format="pdf")
     plt.close()
 
@@ -43,6 +55,11 @@ if __name__ == "__main__":
     parser.add_argument("--experiment_name", type=str, required=True)
     parser.add_argument("--title", type=str, required=True)
     parser.add_argument("--reverse", type=str, required=False)
+    parser.add_argument("--row_labels", type=str, required=False)
+    parser.add_argument("--y_label", type=str, required=False)
+    parser.add_argument("--footnote", type=str, required=False)
+    parser.add_argument("--precision", type=int, required=False, default=4)
+    parser.add_argument("--power_states", type=str, required=False)
     args = parser.parse_args()
 
     f = open(args.data, "r")
@@ -53,4 +70,6 @@ if __name__ == "__main__":
     else:
         reverse = False
 
-    gen_plots(data, args.filepath, args.col_labels.split(","), args.x_label, args.experiment_name, args.title, reverse)
\ No newline at end of file
+    row_labels = args.row_labels.split(",") if args.row_labels is not None else None
+    power_states = args.power_states.split(",") if args.power_states is not None else None
+    gen_plots(data, args.filepath, args.col_labels.split(","), args.x_label, args.experiment_name, args.title, reverse, row_labels, args.y_label, args.footnote, args.precision, power_states)
\ No newline at end of file
@@ -4,9 +4,13 @@ use std::{
 };
 
 use common::{
-    bench::{BenchInfo, BenchParams},
+    bench::{BenchInfo, BenchParams, MARKERS_FILENAME},
     config::{Config, Settings},
-    plot::{HeatmapJob, Plot, PlotType, collect_run_groups, ensure_dirs, render_heatmaps},
+    plot::{
+        HeatmapJob, OperatingPoint, Plot, PlotMetric, PlotType, collect_run_groups, ensure_dirs,
+        render_heatmaps, write_headline,
+    },
+    sensor::powersensor3_filename,
     util::{
         BarChartKind, Filesystem, SectionStats, calculate_sectioned, make_power_state_bar_config,
         parse_data_size, parse_trace, plot_bar_chart, plot_time_series, power_energy_calculator,
@@ -23,10 +27,25 @@ use serde::{Deserialize, Serialize};
 use tokio::fs::read_to_string;
 use tracing::debug;
 
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new("Read throughput", "MiB/s", "throughput/<name>-read.pdf"),
+    PlotMetric::new("Write throughput", "MiB/s", "throughput/<name>-write.pdf"),
+    PlotMetric::new("IOPS", "kOPS/s", "iops/<name>.pdf"),
+    PlotMetric::new("Operations", "ops", "iops/<name>-total.pdf"),
+    PlotMetric::new("Latency", "ms", "latency/<name>.pdf"),
+    PlotMetric::new("SSD power", "W", "power-ssd/<name>-benchmark.pdf"),
+    PlotMetric::new("CPU power", "W", "power-cpu/<name>-benchmark.pdf"),
+    PlotMetric::new("IOPS/J", "IO/J", "efficiency/<name>-iops-j-benchmark.pdf"),
+];
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct FilebenchBasic {
     var_name: String,
+    /// The headline is the highest IOPS at an SSD power of at most this many W instead of the
+    /// highest IOPS/J of the benchmark section
+    headline_power_cap: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
@@ -47,12 +66,16 @@ impl Plot for FilebenchBasic {
         &["Powersensor3", "Rapl"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
         data_path: &Path,
         plot_path: &Path,
-        _config: &Config,
+        config: &Config,
         bench_info: &BenchInfo,
         dirs: Vec<String>,
         settings: &Settings,
@@ -67,17 +90,19 @@ impl Plot for FilebenchBasic {
         if groups.is_empty() {
             return Ok(());
         }
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
         let entries = join_all(groups.iter().map(|group| {
             let run_dir = data_path.join(&group.dir);
+            let powersensor3_path = run_dir.join(&powersensor3_filename);
             let dir = group.dir.clone();
             let info = group.info.clone();
             async move {
                 (
                     read_json_file::<FilebenchSummary>(run_dir.join("results.json")).await,
-                    read_to_string(run_dir.join("powersensor3.csv")).await,
+                    read_to_string(powersensor3_path).await,
                     read_to_string(run_dir.join("rapl.csv")).await,
                     read_to_string(run_dir.join("netio-http.csv")).await,
-                    read_to_string(run_dir.join("markers.csv")).await,
+                    read_to_string(run_dir.join(MARKERS_FILENAME)).await,
                     dir,
                     info,
                 )
@@ -99,6 +124,7 @@ impl Plot for FilebenchBasic {
                     &["Total"],
                     &[(0.0, settings.cpu_max_power_watts)],
                     power_energy_calculator,
+                    None,
                 )
                 .context("Calculate rapl means")
                 .unwrap();
@@ -109,6 +135,7 @@ impl Plot for FilebenchBasic {
                     &["Total"],
                     &[(0.0, bench_info.device_power_states[0].0)],
                     power_energy_calculator,
+                    None,
                 )
                 .context("Calculate powersensor3 means")
                 .unwrap();
@@ -119,6 +146,7 @@ impl Plot for FilebenchBasic {
                     &[r#"load-\S+"#],
                     &[(0.0, settings.cpu_max_power_watts * 2.0)],
                     power_energy_calculator,
+                    None,
                 )
                 .context("Calculate system power means")
                 .unwrap();
@@ -182,7 +210,7 @@ impl Plot for FilebenchBasic {
                 throughput_dir.join(format!("{experiment_name}-read.pdf")),
                 "throughput",
                 None,
-                "MB/s",
+                "MiB/s",
                 |data| {
                     data.result
                         .ops_stats
@@ -198,7 +226,7 @@ impl Plot for FilebenchBasic {
                 throughput_dir.join(format!("{experiment_name}-write.pdf")),
                 "throughput",
                 None,
-                "MB/s",
+                "MiB/s",
                 |data| {
                     let write_names = ["writefile", "wrtfile", "append", "fsync"];
                     data.result
@@ -222,6 +250,15 @@ impl Plot for FilebenchBasic {
                 "kOPS/s",
                 |data| data.result.summary.ops_per_sec / 1000.0,
             ),
+            (
+                ready_entries.clone(),
+                settings,
+                iops_dir.join(format!("{experiment_name}-total.pdf")),
+                "operations",
+                None,
+                "ops",
+                |data| data.result.summary.total_ops as f64,
+            ),
             (
                 ready_entries.clone(),
                 settings,
@@ -313,7 +350,7 @@ impl Plot for FilebenchBasic {
             item?;
         }
 
-        self.efficiency(ready_entries.clone(), settings, &efficiency_dir)
+        self.efficiency(ready_entries.clone(), settings, &efficiency_dir, bench_info)
             .await?;
         Ok(())
     }
@@ -331,7 +368,8 @@ impl FilebenchBasic {
         get_mean: fn(&PlotEntry) -> f64,
         bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut results = vec![vec![]; num_power_states];
         let (order, labels) = self.get_order_labels(ready_entries.clone());
 
@@ -339,12 +377,10 @@ impl FilebenchBasic {
 
         for item in ready_entries {
             let mean = get_mean(&item);
-            let ps = if item.info.power_state == -1 {
-                0
-            } else {
-                item.info.power_state
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
             };
-            results[ps as usize].push((item, mean));
+            results[ps].push((item, mean));
         }
 
         for item in results.iter_mut() {
@@ -370,10 +406,11 @@ impl FilebenchBasic {
             "throughput" => BarChartKind::Throughput,
             "latency" => BarChartKind::Latency,
             "power" => BarChartKind::Power,
+            "operations" => BarChartKind::Operations,
             other => bail!("Unsupported plotting file {other}"),
         };
         let config = make_power_state_bar_config(chart_kind, x_label, &experiment_name, y_name);
-        plot_bar_chart(&filepath, results, labels, config, bench_info)
+        plot_bar_chart(&filepath, results, labels, config, bench_info, settings)
     }
 
     async fn efficiency(
@@ -381,8 +418,10 @@ impl FilebenchBasic {
         ready_entries: Vec<PlotEntry>,
         settings: &Settings,
         plot_path: &Path,
+        bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let (order, labels) = self.get_order_labels(ready_entries.clone());
         let mut iops_j_overall = vec![vec![0f64; num_power_states]; order.len()];
         let mut iops_j_init = iops_j_overall.clone();
@@ -399,7 +438,7 @@ impl FilebenchBasic {
 
         let results = ready_entries
             .par_iter()
-            .map(|item| {
+            .filter_map(|item| {
                 let iops = item.result.summary.ops_per_sec;
                 let bytes = item.result.summary.mb_per_sec;
                 let latency = item.result.summary.latency_ms;
@@ -412,15 +451,17 @@ impl FilebenchBasic {
                             .unwrap_or(&"default".to_string())
                     ))
                     .unwrap();
-                let y = if item.info.power_state == -1 {
-                    0
-                } else {
-                    item.info.power_state
-                } as usize;
+                let y = power_states.index(item.info.power_state)?;
 
-                (
+                Some((
                     x,
                     y,
+                    OperatingPoint {
+                        label: labels[x].clone(),
+                        power_state: item.info.power_state,
+                        throughput: iops,
+                        ssd_power: item.ssd_power.benchmark.power_mean.unwrap(),
+                    },
                     iops / item.ssd_power.overall.power_mean.unwrap(),
                     iops / item.ssd_power.init.power_mean.unwrap(),
                     iops / item.ssd_power.benchmark.power_mean.unwrap(),
@@ -432,23 +473,25 @@ impl FilebenchBasic {
                     bytes / item.ssd_power.benchmark.power_mean.unwrap(),
                     bytes / item.ssd_power.post_benchmark.power_mean.unwrap(),
                     item.ssd_power.benchmark.power_mean.unwrap() * latency.powi(2),
-                )
+                ))
             })
             .collect::<Vec<_>>();
+        let mut points = Vec::new();
         for item in results {
             let x = item.0;
             let y = item.1;
-            iops_j_overall[x][y] = item.2;
-            iops_j_init[x][y] = item.3;
-            iops_j_benchmark[x][y] = item.4;
-            iops_j_benchmark_cpu[x][y] = item.5;
-            iops_j_benchmark_server[x][y] = item.6;
-            iops_j_post_benchmark[x][y] = item.7;
-            bytes_j_overall[x][y] = item.8;
-            bytes_j_init[x][y] = item.9;
-            bytes_j_benchmark[x][y] = item.10;
-            bytes_j_post_benchmark[x][y] = item.11;
-            edp[x][y] = item.12;
+            points.push(item.2);
+            iops_j_overall[x][y] = item.3;
+            iops_j_init[x][y] = item.4;
+            iops_j_benchmark[x][y] = item.5;
+            iops_j_benchmark_cpu[x][y] = item.6;
+            iops_j_benchmark_server[x][y] = item.7;
+            iops_j_post_benchmark[x][y] = item.8;
+            bytes_j_overall[x][y] = item.9;
+            bytes_j_init[x][y] = item.10;
+            bytes_j_benchmark[x][y] = item.11;
+            bytes_j_post_benchmark[x][y] = item.12;
+            edp[x][y] = item.13;
         }
 
         let jobs = vec![
@@ -498,21 +541,21 @@ impl FilebenchBasic {
             HeatmapJob {
                 filepath: plot_path.join(format!("{}-bytes-j-overall.pdf", &experiment_name)),
                 data: bytes_j_overall,
-                title: "Bytes/J",
+                title: "MiB/J",
                 x_label: "overall",
                 reverse: false,
             },
             HeatmapJob {
                 filepath: plot_path.join(format!("{}-bytes-j-init.pdf", &experiment_name)),
                 data: bytes_j_init,
-                title: "Bytes/J",
+                title: "MiB/J",
                 x_label: "init",
                 reverse: false,
             },
             HeatmapJob {
                 filepath: plot_path.join(format!("{}-bytes-j-benchmark.pdf", &experiment_name)),
                 data: bytes_j_benchmark,
-                title: "Bytes/J",
+                title: "MiB/J",
                 x_label: "benchmark",
                 reverse: false,
             },
@@ -520,7 +563,7 @@ impl FilebenchBasic {
                 filepath: plot_path
                     .join(format!("{}-bytes-j-post-benchmark.pdf", &experiment_name)),
                 data: bytes_j_post_benchmark,
-                title: "Bytes/J",
+                title: "MiB/J",
                 x_label: "post-benchmark",
                 reverse: false,
             },
@@ -533,7 +576,22 @@ impl FilebenchBasic {
             },
         ];
 
-        render_heatmaps(&experiment_name, &labels, plot_path, &jobs)
+        render_heatmaps(
+            &experiment_name,
+            &labels,
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("IO/s", "IO/J"),
+            self.headline_power_cap,
+            settings,
+        )
     }
 
     fn get_order_labels(
@@ -597,6 +655,10 @@ impl Plot for FilebenchPowerTime {
         &["Powersensor3", "Rapl", "Sysinfo"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        plot_common::POWER_TIME_METRICS
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
@@ -605,7 +667,7 @@ impl Plot for FilebenchPowerTime {
         _config_yaml: &Config,
         bench_info: &BenchInfo,
         dirs: Vec<String>,
-        _: &Settings,
+        settings: &Settings,
         completed_dirs: &mut Vec<String>,
     ) -> Result<()> {
         if *plot_type == PlotType::Total {
@@ -630,6 +692,7 @@ impl Plot for FilebenchPowerTime {
                     &inner_dir,
                     &data.info,
                     bench_info,
+                    settings,
                 )
             })
             .collect::<Vec<_>>();
@@ -647,6 +710,7 @@ impl FilebenchPowerTime {
         plot_path: &Path,
         info: &BenchParams,
         bench_info: &BenchInfo,
+        settings: &Settings,
     ) -> Result<()> {
         let config = info.args.downcast_ref::<Filebench>().unwrap();
         let vars = &config.vars.as_ref().unwrap()[0];
@@ -665,6 +729,7 @@ impl FilebenchPowerTime {
             write_csv(
                 &plot_path.join("plot_data").join(format!("{name}.csv")),
                 &trace,
+                &settings.csv_format_or_default(),
             )?;
         }
 
@@ -1,33 +1,174 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{File, read_dir},
     hash::{Hash, Hasher},
+    io::{BufRead, BufReader},
     path::{Path, PathBuf},
 };
 
 use common::{
-    bench::{BenchInfo, BenchParams},
+    bench::{Bench, BenchInfo, BenchParams, MARKERS_FILENAME, OUTPUT_FILENAME, PREPARE_DIRNAME},
     config::{Config, Settings},
-    plot::{HeatmapJob, Plot, PlotType, collect_run_groups, ensure_dirs, render_heatmaps},
+    plot::{
+        GridHeatmapJob, HeatmapJob, OperatingPoint, ParetoJob, Plot, PlotMetric, PlotType,
+        RunGroup, collect_run_groups, ensure_dirs, normalize_to_ps0, render_grid_heatmap,
+        render_heatmaps, render_pareto, write_headline,
+    },
+    sensor::powersensor3_filename,
     util::{
-        BarChartKind, SectionStats, TimeSeriesAxis, TimeSeriesPlot, TimeSeriesSpec,
-        calculate_sectioned, make_power_state_bar_config, parse_data_size, parse_time,
-        plot_bar_chart, plot_time_series, power_energy_calculator, read_json_file,
+        BYTES_PER_GIB, BarChartConfig, BarChartKind, PowerStatistic, SectionCalculator,
+        SectionStats, SteadyStateDetection, TimeSeriesAxis, TimeSeriesPlot, TimeSeriesSpec,
+        calculate_sectioned, calculate_sectioned_checked, make_power_state_bar_config,
+        parse_data_size, parse_time, percentile, plot_bar_chart, plot_box_chart, plot_line_chart,
+        plot_time_series, power_energy_calculator, read_json_file, summed_power_energy_calculator,
         sysinfo_average_calculator,
     },
 };
 use default_benches::BenchKind;
-use eyre::{Context, ContextCompat, Result};
+use eyre::{Context, ContextCompat, Result, bail};
 use fio::{
-    Fio,
-    result::{FioResult, Job},
+    Fio, FioTestType,
+    result::{ClatNs, FioResult, Job, Percentile},
 };
 use futures::future::join_all;
 use itertools::Itertools;
+use plot_common::latency::{LatencyHistogram, iteration_dirs, merged_percentile};
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tokio::fs::read_to_string;
-use tracing::debug;
+use tracing::{debug, warn};
+
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new("Throughput", "MiB/s", "throughput/<name>.pdf"),
+    PlotMetric::new("IOPS", "IO/s", "throughput/<name>-iops.pdf"),
+    PlotMetric::new("Read data", "GiB", "throughput/<name>-total-read.pdf"),
+    PlotMetric::new("Written data", "GiB", "throughput/<name>-total-write.pdf"),
+    PlotMetric::new(
+        "Throughput coefficient of variation",
+        "-",
+        "throughput/<name>-cov.pdf",
+    ),
+    PlotMetric::new("Completion latency", "ms", "latency/<name>.pdf"),
+    PlotMetric::new("Submission latency", "ms", "latency/<name>-slat.pdf"),
+    PlotMetric::new("Total latency", "ms", "latency/<name>-lat.pdf"),
+    PlotMetric::new("P99 completion latency", "ms", "latency/<name>-p99.pdf"),
+    PlotMetric::new("Latency outliers", "IOs", "latency/<name>-outliers.pdf"),
+    PlotMetric::new("SSD power", "W", "power/<name>-ssd.pdf"),
+    PlotMetric::new("SSD power stddev", "W", "power/<name>-stdev-ssd.pdf"),
+    PlotMetric::new(
+        "SSD power rolling stddev",
+        "W",
+        "power/<name>-rolling-stdev-ssd.pdf",
+    ),
+    PlotMetric::new(
+        "SSD power of rated maximum",
+        "%",
+        "power/<name>-rated-ssd.pdf",
+    ),
+    PlotMetric::new("CPU power", "W", "power/<name>-cpu.pdf"),
+    PlotMetric::new("CPU power increase", "%", "power/<name>-norm-cpu.pdf"),
+    PlotMetric::new("CPU power stddev", "W", "power/<name>-stdev-cpu.pdf"),
+    PlotMetric::new(
+        "CPU power rolling stddev",
+        "W",
+        "power/<name>-rolling-stdev-cpu.pdf",
+    ),
+    PlotMetric::new("System power", "W", "power/<name>-system.pdf"),
+    PlotMetric::new("CPU frequency", "MHz", "power/<name>-freq.pdf"),
+    PlotMetric::new("CPU load", "%", "power/<name>-load.pdf"),
+    PlotMetric::new(
+        "SSD provisioning energy",
+        "J",
+        "power/<name>-prepare-energy-ssd.pdf",
+    ),
+    PlotMetric::new("IOPS/J", "IO/J", "efficiency/<name>-iops-j.pdf"),
+    PlotMetric::new(
+        "IOPS/J incl. CPU",
+        "IO/J",
+        "efficiency/<name>-+cpu-iops-j.pdf",
+    ),
+    PlotMetric::new("MiB/J", "MiB/J", "efficiency/<name>-bytes-j.pdf"),
+    PlotMetric::new(
+        "MiB/J incl. CPU",
+        "MiB/J",
+        "efficiency/<name>-+cpu-bytes-j.pdf",
+    ),
+    PlotMetric::new("EDP", "J*s", "efficiency/<name>-edp.pdf"),
+    PlotMetric::new("P99 EDP", "J*s", "efficiency/<name>-edp-p99.pdf"),
+];
+
+/// Metrics of [`FioBwOverTime`], one plot per run
+const BW_OVER_TIME_METRICS: &[PlotMetric] = &[
+    PlotMetric::new(
+        "Fio throughput vs Diskstat throughput",
+        "MiB/s",
+        "fio_time/<name>/<name>-ps<power state>-<label>-throughput-verify.pdf",
+    ),
+    PlotMetric::new(
+        "Throughput vs SSD power",
+        "W",
+        "fio_time/<name>/<name>-ps<power state>-<label>-ssd.pdf",
+    ),
+    PlotMetric::new(
+        "Throughput vs instantaneous efficiency",
+        "MiB/J",
+        "fio_time/<name>/<name>-ps<power state>-<label>-mib-j.pdf",
+    ),
+    PlotMetric::new(
+        "Throughput vs CPU power",
+        "W",
+        "fio_time/<name>/<name>-ps<power state>-<label>-cpu.pdf",
+    ),
+    PlotMetric::new(
+        "Throughput vs CPU freq",
+        "MHz",
+        "fio_time/<name>/<name>-ps<power state>-<label>-cpu-freq.pdf",
+    ),
+    PlotMetric::new(
+        "Throughput vs CPU load",
+        "%",
+        "fio_time/<name>/<name>-ps<power state>-<label>-cpu-load.pdf",
+    ),
+    PlotMetric::new(
+        "Completion latency vs SSD power",
+        "ms",
+        "fio_time/<name>/<name>-ps<power state>-<label>-latency.pdf",
+    ),
+];
+
+/// Metrics of [`FioQdMap`], the file is named after the configured [`QdMapMetric`]
+const QD_MAP_METRICS: &[PlotMetric] = &[
+    PlotMetric::new("IOPS/J", "IO/J", "qd_map/<name>-ps<power state>-iops-j.pdf"),
+    PlotMetric::new("P99 latency", "ms", "qd_map/<name>-ps<power state>-p99.pdf"),
+    PlotMetric::new(
+        "Throughput",
+        "MiB/s",
+        "qd_map/<name>-ps<power state>-throughput.pdf",
+    ),
+];
+
+/// Metrics of [`FioEfficiencyCurve`]
+const EFFICIENCY_CURVE_METRICS: &[PlotMetric] = &[PlotMetric::new(
+    "Efficiency vs. request size",
+    "MiB/J",
+    "efficiency/<name>-curve.pdf",
+)];
+
+/// Metrics of [`FioLatencyDistribution`]
+const DISTRIBUTION_METRICS: &[PlotMetric] = &[PlotMetric::new(
+    "Completion latency distribution",
+    "ms",
+    "latency/<name>-distribution.pdf",
+)];
+
+/// Metrics of [`FioPareto`]
+const PARETO_METRICS: &[PlotMetric] = &[PlotMetric::new(
+    "IOPS over SSD power",
+    "IO/s",
+    "efficiency/<name>-pareto.pdf",
+)];
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FioBasic {
@@ -36,6 +177,26 @@ pub struct FioBasic {
     pub group: Option<Group>,
     pub labels: Option<Vec<String>>,
     pub matched_labels: Option<Vec<MatchedLabelEntry>>,
+    /// Use only the RAPL package (and dram) of the NUMA node fio is bound to for CPU power
+    pub numa_cpu_power: Option<bool>,
+    /// Power statistic used for the efficiency heatmaps, defaults to the mean
+    pub power_statistic: Option<PowerStatistic>,
+    /// Alias of the Powersensor3 instance measuring the SSD, defaults to the instance without an alias
+    pub powersensor3_alias: Option<String>,
+    /// Plot the efficiency heatmaps relative to PS0 instead of absolute values
+    pub normalized: Option<bool>,
+    /// Plot the number of IOs with a completion latency above this threshold (ms)
+    pub outlier_latency_ms: Option<f64>,
+    /// Bar charts of the same metric, ie. all latency or all SSD power charts, share their y axis
+    /// maximum, so that they can be compared by the height of their bars
+    pub harmonize_axes: Option<bool>,
+    /// The headline is the highest IOPS at an SSD power of at most this many W instead of the
+    /// highest IOPS/J, see [`write_headline`]
+    pub headline_power_cap: Option<f64>,
+    /// Runs without a `ramp_time` or markers are averaged from where their SSD power settles
+    /// instead of from the start, ie. from where the rolling mean of the SSD power stays within a
+    /// tolerance of its steady state mean, see [`SteadyStateDetection`]
+    pub steady_state_detection: Option<SteadyStateDetection>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -55,6 +216,8 @@ pub struct MatchedLabelEntry {
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Group {
     pub filter: String,
+    /// Only aggregate experiments with this tag
+    pub tag: Option<String>,
     pub name: String,
     pub x_label: String,
 }
@@ -67,6 +230,16 @@ struct PlotEntry {
     ssd_power: SectionStats,
     cpu_power: SectionStats,
     system_power: SectionStats,
+    /// SSD power while the run was prepared, see `measure_prepare`
+    prepare_power: SectionStats,
+    /// Maximum power (W) of the run's power state reported by the SSD, see [`rated_power`]
+    rated_power: Option<f64>,
+    /// IOs above `outlier_latency_ms`, if set
+    latency_outliers: Option<f64>,
+    /// P99 completion latency (ms) summed over the jobs, see [`iterations_p99_latency`]
+    p99_latency: f64,
+    /// Coefficient of variation of the diskstat throughput in the steady state, if recorded
+    throughput_cov: Option<f64>,
     plot: FioPlotter,
     load: f64,
     freq: f64,
@@ -79,6 +252,14 @@ impl Plot for FioBasic {
         &["Powersensor3", "Rapl", "Sysinfo", "Diskstat"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
+    fn validate(&self, bench: &dyn Bench) -> Result<()> {
+        FioPlotter::from(self.clone()).validate(bench)
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
@@ -98,7 +279,9 @@ impl Plot for FioBasic {
                 let r = Regex::new(&group.filter)?;
                 dirs.into_iter()
                     .filter(|x| {
-                        r.is_match(&bench_info.param_map.get(x).unwrap().name)
+                        let params = bench_info.param_map.get(x).unwrap();
+                        r.is_match(&params.name)
+                            && group.tag.as_ref().is_none_or(|tag| params.has_tag(tag))
                             && !completed_dirs.contains(x)
                     })
                     .collect()
@@ -112,6 +295,7 @@ impl Plot for FioBasic {
         };
 
         debug!("Got {} dirs", dirs.len());
+        let all_dirs = dirs.clone();
         let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
         if groups.is_empty() {
             return Ok(());
@@ -139,22 +323,42 @@ impl Plot for FioBasic {
             })
             .collect::<Result<Vec<_>>>()?;
 
+        let powersensor3_filename =
+            powersensor3_filename(&config_yaml.sensors, self.powersensor3_alias.as_deref())?;
         let entries = join_all(groups.iter().map(|(group, plot)| {
             let run_dir = data_path.join(&group.dir);
+            let powersensor3_filename = &powersensor3_filename;
             let plot_clone = plot.clone();
             let info_clone = group.info.clone();
+            let all_dirs = &all_dirs;
             async move {
                 let results = read_json_file::<FioResult>(run_dir.join("results.json")).await;
-                let ps3 = read_to_string(run_dir.join("powersensor3.csv")).await;
+                let p99_latency = match &results {
+                    Ok(result) => {
+                        iterations_p99_latency(data_path, result, group, all_dirs, bench_info).await
+                    }
+                    Err(_) => 0.0,
+                };
+                let ps3 = read_to_string(run_dir.join(powersensor3_filename)).await;
                 let rapl = read_to_string(run_dir.join("rapl.csv")).await;
                 let sysinfo = read_to_string(run_dir.join("sysinfo.csv")).await;
                 let system = read_to_string(run_dir.join("netio-http.csv")).await;
+                let markers = read_to_string(run_dir.join(MARKERS_FILENAME)).await.ok();
+                let diskstat = read_to_string(run_dir.join("diskstat.csv")).await.ok();
+                let prepare_ps3 =
+                    read_to_string(run_dir.join(PREPARE_DIRNAME).join(powersensor3_filename))
+                        .await
+                        .ok();
                 (
                     results,
+                    p99_latency,
                     ps3,
+                    prepare_ps3,
                     rapl,
                     sysinfo,
                     system,
+                    markers,
+                    diskstat,
                     group.dir.clone(),
                     info_clone,
                     plot_clone,
@@ -165,52 +369,92 @@ impl Plot for FioBasic {
 
         let ready_entries = entries
             .into_par_iter()
-            .map(|item| {
-                let (json, powersensor3, rapl, sysinfo, system, dir, info, plot) = item;
+            .filter_map(|item| {
+                let (
+                    json,
+                    p99_latency,
+                    powersensor3,
+                    prepare_powersensor3,
+                    rapl,
+                    sysinfo,
+                    system,
+                    markers,
+                    diskstat,
+                    dir,
+                    info,
+                    plot,
+                ) = item;
                 let rapl = rapl.context("Read rapl").unwrap();
                 let powersensor3 = powersensor3.context("Read powersensor3").unwrap();
                 let sysinfo = sysinfo.context("Read sysinfo").unwrap();
                 let system = system.context("Read system power").unwrap();
                 let fio_result = json
                     .context(format!(
-                        "Could not parse fio results.json in {dir} for {info:#?}"
+                        "Could not parse fio results.json in {dir} for {info:#?}, see {dir}/{OUTPUT_FILENAME}"
                     ))
                     .unwrap();
 
-                let parse_ramp_time = |ramp_time: &Option<String>| match ramp_time {
-                    Some(x) => parse_time(&x).context("Parse ramp time").unwrap(),
-                    None => 0,
-                };
-                let ramp_time = if let Some(g) = &fio_result.global_options {
-                    parse_ramp_time(&g.ramp_time)
-                } else {
-                    parse_ramp_time(&fio_result.jobs[0].job_options.ramp_time)
+                let detected = plot.steady_state_detection.and_then(|detection| {
+                    detect_ramp(
+                        &fio_result,
+                        markers.as_deref(),
+                        &detection,
+                        &powersensor3,
+                        bench_info.device_power_states[0].0,
+                    )
+                });
+                let markers = match detected {
+                    Some(time) => format!("time,marker_name\n{time},{STEADY_STATE_MARKER}\n"),
+                    None => steady_state_markers(&fio_result, markers),
                 };
 
-                let markers = format!("time,marker_name\n{ramp_time},ramp_time\n");
-
-                let (rapl, _, _) = calculate_sectioned::<_, 2>(
-                    Some(&markers),
+                let rapl_columns = match &settings.numa {
+                    Some(numa) if plot.numa_cpu_power.unwrap_or(false) => vec![
+                        format!("package-{}$", numa.cpunodebind),
+                        format!("dram-{}$", numa.cpunodebind),
+                    ],
+                    _ => vec!["Total".to_owned()],
+                };
+                let rapl = steady_state(
+                    &markers,
                     &rapl,
-                    &["Total"],
-                    &[(0.0, settings.cpu_max_power_watts)],
-                    power_energy_calculator,
+                    &rapl_columns.iter().map(String::as_str).collect::<Vec<_>>(),
+                    &vec![(0.0, settings.cpu_max_power_watts); rapl_columns.len()],
+                    summed_power_energy_calculator,
                 )
                 .context("Calculate rapl means")
-                .unwrap();
+                .inspect_err(|err| warn!("Skipping {dir}: {err:#}"))
+                .ok()?;
 
-                let (ps3, _, _) = calculate_sectioned::<_, 2>(
-                    Some(&markers),
+                let ps3 = steady_state(
+                    &markers,
                     &powersensor3,
                     &["Total"],
                     &[(0.0, bench_info.device_power_states[0].0)],
                     power_energy_calculator,
                 )
                 .context("Calculate powersensor3 means")
-                .unwrap();
+                .inspect_err(|err| warn!("Skipping {dir}: {err:#}"))
+                .ok()?;
+
+                let prepare_ps3 = prepare_powersensor3
+                    .map(|csv| {
+                        calculate_sectioned::<_, 1>(
+                            None,
+                            &csv,
+                            &["Total"],
+                            &[(0.0, bench_info.device_power_states[0].0)],
+                            power_energy_calculator,
+                            None,
+                        )
+                        .context("Calculate prepare powersensor3 means")
+                        .unwrap()
+                        .1
+                    })
+                    .unwrap_or_default();
 
-                let (sysinfo, _, _) = calculate_sectioned::<_, 2>(
-                    Some(&markers),
+                let sysinfo = steady_state(
+                    &markers,
                     &sysinfo,
                     &["cpu-[0-9]{0,3}-freq", "cpu-[0-9]{0,3}-load"],
                     &[
@@ -223,31 +467,64 @@ impl Plot for FioBasic {
                     sysinfo_average_calculator,
                 )
                 .context("Calculate sysinfo means")
-                .unwrap();
+                .inspect_err(|err| warn!("Skipping {dir}: {err:#}"))
+                .ok()?;
 
-                let (system, _, _) = calculate_sectioned::<_, 2>(
-                    Some(&markers),
+                let system = steady_state(
+                    &markers,
                     &system,
                     &[r#"load-\S+"#],
                     &[(0.0, settings.cpu_max_power_watts * 2.0)],
                     power_energy_calculator,
                 )
                 .context("Calculate system power means")
-                .unwrap();
+                .inspect_err(|err| warn!("Skipping {dir}: {err:#}"))
+                .ok()?;
+
+                let throughput_cov = diskstat.and_then(|diskstat| {
+                    steady_state(
+                        &markers,
+                        &diskstat,
+                        &["^read$", "^write$"],
+                        &[(0.0, f64::MAX), (0.0, f64::MAX)],
+                        throughput_cov_calculator,
+                    )
+                    .context("Calculate diskstat throughput variation")
+                    .inspect_err(|err| {
+                        warn!("No throughput coefficient of variation for {dir}: {err:#}")
+                    })
+                    .ok()
+                });
+
+                let latency_outliers = plot.outlier_latency_ms.map(|ms| {
+                    fio_result
+                        .jobs
+                        .iter()
+                        .map(|x| latency_outliers(x, ms * 1_000_000.0))
+                        .sum::<f64>()
+                });
 
-                PlotEntry {
+                Some(PlotEntry {
                     result: fio_result,
                     args: info.args.downcast_ref::<Fio>().unwrap().clone(),
+                    rated_power: rated_power(bench_info, info.power_state),
                     info,
-                    ssd_power: ps3[1],
-                    cpu_power: rapl[1],
-                    system_power: system[1],
+                    ssd_power: ps3,
+                    cpu_power: rapl,
+                    system_power: system,
+                    prepare_power: prepare_ps3,
+                    latency_outliers,
+                    p99_latency,
+                    throughput_cov,
                     plot: plot.into(),
-                    freq: sysinfo[1].0,
-                    load: sysinfo[1].1,
-                }
+                    freq: sysinfo.0,
+                    load: sysinfo.1,
+                })
             })
             .collect::<Vec<_>>();
+        if ready_entries.is_empty() {
+            return Ok(());
+        }
 
         let experiment_name = match &self.group {
             Some(group) => group.name.clone(),
@@ -266,7 +543,7 @@ impl Plot for FioBasic {
         ])
         .await?;
 
-        let plot_jobs: Vec<(
+        let mut plot_jobs: Vec<(
             Vec<PlotEntry>,
             &Settings,
             PathBuf,
@@ -280,44 +557,57 @@ impl Plot for FioBasic {
                 throughput_dir.join(format!("{experiment_name}.pdf")),
                 BarChartKind::Throughput,
                 None,
+                |data| throughput(&data.result),
+            ),
+            (
+                ready_entries.clone(),
+                settings,
+                throughput_dir.join(format!("{experiment_name}-iops.pdf")),
+                BarChartKind::Throughput,
+                Some("IOPS"),
+                |data| iops(&data.result),
+            ),
+            (
+                ready_entries.clone(),
+                settings,
+                latency_dir.join(format!("{experiment_name}.pdf")),
+                BarChartKind::Latency,
+                None,
                 |data| {
-                    (((data
-                        .result
+                    data.result
                         .jobs
                         .iter()
-                        .map(|x| x.read.io_bytes)
-                        .sum::<i64>()
-                        + data
-                            .result
-                            .jobs
-                            .iter()
-                            .map(|x| x.write.io_bytes)
-                            .sum::<i64>()) as f64)
-                        / 1048576.0)
-                        / (data.result.jobs[0].job_runtime as f64 / 1000.0)
+                        .map(|x| mean_latency(x, LatencyKind::Completion))
+                        .sum::<f64>()
                 },
             ),
             (
                 ready_entries.clone(),
                 settings,
-                throughput_dir.join(format!("{experiment_name}-iops.pdf")),
-                BarChartKind::Throughput,
+                latency_dir.join(format!("{experiment_name}-slat.pdf")),
+                BarChartKind::Latency,
                 None,
                 |data| {
                     data.result
                         .jobs
                         .iter()
-                        .map(|x| x.read.iops_mean + x.write.iops_mean)
+                        .map(|x| mean_latency(x, LatencyKind::Submission))
                         .sum::<f64>()
                 },
             ),
             (
                 ready_entries.clone(),
                 settings,
-                latency_dir.join(format!("{experiment_name}.pdf")),
+                latency_dir.join(format!("{experiment_name}-lat.pdf")),
                 BarChartKind::Latency,
                 None,
-                |data| data.result.jobs.iter().map(mean_latency).sum::<f64>(),
+                |data| {
+                    data.result
+                        .jobs
+                        .iter()
+                        .map(|x| mean_latency(x, LatencyKind::Total))
+                        .sum::<f64>()
+                },
             ),
             (
                 ready_entries.clone(),
@@ -325,7 +615,7 @@ impl Plot for FioBasic {
                 latency_dir.join(format!("{experiment_name}-p99.pdf")),
                 BarChartKind::Latency,
                 None,
-                |data| data.result.jobs.iter().map(mean_p99_latency).sum::<f64>(),
+                |data| data.p99_latency,
             ),
             (
                 ready_entries.clone(),
@@ -408,21 +698,91 @@ impl Plot for FioBasic {
                 |data| data.load,
             ),
         ];
+        if ready_entries
+            .iter()
+            .any(|x| x.prepare_power.energy.is_some())
+        {
+            plot_jobs.push((
+                ready_entries.clone(),
+                settings,
+                power_dir.join(format!("{experiment_name}-prepare-energy-ssd.pdf")),
+                BarChartKind::Energy,
+                Some("Provisioning SSD"),
+                |data| data.prepare_power.energy.unwrap_or(0.0),
+            ));
+        }
+        if ready_entries.iter().any(|x| x.rated_power.is_some()) {
+            plot_jobs.push((
+                ready_entries.clone(),
+                settings,
+                power_dir.join(format!("{experiment_name}-rated-ssd.pdf")),
+                BarChartKind::RatedPower,
+                Some("SSD"),
+                |data| match data.rated_power {
+                    Some(rated) => data.ssd_power.power_mean.unwrap() / rated * 100.0,
+                    None => f64::NAN,
+                },
+            ));
+        }
+        if ready_entries.iter().any(|x| total_gib(x, true) > 0.0) {
+            plot_jobs.push((
+                ready_entries.clone(),
+                settings,
+                throughput_dir.join(format!("{experiment_name}-total-read.pdf")),
+                BarChartKind::Data,
+                Some("Read"),
+                |data| total_gib(data, true),
+            ));
+        }
+        if ready_entries.iter().any(|x| total_gib(x, false) > 0.0) {
+            plot_jobs.push((
+                ready_entries.clone(),
+                settings,
+                throughput_dir.join(format!("{experiment_name}-total-write.pdf")),
+                BarChartKind::Data,
+                Some("Written"),
+                |data| total_gib(data, false),
+            ));
+        }
+        if ready_entries.iter().any(|x| x.throughput_cov.is_some()) {
+            plot_jobs.push((
+                ready_entries.clone(),
+                settings,
+                throughput_dir.join(format!("{experiment_name}-cov.pdf")),
+                BarChartKind::Variation,
+                Some("Throughput"),
+                |data| data.throughput_cov.unwrap_or(f64::NAN),
+            ));
+        }
+        if ready_entries.iter().any(|x| x.latency_outliers.is_some()) {
+            plot_jobs.push((
+                ready_entries.clone(),
+                settings,
+                latency_dir.join(format!("{experiment_name}-outliers.pdf")),
+                BarChartKind::Count,
+                Some("Latency outlier"),
+                |data| data.latency_outliers.unwrap_or(0.0),
+            ));
+        }
 
         let plotter = self.clone().into();
-        let results = plot_jobs
+        let mut charts = plot_jobs
             .into_par_iter()
-            .map(|x| {
-                self.bar_plot(
-                    x.0,
-                    x.1,
-                    x.2,
-                    x.3,
-                    x.4,
-                    x.5,
+            .map(|x| self.bar_data(x.0, x.1, x.2, x.3, x.4, x.5, config_yaml, &plotter))
+            .collect::<Vec<_>>();
+        if self.harmonize_axes.unwrap_or(false) {
+            harmonize_y_max(&mut charts);
+        }
+        let results = charts
+            .into_par_iter()
+            .map(|chart| {
+                plot_bar_chart(
+                    &chart.filepath,
+                    chart.data,
+                    chart.labels,
+                    chart.config,
                     bench_info,
-                    config_yaml,
-                    &plotter,
+                    settings,
                 )
             })
             .collect::<Vec<_>>();
@@ -435,6 +795,7 @@ impl Plot for FioBasic {
             ready_entries.clone(),
             settings,
             &efficiency_dir,
+            bench_info,
             config_yaml,
             &plotter,
         )
@@ -456,6 +817,57 @@ impl From<FioBasic> for FioPlotter {
 }
 
 impl FioPlotter {
+    /// Checks that `matched_labels` label every combination of `matched_args` that the sweep
+    /// produces, which [`Self::get_variable_ordering`] requires to order the `io_engines`
+    fn validate(&self, bench: &dyn Bench) -> Result<()> {
+        let (Some(matched_labels), Some(fio)) = (&self.matched_labels, bench.downcast_ref::<Fio>())
+        else {
+            return Ok(());
+        };
+        if !self.variables.iter().any(|x| x == "io_engines") {
+            return Ok(());
+        }
+
+        let Some(matched) = &fio.matched_args else {
+            bail!("matched_labels requires matched_args in the fio benchmark");
+        };
+        fio.validate_matched_args()?;
+        for label in matched_labels {
+            if let Some(idx) = label.items.iter().find(|x| **x >= matched.len()) {
+                bail!(
+                    "matched_labels entry {} refers to matched_args[{idx}], but there are only {} matched_args",
+                    label.label,
+                    matched.len()
+                );
+            }
+        }
+        for combination in fio.matched_combinations() {
+            let labeled = matched_labels.iter().any(|label| {
+                if combination.is_empty() {
+                    label.items.is_empty()
+                } else {
+                    !label.items.is_empty() && label.items.iter().all(|x| combination.contains(x))
+                }
+            });
+            if !labeled {
+                let args = combination
+                    .iter()
+                    .map(|x| format!("{}={}", matched[*x].key, matched[*x].value.join(", ")))
+                    .join(" ");
+                bail!(
+                    "No matched_labels entry labels the runs with {}, add an entry with items {:?}",
+                    if args.is_empty() {
+                        "no matched_args".to_owned()
+                    } else {
+                        format!("matched_args {args}")
+                    },
+                    combination
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn get_variable_ordering<'a>(
         &self,
         config: &Config,
@@ -522,7 +934,7 @@ impl FioPlotter {
                     let data = ready_entries
                         .iter()
                         .map(|x| {
-                            let key = x.args.matched_args.as_ref().unwrap();
+                            let key = x.args.matched_args.as_deref().unwrap_or_default();
                             let config_args = config
                                 .benches
                                 .iter()
@@ -532,9 +944,8 @@ impl FioPlotter {
 
                             let matched_indexes = fio_opts
                                 .matched_args
-                                .as_ref()
-                                .unwrap()
                                 .iter()
+                                .flatten()
                                 .enumerate()
                                 .filter_map(|(idx, x)| {
                                     if key.iter().find(|y| y.eq(&x)).is_some() {
@@ -607,6 +1018,136 @@ impl FioPlotter {
                     .collect::<HashSet<_>>();
                 finalize_hashset(set, true)
             }
+            "fsync" | "fdatasync" => {
+                let set = ready_entries
+                    .iter()
+                    .map(|item| {
+                        let x = match variable {
+                            "fsync" => item.args.fsync.as_ref(),
+                            "fdatasync" => item.args.fdatasync.as_ref(),
+                            _ => unreachable!(),
+                        }
+                        .map_or(0, |x| x[0]);
+                        OrderingEntry {
+                            entry: item,
+                            value: x,
+                            label: format!("{variable}={x}"),
+                        }
+                    })
+                    .collect::<HashSet<_>>();
+                finalize_hashset(set, true)
+            }
+            "iodepth_batch" => {
+                let set = ready_entries
+                    .iter()
+                    .map(|item| {
+                        let submit = item.args.iodepth_batch_submit.as_ref().map(|x| x[0]);
+                        let complete = item.args.iodepth_batch_complete.as_ref().map(|x| x[0]);
+                        let label = [("submit", submit), ("complete", complete)]
+                            .into_iter()
+                            .filter_map(|(name, x)| x.map(|x| format!("{name}={x}")))
+                            .join(" ");
+                        OrderingEntry {
+                            entry: item,
+                            // ordered by submit then complete, unset values (fio defaults)
+                            // are ordered last
+                            value: ((submit.unwrap_or(usize::MAX) as u128) << 64)
+                                | complete.unwrap_or(usize::MAX) as u128,
+                            label: if label.is_empty() {
+                                "default".to_owned()
+                            } else {
+                                label
+                            },
+                        }
+                    })
+                    .collect::<HashSet<_>>();
+                finalize_hashset(set, true)
+            }
+            "random_distribution" => {
+                let set = ready_entries
+                    .iter()
+                    .map(|item| {
+                        let distribution = item
+                            .args
+                            .random_distribution
+                            .as_ref()
+                            .map_or("random".to_owned(), |x| x[0].clone());
+                        OrderingEntry {
+                            entry: item,
+                            value: distribution.clone(),
+                            label: distribution,
+                        }
+                    })
+                    .collect::<HashSet<_>>();
+                finalize_hashset(set, true)
+            }
+            "cpu_governors" => {
+                let set = ready_entries
+                    .iter()
+                    .map(|item| {
+                        let governor = item
+                            .args
+                            .cpu_governors
+                            .as_ref()
+                            .map_or("default".to_owned(), |x| x[0].clone());
+                        OrderingEntry {
+                            entry: item,
+                            value: governor.clone(),
+                            label: governor,
+                        }
+                    })
+                    .collect::<HashSet<_>>();
+                finalize_hashset(set, true)
+            }
+            "use_threads" => {
+                let set = ready_entries
+                    .iter()
+                    .map(|item| {
+                        let threads = item.args.use_threads.unwrap_or(false);
+                        OrderingEntry {
+                            entry: item,
+                            value: threads,
+                            label: if threads { "threads" } else { "processes" }.to_owned(),
+                        }
+                    })
+                    .collect::<HashSet<_>>();
+                finalize_hashset(set, true)
+            }
+            "rapl_power_limits" => {
+                let set = ready_entries
+                    .iter()
+                    .map(|item| {
+                        let limit = item.args.rapl_power_limits.as_ref().map(|x| x[0]);
+                        OrderingEntry {
+                            entry: item,
+                            // uncapped runs are ordered last
+                            value: limit.map_or(u64::MAX, |x| (x * 1000.0) as u64),
+                            label: limit.map_or("uncapped".to_owned(), |x| format!("{x}W")),
+                        }
+                    })
+                    .collect::<HashSet<_>>();
+                finalize_hashset(set, true)
+            }
+            "rwmixread" => {
+                let set =
+                    ready_entries
+                        .iter()
+                        .map(|item| {
+                            let read = item.args.test_type.args.as_ref().map(|x| x.read).unwrap_or(
+                                match item.args.test_type._type {
+                                    FioTestType::Read | FioTestType::Randread => 100,
+                                    _ => 0,
+                                },
+                            );
+                            OrderingEntry {
+                                entry: item,
+                                value: read,
+                                label: format!("{read}% read"),
+                            }
+                        })
+                        .collect::<HashSet<_>>();
+                finalize_hashset(set, true)
+            }
             "extra_options" => {
                 if ready_entries.iter().any(|x| x.plot.group.is_some()) {
                     let data = ready_entries
@@ -692,10 +1233,12 @@ impl FioBasic {
         ready_entries: Vec<PlotEntry>,
         settings: &Settings,
         plot_path: &Path,
+        bench_info: &BenchInfo,
         config: &Config,
         plotter: &FioPlotter,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let (order, labels) = plotter.get_order_labels(config, &ready_entries);
         let mut iops_j = vec![vec![0f64; num_power_states]; order.len()];
         let mut iops_j_cpu = iops_j.clone();
@@ -709,136 +1252,162 @@ impl FioBasic {
             Some(group) => group.name.clone(),
             None => ready_entries[0].info.name.clone(),
         };
+        let statistic = self.power_statistic.unwrap_or_default();
+        let normalized = self.normalized.unwrap_or(false);
+        let title = |title: &str| {
+            let title = match statistic {
+                PowerStatistic::Mean => title.to_owned(),
+                _ => format!("{title} ({} power)", statistic.label()),
+            };
+            if normalized {
+                format!("{title} relative to PS0")
+            } else {
+                title
+            }
+        };
+        let filepath = |name: &str| {
+            plot_path.join(format!(
+                "{experiment_name}-{name}{}.pdf",
+                if normalized { "-vs-ps0" } else { "" }
+            ))
+        };
 
         let results = ready_entries
             .par_iter()
-            .map(|item| {
-                let iops = item
-                    .result
-                    .jobs
-                    .iter()
-                    .map(|x| x.read.iops_mean + x.write.iops_mean)
-                    .sum::<f64>();
-                let mb_s = (((item
+            .filter_map(|item| {
+                let iops = iops(&item.result);
+                let mb_s = throughput(&item.result);
+                let latency = item
                     .result
                     .jobs
                     .iter()
-                    .map(|x| x.read.io_bytes)
-                    .sum::<i64>()
-                    + item
-                        .result
-                        .jobs
-                        .iter()
-                        .map(|x| x.write.io_bytes)
-                        .sum::<i64>()) as f64)
-                    / 1048576.0)
-                    / (item.result.jobs[0].job_runtime as f64 / 1000.0);
-                let latency = item.result.jobs.iter().map(mean_latency).sum::<f64>()
+                    .map(|x| mean_latency(x, LatencyKind::Completion))
+                    .sum::<f64>()
                     / item.result.jobs.len() as f64;
-                let p99_latency = item.result.jobs.iter().map(mean_p99_latency).sum::<f64>();
+                let p99_latency = item.p99_latency;
                 let x = *order
                     .get(&plotter.get_order_key(item.clone(), config))
                     .unwrap();
-                let y = if item.info.power_state == -1 {
-                    0
-                } else {
-                    item.info.power_state
-                } as usize;
+                let y = power_states.index(item.info.power_state)?;
+                let ssd_power = item.ssd_power.power(statistic).unwrap_or(f64::NAN);
+                let cpu_power = item.cpu_power.power(statistic).unwrap_or(f64::NAN);
 
-                (
+                Some((
                     x,
                     y,
-                    (iops) / item.ssd_power.power_mean.unwrap(),
-                    (iops)
-                        / (item.cpu_power.power_mean.unwrap() + item.ssd_power.power_mean.unwrap()),
-                    mb_s / item.ssd_power.power_mean.unwrap(),
-                    mb_s / (item.cpu_power.power_mean.unwrap()
-                        + item.ssd_power.power_mean.unwrap()),
-                    item.ssd_power.power_mean.unwrap() * latency,
-                    item.ssd_power.power_mean.unwrap() * p99_latency,
-                    (item.ssd_power.power_mean.unwrap() + item.cpu_power.power_mean.unwrap())
-                        * latency,
-                    mb_s / item.cpu_power.power_mean.unwrap(),
-                )
+                    OperatingPoint {
+                        label: labels[x].clone(),
+                        power_state: item.info.power_state,
+                        throughput: iops,
+                        ssd_power,
+                    },
+                    efficiency_metrics(iops, mb_s, latency, p99_latency, ssd_power, cpu_power),
+                ))
             })
             .collect::<Vec<_>>();
-        for item in results {
-            let x = item.0;
-            let y = item.1;
-            iops_j[x][y] = item.2;
-            iops_j_cpu[x][y] = item.3;
-            bytes_j[x][y] = item.4;
-            bytes_j_cpu[x][y] = item.5;
-            edp[x][y] = item.6;
-            edp_p99[x][y] = item.7;
-            edp_total[x][y] = item.8;
-            cpu_only_bytes_j[x][y] = item.9;
+        let mut points = Vec::new();
+        for (x, y, point, metrics) in results {
+            points.push(point);
+            iops_j[x][y] = metrics.iops_j;
+            iops_j_cpu[x][y] = metrics.iops_j_cpu;
+            bytes_j[x][y] = metrics.bytes_j;
+            bytes_j_cpu[x][y] = metrics.bytes_j_cpu;
+            edp[x][y] = metrics.edp;
+            edp_p99[x][y] = metrics.edp_p99;
+            edp_total[x][y] = metrics.edp_total;
+            cpu_only_bytes_j[x][y] = metrics.cpu_only_bytes_j;
         }
 
         let x_label = self.x_label.as_str();
-        let jobs = vec![
+        let iops_j_title = title("IOPS/J");
+        let bytes_j_title = title("MiB/J");
+        let edp_title = title("EDP");
+        let edp_p99_title = title("P99 EDP");
+        let edp_total_title = title("EDP total");
+        let mut jobs = vec![
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-iops-j.pdf", &experiment_name)),
+                filepath: filepath("iops-j"),
                 data: iops_j,
-                title: "IOPS/J",
+                title: &iops_j_title,
                 x_label,
                 reverse: false,
             },
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-+cpu-iops-j.pdf", &experiment_name)),
+                filepath: filepath("+cpu-iops-j"),
                 data: iops_j_cpu,
-                title: "IOPS/J",
+                title: &iops_j_title,
                 x_label,
                 reverse: false,
             },
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-bytes-j.pdf", &experiment_name)),
+                filepath: filepath("bytes-j"),
                 data: bytes_j,
-                title: "MiB/J",
+                title: &bytes_j_title,
                 x_label,
                 reverse: false,
             },
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-only-cpu-bytes-j.pdf", &experiment_name)),
+                filepath: filepath("only-cpu-bytes-j"),
                 data: cpu_only_bytes_j,
-                title: "MiB/J",
+                title: &bytes_j_title,
                 x_label,
                 reverse: false,
             },
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-+cpu-bytes-j.pdf", &experiment_name)),
+                filepath: filepath("+cpu-bytes-j"),
                 data: bytes_j_cpu,
-                title: "MiB/J",
+                title: &bytes_j_title,
                 x_label,
                 reverse: false,
             },
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-edp.pdf", &experiment_name)),
+                filepath: filepath("edp"),
                 data: edp,
-                title: "EDP",
+                title: &edp_title,
                 x_label,
                 reverse: true,
             },
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-edp-p99.pdf", &experiment_name)),
+                filepath: filepath("edp-p99"),
                 data: edp_p99,
-                title: "P99 EDP",
+                title: &edp_p99_title,
                 x_label,
                 reverse: true,
             },
             HeatmapJob {
-                filepath: plot_path.join(format!("{}-edp-total.pdf", &experiment_name)),
+                filepath: filepath("edp-total"),
                 data: edp_total,
-                title: "EDP total",
+                title: &edp_total_title,
                 x_label,
                 reverse: true,
             },
         ];
+        if normalized {
+            for job in &mut jobs {
+                normalize_to_ps0(&mut job.data, job.reverse);
+                job.reverse = false;
+            }
+        }
 
-        render_heatmaps(&experiment_name, &labels, plot_path, &jobs)
+        render_heatmaps(
+            &experiment_name,
+            &labels,
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("IO/s", "IO/J"),
+            self.headline_power_cap,
+            settings,
+        )
     }
 
-    fn bar_plot(
+    fn bar_data(
         &self,
         ready_entries: Vec<PlotEntry>,
         settings: &Settings,
@@ -846,11 +1415,11 @@ impl FioBasic {
         chart_kind: BarChartKind,
         name: Option<&str>,
         get_mean: fn(&PlotEntry) -> f64,
-        bench_info: &BenchInfo,
         config: &Config,
         plotter: &FioPlotter,
-    ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+    ) -> BarChart {
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut results = vec![vec![]; num_power_states];
         let (order, labels) = plotter.get_order_labels(config, &ready_entries);
 
@@ -861,12 +1430,10 @@ impl FioBasic {
 
         for item in ready_entries {
             let mean = get_mean(&item);
-            let ps = if item.info.power_state == -1 {
-                0
-            } else {
-                item.info.power_state
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
             };
-            results[ps as usize].push((item, mean));
+            results[ps].push((item, mean));
         }
 
         for item in results.iter_mut() {
@@ -895,21 +1462,260 @@ impl FioBasic {
             .iter()
             .map(|x| x.iter().map(|x| x.1).collect::<Vec<_>>())
             .collect::<Vec<_>>();
-        let config = make_power_state_bar_config(chart_kind, &self.x_label, &experiment_name, name);
-        plot_bar_chart(&filepath, results, labels, config, bench_info)
+        BarChart {
+            filepath,
+            data: results,
+            labels,
+            config: make_power_state_bar_config(chart_kind, &self.x_label, &experiment_name, name),
+            metric: format!("{chart_kind:?} {}", name.unwrap_or_default()),
+        }
+    }
+}
+
+/// Data of a bar chart of [`FioBasic`], plotted once all charts are known
+struct BarChart {
+    filepath: PathBuf,
+    data: Vec<Vec<f64>>,
+    labels: Vec<String>,
+    config: BarChartConfig,
+    /// Kind and subject of the chart, ie. `Power SSD` for the SSD power charts
+    metric: String,
+}
+
+/// Sets the y axis maximum of every chart to the largest value of all charts of the same metric,
+/// ie. the latency charts or the SSD power charts, so that the power states and charts can be
+/// compared by the height of their bars
+fn harmonize_y_max(charts: &mut [BarChart]) {
+    let mut maxima = HashMap::new();
+    for chart in charts.iter() {
+        let max = chart
+            .data
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|x| x.is_finite())
+            .fold(f64::NEG_INFINITY, f64::max);
+        let entry = maxima
+            .entry(chart.metric.clone())
+            .or_insert(f64::NEG_INFINITY);
+        *entry = entry.max(max);
+    }
+    for chart in charts {
+        let max = maxima[&chart.metric];
+        if max > 0.0 {
+            // the default margin of matplotlib above the highest bar
+            chart.config.y_max = Some(max * 1.05);
+        }
+    }
+}
+
+/// Maximum power (W) of a power state from the power state descriptors of the SSD, `None` for
+/// runs that left the power state unchanged (`-1`)
+fn rated_power(bench_info: &BenchInfo, power_state: i32) -> Option<f64> {
+    let state = usize::try_from(power_state).ok()?;
+    bench_info
+        .device_power_states
+        .get(state)
+        .map(|x| x.0)
+        .filter(|x| *x > 0.0)
+}
+
+/// Efficiency of a single run, see [`efficiency_metrics`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EfficiencyMetrics {
+    iops_j: f64,
+    iops_j_cpu: f64,
+    bytes_j: f64,
+    bytes_j_cpu: f64,
+    cpu_only_bytes_j: f64,
+    edp: f64,
+    edp_p99: f64,
+    edp_total: f64,
+}
+
+/// Efficiency of a run from its IOPS, throughput (MiB/s), mean and p99 latency (ms), and SSD and
+/// CPU power (W). Work per Joule over a power that is not positive is NaN instead of infinite, and
+/// so is the energy-delay product instead of 0, which leaves the heatmap cell empty.
+fn efficiency_metrics(
+    iops: f64,
+    mib_s: f64,
+    latency: f64,
+    p99_latency: f64,
+    ssd_power: f64,
+    cpu_power: f64,
+) -> EfficiencyMetrics {
+    let per_joule = |work: f64, power: f64| {
+        if power > 0.0 { work / power } else { f64::NAN }
+    };
+    let delay_product = |power: f64, latency: f64| {
+        if power > 0.0 {
+            power * latency
+        } else {
+            f64::NAN
+        }
+    };
+    let total_power = ssd_power + cpu_power;
+    EfficiencyMetrics {
+        iops_j: per_joule(iops, ssd_power),
+        iops_j_cpu: per_joule(iops, total_power),
+        bytes_j: per_joule(mib_s, ssd_power),
+        bytes_j_cpu: per_joule(mib_s, total_power),
+        cpu_only_bytes_j: per_joule(mib_s, cpu_power),
+        edp: delay_product(ssd_power, latency),
+        edp_p99: delay_product(ssd_power, p99_latency),
+        edp_total: delay_product(total_power, latency),
+    }
+}
+
+/// Markers of the steady state, written by the bench if `wind_down` is set, otherwise the steady
+/// state runs from the ramp time to the end
+fn steady_state_markers(result: &FioResult, markers: Option<String>) -> String {
+    markers.unwrap_or_else(|| {
+        let ramp_time = ramp_time(result);
+        format!("time,marker_name\n{ramp_time},ramp_time\n")
+    })
+}
+
+/// `ramp_time` of the run in ms, 0 if it has none
+fn ramp_time(result: &FioResult) -> usize {
+    let parse_ramp_time = |ramp_time: &Option<String>| match ramp_time {
+        Some(x) => parse_time(x).context("Parse ramp time").unwrap(),
+        None => 0,
+    };
+    if let Some(g) = &result.global_options {
+        parse_ramp_time(&g.ramp_time)
+    } else {
+        parse_ramp_time(&result.jobs[0].job_options.ramp_time)
+    }
+}
+
+/// Name of the marker at the start of a detected steady state
+const STEADY_STATE_MARKER: &str = "steady_state";
+
+/// Time (ms) at which the SSD power of a run without markers or `ramp_time` settles, `None` if
+/// the run has either of them or its power does not settle
+fn detect_ramp(
+    result: &FioResult,
+    markers: Option<&str>,
+    detection: &SteadyStateDetection,
+    powersensor3: &str,
+    max_power: f64,
+) -> Option<usize> {
+    if markers.is_some() || ramp_time(result) > 0 {
+        return None;
+    }
+    match detection.start_time(powersensor3, &["Total"], &[(0.0, max_power)]) {
+        Ok(Some(time)) => {
+            debug!("SSD power settles at {time} ms");
+            Some(time)
+        }
+        Ok(None) => {
+            warn!("SSD power does not settle, the steady state starts at 0 ms");
+            None
+        }
+        Err(err) => {
+            warn!("Could not detect the steady state: {err:#}");
+            None
+        }
+    }
+}
+
+/// Total IOPS over all jobs
+fn iops(result: &FioResult) -> f64 {
+    result
+        .jobs
+        .iter()
+        .map(|x| x.read.iops_mean + x.write.iops_mean)
+        .sum::<f64>()
+}
+
+/// Total throughput over all jobs in MiB/s
+fn throughput(result: &FioResult) -> f64 {
+    result.total_throughput()
+}
+
+/// Calculates the section between the `ramp_time` marker and the optional `wind_down` marker
+fn steady_state<CalculatedData: std::fmt::Debug + Default + Copy>(
+    markers: &str,
+    csv_to_section: &str,
+    columns: &[&str],
+    limits: &[(f64, f64)],
+    calculator: SectionCalculator<CalculatedData>,
+) -> Result<CalculatedData> {
+    let num_markers = markers.lines().skip(1).filter(|x| !x.is_empty()).count();
+    let (stats, shortfall) = if num_markers > 1 {
+        let (stats, _, _, shortfall) = calculate_sectioned_checked::<_, 3>(
+            Some(markers),
+            csv_to_section,
+            columns,
+            limits,
+            calculator,
+            None,
+        )?;
+        (stats[1], shortfall)
+    } else {
+        let (stats, _, _, shortfall) = calculate_sectioned_checked::<_, 2>(
+            Some(markers),
+            csv_to_section,
+            columns,
+            limits,
+            calculator,
+            None,
+        )?;
+        (stats[1], shortfall)
+    };
+    match shortfall {
+        Some(shortfall) if !shortfall.is_complete(1) => {
+            bail!("Sensor CSV ends before the steady state does, {shortfall}")
+        }
+        _ => Ok(stats),
+    }
+}
+
+/// Coefficient of variation (stddev / mean) of the diskstat read + write throughput samples, NaN
+/// without throughput
+fn throughput_cov_calculator(data: &[(usize, Vec<f64>)]) -> f64 {
+    let samples = data
+        .iter()
+        .map(|(_, v)| v.iter().sum::<f64>())
+        .collect::<Vec<_>>();
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    let variance =
+        samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / samples.len() as f64;
+    if mean > 0.0 {
+        variance.sqrt() / mean
+    } else {
+        f64::NAN
     }
 }
 
-fn mean_latency(x: &Job) -> f64 {
-    let n = x.read.clat_ns.mean + x.write.clat_ns.mean;
+/// Latency reported by fio, `Total` is the sum of `Submission` and `Completion`
+#[derive(Debug, Clone, Copy)]
+enum LatencyKind {
+    Submission,
+    Completion,
+    Total,
+}
+
+/// Mean latency in ms over the directions a job performed IO in, 0 if the job did not report
+/// this latency (ie. submission latency of synchronous io engines)
+fn mean_latency(x: &Job, kind: LatencyKind) -> f64 {
+    let (r, w) = match kind {
+        LatencyKind::Submission => (x.read.slat_ns.mean, x.write.slat_ns.mean),
+        LatencyKind::Completion => (x.read.clat_ns.mean, x.write.clat_ns.mean),
+        LatencyKind::Total => (x.read.lat_ns.mean, x.write.lat_ns.mean),
+    };
     let mut d = 0;
-    if x.read.clat_ns.mean > 0.0 {
+    if r > 0.0 {
         d += 1000000;
     }
-    if x.write.clat_ns.mean > 0.0 {
+    if w > 0.0 {
         d += 1000000;
     }
-    n / d as f64
+    if d == 0 {
+        return 0.0;
+    }
+    (r + w) / d as f64
 }
 
 fn mean_p99_latency(x: &Job) -> f64 {
@@ -937,13 +1743,177 @@ fn mean_p99_latency(x: &Job) -> f64 {
     (r + w) as f64 / d as f64
 }
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
-pub struct FioBwOverTime {
-    pub variables: Vec<String>,
-    pub group: Option<Group>,
-    pub labels: Option<Vec<String>>,
-    pub matched_labels: Option<Vec<MatchedLabelEntry>>,
-}
+/// P99 completion latency (ms) of a run summed over its jobs, like [`mean_p99_latency`]
+///
+/// If fio reported the latency histograms (`--output-format=json+`), the histograms of all
+/// iterations of the run are merged and the p99 taken of the merged histogram, instead of only
+/// using the first iteration.
+async fn iterations_p99_latency(
+    data_path: &Path,
+    result: &FioResult,
+    group: &RunGroup,
+    dirs: &[String],
+    bench_info: &BenchInfo,
+) -> f64 {
+    let has_bins = result
+        .jobs
+        .iter()
+        .any(|x| x.read.clat_ns.bins.is_some() || x.write.clat_ns.bins.is_some());
+    if has_bins {
+        let iterations = join_all(
+            iteration_dirs(group, dirs, &bench_info.param_map)
+                .into_iter()
+                .filter(|dir| *dir != group.dir)
+                .map(|dir| read_json_file::<FioResult>(data_path.join(dir).join("results.json"))),
+        )
+        .await
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+        let results = std::iter::once(result)
+            .chain(iterations.iter())
+            .collect::<Vec<_>>();
+        if let Some(p99) = merged_p99_latency(&results) {
+            return p99;
+        }
+    }
+    result.jobs.iter().map(mean_p99_latency).sum()
+}
+
+/// See [`iterations_p99_latency`], `None` if a histogram is missing or the runs differ in jobs
+fn merged_p99_latency(results: &[&FioResult]) -> Option<f64> {
+    let num_jobs = results.first()?.jobs.len();
+    if results.iter().any(|x| x.jobs.len() != num_jobs) {
+        return None;
+    }
+
+    (0..num_jobs)
+        .map(|job| {
+            let r = merged_clat_p99(results.iter().map(|x| &x.jobs[job].read.clat_ns))?;
+            let w = merged_clat_p99(results.iter().map(|x| &x.jobs[job].write.clat_ns))?;
+            let mut d = 0;
+            if r > 0 {
+                d += 1000000;
+            }
+            if w > 0 {
+                d += 1000000;
+            }
+            Some((r + w) as f64 / d as f64)
+        })
+        .sum()
+}
+
+/// P99 (ns) of the merged completion latency histograms of one direction of a job, 0 without IOs
+fn merged_clat_p99<'a>(clats: impl Iterator<Item = &'a ClatNs>) -> Option<u64> {
+    let histograms = clats
+        .map(|clat| {
+            if clat.n == 0 {
+                return Some(LatencyHistogram::default());
+            }
+            clat.bins
+                .as_ref()?
+                .iter()
+                .map(|(latency, count)| Some((latency.parse().ok()?, *count as u64)))
+                .collect()
+        })
+        .collect::<Option<Vec<LatencyHistogram>>>()?;
+    Some(merged_percentile(&histograms, 99.0).unwrap_or(0))
+}
+
+/// GiB read or written by all jobs of a run
+fn total_gib(data: &PlotEntry, read: bool) -> f64 {
+    data.result
+        .jobs
+        .iter()
+        .map(|x| if read { x.read.io_bytes } else { x.write.io_bytes } as f64)
+        .sum::<f64>()
+        / BYTES_PER_GIB
+}
+
+/// Number of IOs of a job with a completion latency above `threshold_ns`, over all directions
+fn latency_outliers(x: &Job, threshold_ns: f64) -> f64 {
+    x.read.clat_ns.count_above(threshold_ns) + x.write.clat_ns.count_above(threshold_ns)
+}
+
+/// Width of the buckets of the completion latency over time, in ms
+const LATENCY_BUCKET_MS: u64 = 1000;
+
+/// Completion latency percentiles of a bucket of [`LATENCY_BUCKET_MS`], in ms
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct LatencyBucket {
+    /// Start of the bucket in ms, on the time axis of fio's bw log
+    time: u64,
+    p50: f64,
+    p99: f64,
+}
+
+/// Completion latency logs of a run, fio writes `log_clat.log` or, ie. with `numjobs > 1`, one
+/// `log_clat.N.log` per job
+fn clat_logs(run_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut logs = read_dir(run_dir)?
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter(|x| {
+            x.file_name()
+                .and_then(|x| x.to_str())
+                .is_some_and(|x| x.starts_with("log_clat.") && x.ends_with(".log"))
+        })
+        .collect::<Vec<_>>();
+    logs.sort();
+    Ok(logs)
+}
+
+/// p50 and p99 of the completion latency of all jobs over time. Every line of a log is
+/// `time (ms), latency (ns), direction, block size, offset`, with `log_avg_msec` the latency is
+/// the mean of each window, so the percentiles are of the window means.
+fn latency_over_time(logs: impl IntoIterator<Item = impl BufRead>) -> Result<Vec<LatencyBucket>> {
+    let mut buckets: BTreeMap<u64, Vec<f64>> = BTreeMap::new();
+    for log in logs {
+        for line in log.lines() {
+            let line = line?;
+            let mut fields = line.split(',').map(str::trim);
+            let (Some(time), Some(latency)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let time = time
+                .parse::<u64>()
+                .context(format!("Parsing time of {line}"))?;
+            let latency = latency
+                .parse::<f64>()
+                .context(format!("Parsing latency of {line}"))?;
+            buckets
+                .entry(time / LATENCY_BUCKET_MS * LATENCY_BUCKET_MS)
+                .or_default()
+                .push(latency / 1_000_000.0);
+        }
+    }
+
+    Ok(buckets
+        .into_iter()
+        .map(|(time, mut latencies)| {
+            latencies.sort_by(f64::total_cmp);
+            LatencyBucket {
+                time,
+                p50: percentile(&latencies, 0.5),
+                p99: percentile(&latencies, 0.99),
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FioBwOverTime {
+    pub variables: Vec<String>,
+    pub group: Option<Group>,
+    pub labels: Option<Vec<String>>,
+    pub matched_labels: Option<Vec<MatchedLabelEntry>>,
+    /// Also plots the SSD power of every power state over time on one chart per label
+    pub overlay_power_states: Option<bool>,
+    /// Marks where the SSD power settles in runs without a `ramp_time` or markers, like
+    /// [`FioBasic::steady_state_detection`]
+    pub steady_state_detection: Option<SteadyStateDetection>,
+}
 
 #[async_trait::async_trait]
 #[typetag::serde]
@@ -952,6 +1922,14 @@ impl Plot for FioBwOverTime {
         &["Powersensor3", "Rapl", "Sysinfo"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        BW_OVER_TIME_METRICS
+    }
+
+    fn validate(&self, bench: &dyn Bench) -> Result<()> {
+        self.plotter().validate(bench)
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
@@ -988,41 +1966,90 @@ impl Plot for FioBwOverTime {
             )
             .expect("Error running fio_time");
         });
+
+        if self.overlay_power_states.unwrap_or(false) {
+            self.power_state_overlay(config, data_path, &bw_inner_dir, &groups, bench_info)?;
+        }
         Ok(())
     }
 }
 
 impl FioBwOverTime {
-    fn fio_time(
-        &self,
-        config: &Config,
-        data_path: &Path,
-        group_dir: &str,
-        plot_path: &Path,
-        info: &BenchParams,
-        bench_info: &BenchInfo,
-    ) -> Result<()> {
-        let plotter = FioPlotter {
+    fn plotter(&self) -> FioPlotter {
+        FioPlotter {
             group: self.group.clone(),
             labels: self.labels.clone(),
             matched_labels: self.matched_labels.clone(),
             variables: self.variables.clone(),
-        };
+        }
+    }
 
-        let args = info.args.downcast_ref::<Fio>().unwrap();
+    /// Labels of the variable values of a single run
+    fn run_labels(&self, config: &Config, info: &BenchParams) -> Vec<String> {
+        let plotter = self.plotter();
         let entry = PlotEntry {
             result: FioResult::default(),
             info: info.clone(),
-            args: args.clone(),
+            args: info.args.downcast_ref::<Fio>().unwrap().clone(),
             ssd_power: SectionStats::default(),
             cpu_power: SectionStats::default(),
             system_power: SectionStats::default(),
+            prepare_power: SectionStats::default(),
+            rated_power: None,
+            latency_outliers: None,
+            p99_latency: 0.0,
+            throughput_cov: None,
             plot: plotter.clone(),
             load: 0.0,
             freq: 0.0,
         };
-        let (_, labels) = plotter.get_order_labels(&config, &[entry]);
-        for l in labels {
+        plotter.get_order_labels(config, &[entry]).1
+    }
+
+    fn fio_time(
+        &self,
+        config: &Config,
+        data_path: &Path,
+        group_dir: &str,
+        plot_path: &Path,
+        info: &BenchParams,
+        bench_info: &BenchInfo,
+    ) -> Result<()> {
+        let args = info.args.downcast_ref::<Fio>().unwrap();
+        let logs = clat_logs(&data_path.join(group_dir))?
+            .iter()
+            .map(|x| File::open(x).map(BufReader::new))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let latency = latency_over_time(logs)?;
+        let steady_state = match &self.steady_state_detection {
+            Some(detection) => {
+                let run_dir = data_path.join(group_dir);
+                let result: FioResult = serde_json::from_str(
+                    &std::fs::read_to_string(run_dir.join("results.json"))
+                        .context("Read fio results")?,
+                )?;
+                let markers = std::fs::read_to_string(run_dir.join(MARKERS_FILENAME)).ok();
+                let powersensor3 = std::fs::read_to_string(
+                    run_dir.join(powersensor3_filename(&config.sensors, None)?),
+                )
+                .context("Read powersensor3")?;
+                detect_ramp(
+                    &result,
+                    markers.as_deref(),
+                    detection,
+                    &powersensor3,
+                    bench_info.device_power_states[0].0,
+                )
+            }
+            None => None,
+        };
+        // read from the `latency` dataset of the bench data, see `time_series.py`
+        let latency_axis = |axis: TimeSeriesAxis| TimeSeriesAxis {
+            dataset_name: "latency".to_owned(),
+            ..axis
+        };
+
+        for l in self.run_labels(config, info) {
             let name = format!("{}-ps{}-{l}", info.name, info.power_state);
             let default = TimeSeriesPlot::new(
                 None,
@@ -1045,50 +2072,975 @@ impl FioBwOverTime {
                 common::util::write_csv(
                     &plot_path.join("plot_data").join(format!("{name}.csv")),
                     &trace,
+                    &config.settings.csv_format_or_default(),
+                )?;
+            }
+
+            let mut plots = vec![
+                TimeSeriesPlot::new(
+                    None,
+                    format!("{name}-throughput-verify"),
+                    "Fio throughput vs Diskstat throughput",
+                    TimeSeriesAxis::bench_time(),
+                    vec![TimeSeriesAxis::bench(
+                        "smoothed",
+                        "fio",
+                        "Fio Throughput (MiB/s)",
+                    )],
+                )
+                .with_secondary(diskstat::DISKSTAT_PLOT_AXIS.to_vec()),
+                default
+                    .clone()
+                    .with_title("Throughput vs SSD power")
+                    .with_filename(format!("{name}-ssd"))
+                    .with_secondary(powersensor3::POWERSENSOR_PLOT_AXIS.to_vec()),
+                default
+                    .clone()
+                    .with_title("Throughput vs instantaneous efficiency")
+                    .with_filename(format!("{name}-mib-j"))
+                    .with_secondary(vec![TimeSeriesAxis::sensor(
+                        "efficiency",
+                        "mib_j",
+                        "Diskstat throughput / SSD power",
+                        "Efficiency (MiB/J)",
+                    )]),
+                default
+                    .clone()
+                    .with_title("Throughput vs CPU power")
+                    .with_filename(format!("{name}-cpu"))
+                    .with_secondary(rapl::RAPL_PLOT_AXIS.to_vec()),
+                default
+                    .clone()
+                    .with_title("Throughput vs CPU freq")
+                    .with_filename(format!("{name}-cpu-freq"))
+                    .with_secondary(sysinfo::sysinfo_freq_plot_axis(&bench_info.cpu_topology)),
+                default
+                    .clone()
+                    .with_title("Throughput vs CPU load")
+                    .with_filename(format!("{name}-cpu-load"))
+                    .with_secondary(sysinfo::sysinfo_load_plot_axis(&bench_info.cpu_topology)),
+            ];
+            if !latency.is_empty() {
+                common::util::write_csv(
+                    &plot_path
+                        .join("plot_data")
+                        .join(format!("{name}-latency.csv")),
+                    &latency,
+                    &config.settings.csv_format_or_default(),
                 )?;
+                plots.push(
+                    TimeSeriesPlot::new(
+                        None,
+                        format!("{name}-latency"),
+                        "Completion latency vs SSD power",
+                        latency_axis(TimeSeriesAxis::bench_time()),
+                        ["p50", "p99"]
+                            .map(|x| {
+                                latency_axis(TimeSeriesAxis::bench(x, x, "Completion latency (ms)"))
+                            })
+                            .to_vec(),
+                    )
+                    .with_secondary(powersensor3::POWERSENSOR_PLOT_AXIS.to_vec()),
+                );
             }
 
-            plot_time_series(TimeSeriesSpec::new(
+            let mut spec = TimeSeriesSpec::new(
                 BenchKind::Fio.name(),
                 plot_path.to_path_buf(),
                 data_path.join(group_dir),
                 &name,
-                vec![
-                    TimeSeriesPlot::new(
-                        None,
-                        format!("{name}-throughput-verify"),
-                        "Fio throughput vs Diskstat throughput",
-                        TimeSeriesAxis::bench_time(),
-                        vec![TimeSeriesAxis::bench(
-                            "smoothed",
-                            "fio",
-                            "Fio Throughput (MiB/s)",
-                        )],
+                plots,
+            );
+            if let Some(time) = steady_state {
+                spec = spec.with_marker(time, STEADY_STATE_MARKER);
+            }
+            plot_time_series(spec)?;
+        }
+        Ok(())
+    }
+
+    /// SSD power over time of the runs of every power state with the same label, overlaid on one
+    /// chart, eg. to compare how often the SSD idles in each power state
+    fn power_state_overlay(
+        &self,
+        config: &Config,
+        data_path: &Path,
+        plot_path: &Path,
+        groups: &[RunGroup],
+        bench_info: &BenchInfo,
+    ) -> Result<()> {
+        let mut runs: HashMap<String, Vec<&RunGroup>> = HashMap::new();
+        for group in groups {
+            for label in self.run_labels(config, &group.info) {
+                runs.entry(label).or_default().push(group);
+            }
+        }
+
+        for (label, mut runs) in runs {
+            runs.sort_by_key(|x| (x.info.power_state, x.info.idx));
+            // runs whose differences are not in the variables can not be told apart
+            runs.dedup_by(|a, b| {
+                if a.info.power_state == b.info.power_state {
+                    warn!(
+                        "Leaving {} out of the power state overlay of {label}, {} has the same power state",
+                        a.dir, b.dir
+                    );
+                    true
+                } else {
+                    false
+                }
+            });
+
+            let filename = powersensor3_filename(&config.sensors, None)?;
+            let name = format!("{}-{label}-power-states", runs[0].info.name);
+            let mut spec = TimeSeriesSpec::new(
+                BenchKind::Fio.name(),
+                plot_path.to_path_buf(),
+                data_path.join(&runs[0].dir),
+                &name,
+                Vec::new(),
+            );
+            let mut y_axis = Vec::new();
+            for run in &runs {
+                let power_state = usize::try_from(run.info.power_state)
+                    .ok()
+                    .and_then(|x| bench_info.device_power_states.get(x))
+                    .map_or_else(|| "Default".to_owned(), |x| x.1.clone());
+                spec = spec.with_overlay_run(&power_state, data_path.join(&run.dir));
+                y_axis.extend(powersensor3::POWERSENSOR_PLOT_AXIS.iter().map(|x| {
+                    TimeSeriesAxis {
+                        dataset_name: filename.clone(),
+                        plot_label: power_state.clone(),
+                        ..x.clone()
+                    }
+                    .with_run(&power_state)
+                }));
+            }
+            let time =
+                TimeSeriesAxis::sensor_time(&filename).with_run(y_axis[0].run.clone().unwrap());
+            plot_time_series(spec.with_plots(vec![TimeSeriesPlot::new(
+                None,
+                &name,
+                format!("SSD power per power state ({label})"),
+                time,
+                y_axis,
+            )]))?;
+        }
+        Ok(())
+    }
+}
+
+/// Metric shown by [`FioQdMap`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum QdMapMetric {
+    /// IOPS per Joule of SSD energy
+    #[default]
+    IopsJ,
+    /// P99 completion latency in ms, averaged over the jobs
+    P99,
+    /// Throughput in MiB/s
+    Throughput,
+}
+
+impl QdMapMetric {
+    fn title(&self) -> &'static str {
+        match self {
+            QdMapMetric::IopsJ => "IOPS/J",
+            QdMapMetric::P99 => "P99 latency (ms)",
+            QdMapMetric::Throughput => "Throughput (MiB/s)",
+        }
+    }
+
+    fn filename(&self) -> &'static str {
+        match self {
+            QdMapMetric::IopsJ => "iops-j",
+            QdMapMetric::P99 => "p99",
+            QdMapMetric::Throughput => "throughput",
+        }
+    }
+}
+
+/// Heatmap of a single metric over the grid of two variables, ie. `num_jobs` × `io_depths`, one
+/// per power state
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FioQdMap {
+    /// Variable on the x axis, defaults to `num_jobs`
+    pub x: Option<String>,
+    /// Variable on the y axis, defaults to `io_depths`
+    pub y: Option<String>,
+    /// Defaults to [`QdMapMetric::IopsJ`]
+    pub metric: Option<QdMapMetric>,
+    pub labels: Option<Vec<String>>,
+    pub matched_labels: Option<Vec<MatchedLabelEntry>>,
+    /// Alias of the Powersensor3 instance measuring the SSD, defaults to the instance without an alias
+    pub powersensor3_alias: Option<String>,
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Plot for FioQdMap {
+    fn required_sensors(&self) -> &'static [&'static str] {
+        &["Powersensor3"]
+    }
+
+    fn metrics(&self) -> &'static [PlotMetric] {
+        QD_MAP_METRICS
+    }
+
+    fn validate(&self, bench: &dyn Bench) -> Result<()> {
+        FioPlotter {
+            variables: vec![
+                self.x.clone().unwrap_or("num_jobs".to_owned()),
+                self.y.clone().unwrap_or("io_depths".to_owned()),
+            ],
+            group: None,
+            labels: self.labels.clone(),
+            matched_labels: self.matched_labels.clone(),
+        }
+        .validate(bench)
+    }
+
+    async fn plot(
+        &self,
+        plot_type: &PlotType,
+        data_path: &Path,
+        plot_path: &Path,
+        config: &Config,
+        bench_info: &BenchInfo,
+        dirs: Vec<String>,
+        settings: &Settings,
+        completed_dirs: &mut Vec<String>,
+    ) -> Result<()> {
+        if *plot_type == PlotType::Total {
+            return Ok(());
+        }
+
+        let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let metric = self.metric.unwrap_or_default();
+        let x = self.x.clone().unwrap_or("num_jobs".to_owned());
+        let y = self.y.clone().unwrap_or("io_depths".to_owned());
+        let plotter = FioPlotter {
+            variables: vec![x.clone(), y.clone()],
+            group: None,
+            labels: self.labels.clone(),
+            matched_labels: self.matched_labels.clone(),
+        };
+        let powersensor3_filename =
+            powersensor3_filename(&config.sensors, self.powersensor3_alias.as_deref())?;
+
+        let mut entries = Vec::new();
+        for group in &groups {
+            let run_dir = data_path.join(&group.dir);
+            let result = read_json_file::<FioResult>(run_dir.join("results.json"))
+                .await
+                .context(format!(
+                    "Could not parse fio results.json in {}, see {}/{OUTPUT_FILENAME}",
+                    group.dir, group.dir
+                ))?;
+            let ssd_power = match metric {
+                QdMapMetric::IopsJ => {
+                    let powersensor3 = read_to_string(run_dir.join(&powersensor3_filename))
+                        .await
+                        .context("Read powersensor3")?;
+                    let markers = read_to_string(run_dir.join(MARKERS_FILENAME)).await.ok();
+                    steady_state(
+                        &steady_state_markers(&result, markers),
+                        &powersensor3,
+                        &["Total"],
+                        &[(0.0, bench_info.device_power_states[0].0)],
+                        power_energy_calculator,
                     )
-                    .with_secondary(diskstat::DISKSTAT_PLOT_AXIS.to_vec()),
-                    default
-                        .clone()
-                        .with_title("Throughput vs SSD power")
-                        .with_filename(format!("{name}-ssd"))
-                        .with_secondary(powersensor3::POWERSENSOR_PLOT_AXIS.to_vec()),
-                    default
-                        .clone()
-                        .with_title("Throughput vs CPU power")
-                        .with_filename(format!("{name}-cpu"))
-                        .with_secondary(rapl::RAPL_PLOT_AXIS.to_vec()),
-                    default
-                        .clone()
-                        .with_title("Throughput vs CPU freq")
-                        .with_filename(format!("{name}-cpu-freq"))
-                        .with_secondary(sysinfo::sysinfo_freq_plot_axis(&bench_info.cpu_topology)),
-                    default
-                        .clone()
-                        .with_title("Throughput vs CPU load")
-                        .with_filename(format!("{name}-cpu-load"))
-                        .with_secondary(sysinfo::sysinfo_load_plot_axis(&bench_info.cpu_topology)),
-                ],
-            ))?;
+                    .context("Calculate powersensor3 means")?
+                }
+                _ => SectionStats::default(),
+            };
+            entries.push(PlotEntry {
+                p99_latency: result.jobs.iter().map(mean_p99_latency).sum(),
+                throughput_cov: None,
+                result,
+                info: group.info.clone(),
+                args: group.info.args.downcast_ref::<Fio>().unwrap().clone(),
+                ssd_power,
+                cpu_power: SectionStats::default(),
+                system_power: SectionStats::default(),
+                prepare_power: SectionStats::default(),
+                rated_power: None,
+                latency_outliers: None,
+                plot: plotter.clone(),
+                load: 0.0,
+                freq: 0.0,
+            });
+        }
+
+        let x_order = plotter.get_variable_ordering(config, &x, &entries);
+        let y_order = plotter.get_variable_ordering(config, &y, &entries);
+        let x_ticks = x_order.iter().map(|x| x.2.clone()).collect::<Vec<_>>();
+        let y_ticks = y_order.iter().map(|x| x.2.clone()).collect::<Vec<_>>();
+        let index_of = |variable: &str, order: &[(&PlotEntry, String, String, usize)], entry| {
+            let value = plotter.get_variable_ordering(config, variable, &[entry])[0]
+                .1
+                .clone();
+            order.iter().find(|x| x.1 == value).map(|x| x.3).unwrap()
+        };
+
+        // power state -> [x][y]
+        let mut grids = HashMap::new();
+        for entry in &entries {
+            let value = match metric {
+                QdMapMetric::IopsJ => iops(&entry.result) / entry.ssd_power.power_mean.unwrap(),
+                QdMapMetric::P99 => {
+                    entry.result.jobs.iter().map(mean_p99_latency).sum::<f64>()
+                        / entry.result.jobs.len() as f64
+                }
+                QdMapMetric::Throughput => throughput(&entry.result),
+            };
+            let grid = grids
+                .entry(entry.info.power_state)
+                .or_insert_with(|| vec![vec![f64::NAN; y_ticks.len()]; x_ticks.len()]);
+            grid[index_of(&x, &x_order, entry.clone())][index_of(&y, &y_order, entry.clone())] =
+                value;
+        }
+
+        let experiment_name = &groups[0].info.name;
+        let qd_map_dir = plot_path.join("qd_map");
+        for (power_state, data) in grids {
+            let title = format!("{} ps{power_state}", metric.title());
+            render_grid_heatmap(
+                experiment_name,
+                &GridHeatmapJob {
+                    filepath: qd_map_dir.join(format!(
+                        "{experiment_name}-ps{power_state}-{}.pdf",
+                        metric.filename()
+                    )),
+                    data,
+                    title: &title,
+                    x_label: &x,
+                    x_ticks: &x_ticks,
+                    y_label: &y,
+                    y_ticks: &y_ticks,
+                    reverse: metric == QdMapMetric::P99,
+                },
+                settings,
+            )?;
         }
         Ok(())
     }
 }
+
+/// SSD efficiency in MiB/J over the request sizes, one line per power state, for experiments
+/// that only sweep `request_sizes`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FioEfficiencyCurve {
+    pub labels: Option<Vec<String>>,
+    pub matched_labels: Option<Vec<MatchedLabelEntry>>,
+    /// Alias of the Powersensor3 instance measuring the SSD, defaults to the instance without an alias
+    pub powersensor3_alias: Option<String>,
+}
+
+impl FioEfficiencyCurve {
+    fn plotter(&self) -> FioPlotter {
+        FioPlotter {
+            variables: vec!["request_sizes".to_owned()],
+            group: None,
+            labels: self.labels.clone(),
+            matched_labels: self.matched_labels.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Plot for FioEfficiencyCurve {
+    fn required_sensors(&self) -> &'static [&'static str] {
+        &["Powersensor3"]
+    }
+
+    fn metrics(&self) -> &'static [PlotMetric] {
+        EFFICIENCY_CURVE_METRICS
+    }
+
+    fn validate(&self, bench: &dyn Bench) -> Result<()> {
+        self.plotter().validate(bench)
+    }
+
+    async fn plot(
+        &self,
+        plot_type: &PlotType,
+        data_path: &Path,
+        plot_path: &Path,
+        config: &Config,
+        bench_info: &BenchInfo,
+        dirs: Vec<String>,
+        settings: &Settings,
+        completed_dirs: &mut Vec<String>,
+    ) -> Result<()> {
+        if *plot_type == PlotType::Total {
+            return Ok(());
+        }
+
+        let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let plotter = self.plotter();
+        let powersensor3_filename =
+            powersensor3_filename(&config.sensors, self.powersensor3_alias.as_deref())?;
+
+        let mut entries = Vec::new();
+        for group in &groups {
+            let run_dir = data_path.join(&group.dir);
+            let result = read_json_file::<FioResult>(run_dir.join("results.json"))
+                .await
+                .context(format!(
+                    "Could not parse fio results.json in {}, see {}/{OUTPUT_FILENAME}",
+                    group.dir, group.dir
+                ))?;
+            let powersensor3 = read_to_string(run_dir.join(&powersensor3_filename))
+                .await
+                .context("Read powersensor3")?;
+            let markers = read_to_string(run_dir.join(MARKERS_FILENAME)).await.ok();
+            let ssd_power = steady_state(
+                &steady_state_markers(&result, markers),
+                &powersensor3,
+                &["Total"],
+                &[(0.0, bench_info.device_power_states[0].0)],
+                power_energy_calculator,
+            )
+            .context("Calculate powersensor3 means")?;
+            entries.push(PlotEntry {
+                p99_latency: 0.0,
+                throughput_cov: None,
+                result,
+                info: group.info.clone(),
+                args: group.info.args.downcast_ref::<Fio>().unwrap().clone(),
+                ssd_power,
+                cpu_power: SectionStats::default(),
+                system_power: SectionStats::default(),
+                prepare_power: SectionStats::default(),
+                rated_power: None,
+                latency_outliers: None,
+                plot: plotter.clone(),
+                load: 0.0,
+                freq: 0.0,
+            });
+        }
+
+        let variable = "request_sizes";
+        let order = plotter.get_variable_ordering(config, variable, &entries);
+        let labels = order.iter().map(|x| x.2.clone()).collect::<Vec<_>>();
+        let power_states = settings.power_states();
+        let mut data = vec![vec![f64::NAN; labels.len()]; power_states.len()];
+        for entry in &entries {
+            let Some(ps) = power_states.index(entry.info.power_state) else {
+                continue;
+            };
+            let value =
+                plotter.get_variable_ordering(config, variable, std::slice::from_ref(entry))[0]
+                    .1
+                    .clone();
+            let x = order.iter().find(|x| x.1 == value).map(|x| x.3).unwrap();
+            let Some(ssd_power) = entry.ssd_power.power_mean else {
+                warn!(
+                    "{} has no SSD power readings at request size {}, leaving it out of the efficiency curve",
+                    entry.info.name, labels[x]
+                );
+                continue;
+            };
+            if !data[ps][x].is_nan() {
+                warn!(
+                    "{} has several runs with request size {}, only request_sizes may be swept for FioEfficiencyCurve",
+                    entry.info.name, labels[x]
+                );
+                continue;
+            }
+            data[ps][x] = throughput(&entry.result) / ssd_power;
+        }
+
+        let experiment_name = &groups[0].info.name;
+        let mut chart = BarChartConfig::new(
+            format!("Efficiency vs. request size {experiment_name}"),
+            "Request size",
+            "Efficiency (MiB/J)",
+        );
+        chart.legend_labels = Some(
+            power_states
+                .states()
+                .iter()
+                .map(|x| {
+                    usize::try_from(*x)
+                        .ok()
+                        .and_then(|x| bench_info.device_power_states.get(x))
+                        .map_or_else(|| "Default".to_owned(), |x| x.1.clone())
+                })
+                .collect(),
+        );
+        plot_line_chart(
+            &plot_path
+                .join("efficiency")
+                .join(format!("{experiment_name}-curve.pdf")),
+            data,
+            labels,
+            chart,
+            bench_info,
+            settings,
+        )
+    }
+}
+
+/// IOPS over the mean SSD power of every run, with the Pareto front of the runs that no other run
+/// beats in both
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FioPareto {
+    pub variables: Vec<String>,
+    pub labels: Option<Vec<String>>,
+    pub matched_labels: Option<Vec<MatchedLabelEntry>>,
+    /// Alias of the Powersensor3 instance measuring the SSD, defaults to the instance without an alias
+    pub powersensor3_alias: Option<String>,
+}
+
+impl FioPareto {
+    fn plotter(&self) -> FioPlotter {
+        FioPlotter {
+            variables: self.variables.clone(),
+            group: None,
+            labels: self.labels.clone(),
+            matched_labels: self.matched_labels.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Plot for FioPareto {
+    fn required_sensors(&self) -> &'static [&'static str] {
+        &["Powersensor3"]
+    }
+
+    fn metrics(&self) -> &'static [PlotMetric] {
+        PARETO_METRICS
+    }
+
+    fn validate(&self, bench: &dyn Bench) -> Result<()> {
+        self.plotter().validate(bench)
+    }
+
+    async fn plot(
+        &self,
+        plot_type: &PlotType,
+        data_path: &Path,
+        plot_path: &Path,
+        config: &Config,
+        bench_info: &BenchInfo,
+        dirs: Vec<String>,
+        settings: &Settings,
+        completed_dirs: &mut Vec<String>,
+    ) -> Result<()> {
+        if *plot_type == PlotType::Total {
+            return Ok(());
+        }
+
+        let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let plotter = self.plotter();
+        let powersensor3_filename =
+            powersensor3_filename(&config.sensors, self.powersensor3_alias.as_deref())?;
+
+        let mut entries = Vec::new();
+        for group in &groups {
+            let run_dir = data_path.join(&group.dir);
+            let result = read_json_file::<FioResult>(run_dir.join("results.json"))
+                .await
+                .context(format!(
+                    "Could not parse fio results.json in {}, see {}/{OUTPUT_FILENAME}",
+                    group.dir, group.dir
+                ))?;
+            let powersensor3 = read_to_string(run_dir.join(&powersensor3_filename))
+                .await
+                .context("Read powersensor3")?;
+            let markers = read_to_string(run_dir.join(MARKERS_FILENAME)).await.ok();
+            let ssd_power = steady_state(
+                &steady_state_markers(&result, markers),
+                &powersensor3,
+                &["Total"],
+                &[(0.0, bench_info.device_power_states[0].0)],
+                power_energy_calculator,
+            )
+            .context("Calculate powersensor3 means")?;
+            entries.push(PlotEntry {
+                p99_latency: 0.0,
+                throughput_cov: None,
+                result,
+                info: group.info.clone(),
+                args: group.info.args.downcast_ref::<Fio>().unwrap().clone(),
+                ssd_power,
+                cpu_power: SectionStats::default(),
+                system_power: SectionStats::default(),
+                prepare_power: SectionStats::default(),
+                rated_power: None,
+                latency_outliers: None,
+                plot: plotter.clone(),
+                load: 0.0,
+                freq: 0.0,
+            });
+        }
+
+        let (order, labels) = plotter.get_order_labels(config, &entries);
+        let points = entries
+            .iter()
+            .map(|entry| {
+                let x = *order
+                    .get(&plotter.get_order_key(entry.clone(), config))
+                    .unwrap();
+                OperatingPoint {
+                    label: labels[x].clone(),
+                    power_state: entry.info.power_state,
+                    throughput: iops(&entry.result),
+                    ssd_power: entry.ssd_power.power_mean.unwrap_or(f64::NAN),
+                }
+            })
+            .collect();
+
+        let experiment_name = &groups[0].info.name;
+        let title = format!("IOPS vs. SSD power {experiment_name}");
+        render_pareto(
+            experiment_name,
+            &ParetoJob {
+                filepath: plot_path
+                    .join("efficiency")
+                    .join(format!("{experiment_name}-pareto.pdf")),
+                points,
+                title: &title,
+                x_label: "SSD power (W)",
+                y_label: "IOPS",
+            },
+            bench_info,
+            settings,
+        )
+    }
+}
+
+/// Box plot of the completion latency distribution of every run, one box per power state
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FioLatencyDistribution {
+    pub variables: Vec<String>,
+    pub x_label: String,
+    pub labels: Option<Vec<String>>,
+    pub matched_labels: Option<Vec<MatchedLabelEntry>>,
+}
+
+impl FioLatencyDistribution {
+    fn plotter(&self) -> FioPlotter {
+        FioPlotter {
+            variables: self.variables.clone(),
+            group: None,
+            labels: self.labels.clone(),
+            matched_labels: self.matched_labels.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Plot for FioLatencyDistribution {
+    fn required_sensors(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn metrics(&self) -> &'static [PlotMetric] {
+        DISTRIBUTION_METRICS
+    }
+
+    fn validate(&self, bench: &dyn Bench) -> Result<()> {
+        self.plotter().validate(bench)
+    }
+
+    async fn plot(
+        &self,
+        plot_type: &PlotType,
+        data_path: &Path,
+        plot_path: &Path,
+        config: &Config,
+        bench_info: &BenchInfo,
+        dirs: Vec<String>,
+        settings: &Settings,
+        completed_dirs: &mut Vec<String>,
+    ) -> Result<()> {
+        if *plot_type == PlotType::Total {
+            return Ok(());
+        }
+
+        let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let plotter = self.plotter();
+        let mut entries = Vec::new();
+        for group in &groups {
+            let result =
+                read_json_file::<FioResult>(data_path.join(&group.dir).join("results.json"))
+                    .await
+                    .context(format!(
+                        "Could not parse fio results.json in {}, see {}/{OUTPUT_FILENAME}",
+                        group.dir, group.dir
+                    ))?;
+            entries.push(PlotEntry {
+                p99_latency: 0.0,
+                throughput_cov: None,
+                result,
+                info: group.info.clone(),
+                args: group.info.args.downcast_ref::<Fio>().unwrap().clone(),
+                ssd_power: SectionStats::default(),
+                cpu_power: SectionStats::default(),
+                system_power: SectionStats::default(),
+                prepare_power: SectionStats::default(),
+                rated_power: None,
+                latency_outliers: None,
+                plot: plotter.clone(),
+                load: 0.0,
+                freq: 0.0,
+            });
+        }
+
+        let (order, labels) = plotter.get_order_labels(config, &entries);
+        let power_states = settings.power_states();
+        let mut boxes = vec![vec![[f64::NAN; 6]; labels.len()]; power_states.len()];
+        for entry in &entries {
+            let Some(ps) = power_states.index(entry.info.power_state) else {
+                continue;
+            };
+            let Some(distribution) = latency_distribution(&entry.result) else {
+                warn!(
+                    "No completion latency percentiles in {}, left out of the latency distribution",
+                    entry.info.name
+                );
+                continue;
+            };
+            let x = *order
+                .get(&plotter.get_order_key(entry.clone(), config))
+                .unwrap();
+            boxes[ps][x] = distribution;
+        }
+
+        let experiment_name = &groups[0].info.name;
+        plot_box_chart(
+            &plot_path
+                .join("latency")
+                .join(format!("{experiment_name}-distribution.pdf")),
+            boxes,
+            labels,
+            make_power_state_bar_config(
+                BarChartKind::BoxPlot,
+                &self.x_label,
+                experiment_name,
+                Some("Completion"),
+            ),
+            bench_info,
+            settings,
+        )
+    }
+}
+
+/// Min, p25, p50, p75, p99 and max completion latency (ms) of a run, the percentiles are averaged
+/// over the jobs and directions with IOs like [`mean_p99_latency`], `None` without percentiles
+///
+/// fio reports no p25 and p75, they are interpolated between p20 and p30, and p70 and p80.
+fn latency_distribution(result: &FioResult) -> Option<[f64; 6]> {
+    let clats = result
+        .jobs
+        .iter()
+        .flat_map(|x| [&x.read.clat_ns, &x.write.clat_ns])
+        .filter(|x| x.n > 0)
+        .filter_map(|x| x.percentile.as_ref().map(|p| (x, p)))
+        .collect::<Vec<_>>();
+    if clats.is_empty() {
+        return None;
+    }
+
+    let mean = |percentile: fn(&Percentile) -> f64| {
+        clats.iter().map(|x| percentile(x.1)).sum::<f64>() / clats.len() as f64 / 1e6
+    };
+    Some([
+        clats.iter().map(|x| x.0.min).min().unwrap() as f64 / 1e6,
+        mean(|x| (x.n20_000000 + x.n30_000000) as f64 / 2.0),
+        mean(|x| x.n50_000000 as f64),
+        mean(|x| (x.n70_000000 + x.n80_000000) as f64 / 2.0),
+        mean(|x| x.n99_000000 as f64),
+        clats.iter().map(|x| x.0.max).max().unwrap() as f64 / 1e6,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn efficiency_of_a_run() {
+        let metrics = efficiency_metrics(1000.0, 4.0, 2.0, 5.0, 5.0, 20.0);
+        assert_eq!(
+            metrics,
+            EfficiencyMetrics {
+                iops_j: 200.0,
+                iops_j_cpu: 40.0,
+                bytes_j: 0.8,
+                bytes_j_cpu: 0.16,
+                cpu_only_bytes_j: 0.2,
+                edp: 10.0,
+                edp_p99: 25.0,
+                edp_total: 50.0,
+            }
+        );
+    }
+
+    #[test]
+    fn efficiency_without_power_is_nan() {
+        let metrics = efficiency_metrics(1000.0, 4.0, 2.0, 5.0, 0.0, 0.0);
+        assert!(metrics.iops_j.is_nan());
+        assert!(metrics.iops_j_cpu.is_nan());
+        assert!(metrics.bytes_j.is_nan());
+        assert!(metrics.bytes_j_cpu.is_nan());
+        assert!(metrics.cpu_only_bytes_j.is_nan());
+        assert!(metrics.edp.is_nan());
+        assert!(metrics.edp_p99.is_nan());
+        assert!(metrics.edp_total.is_nan());
+
+        // only the CPU reading is missing, ie. no RAPL
+        let metrics = efficiency_metrics(1000.0, 4.0, 2.0, 5.0, 5.0, 0.0);
+        assert_eq!(metrics.iops_j, 200.0);
+        assert_eq!(metrics.iops_j_cpu, 200.0);
+        assert!(metrics.cpu_only_bytes_j.is_nan());
+        assert_eq!(metrics.edp_total, 10.0);
+
+        // a missing power statistic
+        let metrics = efficiency_metrics(1000.0, 4.0, 2.0, 5.0, f64::NAN, 20.0);
+        assert!(metrics.iops_j.is_nan());
+        assert!(metrics.edp.is_nan());
+    }
+
+    #[test]
+    fn rated_power_of_a_power_state() {
+        let bench_info = BenchInfo {
+            param_map: HashMap::new(),
+            device_power_states: vec![
+                (8.25, "8.25W".to_owned()),
+                (3.5, "3.5W".to_owned()),
+                // a descriptor without a maximum power
+                (0.0, "0W".to_owned()),
+            ],
+            cpu_freq_limits: (800_000, 3_000_000),
+            cpu_topology: HashMap::new(),
+            warmup: None,
+            device: None,
+        };
+        assert_eq!(rated_power(&bench_info, 0), Some(8.25));
+        assert_eq!(rated_power(&bench_info, 1), Some(3.5));
+        assert_eq!(rated_power(&bench_info, 2), None);
+        assert_eq!(rated_power(&bench_info, 3), None);
+        // power state left unchanged
+        assert_eq!(rated_power(&bench_info, -1), None);
+    }
+
+    fn clat(min: i64, max: i64, n: i64, percentiles: Option<[i64; 6]>) -> ClatNs {
+        ClatNs {
+            min,
+            max,
+            n,
+            percentile: percentiles.map(|[p20, p30, p50, p70, p80, p99]| Percentile {
+                n20_000000: p20,
+                n30_000000: p30,
+                n50_000000: p50,
+                n70_000000: p70,
+                n80_000000: p80,
+                n99_000000: p99,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn job(read: ClatNs, write: ClatNs) -> Job {
+        Job {
+            read: fio::result::Read {
+                clat_ns: read,
+                ..Default::default()
+            },
+            write: fio::result::Write {
+                clat_ns: write,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn latency_distribution_of_all_jobs() {
+        let ms = 1_000_000;
+        let result = FioResult {
+            jobs: vec![
+                job(
+                    clat(
+                        ms / 10,
+                        9 * ms,
+                        10,
+                        Some([ms, 2 * ms, 3 * ms, 4 * ms, 6 * ms, 8 * ms]),
+                    ),
+                    // no writes
+                    clat(0, 0, 0, None),
+                ),
+                job(
+                    // without percentiles
+                    clat(ms / 100, 20 * ms, 5, None),
+                    clat(
+                        ms / 20,
+                        12 * ms,
+                        5,
+                        Some([3 * ms, 4 * ms, 5 * ms, 6 * ms, 8 * ms, 10 * ms]),
+                    ),
+                ),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            latency_distribution(&result),
+            Some([0.05, 2.5, 4.0, 6.0, 9.0, 12.0])
+        );
+
+        let result = FioResult {
+            jobs: vec![job(clat(ms, 2 * ms, 10, None), clat(0, 0, 0, None))],
+            ..Default::default()
+        };
+        assert_eq!(latency_distribution(&result), None);
+    }
+
+    #[test]
+    fn latency_of_all_jobs_is_bucketed() {
+        // two jobs of numjobs=2, latencies in ns
+        let job1 = "10, 1000000, 0, 4096, 0\n1010, 3000000, 0, 4096, 0\n";
+        let job2 = "20, 2000000, 1, 4096, 0\n";
+        let buckets = latency_over_time([job1.as_bytes(), job2.as_bytes()]).unwrap();
+        assert_eq!(
+            buckets,
+            vec![
+                LatencyBucket {
+                    time: 0,
+                    p50: 1.5,
+                    p99: 1.99
+                },
+                LatencyBucket {
+                    time: 1000,
+                    p50: 3.0,
+                    p99: 3.0
+                },
+            ]
+        );
+        assert!(latency_over_time(["x, 1".as_bytes()]).is_err());
+    }
+}
@@ -4,12 +4,15 @@ use std::{
 };
 
 use common::{
-    MB_TO_MIB,
     bench::{BenchInfo, BenchParams},
     config::{Config, Settings},
-    plot::{HeatmapJob, Plot, PlotType, collect_run_groups, ensure_plot_dirs, render_heatmaps},
+    plot::{
+        HeatmapJob, OperatingPoint, Plot, PlotMetric, PlotType, collect_run_groups,
+        ensure_plot_dirs, render_heatmaps, write_headline,
+    },
+    sensor::powersensor3_filename,
     util::{
-        BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config,
+        BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config, mb_to_mib,
         plot_bar_chart, power_energy_calculator,
     },
 };
@@ -23,9 +26,25 @@ use serde::{Deserialize, Serialize};
 use tokio::fs::read_to_string;
 use tracing::debug;
 
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new("Throughput", "MiB/s", "throughput/<name>.pdf"),
+    PlotMetric::new("Metadata time", "s", "throughput/<name>-metadata.pdf"),
+    PlotMetric::new("MiB/J", "MiB/J", "efficiency/<name>-bytes-j.pdf"),
+    PlotMetric::new(
+        "MiB/J incl. CPU",
+        "MiB/J",
+        "efficiency/<name>-bytes-j+cpu.pdf",
+    ),
+];
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub struct H5BenchBasic;
+pub struct H5BenchBasic {
+    /// The headline is the highest throughput at an SSD power of at most this many W instead of
+    /// the highest MiB/J
+    pub headline_power_cap: Option<f64>,
+}
 
 #[derive(Debug, Clone, Deserialize)]
 struct H5BenchResultRecord {
@@ -50,12 +69,16 @@ impl Plot for H5BenchBasic {
         &["Powersensor3", "Rapl"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
         data_path: &Path,
         plot_path: &Path,
-        _config: &Config,
+        config: &Config,
         bench_info: &BenchInfo,
         dirs: Vec<String>,
         settings: &Settings,
@@ -71,14 +94,16 @@ impl Plot for H5BenchBasic {
             return Ok(());
         }
 
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
         let entries = join_all(groups.iter().map(|group| {
             let run_dir = data_path.join(&group.dir);
+            let powersensor3_path = run_dir.join(&powersensor3_filename);
             let dir = group.dir.clone();
             let info = group.info.clone();
             async move {
                 (
                     read_to_string(run_dir.join("results.csv")).await,
-                    read_to_string(run_dir.join("powersensor3.csv")).await,
+                    read_to_string(powersensor3_path).await,
                     read_to_string(run_dir.join("rapl.csv")).await,
                     dir,
                     info,
@@ -160,26 +185,26 @@ impl Plot for H5BenchBasic {
                 "throughput",
                 "MiB/s",
                 |data| {
-                    data.result
+                    mb_to_mib(
+                        data.result
+                            .get("raw rate")
+                            .unwrap()
+                            .value
+                            .parse::<f64>()
+                            .unwrap(),
+                    ) * if data
+                        .result
                         .get("raw rate")
                         .unwrap()
-                        .value
-                        .parse::<f64>()
+                        .unit
+                        .as_ref()
                         .unwrap()
-                        * MB_TO_MIB
-                        * if data
-                            .result
-                            .get("raw rate")
-                            .unwrap()
-                            .unit
-                            .as_ref()
-                            .unwrap()
-                            .starts_with("G")
-                        {
-                            1000.0
-                        } else {
-                            1.0
-                        }
+                        .starts_with("G")
+                    {
+                        1000.0
+                    } else {
+                        1.0
+                    }
                 },
             ),
             (
@@ -207,7 +232,7 @@ impl Plot for H5BenchBasic {
             item?;
         }
 
-        self.efficiency(ready_entries.clone(), settings, &efficiency_dir)
+        self.efficiency(ready_entries.clone(), settings, &efficiency_dir, bench_info)
             .await?;
         Ok(())
     }
@@ -224,19 +249,18 @@ impl H5BenchBasic {
         get_value: fn(&PlotEntry) -> f64,
         bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut results = vec![vec![]; num_power_states];
 
         let experiment_name = ready_entries[0].info.name.clone();
 
         for item in ready_entries {
             let mean = get_value(&item);
-            let ps = if item.info.power_state == -1 {
-                0
-            } else {
-                item.info.power_state
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
             };
-            results[ps as usize].push((item, mean));
+            results[ps].push((item, mean));
         }
 
         let results = results
@@ -256,6 +280,7 @@ impl H5BenchBasic {
             vec!["Throughput".to_string()],
             config,
             bench_info,
+            settings,
         )
     }
 
@@ -264,15 +289,17 @@ impl H5BenchBasic {
         ready_entries: Vec<PlotEntry>,
         settings: &Settings,
         plot_path: &Path,
+        bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut bytes_j = vec![0f64; num_power_states].clone();
         let mut bytes_j_ssd = bytes_j.clone();
         let experiment_name = ready_entries[0].info.name.clone();
 
         let results = ready_entries
             .par_iter()
-            .map(|item| {
+            .filter_map(|item| {
                 let multiplier = if item
                     .result
                     .get("raw rate")
@@ -286,34 +313,37 @@ impl H5BenchBasic {
                 } else {
                     1.0
                 };
-                let throughput = item
-                    .result
-                    .get("raw rate")
-                    .unwrap()
-                    .value
-                    .parse::<f64>()
-                    .unwrap()
-                    * MB_TO_MIB
-                    * multiplier;
-                let y = if item.info.power_state == -1 {
-                    0
-                } else {
-                    item.info.power_state
-                } as usize;
+                let throughput = mb_to_mib(
+                    item.result
+                        .get("raw rate")
+                        .unwrap()
+                        .value
+                        .parse::<f64>()
+                        .unwrap(),
+                ) * multiplier;
+                let y = power_states.index(item.info.power_state)?;
 
                 let ssd_power = item.ssd_power.power.unwrap();
                 let cpu_power = item.cpu_power.power.unwrap();
-                (
+                Some((
                     y,
+                    OperatingPoint {
+                        label: "overall".to_owned(),
+                        power_state: item.info.power_state,
+                        throughput,
+                        ssd_power,
+                    },
                     throughput / (ssd_power + cpu_power),
                     throughput / ssd_power,
-                )
+                ))
             })
             .collect::<Vec<_>>();
+        let mut points = Vec::new();
         for item in results {
             let y = item.0;
-            bytes_j[y] = item.1;
-            bytes_j_ssd[y] = item.2;
+            points.push(item.1);
+            bytes_j[y] = item.2;
+            bytes_j_ssd[y] = item.3;
         }
 
         let jobs = vec![
@@ -333,7 +363,22 @@ impl H5BenchBasic {
             },
         ];
 
-        render_heatmaps(&experiment_name, &["overall".to_owned()], plot_path, &jobs)
+        render_heatmaps(
+            &experiment_name,
+            &["overall".to_owned()],
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("MiB/s", "MiB/J"),
+            self.headline_power_cap,
+            settings,
+        )
     }
 }
 
@@ -0,0 +1,54 @@
+import json
+import argparse
+
+import numpy as np
+import matplotlib.pyplot as plt
+
+import common
+from bar_chart import get_plot_legends
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--spec", required=True, help="Path to JSON line chart specification, same format as a bar chart")
+    args = parser.parse_args()
+
+    with open(args.spec, "r", encoding="utf-8") as f:
+        spec = json.load(f)
+
+    data = spec["data"]
+    if not data:
+        raise ValueError("No data provided")
+
+    labels = spec["labels"]
+    legends = get_plot_legends(data, spec.get("legend_labels"), spec.get("nvme_power_states"))
+
+    # the labels are ordered but not evenly spaced, ie. request sizes, so they are placed at equal distances
+    x = np.arange(len(labels))
+
+    _, ax = plt.subplots()
+    for idx, series in enumerate(data):
+        # missing values break the line instead of connecting over them
+        series = [np.nan if value is None else value for value in series]
+        color = common.colors[idx % len(common.colors)]
+        ax.plot(x, series, marker="o", label=legends[idx], color=color)
+
+    ax.set_ylabel(spec["y_label"])
+    ax.set_xlabel(spec["x_label"])
+    ax.set_title(spec["title"])
+    ax.set_xticks(x)
+    ax.set_xticklabels(labels)
+    ax.set_ylim(bottom=0)
+    if spec.get("y_reference") is not None:
+        ax.axhline(spec["y_reference"], color="black", linestyle="--", linewidth=1)
+    ax.grid(True, alpha=0.3)
+
+    rotation = spec.get("tick_rotation_deg")
+    if rotation is not None:
+        align = spec.get("tick_horizontal_align", "center")
+        plt.setp(ax.get_xticklabels(), rotation=rotation, ha=align)
+
+    ax.legend()
+    common.add_footnote(spec.get("footnote"))
+    plt.tight_layout()
+    plt.savefig(spec["output_path"], format=spec.get("format", "pdf"))
+    plt.close()
@@ -0,0 +1,366 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use common::{
+    bench::{BenchInfo, BenchParams},
+    config::{Config, Settings},
+    plot::{
+        HeatmapJob, OperatingPoint, Plot, PlotMetric, PlotType, collect_run_groups, ensure_dirs,
+        render_heatmaps, write_headline,
+    },
+    sensor::powersensor3_filename,
+    util::{
+        BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config,
+        plot_bar_chart, power_energy_calculator, read_json_file,
+    },
+};
+use eyre::{Context, Result};
+use futures::future::join_all;
+use mdtest::{Mdtest, result::MdtestResult};
+use plot_common::impl_power_time_plot;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use tokio::fs::read_to_string;
+use tracing::debug;
+
+/// Operations of the mdtest summary that are plotted, with the suffix of their plot files
+const OPERATIONS: [(&str, &str); 6] = [
+    ("File creation", "file-create"),
+    ("File stat", "file-stat"),
+    ("File removal", "file-remove"),
+    ("Directory creation", "dir-create"),
+    ("Directory stat", "dir-stat"),
+    ("Directory removal", "dir-remove"),
+];
+
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new(
+        "Metadata operation rate",
+        "ops/s",
+        "throughput/<name>-<operation>.pdf",
+    ),
+    PlotMetric::new("SSD power", "W", "power/<name>-ssd.pdf"),
+    PlotMetric::new("CPU power", "W", "power/<name>-cpu.pdf"),
+    PlotMetric::new("Ops/J", "ops/J", "efficiency/<name>-<operation>-ops-j.pdf"),
+    PlotMetric::new(
+        "Ops/J incl. CPU",
+        "ops/J",
+        "efficiency/<name>-<operation>-ops-j+cpu.pdf",
+    ),
+];
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MdtestBasic {
+    /// The headline is the highest rate of the first plotted operation at an SSD power of at
+    /// most this many W instead of its highest ops/J
+    pub headline_power_cap: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+struct PlotEntry {
+    result: MdtestResult,
+    info: BenchParams,
+    args: Mdtest,
+    ssd_power: SectionStats,
+    cpu_power: SectionStats,
+}
+
+/// Bar chart of one value of each run over the ranks
+struct BarJob {
+    filepath: PathBuf,
+    kind: BarChartKind,
+    y_label: String,
+    get_value: Box<dyn Fn(&PlotEntry) -> f64 + Send + Sync>,
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Plot for MdtestBasic {
+    fn required_sensors(&self) -> &'static [&'static str] {
+        &["Powersensor3", "Rapl"]
+    }
+
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
+    async fn plot(
+        &self,
+        plot_type: &PlotType,
+        data_path: &Path,
+        plot_path: &Path,
+        config: &Config,
+        bench_info: &BenchInfo,
+        dirs: Vec<String>,
+        settings: &Settings,
+        completed_dirs: &mut Vec<String>,
+    ) -> Result<()> {
+        if *plot_type == PlotType::Total {
+            return Ok(());
+        }
+
+        debug!("Got {} dirs", dirs.len());
+        let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
+        let entries = join_all(groups.iter().map(|group| {
+            let run_dir = data_path.join(&group.dir);
+            let powersensor3_path = run_dir.join(&powersensor3_filename);
+            let info = group.info.clone();
+            async move {
+                (
+                    read_json_file::<MdtestResult>(run_dir.join("results.json")).await,
+                    read_to_string(powersensor3_path).await,
+                    read_to_string(run_dir.join("rapl.csv")).await,
+                    info,
+                )
+            }
+        }))
+        .await;
+        let ready_entries = entries
+            .into_par_iter()
+            .map(|item| {
+                let (result, powersensor3, rapl, info) = item;
+                let rapl = rapl.context("Read rapl").unwrap();
+                let powersensor3 = powersensor3.context("Read powersensor3").unwrap();
+
+                let (_, rapl_overall, _) = calculate_sectioned::<_, 0>(
+                    None,
+                    &rapl,
+                    &["Total"],
+                    &[(0.0, settings.cpu_max_power_watts)],
+                    power_energy_calculator,
+                    None,
+                )
+                .context("Calculate rapl means")
+                .unwrap();
+                let (_, ps3_overall, _times) = calculate_sectioned::<_, 0>(
+                    None,
+                    &powersensor3,
+                    &["Total"],
+                    &[(0.0, bench_info.device_power_states[0].0)],
+                    power_energy_calculator,
+                    None,
+                )
+                .context("Calculate powersensor3 means")
+                .unwrap();
+
+                PlotEntry {
+                    result: result.context("Read results json").unwrap(),
+                    args: info.args.downcast_ref::<Mdtest>().unwrap().clone(),
+                    info,
+                    ssd_power: ps3_overall,
+                    cpu_power: rapl_overall,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let experiment_name = ready_entries[0].info.name.clone();
+        let throughput_dir = plot_path.join("throughput");
+        let efficiency_dir = plot_path.join("efficiency");
+        let power_dir = plot_path.join("power");
+        ensure_dirs(&[
+            throughput_dir.clone(),
+            efficiency_dir.clone(),
+            power_dir.clone(),
+        ])
+        .await?;
+
+        // operations that were skipped, ie. directories with `-F`, are not plotted
+        let operations = OPERATIONS
+            .into_iter()
+            .filter(|(operation, _)| {
+                ready_entries
+                    .iter()
+                    .any(|x| x.result.rate(operation).is_some())
+            })
+            .collect::<Vec<_>>();
+
+        let mut plot_jobs = operations
+            .iter()
+            .map(|&(operation, suffix)| BarJob {
+                filepath: throughput_dir.join(format!("{experiment_name}-{suffix}.pdf")),
+                kind: BarChartKind::Throughput,
+                y_label: format!("{operation} (ops/s)"),
+                get_value: Box::new(move |data| data.result.rate(operation).unwrap_or(f64::NAN)),
+            })
+            .collect::<Vec<_>>();
+        plot_jobs.push(BarJob {
+            filepath: power_dir.join(format!("{experiment_name}-cpu.pdf")),
+            kind: BarChartKind::Power,
+            y_label: "Power (Watts)".to_owned(),
+            get_value: Box::new(|data| data.cpu_power.power_mean.unwrap()),
+        });
+        plot_jobs.push(BarJob {
+            filepath: power_dir.join(format!("{experiment_name}-ssd.pdf")),
+            kind: BarChartKind::Power,
+            y_label: "Power (Watts)".to_owned(),
+            get_value: Box::new(|data| data.ssd_power.power_mean.unwrap()),
+        });
+
+        let results = plot_jobs
+            .into_par_iter()
+            .map(|job| self.bar_plot(&ready_entries, settings, job, bench_info))
+            .collect::<Vec<_>>();
+        for item in results {
+            item?;
+        }
+
+        self.efficiency(
+            &ready_entries,
+            &operations,
+            settings,
+            &efficiency_dir,
+            bench_info,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+impl MdtestBasic {
+    fn bar_plot(
+        &self,
+        ready_entries: &[PlotEntry],
+        settings: &Settings,
+        job: BarJob,
+        bench_info: &BenchInfo,
+    ) -> Result<()> {
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
+        let (order, labels) = self.get_order_labels(ready_entries);
+        let mut results = vec![vec![f64::NAN; order.len()]; num_power_states];
+
+        let experiment_name = ready_entries[0].info.name.clone();
+
+        for item in ready_entries {
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
+            };
+            results[ps][order[&item.args.ranks[0]]] = (job.get_value)(item);
+        }
+
+        let mut config = make_power_state_bar_config(job.kind, "Ranks", &experiment_name, None);
+        config.y_label = job.y_label;
+        plot_bar_chart(&job.filepath, results, labels, config, bench_info, settings)
+    }
+
+    async fn efficiency(
+        &self,
+        ready_entries: &[PlotEntry],
+        operations: &[(&str, &str)],
+        settings: &Settings,
+        plot_path: &Path,
+        bench_info: &BenchInfo,
+    ) -> Result<()> {
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
+        let (order, labels) = self.get_order_labels(ready_entries);
+        let experiment_name = ready_entries[0].info.name.clone();
+
+        let mut jobs = Vec::new();
+        let mut points = Vec::new();
+        for (idx, (operation, suffix)) in operations.iter().enumerate() {
+            let mut ops_j = vec![vec![0f64; num_power_states]; order.len()];
+            let mut ops_j_ssd = ops_j.clone();
+
+            let results = ready_entries
+                .par_iter()
+                .filter_map(|item| {
+                    let rate = item.result.rate(operation)?;
+                    let x = order[&item.args.ranks[0]];
+                    let y = power_states.index(item.info.power_state)?;
+
+                    let ssd_power = item.ssd_power.power_mean.unwrap();
+                    let cpu_power = item.cpu_power.power_mean.unwrap();
+                    let point = OperatingPoint {
+                        label: format!("{} ranks, {operation}", labels[x]),
+                        power_state: item.info.power_state,
+                        throughput: rate,
+                        ssd_power,
+                    };
+                    Some((
+                        x,
+                        y,
+                        point,
+                        rate / (ssd_power + cpu_power),
+                        rate / ssd_power,
+                    ))
+                })
+                .collect::<Vec<_>>();
+            for item in results {
+                // the rates of different operations are not comparable
+                if idx == 0 {
+                    points.push(item.2);
+                }
+                ops_j[item.0][item.1] = item.3;
+                ops_j_ssd[item.0][item.1] = item.4;
+            }
+
+            jobs.push(HeatmapJob {
+                filepath: plot_path.join(format!("{experiment_name}-{suffix}-ops-j+cpu.pdf")),
+                data: ops_j,
+                title: "Ops/J",
+                x_label: "Ranks",
+                reverse: false,
+            });
+            jobs.push(HeatmapJob {
+                filepath: plot_path.join(format!("{experiment_name}-{suffix}-ops-j.pdf")),
+                data: ops_j_ssd,
+                title: "Ops/J",
+                x_label: "Ranks",
+                reverse: false,
+            });
+        }
+
+        render_heatmaps(
+            &experiment_name,
+            &labels,
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("ops/s", "ops/J"),
+            self.headline_power_cap,
+            settings,
+        )
+    }
+
+    /// Position of each rank count on the x axis, and the matching labels
+    fn get_order_labels(
+        &self,
+        ready_entries: &[PlotEntry],
+    ) -> (HashMap<usize, usize>, Vec<String>) {
+        let ranks = ready_entries
+            .iter()
+            .map(|x| x.args.ranks[0])
+            .collect::<BTreeSet<_>>();
+        let order = ranks.iter().enumerate().map(|(x, y)| (*y, x)).collect();
+        let labels = ranks.iter().map(|x| x.to_string()).collect();
+        (order, labels)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MdtestPowerTime {
+    pub offset: Option<usize>,
+}
+impl_power_time_plot!(
+    MdtestPowerTime,
+    Mdtest,
+    |cfg: &Mdtest| cfg.ranks[0],
+    |cfg: &Mdtest| cfg.fs.clone()
+);
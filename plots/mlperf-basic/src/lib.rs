@@ -4,12 +4,15 @@ use std::{
 };
 
 use common::{
-    MB_TO_MIB,
     bench::{BenchInfo, BenchParams},
     config::{Config, Settings},
-    plot::{HeatmapJob, Plot, PlotType, collect_run_groups, ensure_plot_dirs, render_heatmaps},
+    plot::{
+        HeatmapJob, OperatingPoint, Plot, PlotMetric, PlotType, collect_run_groups,
+        ensure_plot_dirs, render_heatmaps, write_headline,
+    },
+    sensor::powersensor3_filename,
     util::{
-        BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config,
+        BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config, mb_to_mib,
         plot_bar_chart, power_energy_calculator,
     },
 };
@@ -26,9 +29,64 @@ use serde::{Deserialize, Serialize};
 use tokio::fs::read_to_string;
 use tracing::debug;
 
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new(
+        "Training throughput",
+        "MiB/s",
+        "throughput/<name>-train.pdf",
+    ),
+    PlotMetric::new("Samples", "samples/s", "throughput/<name>-samples.pdf"),
+    PlotMetric::new(
+        "Accelerator utilization",
+        "%",
+        "throughput/<name>-utilization.pdf",
+    ),
+    PlotMetric::new("SSD power", "W", "power/<name>-ssd.pdf"),
+    PlotMetric::new("CPU power", "W", "power/<name>-cpu.pdf"),
+    PlotMetric::new("Samples/J", "samples/J", "efficiency/<name>-iops-j.pdf"),
+    PlotMetric::new(
+        "Samples/J incl. GPU",
+        "samples/J",
+        "efficiency/<name>-iops-j+gpu.pdf",
+    ),
+    PlotMetric::new("MiB/J", "MiB/J", "efficiency/<name>-bytes-j.pdf"),
+    PlotMetric::new(
+        "MiB/J incl. CPU",
+        "MiB/J",
+        "efficiency/<name>-bytes-j+cpu.pdf",
+    ),
+];
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub struct MlperfBasic;
+pub struct MlperfBasic {
+    /// Accelerator utilization in % below which a run that read from the SSD is storage-bound,
+    /// defaults to the expectation of the workload, see
+    /// [`mlperf::result::Metric::train_au_meet_expectation`]
+    pub au_threshold: Option<f64>,
+    /// The headline is the highest samples/s at an SSD power of at most this many W instead of
+    /// the highest samples/J
+    pub headline_power_cap: Option<f64>,
+}
+
+/// What limited the training speed of a run, annotated on the throughput bars
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    /// The accelerators waited on the SSD
+    Storage,
+    /// The accelerators were kept busy, the SSD could deliver more than was needed
+    Compute,
+}
+
+impl Bound {
+    fn label(&self) -> &'static str {
+        match self {
+            Bound::Storage => "storage",
+            Bound::Compute => "compute",
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 struct PlotEntry {
@@ -37,6 +95,8 @@ struct PlotEntry {
     args: Mlperf,
     ssd_power: SectionStats,
     cpu_power: SectionStats,
+    /// Power of the GPUs, if the `Nvml` sensor found any
+    gpu_power: Option<SectionStats>,
 }
 
 #[async_trait::async_trait]
@@ -46,12 +106,16 @@ impl Plot for MlperfBasic {
         &["Powersensor3", "Rapl"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
         data_path: &Path,
         plot_path: &Path,
-        _config: &Config,
+        config: &Config,
         bench_info: &BenchInfo,
         dirs: Vec<String>,
         settings: &Settings,
@@ -74,15 +138,18 @@ impl Plot for MlperfBasic {
             serde_json::from_str(&data).context("Parse summary.json")
         }
 
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
         let entries = join_all(groups.iter().map(|group| {
             let run_dir = data_path.join(&group.dir);
+            let powersensor3_path = run_dir.join(&powersensor3_filename);
             let dir = group.dir.clone();
             let info = group.info.clone();
             async move {
                 (
                     read_results_json(run_dir.clone()).await,
-                    read_to_string(run_dir.join("powersensor3.csv")).await,
+                    read_to_string(powersensor3_path).await,
                     read_to_string(run_dir.join("rapl.csv")).await,
+                    read_to_string(run_dir.join("nvml.csv")).await.ok(),
                     dir,
                     info,
                 )
@@ -92,7 +159,7 @@ impl Plot for MlperfBasic {
         let ready_entries = entries
             .into_par_iter()
             .map(|item| {
-                let (json, powersensor3, rapl, _dir, info) = item;
+                let (json, powersensor3, rapl, nvml, _dir, info) = item;
                 let rapl = rapl.context("Read rapl").unwrap();
                 let powersensor3 = powersensor3.context("Read powersensor3").unwrap();
 
@@ -116,6 +183,22 @@ impl Plot for MlperfBasic {
                 )
                 .context("Calculate powersensor3 means")
                 .unwrap();
+                // only the time column is recorded if there are no GPUs
+                let gpu_power = nvml
+                    .filter(|x| x.lines().next().is_some_and(|x| x.contains("Total")))
+                    .map(|nvml| {
+                        calculate_sectioned::<_, 0>(
+                            None,
+                            &nvml,
+                            &["Total"],
+                            &[(0.0, f64::MAX)],
+                            power_energy_calculator,
+                            None,
+                        )
+                        .context("Calculate nvml means")
+                        .unwrap()
+                        .1
+                    });
 
                 PlotEntry {
                     result: json.context("Read results json").unwrap(),
@@ -123,6 +206,7 @@ impl Plot for MlperfBasic {
                     info,
                     ssd_power: ps3_overall,
                     cpu_power: rapl_overall,
+                    gpu_power,
                 }
             })
             .collect::<Vec<_>>();
@@ -152,7 +236,7 @@ impl Plot for MlperfBasic {
                 throughput_dir.join(format!("{experiment_name}-train.pdf")),
                 "throughput",
                 "MiB/s",
-                |data| data.result.metric.train_io_mean_mb_per_second * MB_TO_MIB,
+                |data| mb_to_mib(data.result.metric.train_io_mean_mb_per_second),
             ),
             (
                 ready_entries.clone(),
@@ -196,7 +280,7 @@ impl Plot for MlperfBasic {
             item?;
         }
 
-        self.efficiency(ready_entries.clone(), settings, &efficiency_dir)
+        self.efficiency(ready_entries.clone(), settings, &efficiency_dir, bench_info)
             .await?;
         Ok(())
     }
@@ -213,7 +297,8 @@ impl MlperfBasic {
         get_value: fn(&PlotEntry) -> f64,
         bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut results = vec![vec![]; num_power_states];
         let (order, labels) = self.get_order_labels(ready_entries.clone());
 
@@ -221,12 +306,10 @@ impl MlperfBasic {
 
         for item in ready_entries {
             let mean = get_value(&item);
-            let ps = if item.info.power_state == -1 {
-                0
-            } else {
-                item.info.power_state
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
             };
-            results[ps as usize].push((item, mean));
+            results[ps].push((item, mean));
         }
 
         for item in results.iter_mut() {
@@ -237,7 +320,15 @@ impl MlperfBasic {
             });
         }
 
-        let results = results
+        let annotations = results
+            .iter()
+            .map(|x| {
+                x.iter()
+                    .map(|x| self.bound(&x.0).label().to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let values = results
             .iter()
             .map(|x| x.iter().map(|x| x.1).collect::<Vec<_>>())
             .collect::<Vec<_>>();
@@ -247,8 +338,11 @@ impl MlperfBasic {
             "power" => BarChartKind::Power,
             other => bail!("Unsupported plotting file {other}"),
         };
-        let config = make_power_state_bar_config(chart_kind, x_label, &experiment_name, None);
-        plot_bar_chart(&filepath, results, labels, config, bench_info)
+        let mut config = make_power_state_bar_config(chart_kind, x_label, &experiment_name, None);
+        if plotting_file == "throughput" {
+            config.annotations = Some(annotations);
+        }
+        plot_bar_chart(&filepath, values, labels, config, bench_info, settings)
     }
 
     async fn efficiency(
@@ -256,48 +350,60 @@ impl MlperfBasic {
         ready_entries: Vec<PlotEntry>,
         settings: &Settings,
         plot_path: &Path,
+        bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let (order, labels) = self.get_order_labels(ready_entries.clone());
         let mut iops_j = vec![vec![0f64; num_power_states]; order.len()];
         let mut bytes_j = iops_j.clone();
         let mut bytes_j_ssd = iops_j.clone();
+        let mut iops_j_gpu = iops_j.clone();
         let experiment_name = ready_entries[0].info.name.clone();
 
         let results = ready_entries
             .par_iter()
-            .map(|item| {
+            .filter_map(|item| {
                 let ops = item.result.metric.train_throughput_mean_samples_per_second;
-                let throughput = item.result.metric.train_io_mean_mb_per_second * MB_TO_MIB;
+                let throughput = mb_to_mib(item.result.metric.train_io_mean_mb_per_second);
                 let x = *order
                     .get(&format!("{}", item.args.n_accelerators[0],))
                     .unwrap();
-                let y = if item.info.power_state == -1 {
-                    0
-                } else {
-                    item.info.power_state
-                } as usize;
+                let y = power_states.index(item.info.power_state)?;
 
                 let ssd_power = item.ssd_power.power.unwrap();
                 let cpu_power = item.cpu_power.power.unwrap();
-                (
+                let gpu_power = item
+                    .gpu_power
+                    .map_or(f64::NAN, |x| x.power.unwrap_or(f64::NAN));
+                Some((
                     x,
                     y,
+                    OperatingPoint {
+                        label: labels[x].clone(),
+                        power_state: item.info.power_state,
+                        throughput: ops,
+                        ssd_power,
+                    },
                     ops / (ssd_power + cpu_power),
                     throughput / (ssd_power + cpu_power),
                     throughput / ssd_power,
-                )
+                    ops / (ssd_power + cpu_power + gpu_power),
+                ))
             })
             .collect::<Vec<_>>();
+        let mut points = Vec::new();
         for item in results {
             let x = item.0;
             let y = item.1;
-            iops_j[x][y] = item.2;
-            bytes_j[x][y] = item.3;
-            bytes_j_ssd[x][y] = item.4;
+            points.push(item.2);
+            iops_j[x][y] = item.3;
+            bytes_j[x][y] = item.4;
+            bytes_j_ssd[x][y] = item.5;
+            iops_j_gpu[x][y] = item.6;
         }
 
-        let jobs = vec![
+        let mut jobs = vec![
             HeatmapJob {
                 filepath: plot_path.join(format!("{}-iops-j.pdf", &experiment_name)),
                 data: iops_j,
@@ -320,8 +426,47 @@ impl MlperfBasic {
                 reverse: false,
             },
         ];
+        if ready_entries.iter().any(|x| x.gpu_power.is_some()) {
+            jobs.push(HeatmapJob {
+                filepath: plot_path.join(format!("{}-iops-j+gpu.pdf", &experiment_name)),
+                data: iops_j_gpu,
+                title: "Samples/J",
+                x_label: "overall",
+                reverse: false,
+            });
+        }
 
-        render_heatmaps(&experiment_name, &labels, plot_path, &jobs)
+        render_heatmaps(
+            &experiment_name,
+            &labels,
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("samples/s", "samples/J"),
+            self.headline_power_cap,
+            settings,
+        )
+    }
+
+    /// Storage-bound if the accelerators were not kept busy while data was read from the SSD,
+    /// runs served from the page cache did not depend on the SSD
+    fn bound(&self, entry: &PlotEntry) -> Bound {
+        let metric = &entry.result.metric;
+        let busy = match self.au_threshold {
+            Some(threshold) => metric.train_au_mean_percentage >= threshold,
+            None => metric.train_au_meet_expectation == "success",
+        };
+        if !busy && metric.train_io_mean_mb_per_second > 0.0 {
+            Bound::Storage
+        } else {
+            Bound::Compute
+        }
     }
 
     fn get_order_labels(
@@ -0,0 +1,52 @@
+import json
+import argparse
+
+import matplotlib.pyplot as plt
+
+import common
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--spec", required=True, help="Path to JSON Pareto plot specification")
+    args = parser.parse_args()
+
+    with open(args.spec, "r", encoding="utf-8") as f:
+        spec = json.load(f)
+
+    points = spec["points"]
+    if not points:
+        raise ValueError("No data provided")
+
+    # one color per power state, in the order they first appear
+    power_states = list(dict.fromkeys(x["power_state"] for x in points))
+
+    _, ax = plt.subplots()
+    for idx, power_state in enumerate(power_states):
+        color = common.colors[idx % len(common.colors)]
+        series = [x for x in points if x["power_state"] == power_state and x["ssd_power"] is not None and x["throughput"] is not None]
+        labeled = False
+        # the points on the front are drawn larger and outlined
+        for pareto, style in ((False, {"alpha": 0.5}), (True, {"edgecolors": "black", "s": 60, "zorder": 3})):
+            group = [x for x in series if x["pareto"] == pareto]
+            if not group:
+                continue
+            ax.scatter([x["ssd_power"] for x in group], [x["throughput"] for x in group], color=color, label=None if labeled else power_state, **style)
+            labeled = True
+
+    front = sorted((x for x in points if x["pareto"]), key=lambda x: x["ssd_power"])
+    if front:
+        ax.plot([x["ssd_power"] for x in front], [x["throughput"] for x in front], color="black", linestyle="--", linewidth=1, zorder=2, label="Pareto front")
+        for point in front:
+            ax.annotate(point["label"], (point["ssd_power"], point["throughput"]), textcoords="offset points", xytext=(4, 4), fontsize=8)
+
+    ax.set_xlabel(spec["x_label"])
+    ax.set_ylabel(spec["y_label"])
+    ax.set_title(spec["title"])
+    ax.set_xlim(left=0)
+    ax.set_ylim(bottom=0)
+    ax.grid(True, alpha=0.3)
+    ax.legend()
+    common.add_footnote(spec.get("footnote"))
+    plt.tight_layout()
+    plt.savefig(spec["output_path"], format=spec.get("format", "pdf"))
+    plt.close()
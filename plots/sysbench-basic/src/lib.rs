@@ -0,0 +1,345 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use common::{
+    bench::{BenchInfo, BenchParams},
+    config::{Config, Settings},
+    plot::{
+        HeatmapJob, OperatingPoint, Plot, PlotMetric, PlotType, collect_run_groups, ensure_dirs,
+        render_heatmaps, write_headline,
+    },
+    sensor::powersensor3_filename,
+    util::{
+        BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config,
+        plot_bar_chart, power_energy_calculator, read_json_file,
+    },
+};
+use eyre::{Context, Result};
+use futures::future::join_all;
+use plot_common::impl_power_time_plot;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use sysbench::{Sysbench, result::SysbenchResult};
+use tokio::fs::read_to_string;
+use tracing::debug;
+
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new("Transactions", "tps", "throughput/<name>-tps.pdf"),
+    PlotMetric::new("Queries", "qps", "throughput/<name>-qps.pdf"),
+    PlotMetric::new("P95 latency", "ms", "latency/<name>-p95.pdf"),
+    PlotMetric::new("Average latency", "ms", "latency/<name>-avg.pdf"),
+    PlotMetric::new("SSD power", "W", "power/<name>-ssd.pdf"),
+    PlotMetric::new("CPU power", "W", "power/<name>-cpu.pdf"),
+    PlotMetric::new("Transactions/J", "tx/J", "efficiency/<name>-tx-j.pdf"),
+    PlotMetric::new(
+        "Transactions/J incl. CPU",
+        "tx/J",
+        "efficiency/<name>-tx-j+cpu.pdf",
+    ),
+];
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SysbenchBasic {
+    /// The headline is the highest tps at an SSD power of at most this many W instead of the
+    /// highest transactions/J
+    pub headline_power_cap: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+struct PlotEntry {
+    result: SysbenchResult,
+    info: BenchParams,
+    args: Sysbench,
+    ssd_power: SectionStats,
+    cpu_power: SectionStats,
+}
+
+/// Bar chart of one value of each run over the thread counts
+struct BarJob {
+    filepath: PathBuf,
+    kind: BarChartKind,
+    y_label: &'static str,
+    get_value: fn(&PlotEntry) -> f64,
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Plot for SysbenchBasic {
+    fn required_sensors(&self) -> &'static [&'static str] {
+        &["Powersensor3", "Rapl"]
+    }
+
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
+    async fn plot(
+        &self,
+        plot_type: &PlotType,
+        data_path: &Path,
+        plot_path: &Path,
+        config: &Config,
+        bench_info: &BenchInfo,
+        dirs: Vec<String>,
+        settings: &Settings,
+        completed_dirs: &mut Vec<String>,
+    ) -> Result<()> {
+        if *plot_type == PlotType::Total {
+            return Ok(());
+        }
+
+        debug!("Got {} dirs", dirs.len());
+        let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
+        let entries = join_all(groups.iter().map(|group| {
+            let run_dir = data_path.join(&group.dir);
+            let powersensor3_path = run_dir.join(&powersensor3_filename);
+            let info = group.info.clone();
+            async move {
+                (
+                    read_json_file::<SysbenchResult>(run_dir.join("results.json")).await,
+                    read_to_string(powersensor3_path).await,
+                    read_to_string(run_dir.join("rapl.csv")).await,
+                    info,
+                )
+            }
+        }))
+        .await;
+        let ready_entries = entries
+            .into_par_iter()
+            .map(|item| {
+                let (result, powersensor3, rapl, info) = item;
+                let rapl = rapl.context("Read rapl").unwrap();
+                let powersensor3 = powersensor3.context("Read powersensor3").unwrap();
+
+                let (_, rapl_overall, _) = calculate_sectioned::<_, 0>(
+                    None,
+                    &rapl,
+                    &["Total"],
+                    &[(0.0, settings.cpu_max_power_watts)],
+                    power_energy_calculator,
+                    None,
+                )
+                .context("Calculate rapl means")
+                .unwrap();
+                let (_, ps3_overall, _times) = calculate_sectioned::<_, 0>(
+                    None,
+                    &powersensor3,
+                    &["Total"],
+                    &[(0.0, bench_info.device_power_states[0].0)],
+                    power_energy_calculator,
+                    None,
+                )
+                .context("Calculate powersensor3 means")
+                .unwrap();
+
+                PlotEntry {
+                    result: result.context("Read results json").unwrap(),
+                    args: info.args.downcast_ref::<Sysbench>().unwrap().clone(),
+                    info,
+                    ssd_power: ps3_overall,
+                    cpu_power: rapl_overall,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let experiment_name = ready_entries[0].info.name.clone();
+        let throughput_dir = plot_path.join("throughput");
+        let latency_dir = plot_path.join("latency");
+        let efficiency_dir = plot_path.join("efficiency");
+        let power_dir = plot_path.join("power");
+        ensure_dirs(&[
+            throughput_dir.clone(),
+            latency_dir.clone(),
+            efficiency_dir.clone(),
+            power_dir.clone(),
+        ])
+        .await?;
+
+        let plot_jobs = vec![
+            BarJob {
+                filepath: throughput_dir.join(format!("{experiment_name}-tps.pdf")),
+                kind: BarChartKind::Throughput,
+                y_label: "Transactions/s",
+                get_value: |data| data.result.tps,
+            },
+            BarJob {
+                filepath: throughput_dir.join(format!("{experiment_name}-qps.pdf")),
+                kind: BarChartKind::Throughput,
+                y_label: "Queries/s",
+                get_value: |data| data.result.qps,
+            },
+            BarJob {
+                filepath: latency_dir.join(format!("{experiment_name}-p95.pdf")),
+                kind: BarChartKind::Latency,
+                y_label: "P95 latency (ms)",
+                get_value: |data| data.result.latency_p95_ms,
+            },
+            BarJob {
+                filepath: latency_dir.join(format!("{experiment_name}-avg.pdf")),
+                kind: BarChartKind::Latency,
+                y_label: "Average latency (ms)",
+                get_value: |data| data.result.latency_avg_ms,
+            },
+            BarJob {
+                filepath: power_dir.join(format!("{experiment_name}-cpu.pdf")),
+                kind: BarChartKind::Power,
+                y_label: "Power (Watts)",
+                get_value: |data| data.cpu_power.power_mean.unwrap(),
+            },
+            BarJob {
+                filepath: power_dir.join(format!("{experiment_name}-ssd.pdf")),
+                kind: BarChartKind::Power,
+                y_label: "Power (Watts)",
+                get_value: |data| data.ssd_power.power_mean.unwrap(),
+            },
+        ];
+
+        let results = plot_jobs
+            .into_par_iter()
+            .map(|job| self.bar_plot(&ready_entries, settings, job, bench_info))
+            .collect::<Vec<_>>();
+        for item in results {
+            item?;
+        }
+
+        self.efficiency(&ready_entries, settings, &efficiency_dir, bench_info)
+            .await?;
+        Ok(())
+    }
+}
+
+impl SysbenchBasic {
+    fn bar_plot(
+        &self,
+        ready_entries: &[PlotEntry],
+        settings: &Settings,
+        job: BarJob,
+        bench_info: &BenchInfo,
+    ) -> Result<()> {
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
+        let (order, labels) = self.get_order_labels(ready_entries);
+        let mut results = vec![vec![f64::NAN; order.len()]; num_power_states];
+
+        let experiment_name = ready_entries[0].info.name.clone();
+
+        for item in ready_entries {
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
+            };
+            results[ps][order[&item.args.threads[0]]] = (job.get_value)(item);
+        }
+
+        let mut config = make_power_state_bar_config(job.kind, "Threads", &experiment_name, None);
+        config.y_label = job.y_label.to_owned();
+        plot_bar_chart(&job.filepath, results, labels, config, bench_info, settings)
+    }
+
+    async fn efficiency(
+        &self,
+        ready_entries: &[PlotEntry],
+        settings: &Settings,
+        plot_path: &Path,
+        bench_info: &BenchInfo,
+    ) -> Result<()> {
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
+        let (order, labels) = self.get_order_labels(ready_entries);
+        let experiment_name = ready_entries[0].info.name.clone();
+
+        let mut tx_j = vec![vec![0f64; num_power_states]; order.len()];
+        let mut tx_j_ssd = tx_j.clone();
+        let results = ready_entries
+            .par_iter()
+            .filter_map(|item| {
+                let x = order[&item.args.threads[0]];
+                let y = power_states.index(item.info.power_state)?;
+
+                let tps = item.result.tps;
+                let ssd_power = item.ssd_power.power_mean.unwrap();
+                let cpu_power = item.cpu_power.power_mean.unwrap();
+                let point = OperatingPoint {
+                    label: format!("{} threads", labels[x]),
+                    power_state: item.info.power_state,
+                    throughput: tps,
+                    ssd_power,
+                };
+                Some((x, y, point, tps / (ssd_power + cpu_power), tps / ssd_power))
+            })
+            .collect::<Vec<_>>();
+        let mut points = Vec::with_capacity(results.len());
+        for item in results {
+            points.push(item.2);
+            tx_j[item.0][item.1] = item.3;
+            tx_j_ssd[item.0][item.1] = item.4;
+        }
+
+        let jobs = [
+            HeatmapJob {
+                filepath: plot_path.join(format!("{experiment_name}-tx-j+cpu.pdf")),
+                data: tx_j,
+                title: "Transactions/J",
+                x_label: "Threads",
+                reverse: false,
+            },
+            HeatmapJob {
+                filepath: plot_path.join(format!("{experiment_name}-tx-j.pdf")),
+                data: tx_j_ssd,
+                title: "Transactions/J",
+                x_label: "Threads",
+                reverse: false,
+            },
+        ];
+        render_heatmaps(
+            &experiment_name,
+            &labels,
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("tps", "tx/J"),
+            self.headline_power_cap,
+            settings,
+        )
+    }
+
+    /// Position of each thread count on the x axis, and the matching labels
+    fn get_order_labels(
+        &self,
+        ready_entries: &[PlotEntry],
+    ) -> (HashMap<usize, usize>, Vec<String>) {
+        let threads = ready_entries
+            .iter()
+            .map(|x| x.args.threads[0])
+            .collect::<BTreeSet<_>>();
+        let order = threads.iter().enumerate().map(|(x, y)| (*y, x)).collect();
+        let labels = threads.iter().map(|x| x.to_string()).collect();
+        (order, labels)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SysbenchPowerTime {
+    pub offset: Option<usize>,
+}
+impl_power_time_plot!(
+    SysbenchPowerTime,
+    Sysbench,
+    |cfg: &Sysbench| cfg.threads[0],
+    |cfg: &Sysbench| cfg.fs.clone()
+);
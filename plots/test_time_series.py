@@ -0,0 +1,37 @@
+import unittest
+
+import pandas as pd
+
+from time_series import despike, powersensor3_filename
+
+
+class DespikeTest(unittest.TestCase):
+    def test_even_window_is_rejected(self):
+        with self.assertRaises(ValueError):
+            despike(pd.Series([1.0, 2.0, 3.0]), 4)
+
+    def test_spikes_are_removed_and_steps_kept(self):
+        # single-sample spikes at 3 and 9, a step from 1 to 5 at 6
+        series = pd.Series([1.0, 1.0, 1.0, 10.0, 1.0, 1.0, 5.0, 5.0, 5.0, 0.0, 5.0, 5.0])
+        self.assertEqual(
+            despike(series, 3).tolist(),
+            [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 5.0, 5.0, 5.0, 5.0, 5.0, 5.0],
+        )
+
+    def test_window_below_three_is_unchanged(self):
+        series = pd.Series([1.0, 10.0, 1.0])
+        self.assertEqual(despike(series, 1).tolist(), series.tolist())
+
+
+class Powersensor3FilenameTest(unittest.TestCase):
+    def test_instance_without_alias(self):
+        config = {"sensors": [{"sensor": "Powersensor3"}, {"sensor": "Powersensor3", "alias": "ssd2"}]}
+        self.assertEqual(powersensor3_filename(config), "powersensor3.csv")
+
+    def test_every_instance_aliased(self):
+        config = {"sensors": [{"sensor": "Powersensor3", "alias": "ssd1"}, {"sensor": "Powersensor3", "alias": "ssd2"}]}
+        self.assertIsNone(powersensor3_filename(config))
+
+
+if __name__ == "__main__":
+    unittest.main()
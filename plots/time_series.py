@@ -1,7 +1,9 @@
 import os
+import copy
 import glob
 import argparse
 import json
+import tomllib
 import yaml
 from dataclasses import dataclass, field
 from typing import Optional, Dict, Any, List
@@ -21,6 +23,23 @@ def clean_sensor(sensor: str, spec: "Spec", df: pd.DataFrame) -> pd.DataFrame:
         case _:
             return common.fill_clean(df, trim=spec.trim_from_end, offset=spec.offset)
 
+def read_config(path: str) -> Dict[str, Any]:
+    """Reads the config copied to the results folder, which is TOML if the benchmark was run with a TOML config"""
+    if path.endswith(".toml"):
+        with open(path, "rb") as fh:
+            return tomllib.load(fh)
+    with open(path, "r", encoding="utf-8") as fh:
+        return yaml.safe_load(fh)
+
+def despike(series: pd.Series, window: int) -> pd.Series:
+    """Removes single-sample spikes with a median filter, steps longer than half the window are preserved"""
+    if window % 2 == 0:
+        raise ValueError(f"powersensor3_median_filter must be odd, got {window}")
+    if window < 3:
+        return series
+    # centered rolling median, unlike scipy's medfilt the edges are not zero padded
+    return series.rolling(window, center=True, min_periods=1).median()
+
 def prepare_sensor(sensor: str, spec: "Spec", bench_config, bench_info, df: pd.DataFrame) -> pd.DataFrame:
     if spec.trim_from_end < 101:
         window_length = len(spec.trim_from_end) - 1
@@ -35,28 +54,59 @@ def prepare_sensor(sensor: str, spec: "Spec", bench_config, bench_info, df: pd.D
                 df[f"average_freq_node{numa_domain}"] = df.loc[:, f"cpu-{start}-freq":f"cpu-{start + cores - 1}-freq"].max(axis=1)
                 df[f"average_load_node{numa_domain}"] = df.loc[:, f"cpu-{start}-load":f"cpu-{start + cores - 1}-load"].mean(axis=1)
         case "powersensor3.csv":
+            median_filter = bench_config["settings"].get("powersensor3_median_filter")
+            if median_filter:
+                df["Total"] = despike(df["Total"], median_filter)
             df["total_smoothed"] = savgol_filter(df["Total"], window_length=window_length, polyorder=3)
         case "rapl.csv":
             df = df[(df["Total"] < bench_config["settings"]["cpu_max_power_watts"]) & (df["Total"] >= 0)].copy()
             df["total_smoothed"] = savgol_filter(df["Total"], window_length=window_length, polyorder=3)
+        case "nvml.csv":
+            df["total_smoothed"] = savgol_filter(df["Total"], window_length=window_length, polyorder=3)
         case "diskstat.csv":
             df["read"] = df["read"] / 1048576
             df["write"] = df["write"] / 1048576
+            # bandwidth of every device if several were recorded
+            for column in df.columns:
+                if column.endswith("-read") or column.endswith("-write"):
+                    df[column] = df[column] / 1048576
+            # temperatures are read every second and would be zero filled like the bandwidth
+            for column in df.columns:
+                if column.split("-")[-1].startswith(("composite_temp", "sensor", "temp")):
+                    df[column] = df[column].mask(df[column] == 0).ffill()
             df["total"] = df["read"] + df["write"]
             df["total_smoothed"] = savgol_filter(df["total"], window_length=window_length, polyorder=3)
             df["read_smoothed"] = savgol_filter(df["read"], window_length=window_length, polyorder=3)
             df["write_smoothed"] = savgol_filter(df["write"], window_length=window_length, polyorder=3)
     return df
 
+# sensors that are only loaded if they were recorded
+optional_sensors = ["pcie.csv", "cooling.csv", "nvml.csv"]
+
+def sensor_kind(sensor: str, kinds: List[str]) -> str:
+    """Filename of the sensor kind of a sensor instance with an alias, ie. `powersensor3-ssd1.csv` -> `powersensor3.csv`"""
+    for kind in sorted(kinds, key=len, reverse=True):
+        if sensor == kind or sensor.startswith(os.path.splitext(kind)[0] + "-"):
+            return kind
+    return sensor
+
 def read_prepare_sensor_data(spec: "Spec", bench_config, bench_info, bench_data):
     sensors = {}
-    for sensor in spec.sensors:
+    kinds = spec.sensors + [x for x in optional_sensors if x not in spec.sensors]
+    recorded = [x for x in optional_sensors if x not in spec.sensors and os.path.exists(os.path.join(spec.results_dir, x))]
+    for kind in spec.sensors:
+        aliased = glob.glob(os.path.join(spec.results_dir, os.path.splitext(kind)[0] + "-*.csv"))
+        recorded += sorted(os.path.basename(x) for x in aliased if sensor_kind(os.path.basename(x), kinds) == kind)
+    for sensor in spec.sensors + recorded:
         df = pd.read_csv(os.path.join(spec.results_dir, sensor), dtype="float32")
         df.dropna(inplace=True)
+        if sensor in recorded and (len(df.columns) < 2 or df.empty):
+            continue
         if sensor == "powersensor3.csv" and spec.bench_type != "fio":
             spec.trim_from_end = len(df) - spec.trim_end
-        df = clean_sensor(sensor, spec, df)
-        df = prepare_sensor(sensor, spec, bench_config, bench_info, df)
+        kind = sensor_kind(sensor, kinds)
+        df = clean_sensor(kind, spec, df)
+        df = prepare_sensor(kind, spec, bench_config, bench_info, df)
         # if sensor == "rapl.csv" and spec.bench_type == "fio":
             # df = df[df["time"] > bench_data["offset"]]
             # df["dt"] = df["time"].diff()
@@ -67,6 +117,45 @@ def read_prepare_sensor_data(spec: "Spec", bench_config, bench_info, bench_data)
         sensors[sensor] = df
     return sensors
 
+def read_overlay_sensor_data(spec: "Spec", bench_config, bench_info):
+    """Sensors of every overlaid run, keyed `<run label>/<sensor>`"""
+    sensors = {}
+    for label, results_dir in spec.overlay_runs:
+        run_spec = copy.copy(spec)
+        run_spec.results_dir = results_dir
+        # every run is trimmed by its own length
+        ps3_df = pd.read_csv(os.path.join(results_dir, "powersensor3.csv"), dtype="float32")
+        run_spec.trim_from_end = len(ps3_df.dropna()) - spec.trim_end
+        for sensor, df in read_prepare_sensor_data(run_spec, bench_config, bench_info, {}).items():
+            sensors[f"{label}/{sensor}"] = df
+    return sensors
+
+def read_exported_csv(path: str, bench_config, **kwargs) -> pd.DataFrame:
+    """Reads a CSV of `plot_data` written in the `csv_format` of the settings, see `write_csv` in common/src/util.rs"""
+    csv_format = (bench_config.get("settings") or {}).get("csv_format") or {}
+    return pd.read_csv(path, sep=csv_format.get("delimiter", ","), decimal=csv_format.get("decimal_separator", "."), **kwargs)
+
+def powersensor3_filename(bench_config) -> Optional[str]:
+    """Filename of the Powersensor3 instance without an alias, None if every instance has one, see `powersensor3_filename` in common/src/sensor.rs"""
+    instances = [x for x in bench_config.get("sensors") or [] if x.get("sensor") == "Powersensor3"]
+    if instances and all(x.get("alias") for x in instances):
+        return None
+    return "powersensor3.csv"
+
+def derive_datasets(sensors: Dict[str, pd.DataFrame], ssd_power: Optional[str]) -> Dict[str, pd.DataFrame]:
+    """Series computed from several sensors, plotted like a sensor but left out of the stats"""
+    derived = {}
+    if "diskstat.csv" in sensors and ssd_power in sensors:
+        # instantaneous efficiency, the sensors sample at different rates so every throughput
+        # sample is paired with the power sample nearest in time
+        throughput = sensors["diskstat.csv"][["time", "total_smoothed"]].astype("float64").sort_values("time")
+        power = sensors[ssd_power][["time", "total_smoothed"]].astype("float64").sort_values("time")
+        df = pd.merge_asof(throughput, power.rename(columns={"total_smoothed": "power"}), on="time", direction="nearest")
+        df = df[df["power"] > 0].copy()
+        df["mib_j"] = df["total_smoothed"] / df["power"]
+        derived["efficiency"] = df[["time", "mib_j"]].reset_index(drop=True)
+    return derived
+
 nvme_trace_agg_options = {
     'count': 'sum',
     'function': 'first',
@@ -78,9 +167,9 @@ nvme_trace_agg_options = {
     'vfs_fsync': 'first'
 }
 
-def build_trace_graphs(trace_file, orig_rows):
+def build_trace_graphs(trace_file, orig_rows, bench_config):
     trace_graphs = []
-    trace_data = pd.read_csv(trace_file)
+    trace_data = read_exported_csv(trace_file, bench_config)
 
     trace_data_all = (
         trace_data.groupby(["time"], as_index=False).agg(nvme_trace_agg_options)
@@ -190,11 +279,14 @@ def build_trace_graphs(trace_file, orig_rows):
 
     return trace_graphs
 
-def read_prepare_bench_data(spec: "Spec"):
+def read_prepare_bench_data(spec: "Spec", bench_config):
     results = {}
     marker_file = os.path.join(spec.results_dir, "markers.csv")
     if os.path.exists(marker_file):
         results["markers"] = pd.read_csv(marker_file)
+    if spec.markers:
+        extra = pd.DataFrame(spec.markers, columns=["time", "marker_name"])
+        results["markers"] = pd.concat([results["markers"], extra], ignore_index=True) if "markers" in results else extra
 
     match spec.bench_type:
         case "fio":
@@ -233,6 +325,17 @@ def read_prepare_bench_data(spec: "Spec"):
             bw_log["smoothed"] = savgol_filter(bw_log["bw"], window_length=101, polyorder=3)
             results["data"] = bw_log
             results["offset"] = ramp_time
+
+            # completion latency percentiles over time, written by FioBwOverTime
+            latency_file = os.path.join(spec.plot_dir, "plot_data", f"{spec.name}-latency.csv")
+            if os.path.exists(latency_file):
+                latency = read_exported_csv(latency_file, bench_config, dtype="float32")
+                results["latency"] = common.fill_clean(latency, offset=spec.offset, trim=spec.trim_from_end)
+
+    summary_file = os.path.join(spec.results_dir, "summary.json")
+    if os.path.exists(summary_file):
+        with open(summary_file) as f:
+            results["throttling"] = json.load(f).get("throttling") or []
     return results
 
 @dataclass
@@ -242,6 +345,7 @@ class Axis:
     dataset_field: str
     plot_label: str
     axis_label: str
+    run: Optional[str] = None
 
     @classmethod
     def from_dict(cls, data: Dict[str, Any]) -> "Axis":
@@ -254,6 +358,7 @@ class Axis:
             dataset_field=data["dataset_field"],
             plot_label=data["plot_label"],
             axis_label=data["axis_label"],
+            run=data.get("run"),
         )
 
     def fetch_data(self, sensors: Dict[str, pd.DataFrame], bench_data: Optional[Any], skip_offset=False):
@@ -261,10 +366,15 @@ class Axis:
             offset = bench_data["offset"]
         else:
             offset = 0
-        if skip_offset:
+        # the bench offset is of the main run
+        if skip_offset or self.run is not None:
             offset = 0
+        if self.axis_type == "sensor" and self.run is not None:
+            return sensors[f"{self.run}/{self.dataset_name}"][self.dataset_field]
         if self.axis_type == "sensor":
             return sensors[self.dataset_name][self.dataset_field] + offset
+        if self.axis_type == "bench" and self.dataset_name != "bench":
+            return bench_data[self.dataset_name][self.dataset_field] + offset
         if self.axis_type == "bench":
             return bench_data["data"][self.dataset_field] + offset
         raise ValueError(f"Unsupported axis type '{self.axis_type}'")
@@ -276,6 +386,7 @@ class Axis:
             dataset_field="time",
             plot_label=self.plot_label,
             axis_label=self.axis_label,
+            run=self.run,
         )
 
 @dataclass
@@ -307,7 +418,7 @@ class Spec:
     results_dir: Optional[str] = None
     name: Optional[str] = None
     bench_type: Optional[str] = None
-    config_yaml: Optional[str] = None
+    config_file: Optional[str] = None
     info_json: Optional[str] = None
     plots: List[Plot] = field(default_factory=list)
 
@@ -317,6 +428,9 @@ class Spec:
     trim_from_end: Optional[int] = None
 
     sensors: List[str] = field(default_factory=list)
+    overlay_runs: List[List[str]] = field(default_factory=list)
+    # drawn along with markers.csv, as [time, name]
+    markers: List[List[Any]] = field(default_factory=list)
 
     @classmethod
     def from_json_file(cls, s: Optional[str]) -> "Spec":
@@ -334,13 +448,15 @@ class Spec:
             results_dir=data.get("results_dir"),
             name=data.get("name"),
             bench_type=data.get("bench_type"),
-            config_yaml=data.get("config_yaml"),
+            config_file=data.get("config_file"),
             info_json=data.get("info_json"),
             offset=data.get("offset", 0),
             trim_end=data.get("trim_end", 0),
             width=data.get("width", 12),
             plots=plots,
             sensors=sensors,
+            overlay_runs=data.get("overlay_runs", []),
+            markers=data.get("markers", []),
         )
 
     @classmethod
@@ -357,7 +473,7 @@ class Spec:
 
     def merge_overrides(self, overrides: Dict[str, Any]) -> "Spec":
         for k, v in overrides.items():
-            if k == "plots" or k == "sensors" or v is None:
+            if k == "plots" or k == "sensors" or k == "overlay_runs" or v is None:
                 continue
             setattr(self, k, v)
         return self
@@ -371,7 +487,12 @@ def plot(p: "Plot", spec: "Spec", sensors: Dict[str, pd.DataFrame], bench_data,
     color_idx = 0
     fig, ax = plt.subplots(figsize=(spec.width, 6.5))
     for y_axis in p.y_axis:
-        ax.plot(p.time.fetch_data(sensors, bench_data), y_axis.fetch_data(sensors, bench_data), color=common.colors[color_idx % len(common.colors)], label=y_axis.plot_label,)
+        # series of other runs are plotted over their own time
+        if (y_axis.run, y_axis.dataset_name) == (p.time.run, p.time.dataset_name):
+            time = p.time
+        else:
+            time = y_axis.copy_as_time()
+        ax.plot(time.fetch_data(sensors, bench_data), y_axis.fetch_data(sensors, bench_data), color=common.colors[color_idx % len(common.colors)], label=y_axis.plot_label,)
         color_idx += 1
 
     ax.set_ylabel(p.y_axis[0].axis_label)
@@ -379,12 +500,17 @@ def plot(p: "Plot", spec: "Spec", sensors: Dict[str, pd.DataFrame], bench_data,
     ax.set_ylim(bottom=0)
     ax.set_xlabel(p.time.axis_label)
 
-    if "markers" in bench_data:
+    # markers and throttling are of the main run only
+    overlay = any(y_axis.run is not None for y_axis in p.y_axis)
+    if "markers" in bench_data and not overlay:
         for idx, row in bench_data["markers"].iterrows():
             if row["time"] - spec.offset > 0 and row["time"] < spec.trim_from_end:
                 ax.axvline((row["time"] - spec.offset) / 1000, color=common.colors[color_idx], label=row["marker_name"], linestyle="dashdot")
                 color_idx += 1
 
+    for event in [] if overlay else bench_data.get("throttling", []):
+        ax.axvspan(max(0, event["start"] - spec.offset) / 1000, max(0, event["end"] - spec.offset) / 1000, color="red", alpha=0.15, label=f"{event['series']} drop")
+
     if p.secondary_y_axis:
         ax2 = ax.twinx()
         for y_axis in p.secondary_y_axis:
@@ -512,16 +638,17 @@ if __name__ == "__main__":
     merged.validate()
     spec = merged
 
-    bench_config = yaml.safe_load(open(spec.config_yaml, "r", encoding="utf-8"))
+    bench_config = read_config(spec.config_file)
     bench_info = json.load(open(spec.info_json, "r", encoding="utf-8"))
-    bench_data = read_prepare_bench_data(spec)
+    bench_data = read_prepare_bench_data(spec, bench_config)
     sensors = read_prepare_sensor_data(spec, bench_config, bench_info, bench_data)
     trace_file = os.path.join(spec.plot_dir, "plot_data", f"{spec.name}.csv")
     if os.path.exists(trace_file):
-        bench_data["trace"] = build_trace_graphs(trace_file, len(sensors["powersensor3.csv"]))
+        bench_data["trace"] = build_trace_graphs(trace_file, len(sensors["powersensor3.csv"]), bench_config)
 
+    plot_sensors = {**sensors, **derive_datasets(sensors, powersensor3_filename(bench_config)), **read_overlay_sensor_data(spec, bench_config, bench_info)}
     for p in spec.plots:
-        plot(p, spec, sensors, bench_data, bench_config, bench_info)
+        plot(p, spec, plot_sensors, bench_data, bench_config, bench_info)
 
     stats = gather_stats(sensors, bench_data, bench_config, bench_info)
     with open(os.path.join(spec.plot_dir, f"{spec.name}-stats.json"), "w") as f:
@@ -6,7 +6,11 @@ use std::{
 use common::{
     bench::{BenchInfo, BenchParams},
     config::{Config, Settings},
-    plot::{HeatmapJob, Plot, PlotType, collect_run_groups, ensure_dirs, render_heatmaps},
+    plot::{
+        HeatmapJob, OperatingPoint, Plot, PlotMetric, PlotType, collect_run_groups, ensure_dirs,
+        render_heatmaps, write_headline,
+    },
+    sensor::powersensor3_filename,
     util::{
         BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config,
         plot_bar_chart, power_energy_calculator, read_json_file,
@@ -22,9 +26,23 @@ use tokio::fs::read_to_string;
 use tpcc_postgres::{TpccPostgres, result::TpccPostgresMetrics};
 use tracing::debug;
 
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new("Requests", "requests/s", "throughput/<name>-requests.pdf"),
+    PlotMetric::new("Throughput", "tpmC", "throughput/<name>-tpmc.pdf"),
+    PlotMetric::new("Efficiency", "%", "throughput/<name>-efficiency.pdf"),
+    PlotMetric::new("SSD power", "W", "power/<name>-ssd.pdf"),
+    PlotMetric::new("CPU power", "W", "power/<name>-cpu.pdf"),
+    PlotMetric::new("TPMC/J", "tpmC/J", "efficiency/<name>-iops-j.pdf"),
+];
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub struct TpccBasic;
+pub struct TpccBasic {
+    /// The headline is the highest new-order transactions/s at an SSD power of at most this many
+    /// W instead of the most transactions/J
+    pub headline_power_cap: Option<f64>,
+}
 
 #[derive(Debug, Clone)]
 struct PlotEntry {
@@ -42,12 +60,16 @@ impl Plot for TpccBasic {
         &["Powersensor3", "Rapl"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
         data_path: &Path,
         plot_path: &Path,
-        _config: &Config,
+        config: &Config,
         bench_info: &BenchInfo,
         dirs: Vec<String>,
         settings: &Settings,
@@ -62,9 +84,10 @@ impl Plot for TpccBasic {
         if groups.is_empty() {
             return Ok(());
         }
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
         let entries = join_all(groups.iter().map(|group| {
             let result_path = data_path.join(&group.dir).join("result.json");
-            let ps3_path = data_path.join(&group.dir).join("powersensor3.csv");
+            let ps3_path = data_path.join(&group.dir).join(&powersensor3_filename);
             let rapl_path = data_path.join(&group.dir).join("rapl.csv");
             let dir = group.dir.clone();
             let info = group.info.clone();
@@ -186,7 +209,7 @@ impl Plot for TpccBasic {
             item?;
         }
 
-        self.efficiency(ready_entries.clone(), settings, &efficiency_dir)
+        self.efficiency(ready_entries.clone(), settings, &efficiency_dir, bench_info)
             .await?;
         Ok(())
     }
@@ -203,7 +226,8 @@ impl TpccBasic {
         get_value: fn(&PlotEntry) -> f64,
         bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut results = vec![vec![]; num_power_states];
         let (order, labels) = self.get_order_labels(ready_entries.clone());
 
@@ -211,12 +235,10 @@ impl TpccBasic {
 
         for item in ready_entries {
             let mean = get_value(&item);
-            let ps = if item.info.power_state == -1 {
-                0
-            } else {
-                item.info.power_state
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
             };
-            results[ps as usize].push((item, mean));
+            results[ps].push((item, mean));
         }
 
         for item in results.iter_mut() {
@@ -240,7 +262,7 @@ impl TpccBasic {
             }
         };
         let config = make_power_state_bar_config(chart_kind, x_label, &experiment_name, None);
-        plot_bar_chart(&filepath, results, labels, config, bench_info)
+        plot_bar_chart(&filepath, results, labels, config, bench_info, settings)
     }
 
     async fn efficiency(
@@ -248,36 +270,42 @@ impl TpccBasic {
         ready_entries: Vec<PlotEntry>,
         settings: &Settings,
         plot_path: &Path,
+        bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let (order, labels) = self.get_order_labels(ready_entries.clone());
         let mut ops_j = vec![vec![0f64; num_power_states]; order.len()];
         let experiment_name = ready_entries[0].info.name.clone();
 
         let results = ready_entries
             .par_iter()
-            .map(|item| {
+            .filter_map(|item| {
                 let ops = item.result.summary.tpmc as f64;
                 let x = *order
                     .get(&format!("{}", item.args.num_clients[0],))
                     .unwrap();
-                let y = if item.info.power_state == -1 {
-                    0
-                } else {
-                    item.info.power_state
-                } as usize;
+                let y = power_states.index(item.info.power_state)?;
 
-                (
+                Some((
                     x,
                     y,
+                    OperatingPoint {
+                        label: labels[x].clone(),
+                        power_state: item.info.power_state,
+                        throughput: ops / 60.0,
+                        ssd_power: item.ssd_power.power.unwrap(),
+                    },
                     ops / ((item.ssd_power.power.unwrap() + item.cpu_power.power.unwrap()) * 60.0),
-                )
+                ))
             })
             .collect::<Vec<_>>();
+        let mut points = Vec::new();
         for item in results {
             let x = item.0;
             let y = item.1;
-            ops_j[x][y] = item.2;
+            points.push(item.2);
+            ops_j[x][y] = item.3;
         }
 
         let jobs = vec![HeatmapJob {
@@ -288,7 +316,22 @@ impl TpccBasic {
             reverse: false,
         }];
 
-        render_heatmaps(&experiment_name, &labels, plot_path, &jobs)
+        render_heatmaps(
+            &experiment_name,
+            &labels,
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("tx/s", "tx/J"),
+            self.headline_power_cap,
+            settings,
+        )
     }
 
     fn get_order_labels(
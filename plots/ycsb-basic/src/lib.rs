@@ -4,9 +4,13 @@ use std::{
 };
 
 use common::{
-    bench::{BenchInfo, BenchParams},
+    bench::{BenchInfo, BenchParams, MARKERS_FILENAME},
     config::{Config, Settings},
-    plot::{HeatmapJob, Plot, PlotType, collect_run_groups, ensure_plot_dirs, render_heatmaps},
+    plot::{
+        HeatmapJob, OperatingPoint, Plot, PlotMetric, PlotType, collect_run_groups,
+        ensure_plot_dirs, render_heatmaps, write_headline,
+    },
+    sensor::powersensor3_filename,
     util::{
         BarChartKind, SectionStats, calculate_sectioned, make_power_state_bar_config,
         plot_bar_chart, power_energy_calculator, read_json_file,
@@ -15,22 +19,51 @@ use common::{
 use eyre::{Context, Result, bail};
 use futures::future::join_all;
 use itertools::Itertools;
-use plot_common::{default_timeseries_plot, impl_power_time_plot};
+use plot_common::{
+    default_timeseries_plot, impl_power_time_plot,
+    latency::{LatencyHistogram, iteration_dirs, merged_percentile},
+};
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use tokio::fs::read_to_string;
 use tracing::debug;
-use ycsb::{Ycsb, result::YcsbMetrics};
+use ycsb::{
+    Ycsb,
+    result::{OpMetrics, YcsbMetrics},
+};
+
+/// Metrics of the bar charts and heatmaps, see [`Plot::metrics`]
+const METRICS: &[PlotMetric] = &[
+    PlotMetric::new("Throughput", "kOPS/s", "iops/<name>.pdf"),
+    PlotMetric::new("Operations", "ops", "iops/<name>-total.pdf"),
+    PlotMetric::new("P99 read latency", "ms", "latency/<name>-read.pdf"),
+    PlotMetric::new("P99 update latency", "ms", "latency/<name>-update.pdf"),
+    PlotMetric::new("SSD power", "W", "power/<name>-ssd.pdf"),
+    PlotMetric::new("CPU power", "W", "power/<name>-cpu.pdf"),
+    PlotMetric::new(
+        "kIOPS/J",
+        "kOPS/J",
+        "efficiency/<name>-iops-j-benchmark.pdf",
+    ),
+];
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub struct YcsbBasic;
+pub struct YcsbBasic {
+    /// The headline is the highest kIOPS at an SSD power of at most this many W instead of the
+    /// highest kIOPS/J of the benchmark section
+    pub headline_power_cap: Option<f64>,
+}
 
 #[derive(Debug, Clone)]
 struct PlotEntry {
     result: YcsbMetrics,
     info: BenchParams,
     args: Ycsb,
+    /// P99 read latency (ms), see [`merged_p99_ms`]
+    read_p99_ms: Option<f64>,
+    /// P99 update latency (ms), see [`merged_p99_ms`]
+    update_p99_ms: Option<f64>,
     ssd_power: SectionedCalculation,
     cpu_power: SectionedCalculation,
 }
@@ -49,12 +82,16 @@ impl Plot for YcsbBasic {
         &["Powersensor3", "Rapl"]
     }
 
+    fn metrics(&self) -> &'static [PlotMetric] {
+        METRICS
+    }
+
     async fn plot(
         &self,
         plot_type: &PlotType,
         data_path: &Path,
         plot_path: &Path,
-        _config: &Config,
+        config: &Config,
         bench_info: &BenchInfo,
         dirs: Vec<String>,
         settings: &Settings,
@@ -65,21 +102,36 @@ impl Plot for YcsbBasic {
         }
 
         debug!("Got {} dirs", dirs.len());
+        let all_dirs = dirs.clone();
         let groups = collect_run_groups(dirs, &bench_info.param_map, completed_dirs)?;
         if groups.is_empty() {
             return Ok(());
         }
 
+        let powersensor3_filename = powersensor3_filename(&config.sensors, None)?;
         let entries = join_all(groups.iter().map(|group| {
             let run_dir = data_path.join(&group.dir);
+            let powersensor3_path = run_dir.join(&powersensor3_filename);
             let dir = group.dir.clone();
             let info = group.info.clone();
+            let iterations = iteration_dirs(group, &all_dirs, &bench_info.param_map)
+                .into_iter()
+                .filter(|x| *x != group.dir)
+                .map(|x| data_path.join(x).join("results.json"))
+                .collect::<Vec<_>>();
             async move {
+                let iterations =
+                    join_all(iterations.into_iter().map(read_json_file::<YcsbMetrics>))
+                        .await
+                        .into_iter()
+                        .filter_map(Result::ok)
+                        .collect::<Vec<_>>();
                 (
                     read_json_file::<YcsbMetrics>(run_dir.join("results.json")).await,
-                    read_to_string(run_dir.join("powersensor3.csv")).await,
+                    iterations,
+                    read_to_string(powersensor3_path).await,
                     read_to_string(run_dir.join("rapl.csv")).await,
-                    read_to_string(run_dir.join("markers.csv")).await,
+                    read_to_string(run_dir.join(MARKERS_FILENAME)).await,
                     dir,
                     info,
                 )
@@ -89,7 +141,11 @@ impl Plot for YcsbBasic {
         let ready_entries = entries
             .into_par_iter()
             .map(|item| {
-                let (json, powersensor3, rapl, markers, _dir, info) = item;
+                let (json, iterations, powersensor3, rapl, markers, _dir, info) = item;
+                let json = json.context("Read results json").unwrap();
+                let results = std::iter::once(&json)
+                    .chain(iterations.iter())
+                    .collect::<Vec<_>>();
                 let markers = markers.context("Read markers").unwrap();
                 let rapl = rapl.context("Read rapl").unwrap();
                 let powersensor3 = powersensor3.context("Read powersensor3").unwrap();
@@ -116,7 +172,9 @@ impl Plot for YcsbBasic {
                 .unwrap();
 
                 PlotEntry {
-                    result: json.context("Read results json").unwrap(),
+                    read_p99_ms: merged_p99_ms(&results, |x| x.read.as_ref()),
+                    update_p99_ms: merged_p99_ms(&results, |x| x.update.as_ref()),
+                    result: json,
                     args: info.args.downcast_ref::<Ycsb>().unwrap().clone(),
                     info,
                     ssd_power: SectionedCalculation {
@@ -162,13 +220,21 @@ impl Plot for YcsbBasic {
                 "kOPS/s",
                 |data| data.result.throughput_ops_sec.as_ref().map(|x| x / 1000.0),
             ),
+            (
+                ready_entries.clone(),
+                settings,
+                iops_dir.join(format!("{experiment_name}-total.pdf")),
+                "operations",
+                "Operation type",
+                |data| data.result.total_operations.map(|x| x as f64),
+            ),
             (
                 ready_entries.clone(),
                 settings,
                 latency_dir.join(format!("{experiment_name}-read.pdf")),
                 "latency",
                 "ms",
-                |data| data.result.read.as_ref().map(|x| x.p99_latency_us / 1000.0),
+                |data| data.read_p99_ms,
             ),
             (
                 ready_entries.clone(),
@@ -176,12 +242,7 @@ impl Plot for YcsbBasic {
                 latency_dir.join(format!("{experiment_name}-update.pdf")),
                 "latency",
                 "ms",
-                |data| {
-                    data.result
-                        .update
-                        .as_ref()
-                        .map(|x| x.p99_latency_us / 1000.0)
-                },
+                |data| data.update_p99_ms,
             ),
             (
                 ready_entries.clone(),
@@ -209,7 +270,7 @@ impl Plot for YcsbBasic {
             item?;
         }
 
-        self.efficiency(ready_entries.clone(), settings, &efficiency_dir)
+        self.efficiency(ready_entries.clone(), settings, &efficiency_dir, bench_info)
             .await?;
         Ok(())
     }
@@ -226,15 +287,10 @@ impl YcsbBasic {
         get_value: fn(&PlotEntry) -> Option<f64>,
         bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let mut results = vec![vec![]; num_power_states];
-        let vars = ready_entries
-            .iter()
-            .map(|x| format!("{:?}", x.args._ycsb_op_type.as_ref().unwrap()))
-            .collect::<HashSet<_>>();
-        let data = vars.into_iter().sorted();
-        let order: HashMap<String, usize> = data.clone().enumerate().map(|(x, y)| (y, x)).collect();
-        let labels: Vec<String> = data.map(|x| x.to_string()).collect();
+        let (order, labels) = self.get_order_labels(ready_entries.clone());
 
         let experiment_name = ready_entries[0].info.name.clone();
 
@@ -243,23 +299,14 @@ impl YcsbBasic {
                 Some(x) => x,
                 None => continue,
             };
-            let ps = if item.info.power_state == -1 {
-                0
-            } else {
-                item.info.power_state
+            let Some(ps) = power_states.index(item.info.power_state) else {
+                continue;
             };
-            results[ps as usize].push((item, mean));
+            results[ps].push((item, mean));
         }
 
         for item in results.iter_mut() {
-            item.sort_by_key(|entry| {
-                order
-                    .get(&format!(
-                        "{:?}",
-                        entry.0.args._ycsb_op_type.as_ref().unwrap()
-                    ))
-                    .unwrap()
-            });
+            item.sort_by_key(|entry| order.get(&order_key(&entry.0)).unwrap());
         }
 
         let results = results
@@ -267,13 +314,14 @@ impl YcsbBasic {
             .map(|x| x.iter().map(|x| x.1).collect::<Vec<_>>())
             .collect::<Vec<_>>();
 
-        let chart_kind = match plotting_file {
-            "throughput" => BarChartKind::Throughput,
-            "power" => BarChartKind::Power,
+        let (chart_kind, y_name) = match plotting_file {
+            "throughput" => (BarChartKind::Throughput, None),
+            "power" => (BarChartKind::Power, None),
+            "operations" => (BarChartKind::Operations, None),
             other => bail!("Unsupported plotting file {other}"),
         };
-        let config = make_power_state_bar_config(chart_kind, x_label, &experiment_name, None);
-        plot_bar_chart(&filepath, results, labels, config, bench_info)
+        let config = make_power_state_bar_config(chart_kind, x_label, &experiment_name, y_name);
+        plot_bar_chart(&filepath, results, labels, config, bench_info, settings)
     }
 
     async fn efficiency(
@@ -281,8 +329,10 @@ impl YcsbBasic {
         ready_entries: Vec<PlotEntry>,
         settings: &Settings,
         plot_path: &Path,
+        bench_info: &BenchInfo,
     ) -> Result<()> {
-        let num_power_states = settings.nvme_power_states.clone().unwrap_or(vec![0]).len();
+        let power_states = settings.power_states();
+        let num_power_states = power_states.len();
         let (order, labels) = self.get_order_labels(ready_entries.clone());
         let mut iops_j_overall = vec![vec![0f64; num_power_states]; order.len()];
         let mut iops_j_benchmark = iops_j_overall.clone();
@@ -291,37 +341,34 @@ impl YcsbBasic {
 
         let results = ready_entries
             .par_iter()
-            .map(|item| {
-                let x = *order
-                    .get(&format!(
-                        "{:?} {} {:?}",
-                        item.args.fs,
-                        item.args.workload_file,
-                        item.args._ycsb_op_type.as_ref().unwrap()
-                    ))
-                    .unwrap();
-                let y = if item.info.power_state == -1 {
-                    0
-                } else {
-                    item.info.power_state
-                } as usize;
+            .filter_map(|item| {
+                let x = *order.get(&order_key(item)).unwrap();
+                let y = power_states.index(item.info.power_state)?;
 
                 let throughput = item.result.throughput_ops_sec.as_ref().unwrap() / 1000.0;
-                (
+                Some((
                     x,
                     y,
+                    OperatingPoint {
+                        label: labels[x].clone(),
+                        power_state: item.info.power_state,
+                        throughput,
+                        ssd_power: item.ssd_power.benchmark.power.unwrap(),
+                    },
                     throughput / item.ssd_power.overall.power.unwrap(),
                     throughput / item.ssd_power.benchmark.power.unwrap(),
                     throughput / item.ssd_power.unmount.power.unwrap(),
-                )
+                ))
             })
             .collect::<Vec<_>>();
+        let mut points = Vec::new();
         for item in results {
             let x = item.0;
             let y = item.1;
-            iops_j_overall[x][y] = item.2;
-            iops_j_benchmark[x][y] = item.3;
-            iops_j_unmount[x][y] = item.4;
+            points.push(item.2);
+            iops_j_overall[x][y] = item.3;
+            iops_j_benchmark[x][y] = item.4;
+            iops_j_unmount[x][y] = item.5;
         }
 
         let jobs = vec![
@@ -348,7 +395,22 @@ impl YcsbBasic {
             },
         ];
 
-        render_heatmaps(&experiment_name, &labels, plot_path, &jobs)
+        render_heatmaps(
+            &experiment_name,
+            &labels,
+            plot_path,
+            &jobs,
+            bench_info,
+            settings,
+        )?;
+        write_headline(
+            plot_path,
+            &experiment_name,
+            &points,
+            ("kIO/s", "kIO/J"),
+            self.headline_power_cap,
+            settings,
+        )
     }
 
     fn get_order_labels(
@@ -366,12 +428,10 @@ impl YcsbBasic {
             })
             .collect::<HashSet<_>>();
         let data = vars.into_iter().sorted_by(|a, b| {
-            let fs_cmp = a.0.ord().cmp(&b.0.ord());
-            if fs_cmp != std::cmp::Ordering::Equal {
-                fs_cmp
-            } else {
-                a.2.as_ref().unwrap().cmp(b.2.as_ref().unwrap())
-            }
+            a.0.ord()
+                .cmp(&b.0.ord())
+                .then_with(|| a.1.cmp(&b.1))
+                .then_with(|| a.2.as_ref().unwrap().cmp(b.2.as_ref().unwrap()))
         });
         let order: HashMap<String, usize> = data
             .clone()
@@ -386,6 +446,17 @@ impl YcsbBasic {
     }
 }
 
+/// Key of a run in the order of [`YcsbBasic::get_order_labels`], its filesystem, workload and
+/// operation
+fn order_key(item: &PlotEntry) -> String {
+    format!(
+        "{:?} {} {:?}",
+        item.args.fs,
+        item.args.workload_file,
+        item.args._ycsb_op_type.as_ref().unwrap()
+    )
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct YcsbPowerTime {
@@ -398,3 +469,27 @@ impl_power_time_plot!(
     |cfg: &Ycsb| format!("{:?}-{:?}", cfg._ycsb_op_type.as_ref().unwrap(), cfg.fs),
     |cfg: &Ycsb| cfg.fs.clone()
 );
+
+/// P99 latency (ms) of an operation over all iterations of a run, from the merged latency
+/// histograms if YCSB reported them for every iteration (`measurementtype=histogram`), otherwise
+/// the p99 of the first iteration
+fn merged_p99_ms(
+    results: &[&YcsbMetrics],
+    op: fn(&YcsbMetrics) -> Option<&OpMetrics>,
+) -> Option<f64> {
+    let first = op(results.first()?)?;
+    let merged = results
+        .iter()
+        .map(|x| {
+            op(x)?
+                .histogram
+                .as_ref()
+                .map(|h| h.iter().copied().collect::<LatencyHistogram>())
+        })
+        .collect::<Option<Vec<_>>>()
+        .and_then(|histograms| merged_percentile(&histograms, 99.0));
+    Some(match merged {
+        Some(p99_us) => p99_us as f64 / 1000.0,
+        None => first.p99_latency_us / 1000.0,
+    })
+}
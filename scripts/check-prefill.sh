@@ -0,0 +1,45 @@
+#!/bin/bash
+# Compiles every benchmark that declares the `prefill` feature with it enabled, run from the
+# repository root. setup.toml and the manifests the build script rewrites are restored afterwards.
+set -euo pipefail
+
+mapfile -t benches < <(grep -l '^prefill = ' benches/*/Cargo.toml | xargs -n1 dirname | xargs -n1 basename)
+if [ ${#benches[@]} -eq 0 ]; then
+    echo "No benchmark declares the prefill feature"
+    exit 0
+fi
+
+manifests=(setup.toml Cargo.toml benches/default-benches/Cargo.toml sensors/default-sensors/Cargo.toml plots/default-plots/Cargo.toml plots/common/Cargo.toml)
+backup=$(mktemp -d)
+for f in "${manifests[@]}"; do
+    mkdir -p "$backup/$(dirname "$f")"
+    cp "$f" "$backup/$f"
+done
+restore() {
+    for f in "${manifests[@]}"; do
+        cp "$backup/$f" "$f"
+    done
+    rm -rf "$backup"
+}
+trap restore EXIT
+
+# sensor-common needs at least one sensor for its `SensorKind` enum, so the configured sensors
+# are kept and sysinfo is used if there are none
+sensors=$(grep '^sensors = ' setup.toml | sed 's/^sensors = //' || true)
+if [ "$sensors" = "[]" ] || [ -z "$sensors" ]; then
+    sensors='["sysinfo"]'
+fi
+
+{
+    printf 'benches = [%s]\nsensors = %s\nplots = []\n' "$(printf '"%s", ' "${benches[@]}" | sed 's/, $//')" "$sensors"
+    for bench in "${benches[@]}"; do
+        printf '\n[%s]\nfeatures = ["prefill"]\n' "$bench"
+    done
+} > setup.toml
+
+# the build script adds the benchmarks to the workspace, so they are only found by the second run
+cargo check -p setup
+for bench in "${benches[@]}"; do
+    echo "Checking $bench with prefill"
+    cargo check -p "$bench" --features prefill
+done
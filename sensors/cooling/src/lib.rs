@@ -0,0 +1,223 @@
+use std::{
+    collections::HashSet,
+    fs::{File, read_dir, read_to_string},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use common::{
+    config::Settings,
+    sensor::{Sensor, SensorArgs, SensorReply, SensorRequest},
+    util::{SensorError, TimeSeriesAxis, sensor_reader},
+};
+use eyre::{Context, ContextCompat, Result};
+use flume::{Receiver, Sender};
+use sensor_common::SensorKind;
+use serde::{Deserialize, Serialize};
+use tokio::{spawn, task::JoinHandle};
+use tracing::{debug, error, warn};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoolingConfig {
+    /// Data collection interval in milliseconds, defaults to 500
+    pub interval: Option<u64>,
+}
+
+#[typetag::serde]
+impl SensorArgs for CoolingConfig {
+    fn name(&self) -> SensorKind {
+        SensorKind::Cooling
+    }
+
+    fn sample_interval(&self) -> Option<Duration> {
+        Some(Duration::from_millis(
+            self.interval.unwrap_or(DEFAULT_INTERVAL),
+        ))
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cooling;
+
+const COOLING_FILENAME: &str = "cooling.csv";
+const DEFAULT_INTERVAL: u64 = 500;
+const HWMON_DIR: &str = "/sys/class/hwmon";
+
+impl Sensor for Cooling {
+    fn name(&self) -> SensorKind {
+        SensorKind::Cooling
+    }
+
+    fn filename(&self) -> &'static str {
+        COOLING_FILENAME
+    }
+
+    fn start(
+        &self,
+        args: &dyn SensorArgs,
+        settings: &Settings,
+        filename: &str,
+        rx: Receiver<SensorRequest>,
+        tx: Sender<SensorReply>,
+    ) -> Result<JoinHandle<Result<()>>> {
+        let args = args
+            .downcast_ref::<CoolingConfig>()
+            .context("Invalid sensor args, expected args for Cooling")?
+            .clone();
+
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
+        let handle = spawn(async move {
+            if let Err(err) = sensor_reader(
+                rx,
+                tx,
+                &filename,
+                flush_rows,
+                args,
+                init_cooling,
+                |args: &CoolingConfig,
+                 sensor: &mut Vec<Input>,
+                 _,
+                 _|
+                 -> std::pin::Pin<
+                    Box<dyn Future<Output = Result<Vec<f64>, SensorError>> + Send>,
+                > {
+                    Box::pin(read_cooling(
+                        args.interval.unwrap_or(DEFAULT_INTERVAL),
+                        sensor,
+                    ))
+                },
+            )
+            .await
+            {
+                error!("{err:#?}");
+                return Err(err);
+            }
+            Ok(())
+        });
+        Ok(handle)
+    }
+}
+
+/// A `fanN_input` or `powerN_input` file of a hwmon chip
+struct Input {
+    column: String,
+    path: PathBuf,
+    /// Multiplied with the raw value, hwmon reports power in µW
+    scale: f64,
+}
+
+impl Input {
+    fn read(&self) -> Result<f64> {
+        let value = read_to_string(&self.path)
+            .context(format!("Reading {}", self.path.display()))?
+            .trim()
+            .parse::<f64>()
+            .context(format!("Parsing {}", self.path.display()))?;
+        Ok(value * self.scale)
+    }
+}
+
+/// Fan speed and power inputs of every hwmon chip that has fans, ordered by chip and input
+fn find_inputs() -> Result<Vec<Input>> {
+    let Ok(dir) = read_dir(HWMON_DIR) else {
+        return Ok(Vec::new());
+    };
+    let mut chips = dir
+        .filter_map(|x| x.ok().map(|x| x.path()))
+        .collect::<Vec<_>>();
+    chips.sort();
+
+    let mut inputs = Vec::new();
+    let mut names = HashSet::new();
+    for chip in chips {
+        let mut files = read_dir(&chip)?
+            .filter_map(|x| x.ok())
+            .map(|x| x.file_name().to_string_lossy().to_string())
+            .filter(|x| (x.starts_with("fan") || x.starts_with("power")) && x.ends_with("_input"))
+            .collect::<Vec<_>>();
+        if !files.iter().any(|x| x.starts_with("fan")) {
+            continue;
+        }
+        files.sort();
+
+        // chips of the same driver are told apart by their hwmon directory
+        let name = read_to_string(chip.join("name"))
+            .map(|x| x.trim().to_owned())
+            .unwrap_or_default();
+        let name = if name.is_empty() || !names.insert(name.clone()) {
+            format!(
+                "{name}{}",
+                chip.file_name().unwrap_or_default().to_string_lossy()
+            )
+        } else {
+            name
+        };
+        for file in files {
+            let input = Input {
+                column: format!("{name}-{}", file.trim_end_matches("_input")),
+                path: chip.join(&file),
+                scale: if file.starts_with("power") { 1e-6 } else { 1.0 },
+            };
+            // ie. fans that are not connected return ENODATA
+            match input.read() {
+                Ok(_) => inputs.push(input),
+                Err(err) => debug!("Skipping {}: {err:#}", input.path.display()),
+            }
+        }
+    }
+    Ok(inputs)
+}
+
+async fn init_cooling(_: CoolingConfig) -> Result<(Vec<Input>, Vec<String>)> {
+    let inputs = find_inputs()?;
+    if inputs.is_empty() {
+        warn!("No fans found in {HWMON_DIR}, cooling sensor will not record any data");
+    }
+    for input in &inputs {
+        debug!("Recording {} from {}", input.column, input.path.display());
+    }
+    let columns = inputs.iter().map(|x| x.column.clone()).collect();
+    Ok((inputs, columns))
+}
+
+async fn read_cooling(interval: u64, inputs: &[Input]) -> Result<Vec<f64>, SensorError> {
+    let start = Instant::now();
+    let readings = inputs
+        .iter()
+        .map(|x| x.read())
+        .collect::<Result<Vec<_>>>()
+        .map_err(SensorError::MajorFailure)?;
+    async_io::Timer::after(Duration::from_millis(interval).saturating_sub(start.elapsed())).await;
+    Ok(readings)
+}
+
+/// Fan speed and fan power axes for the columns recorded in the run's `cooling.csv`, empty if no
+/// fans were found
+pub fn cooling_plot_axis(data_path: &Path) -> (Vec<TimeSeriesAxis>, Vec<TimeSeriesAxis>) {
+    let mut header = String::new();
+    if File::open(data_path.join(COOLING_FILENAME))
+        .and_then(|file| BufReader::new(file).read_line(&mut header))
+        .is_err()
+    {
+        return (Vec::new(), Vec::new());
+    }
+    let columns = header.trim_end().split(',').filter(|x| *x != "time");
+    let axis = |column: &str, axis_label: &str| {
+        TimeSeriesAxis::sensor(COOLING_FILENAME, column, column, axis_label)
+    };
+    (
+        columns
+            .clone()
+            .filter(|x| x.contains("-fan"))
+            .map(|x| axis(x, "Fan speed (RPM)"))
+            .collect(),
+        columns
+            .filter(|x| x.contains("-power"))
+            .map(|x| axis(x, "Fan power (W)"))
+            .collect(),
+    )
+}
@@ -1,6 +1,8 @@
 use std::{
     fs::File,
+    io::{BufRead, BufReader},
     os::unix::fs::FileExt,
+    path::{Path, PathBuf},
     sync::{Arc, LazyLock},
     time::{Duration, Instant},
 };
@@ -14,8 +16,13 @@ use eyre::{Context, Result};
 use flume::{Receiver, Sender};
 use sensor_common::SensorKind;
 use serde::{Deserialize, Serialize};
-use tokio::{fs::read_to_string, spawn, sync::Mutex, task::JoinHandle};
-use tracing::error;
+use tokio::{
+    fs::{read_dir, read_to_string},
+    spawn,
+    sync::Mutex,
+    task::{JoinHandle, spawn_blocking},
+};
+use tracing::{error, warn};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -31,7 +38,7 @@ impl SensorArgs for DiskstatConfig {
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 struct InternalDiskStatConfig {
-    device: String,
+    devices: Vec<String>,
 }
 
 #[typetag::serde]
@@ -39,6 +46,10 @@ impl SensorArgs for InternalDiskStatConfig {
     fn name(&self) -> SensorKind {
         SensorKind::Diskstat
     }
+
+    fn sample_interval(&self) -> Option<Duration> {
+        Some(DISKSTAT_INTERVAL)
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -49,6 +60,13 @@ struct InternalDiskStat {
     file: File,
     hw_sector_size: u64,
     prev: DiskStatData,
+    /// `temp*_input` files of the controller's hwmon, composite temperature first
+    temps: Arc<Vec<File>>,
+    /// Which of `temps` have a column of their own, see [`lists_temp`]
+    listed_temps: Vec<bool>,
+    /// Last temperatures read from `temps` in °C
+    temp_values: Vec<f64>,
+    temp_read: Option<Instant>,
 }
 
 #[derive(Default)]
@@ -58,6 +76,10 @@ struct DiskStatData {
 }
 
 const DISKSTAT_FILENAME: &str = "diskstat.csv";
+const DISKSTAT_INTERVAL: Duration = Duration::from_millis(10);
+/// Every hwmon read issues a SMART log command to the drive, so temperatures are read far less
+/// often than the I/O statistics
+const TEMP_INTERVAL: Duration = Duration::from_secs(1);
 
 impl Sensor for Diskstat {
     fn name(&self) -> SensorKind {
@@ -72,16 +94,25 @@ impl Sensor for Diskstat {
         &self,
         _: &dyn SensorArgs,
         settings: &Settings,
+        filename: &str,
         rx: Receiver<SensorRequest>,
         tx: Sender<SensorReply>,
     ) -> Result<JoinHandle<Result<()>>> {
-        let device = settings.device.strip_prefix("/dev/").unwrap().to_string();
+        let devices = settings
+            .device
+            .all()
+            .iter()
+            .map(|x| x.strip_prefix("/dev/").unwrap().to_string())
+            .collect();
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
         let handle = spawn(async move {
             if let Err(err) = sensor_reader(
                 rx,
                 tx,
-                DISKSTAT_FILENAME,
-                InternalDiskStatConfig { device },
+                &filename,
+                flush_rows,
+                InternalDiskStatConfig { devices },
                 init_diskstat,
                 |_,
                  sensor,
@@ -102,91 +133,236 @@ impl Sensor for Diskstat {
     }
 }
 
+/// Name of the NVMe controller of a namespace, ie. `nvme0` of `nvme0n1`
+fn controller_name(device: &str) -> Option<&str> {
+    let (controller, namespace) = device.rsplit_once('n')?;
+    (controller.starts_with("nvme")
+        && !namespace.is_empty()
+        && namespace.bytes().all(|x| x.is_ascii_digit()))
+    .then_some(controller)
+}
+
+/// Column of a hwmon temperature by its label, ie. `Composite` or `Sensor 1`
+fn temp_column(index: u32, label: Option<&str>) -> String {
+    match label.map(|x| x.trim()) {
+        Some("Composite") => "composite_temp".to_owned(),
+        Some(label) if label.starts_with("Sensor ") => {
+            format!("sensor{}_temp", label.trim_start_matches("Sensor "))
+        }
+        _ => format!("temp{index}"),
+    }
+}
+
+/// `temp*_input` files of the hwmon of a device and their columns, sorted by index so that the
+/// composite temperature is first, empty if the device exposes no hwmon
+async fn hwmon_temps(device: &str) -> Result<Vec<(PathBuf, String)>> {
+    let mut parents = vec![PathBuf::from(format!("/sys/block/{device}/device"))];
+    if let Some(controller) = controller_name(device) {
+        parents.push(PathBuf::from(format!("/sys/class/nvme/{controller}")));
+    }
+
+    for parent in parents {
+        let Ok(mut entries) = read_dir(&parent).await else {
+            continue;
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_name().to_string_lossy().starts_with("hwmon") {
+                continue;
+            }
+            let hwmon = entry.path();
+            let mut temps = Vec::new();
+            let mut files = read_dir(&hwmon).await?;
+            while let Some(file) = files.next_entry().await? {
+                let name = file.file_name().to_string_lossy().to_string();
+                let Some(index) = name
+                    .strip_prefix("temp")
+                    .and_then(|x| x.strip_suffix("_input"))
+                    .and_then(|x| x.parse::<u32>().ok())
+                else {
+                    continue;
+                };
+                let label = read_to_string(hwmon.join(format!("temp{index}_label")))
+                    .await
+                    .ok();
+                temps.push((index, file.path(), temp_column(index, label.as_deref())));
+            }
+            if !temps.is_empty() {
+                temps.sort_by_key(|x| x.0);
+                return Ok(temps
+                    .into_iter()
+                    .map(|(_, path, column)| (path, column))
+                    .collect());
+            }
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Opens the stat and hwmon files of every device, see [`diskstat_columns`] for the columns
 async fn init_diskstat(
     config: InternalDiskStatConfig,
-) -> Result<(Arc<Mutex<InternalDiskStat>>, Vec<String>)> {
-    let hw_sector_size =
-        read_to_string(format!("/sys/block/{}/queue/hw_sector_size", config.device))
+) -> Result<(Arc<Mutex<Vec<InternalDiskStat>>>, Vec<String>)> {
+    let mut disks = Vec::new();
+    for device in &config.devices {
+        let hw_sector_size = read_to_string(format!("/sys/block/{device}/queue/hw_sector_size"))
             .await?
             .trim()
             .parse()?;
-    let file = File::open(format!("/sys/block/{}/stat", config.device))?;
-    let mut diskstat = InternalDiskStat {
-        file,
-        hw_sector_size,
-        prev: DiskStatData::default(),
-    };
-    _ = diskstat.read(&Instant::now()).await?;
-    Ok((
-        Arc::new(Mutex::new(diskstat)),
-        vec![
-            "bytes_read",
-            "bytes_write",
-            "read",
-            "write",
-            "read_ios",
-            "write_ios",
-            "read_merges",
-            "write_merges",
-            "time_in_queue",
-            "read_ticks",
-            "write_ticks",
-        ]
-        .into_iter()
-        .map(|x| x.to_owned())
-        .collect(),
-    ))
+        let file = File::open(format!("/sys/block/{device}/stat"))?;
+        let temps = hwmon_temps(device)
+            .await
+            .context(format!("Finding the hwmon of {device}"))?;
+        if temps.is_empty() {
+            warn!("{device} exposes no hwmon, its temperature will not be recorded");
+        }
+        let mut diskstat = InternalDiskStat {
+            file,
+            hw_sector_size,
+            prev: DiskStatData::default(),
+            temps: Arc::new(
+                temps
+                    .iter()
+                    .map(|(path, _)| {
+                        File::open(path).context(format!("Opening {}", path.display()))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            listed_temps: temps
+                .iter()
+                .map(|(_, column)| lists_temp(config.devices.len(), column))
+                .collect(),
+            temp_values: Vec::new(),
+            temp_read: None,
+        };
+        _ = diskstat.read(&Instant::now()).await?;
+        disks.push((
+            diskstat,
+            temps.into_iter().map(|(_, column)| column).collect(),
+        ));
+    }
+
+    let temps = disks.iter().map(|(_, temps)| temps).collect::<Vec<_>>();
+    let columns = diskstat_columns(&config.devices, &temps);
+    let disks = disks.into_iter().map(|(disk, _)| disk).collect();
+    Ok((Arc::new(Mutex::new(disks)), columns))
+}
+
+/// Whether a temperature has a column of its own, the composite temperature of a single device is
+/// already the `composite_temp` column
+fn lists_temp(devices: usize, column: &str) -> bool {
+    devices > 1 || column != "composite_temp"
+}
+
+/// Columns of `diskstat.csv` for `devices` with the hwmon temperature columns `temps` of every
+/// device. The columns are the sum over all devices followed by `total`, the read and write
+/// throughput combined, with several devices the read and write throughput of every device follow
+/// as `<device>-read` and `<device>-write`. If the devices expose
+/// their temperatures through hwmon, `composite_temp` (the hottest device with several of them) and
+/// the temperature of every sensor follow, prefixed by `<device>-` with several devices
+fn diskstat_columns(devices: &[String], temps: &[&Vec<String>]) -> Vec<String> {
+    let mut columns = [
+        "bytes_read",
+        "bytes_write",
+        "read",
+        "write",
+        "read_ios",
+        "write_ios",
+        "read_merges",
+        "write_merges",
+        "time_in_queue",
+        "read_ticks",
+        "write_ticks",
+        "total",
+    ]
+    .into_iter()
+    .map(|x| x.to_owned())
+    .collect::<Vec<_>>();
+    if devices.len() > 1 {
+        columns.extend(
+            devices
+                .iter()
+                .flat_map(|x| [format!("{x}-read"), format!("{x}-write")]),
+        );
+    }
+    if temps.iter().any(|x| !x.is_empty()) {
+        columns.push("composite_temp".to_owned());
+        for (device, temps) in devices.iter().zip(temps) {
+            columns.extend(
+                temps
+                    .iter()
+                    .filter(|x| lists_temp(devices.len(), x))
+                    .map(|column| {
+                        if devices.len() > 1 {
+                            format!("{device}-{column}")
+                        } else {
+                            column.clone()
+                        }
+                    }),
+            );
+        }
+    }
+    columns
 }
 
 type ReadDiskResult = Result<Vec<f64>, SensorError>;
-async fn read_diskstat(sensor: Arc<Mutex<InternalDiskStat>>, last_time: Instant) -> ReadDiskResult {
-    let mut sensor = sensor.lock().await;
-    let readings = sensor.read(&last_time).await?;
-    async_io::Timer::after(Duration::from_micros(10000)).await;
+async fn read_diskstat(
+    sensor: Arc<Mutex<Vec<InternalDiskStat>>>,
+    last_time: Instant,
+) -> ReadDiskResult {
+    let mut disks = sensor.lock().await;
+    let mut readings = Vec::new();
+    let mut per_device = Vec::new();
+    let mut temps = Vec::new();
+    let mut composite_temp = None::<f64>;
+    for disk in disks.iter_mut() {
+        let disk_readings = disk.read(&last_time).await?;
+        if readings.is_empty() {
+            readings = disk_readings.clone();
+        } else {
+            readings
+                .iter_mut()
+                .zip(&disk_readings)
+                .for_each(|(sum, x)| *sum += x);
+        }
+        per_device.extend([disk_readings[2], disk_readings[3]]);
+
+        disk.read_temps().await?;
+        if let Some(composite) = disk.temp_values.first() {
+            composite_temp = Some(composite_temp.map_or(*composite, |x| x.max(*composite)));
+        }
+        temps.extend(
+            disk.temp_values
+                .iter()
+                .zip(&disk.listed_temps)
+                .filter(|(_, listed)| **listed)
+                .map(|(x, _)| *x),
+        );
+    }
+    readings.push(readings[2] + readings[3]);
+    if disks.len() > 1 {
+        readings.extend(per_device);
+    }
+    if let Some(composite_temp) = composite_temp {
+        readings.push(composite_temp);
+        readings.extend(temps);
+    }
+    drop(disks);
+    async_io::Timer::after(DISKSTAT_INTERVAL).await;
     Ok(readings)
 }
 
 impl InternalDiskStat {
     async fn read(&mut self, prev_time: &Instant) -> ReadDiskResult {
-        let mut buf = [0u8; 256];
+        let mut buf = [0u8; 512];
         let read = self
             .file
             .read_at(&mut buf, 0)
             .context("Read stat file")
             .map_err(|e| SensorError::MajorFailure(e))?;
 
-        let mut fields: [&[u8]; 17] = [&[]; 17];
-        let mut n = 0usize;
-        let mut i = 0usize;
-        let mut in_token = false;
-
-        while i < read {
-            let b = buf[i];
-            let is_ws = matches!(b, b' ' | b'\t' | b'\n' | b'\r');
-            if !is_ws && !in_token {
-                let start = i;
-                let mut j = i + 1;
-                while j < buf.len() {
-                    let c = buf[j];
-                    if matches!(c, b' ' | b'\t' | b'\n' | b'\r') {
-                        break;
-                    }
-                    j += 1;
-                }
-                if n < fields.len() {
-                    fields[n] = &buf[start..j];
-                    n += 1;
-                }
-                i = j;
-                in_token = false;
-            } else {
-                i += 1;
-            }
-        }
-
-        use atoi::FromRadix10;
-        let reads = u64::from_radix_10(fields[2]).0;
-        let writes = u64::from_radix_10(fields[6]).0;
+        let fields = parse_stat(&buf[..read]);
+        let reads = fields[2];
+        let writes = fields[6];
         let readings = vec![
             reads as f64,
             writes as f64,
@@ -194,13 +370,13 @@ impl InternalDiskStat {
                 / prev_time.elapsed().as_secs_f64(),
             ((writes as f64 - self.prev.write as f64) * self.hw_sector_size as f64)
                 / prev_time.elapsed().as_secs_f64(),
-            u64::from_radix_10(fields[0]).0 as f64,
-            u64::from_radix_10(fields[4]).0 as f64,
-            u64::from_radix_10(fields[1]).0 as f64,
-            u64::from_radix_10(fields[5]).0 as f64,
-            u64::from_radix_10(fields[10]).0 as f64,
-            u64::from_radix_10(fields[3]).0 as f64,
-            u64::from_radix_10(fields[7]).0 as f64,
+            fields[0] as f64,
+            fields[4] as f64,
+            fields[1] as f64,
+            fields[5] as f64,
+            fields[10] as f64,
+            fields[3] as f64,
+            fields[7] as f64,
         ];
 
         self.prev.read = reads;
@@ -208,6 +384,55 @@ impl InternalDiskStat {
 
         Ok(readings)
     }
+
+    /// Updates [`InternalDiskStat::temp_values`], at most every [`TEMP_INTERVAL`]. A hwmon read
+    /// waits for the drive to answer, so it is done on a blocking thread.
+    async fn read_temps(&mut self) -> Result<(), SensorError> {
+        if self.temp_read.is_some_and(|x| x.elapsed() < TEMP_INTERVAL) {
+            return Ok(());
+        }
+        let temps = self.temps.clone();
+        self.temp_values = spawn_blocking(move || read_hwmon_temps(&temps))
+            .await
+            .context("Join hwmon read")
+            .map_err(SensorError::MajorFailure)??;
+        self.temp_read = Some(Instant::now());
+        Ok(())
+    }
+}
+
+/// Temperatures of `temps` in °C
+fn read_hwmon_temps(temps: &[File]) -> Result<Vec<f64>, SensorError> {
+    temps
+        .iter()
+        .map(|file| {
+            let mut buf = [0u8; 32];
+            let read = file
+                .read_at(&mut buf, 0)
+                .context("Read hwmon temperature")
+                .map_err(SensorError::MajorFailure)?;
+            let millidegrees: i64 = String::from_utf8_lossy(&buf[..read])
+                .trim()
+                .parse()
+                .context("Parse hwmon temperature")
+                .map_err(SensorError::MajorFailure)?;
+            Ok(millidegrees as f64 / 1000.0)
+        })
+        .collect()
+}
+
+/// Fields of a `/sys/block/<dev>/stat` line, older kernels report 11 or 15 of them, the ones they
+/// do not report are 0
+fn parse_stat(line: &[u8]) -> [u64; 17] {
+    use atoi::FromRadix10;
+    let mut fields = [0; 17];
+    for (field, token) in fields.iter_mut().zip(
+        line.split(|x| x.is_ascii_whitespace())
+            .filter(|x| !x.is_empty()),
+    ) {
+        *field = u64::from_radix_10(token).0;
+    }
+    fields
 }
 
 pub static DISKSTAT_PLOT_AXIS: LazyLock<[TimeSeriesAxis; 3]> = LazyLock::new(|| {
@@ -227,3 +452,118 @@ pub static DISKSTAT_PLOT_AXIS: LazyLock<[TimeSeriesAxis; 3]> = LazyLock::new(||
         ),
     ]
 });
+
+pub static DISKSTAT_TEMP_PLOT_AXIS: LazyLock<[TimeSeriesAxis; 1]> = LazyLock::new(|| {
+    [TimeSeriesAxis::sensor(
+        DISKSTAT_FILENAME,
+        "composite_temp",
+        "SSD temperature",
+        "Temperature (°C)",
+    )]
+});
+
+/// [`DISKSTAT_TEMP_PLOT_AXIS`] if the run's `diskstat.csv` recorded the temperature, empty if
+/// the devices exposed no hwmon
+pub fn diskstat_temp_plot_axis(data_path: &Path) -> Vec<TimeSeriesAxis> {
+    let mut header = String::new();
+    if File::open(data_path.join(DISKSTAT_FILENAME))
+        .and_then(|file| BufReader::new(file).read_line(&mut header))
+        .is_err()
+        || !header.trim_end().split(',').any(|x| x == "composite_temp")
+    {
+        return Vec::new();
+    }
+    DISKSTAT_TEMP_PLOT_AXIS.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat_lines() {
+        // 5.5+ with discard and flush fields
+        assert_eq!(
+            parse_stat(
+                b"  291633   101345 18731498   138950  1466036   978385 97584554  3003571        0  1338660  3331370    25134        0 52436720    14417    98341   174431\n"
+            ),
+            [
+                291633, 101345, 18731498, 138950, 1466036, 978385, 97584554, 3003571, 0, 1338660,
+                3331370, 25134, 0, 52436720, 14417, 98341, 174431
+            ]
+        );
+        // pre 4.18, without discard and flush fields
+        assert_eq!(
+            parse_stat(
+                b"    4310       12   345678     1520     9024      301  2870136    40311        0    21480    41831\n"
+            ),
+            [
+                4310, 12, 345678, 1520, 9024, 301, 2870136, 40311, 0, 21480, 41831, 0, 0, 0, 0, 0,
+                0
+            ]
+        );
+        assert_eq!(parse_stat(b"1\t2 \n 3")[..4], [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn names_hwmon_temperatures() {
+        assert_eq!(controller_name("nvme0n1"), Some("nvme0"));
+        assert_eq!(controller_name("nvme12n3"), Some("nvme12"));
+        assert_eq!(controller_name("sda"), None);
+        assert_eq!(temp_column(1, Some("Composite\n")), "composite_temp");
+        assert_eq!(temp_column(2, Some("Sensor 1\n")), "sensor1_temp");
+        assert_eq!(temp_column(4, None), "temp4");
+    }
+
+    #[test]
+    fn lists_composite_temperature_once() {
+        let stats = [
+            "bytes_read",
+            "bytes_write",
+            "read",
+            "write",
+            "read_ios",
+            "write_ios",
+            "read_merges",
+            "write_merges",
+            "time_in_queue",
+            "read_ticks",
+            "write_ticks",
+            "total",
+        ];
+        let temps = vec!["composite_temp".to_owned(), "sensor1_temp".to_owned()];
+        assert_eq!(
+            diskstat_columns(&["nvme0n1".to_owned()], &[&temps]),
+            stats
+                .iter()
+                .chain(&["composite_temp", "sensor1_temp"])
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            diskstat_columns(
+                &["nvme0n1".to_owned(), "nvme1n1".to_owned()],
+                &[&temps, &temps]
+            ),
+            stats
+                .iter()
+                .chain(&[
+                    "nvme0n1-read",
+                    "nvme0n1-write",
+                    "nvme1n1-read",
+                    "nvme1n1-write",
+                    "composite_temp",
+                    "nvme0n1-composite_temp",
+                    "nvme0n1-sensor1_temp",
+                    "nvme1n1-composite_temp",
+                    "nvme1n1-sensor1_temp",
+                ])
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            diskstat_columns(&["sda".to_owned()], &[&Vec::new()]),
+            stats.iter().map(|x| x.to_string()).collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    cmp::min,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 use common::{
     config::Settings,
@@ -36,9 +33,14 @@ impl SensorArgs for NetioHttpConfig {
     fn name(&self) -> SensorKind {
         SensorKind::NetioHttp
     }
+
+    fn sample_interval(&self) -> Option<Duration> {
+        Some(NETIO_INTERVAL)
+    }
 }
 
 const NETIO_FILENAME: &str = "netio-http.csv";
+const NETIO_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -59,7 +61,8 @@ impl Sensor for NetioHttp {
     fn start(
         &self,
         args: &dyn SensorArgs,
-        _: &Settings,
+        settings: &Settings,
+        filename: &str,
         rx: Receiver<SensorRequest>,
         tx: Sender<SensorReply>,
     ) -> Result<JoinHandle<Result<()>>> {
@@ -68,11 +71,14 @@ impl Sensor for NetioHttp {
             .context("Invalid sensor args, expected args for NetioHttp")?;
 
         let args = args.clone();
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
         let handle = spawn(async move {
             if let Err(err) = sensor_reader(
                 rx,
                 tx,
-                NETIO_FILENAME,
+                &filename,
+                flush_rows,
                 args,
                 init_netio_http,
                 |args: &NetioHttpConfig,
@@ -176,10 +182,6 @@ async fn read_netio_http(
         }
     }
 
-    sleep(Duration::from_millis(min(
-        500 - start.elapsed().as_millis() as u64,
-        500,
-    )))
-    .await;
+    sleep(NETIO_INTERVAL.saturating_sub(start.elapsed())).await;
     Ok(data)
 }
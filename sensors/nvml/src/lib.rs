@@ -0,0 +1,209 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use common::{
+    config::Settings,
+    sensor::{Sensor, SensorArgs, SensorReply, SensorRequest},
+    util::{SensorError, TimeSeriesAxis, sensor_reader},
+};
+use eyre::{Context, ContextCompat, Result, bail};
+use flume::{Receiver, Sender};
+use sensor_common::SensorKind;
+use serde::{Deserialize, Serialize};
+use tokio::{process::Command, spawn, task::JoinHandle};
+use tracing::{debug, error, warn};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NvmlConfig {
+    /// Data collection interval in milliseconds, defaults to 500
+    pub interval: Option<u64>,
+}
+
+#[typetag::serde]
+impl SensorArgs for NvmlConfig {
+    fn name(&self) -> SensorKind {
+        SensorKind::Nvml
+    }
+
+    fn sample_interval(&self) -> Option<Duration> {
+        Some(Duration::from_millis(
+            self.interval.unwrap_or(DEFAULT_INTERVAL),
+        ))
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Nvml;
+
+pub const NVML_FILENAME: &str = "nvml.csv";
+const DEFAULT_INTERVAL: u64 = 500;
+
+impl Sensor for Nvml {
+    fn name(&self) -> SensorKind {
+        SensorKind::Nvml
+    }
+
+    fn filename(&self) -> &'static str {
+        NVML_FILENAME
+    }
+
+    fn start(
+        &self,
+        args: &dyn SensorArgs,
+        settings: &Settings,
+        filename: &str,
+        rx: Receiver<SensorRequest>,
+        tx: Sender<SensorReply>,
+    ) -> Result<JoinHandle<Result<()>>> {
+        let args = args
+            .downcast_ref::<NvmlConfig>()
+            .context("Invalid sensor args, expected args for Nvml")?
+            .clone();
+
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
+        let handle = spawn(async move {
+            if let Err(err) = sensor_reader(
+                rx,
+                tx,
+                &filename,
+                flush_rows,
+                args,
+                init_nvml,
+                |args: &NvmlConfig,
+                 sensor: &mut Vec<u32>,
+                 _,
+                 _|
+                 -> std::pin::Pin<
+                    Box<dyn Future<Output = Result<Vec<f64>, SensorError>> + Send>,
+                > {
+                    Box::pin(read_nvml(args.interval.unwrap_or(DEFAULT_INTERVAL), sensor))
+                },
+            )
+            .await
+            {
+                error!("{err:#?}");
+                return Err(err);
+            }
+            Ok(())
+        });
+        Ok(handle)
+    }
+}
+
+/// Power draw (W) of every GPU by its index, `None` for GPUs that do not report it
+async fn query_power() -> Result<Vec<(u32, Option<f64>)>> {
+    let output = Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=index,power.draw",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .await
+        .context("Running nvidia-smi")?;
+    if !output.status.success() {
+        bail!(
+            "nvidia-smi failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|x| !x.trim().is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+/// Parses a line like `0, 71.23`, the power is `[N/A]` if the GPU does not report it
+fn parse_line(line: &str) -> Result<(u32, Option<f64>)> {
+    let (index, power) = line
+        .split_once(',')
+        .context(format!("Unexpected nvidia-smi output: {line}"))?;
+    let index = index
+        .trim()
+        .parse()
+        .context(format!("Parsing GPU index of {line}"))?;
+    Ok((index, power.trim().parse().ok()))
+}
+
+async fn init_nvml(_: NvmlConfig) -> Result<(Vec<u32>, Vec<String>)> {
+    let gpus = match query_power().await {
+        Ok(gpus) => gpus
+            .into_iter()
+            .filter_map(|(index, power)| power.map(|_| index))
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            warn!("{err:#}, nvml sensor will not record any data");
+            return Ok((Vec::new(), Vec::new()));
+        }
+    };
+    if gpus.is_empty() {
+        warn!("No GPUs report their power draw, nvml sensor will not record any data");
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    debug!("Recording power of GPUs {gpus:?}");
+    let columns = gpus
+        .iter()
+        .map(|x| format!("gpu-{x}-power"))
+        .chain(std::iter::once("Total".to_owned()))
+        .collect();
+    Ok((gpus, columns))
+}
+
+async fn read_nvml(interval: u64, gpus: &[u32]) -> Result<Vec<f64>, SensorError> {
+    let start = Instant::now();
+    let mut readings = Vec::with_capacity(gpus.len() + 1);
+    if !gpus.is_empty() {
+        let power = query_power().await.map_err(SensorError::MajorFailure)?;
+        for gpu in gpus {
+            let value = power
+                .iter()
+                .find(|x| x.0 == *gpu)
+                .and_then(|x| x.1)
+                .context(format!("No power draw of GPU {gpu}"))
+                .map_err(SensorError::MajorFailure)?;
+            readings.push(value);
+        }
+        readings.push(readings.iter().sum());
+    }
+    async_io::Timer::after(Duration::from_millis(interval).saturating_sub(start.elapsed())).await;
+    Ok(readings)
+}
+
+/// GPU power of a run, empty if no GPU power was recorded in `data_path`, ie. without GPUs that
+/// report their power draw
+pub fn nvml_plot_axis(data_path: &Path) -> Vec<TimeSeriesAxis> {
+    let mut header = String::new();
+    if File::open(data_path.join(NVML_FILENAME))
+        .and_then(|file| BufReader::new(file).read_line(&mut header))
+        .is_err()
+        || !header.trim_end().split(',').any(|x| x == "Total")
+    {
+        return Vec::new();
+    }
+    vec![TimeSeriesAxis::sensor(
+        NVML_FILENAME,
+        "total_smoothed",
+        "GPU Power",
+        "GPU Power (Watts)",
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nvidia_smi_lines() {
+        assert_eq!(parse_line("0, 71.23").unwrap(), (0, Some(71.23)));
+        assert_eq!(parse_line("3, [N/A]").unwrap(), (3, None));
+        assert!(parse_line("No devices were found").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    fs::{File, read_to_string},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use common::{
+    config::Settings,
+    sensor::{Sensor, SensorArgs, SensorReply, SensorRequest},
+    util::{SensorError, TimeSeriesAxis, sensor_reader},
+};
+use eyre::{Context, ContextCompat, Result};
+use flume::{Receiver, Sender};
+use sensor_common::SensorKind;
+use serde::{Deserialize, Serialize};
+use tokio::{spawn, task::JoinHandle};
+use tracing::{debug, error, warn};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PcieConfig {
+    /// Data collection interval in milliseconds, defaults to 100
+    pub interval: Option<u64>,
+}
+
+#[typetag::serde]
+impl SensorArgs for PcieConfig {
+    fn name(&self) -> SensorKind {
+        SensorKind::Pcie
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InternalPcieConfig {
+    device: String,
+    interval: u64,
+}
+
+#[typetag::serde]
+impl SensorArgs for InternalPcieConfig {
+    fn name(&self) -> SensorKind {
+        SensorKind::Pcie
+    }
+
+    fn sample_interval(&self) -> Option<Duration> {
+        Some(Duration::from_millis(self.interval))
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pcie;
+
+const PCIE_FILENAME: &str = "pcie.csv";
+const DEFAULT_INTERVAL: u64 = 100;
+
+/// ASPM link states, in the order of their bit in the `aspm_state` column
+const ASPM_STATES: [&str; 4] = ["l0s_aspm", "l1_aspm", "l1_1_aspm", "l1_2_aspm"];
+
+impl Sensor for Pcie {
+    fn name(&self) -> SensorKind {
+        SensorKind::Pcie
+    }
+
+    fn filename(&self) -> &'static str {
+        PCIE_FILENAME
+    }
+
+    fn start(
+        &self,
+        args: &dyn SensorArgs,
+        settings: &Settings,
+        filename: &str,
+        rx: Receiver<SensorRequest>,
+        tx: Sender<SensorReply>,
+    ) -> Result<JoinHandle<Result<()>>> {
+        let args = args
+            .downcast_ref::<PcieConfig>()
+            .context("Invalid sensor args, expected args for Pcie")?;
+
+        let device = settings.device.single()?;
+        let config = InternalPcieConfig {
+            device: device.strip_prefix("/dev/").unwrap_or(device).to_string(),
+            interval: args.interval.unwrap_or(DEFAULT_INTERVAL),
+        };
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
+        let handle = spawn(async move {
+            if let Err(err) = sensor_reader(
+                rx,
+                tx,
+                &filename,
+                flush_rows,
+                config,
+                init_pcie,
+                |args: &InternalPcieConfig,
+                 sensor: &mut Option<PcieLink>,
+                 _,
+                 _|
+                 -> std::pin::Pin<
+                    Box<dyn Future<Output = Result<Vec<f64>, SensorError>> + Send>,
+                > { Box::pin(read_pcie(args.interval, sensor)) },
+            )
+            .await
+            {
+                error!("{err:#?}");
+                return Err(err);
+            }
+            Ok(())
+        });
+        Ok(handle)
+    }
+}
+
+struct PcieLink {
+    dir: PathBuf,
+}
+
+impl PcieLink {
+    /// Finds the PCI device backing a block device, `None` if it is not PCIe-attached
+    fn find(device: &str) -> Result<Option<Self>> {
+        let mut block = PathBuf::from(format!("/sys/class/block/{device}"))
+            .canonicalize()
+            .context(format!("Resolving block device {device}"))?;
+        if block.join("partition").exists() {
+            block.pop();
+        }
+
+        // block device -> nvme controller -> pci device
+        let dir = block.join("device/device");
+        if !dir.join("current_link_speed").exists() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            dir: dir.canonicalize()?,
+        }))
+    }
+
+    fn read(&self) -> Result<Vec<f64>> {
+        let link_speed = read_to_string(self.dir.join("current_link_speed"))
+            .context("Reading current_link_speed")?;
+        // eg. "16.0 GT/s PCIe", or "Unknown" when the link is down
+        let link_speed = link_speed
+            .split_whitespace()
+            .next()
+            .and_then(|x| x.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        let link_width = read_to_string(self.dir.join("current_link_width"))
+            .context("Reading current_link_width")?
+            .trim()
+            .parse::<f64>()
+            .unwrap_or(0.0);
+
+        let mut aspm_state = 0u32;
+        for (bit, state) in ASPM_STATES.iter().enumerate() {
+            if let Ok(enabled) = read_to_string(self.dir.join("link").join(state))
+                && enabled.trim() == "1"
+            {
+                aspm_state |= 1 << bit;
+            }
+        }
+
+        Ok(vec![link_speed, link_width, aspm_state as f64])
+    }
+}
+
+async fn init_pcie(config: InternalPcieConfig) -> Result<(Option<PcieLink>, Vec<String>)> {
+    let Some(link) = PcieLink::find(&config.device)? else {
+        warn!(
+            "{} is not PCIe-attached, pcie sensor will not record any data",
+            config.device
+        );
+        return Ok((None, Vec::new()));
+    };
+
+    debug!("Recording PCIe link state from {}", link.dir.display());
+    _ = link.read()?;
+    Ok((
+        Some(link),
+        ["pcie_link_speed", "pcie_link_width", "aspm_state"]
+            .into_iter()
+            .map(|x| x.to_owned())
+            .collect(),
+    ))
+}
+
+async fn read_pcie(interval: u64, sensor: &Option<PcieLink>) -> Result<Vec<f64>, SensorError> {
+    let start = Instant::now();
+    let readings = match sensor {
+        Some(link) => link.read().map_err(SensorError::MajorFailure)?,
+        None => Vec::new(),
+    };
+    async_io::Timer::after(Duration::from_millis(interval).saturating_sub(start.elapsed())).await;
+    Ok(readings)
+}
+
+/// PCIe link axes for the columns recorded in the run's `pcie.csv`, empty if
+/// the device was not PCIe-attached.
+pub fn pcie_plot_axis(data_path: &Path) -> Vec<TimeSeriesAxis> {
+    let mut header = String::new();
+    if File::open(data_path.join(PCIE_FILENAME))
+        .and_then(|file| BufReader::new(file).read_line(&mut header))
+        .is_err()
+    {
+        return Vec::new();
+    }
+    header
+        .trim_end()
+        .split(',')
+        .filter_map(|x| match x {
+            "pcie_link_speed" => Some(TimeSeriesAxis::sensor(
+                PCIE_FILENAME,
+                x,
+                "PCIe link speed",
+                "PCIe link speed (GT/s)",
+            )),
+            "aspm_state" => Some(TimeSeriesAxis::sensor(
+                PCIE_FILENAME,
+                x,
+                "ASPM state",
+                "ASPM state",
+            )),
+            _ => None,
+        })
+        .collect()
+}
@@ -131,6 +131,7 @@ impl Sensor for Pmt {
                 rx,
                 tx,
                 &format!("pmt-{:?}", args.sensor),
+                None,
                 args,
                 init_pmt,
                 |args: &PmtConfig,
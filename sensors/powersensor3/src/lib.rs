@@ -25,7 +25,7 @@ use common::{
     util::{SensorError, TimeSeriesAxis, sensor_reader},
 };
 use cxx::UniquePtr;
-use eyre::{Context, ContextCompat, Result};
+use eyre::{Context, ContextCompat, Result, bail};
 use flume::{Receiver, Sender};
 use sensor_common::SensorKind;
 use serde::{Deserialize, Serialize};
@@ -81,6 +81,18 @@ impl SensorState {
 #[serde(deny_unknown_fields)]
 pub struct Powersensor3Config {
     pub device: String,
+    /// Sensor pairs to record, `-1` records the total across all pairs. Defaults to `[-1, 1, 2]`
+    pub indexes: Option<Vec<i32>>,
+}
+
+const DEFAULT_INDEXES: [i32; 3] = [-1, 1, 2];
+
+impl Powersensor3Config {
+    fn indexes(&self) -> Vec<i32> {
+        self.indexes
+            .clone()
+            .unwrap_or_else(|| DEFAULT_INDEXES.to_vec())
+    }
 }
 
 #[typetag::serde]
@@ -90,7 +102,7 @@ impl SensorArgs for Powersensor3Config {
     }
 }
 
-const POWERSENSOR_FILENAME: &str = "powersensor3.csv";
+pub const POWERSENSOR_FILENAME: &str = common::sensor::POWERSENSOR3_FILENAME;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -108,7 +120,8 @@ impl Sensor for Powersensor3 {
     fn start(
         &self,
         args: &dyn SensorArgs,
-        _: &Settings,
+        settings: &Settings,
+        filename: &str,
         rx: Receiver<SensorRequest>,
         tx: Sender<SensorReply>,
     ) -> Result<JoinHandle<Result<()>>> {
@@ -117,20 +130,25 @@ impl Sensor for Powersensor3 {
             .context("Invalid sensor args, expected args for PowerSensor3")?;
 
         let args = args.clone();
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
         let handle = spawn(async move {
             if let Err(err) = sensor_reader(
                 rx,
                 tx,
-                POWERSENSOR_FILENAME,
+                &filename,
+                flush_rows,
                 args,
                 init_powersensor3,
-                |_: &Powersensor3Config,
+                |args: &Powersensor3Config,
                  sensor: &mut InternalPowersensor3,
                  _,
                  last_time|
                  -> std::pin::Pin<
                     Box<dyn Future<Output = Result<Vec<f64>, SensorError>> + Send>,
-                > { Box::pin(read_powersensor3(sensor, last_time)) },
+                > {
+                    Box::pin(read_powersensor3(args.indexes(), sensor, last_time))
+                },
             )
             .await
             {
@@ -146,15 +164,26 @@ impl Sensor for Powersensor3 {
 async fn init_powersensor3(
     args: Powersensor3Config,
 ) -> Result<(InternalPowersensor3, Vec<String>)> {
+    let indexes = args.indexes();
+    if !indexes.contains(&-1) {
+        bail!("Powersensor3 indexes must include -1, the Total column the plotters read");
+    }
+
     let sensor = InternalPowersensor3::new(&args.device)?;
-    let mut sensor_names = vec!["Total".to_owned()];
-    sensor_names.push(sensor.get_sensor_name(1)?);
-    sensor_names.push(sensor.get_sensor_name(2)?);
-    debug!("Powersensor3 initialized");
+    let mut sensor_names = Vec::new();
+    for idx in indexes {
+        if idx == -1 {
+            sensor_names.push("Total".to_owned());
+        } else {
+            sensor_names.push(sensor.get_sensor_name(idx)?);
+        }
+    }
+    debug!("Powersensor3 initialized with sensors {sensor_names:?}");
     Ok((sensor, sensor_names))
 }
 
 async fn read_powersensor3(
+    indexes: Vec<i32>,
     sensor: &InternalPowersensor3,
     _: Instant,
 ) -> Result<Vec<f64>, SensorError> {
@@ -168,11 +197,10 @@ async fn read_powersensor3(
         .context("Read sensor")
         .map_err(SensorError::MajorFailure)?;
 
-    Ok(vec![
-        start.watts(&end, Some(-1)),
-        start.watts(&end, Some(1)),
-        start.watts(&end, Some(2)),
-    ])
+    Ok(indexes
+        .into_iter()
+        .map(|idx| start.watts(&end, Some(idx)))
+        .collect())
 }
 
 pub static POWERSENSOR_PLOT_AXIS: LazyLock<[TimeSeriesAxis; 1]> = LazyLock::new(|| {
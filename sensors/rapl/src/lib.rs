@@ -1,6 +1,8 @@
 use std::{
-    fs::File,
-    os::unix::fs::FileExt,
+    collections::HashSet,
+    fs::{File, read_to_string},
+    io::Read,
+    os::{fd::FromRawFd, unix::fs::FileExt},
     sync::LazyLock,
     time::{Duration, Instant},
 };
@@ -10,13 +12,13 @@ use common::{
     sensor::{Sensor, SensorArgs, SensorReply, SensorRequest},
     util::{SensorError, TimeSeriesAxis, blocking_sensor_reader, get_cpu_topology},
 };
-use eyre::{ContextCompat, Result};
+use eyre::{ContextCompat, Result, bail};
 use flume::{Receiver, Sender};
 use sensor_common::SensorKind;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::{runtime::Handle, spawn, task::JoinHandle};
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 
 #[derive(Error, Debug)]
 pub enum RaplError {
@@ -30,9 +32,29 @@ pub enum RaplError {
     IoError(#[from] std::io::Error),
 }
 
+const PERF_POWER_DIR: &str = "/sys/bus/event_source/devices/power";
+
+/// Interface the energy counters of every package are read from
+enum RaplInterface {
+    /// `energy_uj` files of the powercap sysfs, in microjoules
+    Powercap(Vec<(File, File)>),
+    /// Counters of the `power/energy-pkg/` and `power/energy-ram/` perf events, which may be
+    /// readable when the powercap files are not, ie. with `perf_event_paranoid` set to 0, the
+    /// counters are multiplied by their scale (Joules per count)
+    PerfEvent {
+        counters: Vec<(File, Option<File>)>,
+        pkg_scale: f64,
+        ram_scale: f64,
+    },
+}
+
 struct InternalRapl {
     packages: Vec<u32>,
-    files: Vec<(File, File)>,
+    interface: RaplInterface,
+    /// Values (µJ) the CPU and DRAM energy counters of every package wrap around at
+    max_energy: Vec<(u64, u64)>,
+    /// Packages whose stalled energy counter was already warned about
+    warned_stalled: HashSet<u32>,
 }
 
 impl InternalRapl {
@@ -44,54 +66,201 @@ impl InternalRapl {
         packages.sort();
         debug!("cpu packages: {packages:?}");
 
+        let interface = match Self::open_powercap(&packages) {
+            Ok(files) => RaplInterface::Powercap(files),
+            Err(powercap_err) => {
+                let interface = Self::open_perf_event(&packages).map_err(|perf_err| {
+                    RaplError::CreationFailed(format!(
+                        "powercap: {powercap_err}, perf_event: {perf_err}"
+                    ))
+                })?;
+                info!("Powercap RAPL files are not readable ({powercap_err}), using perf_event");
+                interface
+            }
+        };
+        let max_energy = match &interface {
+            RaplInterface::Powercap(_) => packages
+                .iter()
+                .map(|package| {
+                    Ok((
+                        max_energy_range(&format!("intel-rapl:{package}"))?,
+                        max_energy_range(&format!("intel-rapl:{package}:0"))?,
+                    ))
+                })
+                .collect::<Result<_, RaplError>>()?,
+            // perf accumulates the counters in 64 bits
+            RaplInterface::PerfEvent { .. } => vec![(u64::MAX, u64::MAX); packages.len()],
+        };
+        debug!("max energy ranges: {max_energy:?}");
+        Ok(Self {
+            packages,
+            interface,
+            max_energy,
+            warned_stalled: HashSet::new(),
+        })
+    }
+
+    /// Packages whose CPU energy counter did not change between `start` and `end` and were not
+    /// reported before, an idle package of a multi-socket system may not advance within a sample
+    fn newly_stalled(
+        &mut self,
+        start: &[(u64, u64)],
+        end: &[(u64, u64)],
+    ) -> Result<Vec<u32>, SensorError> {
+        let stalled = self
+            .packages
+            .iter()
+            .zip(start.iter().zip(end))
+            .filter(|(_, (x, y))| x.0 == y.0)
+            .map(|(package, _)| *package)
+            .collect::<Vec<_>>();
+        if stalled.len() == self.packages.len() {
+            return Err(SensorError::NoChanges);
+        }
+        Ok(stalled
+            .into_iter()
+            .filter(|x| self.warned_stalled.insert(*x))
+            .collect())
+    }
+
+    fn open_powercap(packages: &[u32]) -> Result<Vec<(File, File)>, RaplError> {
         let mut files = Vec::new();
-        for package in &packages {
+        for package in packages {
             debug!("/sys/class/powercap/intel-rapl:{package}/energy_uj");
             debug!("/sys/class/powercap/intel-rapl:{package}:0/energy_uj");
             let cpu = File::open(format!(
                 "/sys/class/powercap/intel-rapl:{package}/energy_uj"
-            ))
-            .map_err(|e| RaplError::CreationFailed(e.to_string()))?;
+            ))?;
             let dram = File::open(format!(
                 "/sys/class/powercap/intel-rapl:{package}:0/energy_uj"
-            ))
-            .map_err(|e| RaplError::CreationFailed(e.to_string()))?;
+            ))?;
+            // opening succeeds for unprivileged users, reading does not
+            cpu.read_at(&mut [0u8; 32], 0)?;
             files.push((cpu, dram));
         }
+        Ok(files)
+    }
+
+    fn open_perf_event(packages: &[u32]) -> Result<RaplInterface, RaplError> {
+        let pmu_type = read_to_string(format!("{PERF_POWER_DIR}/type"))?
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| RaplError::CreationFailed(e.to_string()))?;
+        let (pkg_event, pkg_scale) = perf_event("energy-pkg")?;
+        let ram_event = perf_event("energy-ram");
+        if ram_event.is_err() {
+            warn!("No power/energy-ram/ perf event, DRAM power is recorded as 0");
+        }
+
+        // the PMU counts on one CPU of every package
+        let mut package_cpus = Vec::new();
+        for cpu in parse_cpu_list(&read_to_string(format!("{PERF_POWER_DIR}/cpumask"))?)? {
+            let package = read_to_string(format!(
+                "/sys/devices/system/cpu/cpu{cpu}/topology/physical_package_id"
+            ))?
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| RaplError::CreationFailed(e.to_string()))?;
+            package_cpus.push((package, cpu));
+        }
+
+        let mut counters = Vec::new();
+        for package in packages {
+            let Some((_, cpu)) = package_cpus.iter().find(|(x, _)| x == package) else {
+                return Err(RaplError::CreationFailed(format!(
+                    "No CPU of package {package} in {PERF_POWER_DIR}/cpumask"
+                )));
+            };
+            let pkg = open_perf_counter(pmu_type, pkg_event, *cpu)?;
+            let ram = match &ram_event {
+                Ok((event, _)) => Some(open_perf_counter(pmu_type, *event, *cpu)?),
+                Err(_) => None,
+            };
+            counters.push((pkg, ram));
+        }
+        Ok(RaplInterface::PerfEvent {
+            counters,
+            pkg_scale,
+            ram_scale: ram_event.map_or(0.0, |x| x.1),
+        })
+    }
 
-        Ok(Self { packages, files })
+    fn len(&self) -> usize {
+        match &self.interface {
+            RaplInterface::Powercap(files) => files.len(),
+            RaplInterface::PerfEvent { counters, .. } => counters.len(),
+        }
     }
 
+    /// Energy counters of every package in microjoules
     fn read(&self, result: &mut [(u64, u64)]) {
-        let mut buf_cpu = [0u8; 32];
-        let mut buf_dram = [0u8; 32];
-        self.files.iter().zip(result.iter_mut()).for_each(|(s, r)| {
-            use atoi::FromRadix10;
-            let cpu = s.0.read_at(&mut buf_cpu, 0).unwrap();
-            let dram = s.1.read_at(&mut buf_dram, 0).unwrap();
-            let cpu = u64::from_radix_10(&buf_cpu[0..cpu]).0;
-            let dram = u64::from_radix_10(&buf_dram[0..dram]).0;
-            *r = (cpu, dram)
-        });
+        match &self.interface {
+            RaplInterface::Powercap(files) => {
+                let mut buf_cpu = [0u8; 32];
+                let mut buf_dram = [0u8; 32];
+                files.iter().zip(result.iter_mut()).for_each(|(s, r)| {
+                    use atoi::FromRadix10;
+                    let cpu = s.0.read_at(&mut buf_cpu, 0).unwrap();
+                    let dram = s.1.read_at(&mut buf_dram, 0).unwrap();
+                    let cpu = u64::from_radix_10(&buf_cpu[0..cpu]).0;
+                    let dram = u64::from_radix_10(&buf_dram[0..dram]).0;
+                    *r = (cpu, dram)
+                });
+            }
+            RaplInterface::PerfEvent {
+                counters,
+                pkg_scale,
+                ram_scale,
+            } => {
+                let to_uj = |counter: &File, scale: f64| {
+                    let mut buf = [0u8; 8];
+                    (&*counter).read_exact(&mut buf).unwrap();
+                    (u64::from_ne_bytes(buf) as f64 * scale * 1e6) as u64
+                };
+                counters.iter().zip(result.iter_mut()).for_each(|(s, r)| {
+                    let cpu = to_uj(&s.0, *pkg_scale);
+                    let dram = s.1.as_ref().map_or(0, |x| to_uj(x, *ram_scale));
+                    *r = (cpu, dram)
+                });
+            }
+        }
     }
 
-    fn watts(start: u64, end: u64, elapsed: u64) -> f64 {
-        let entry = (end - start) as f64 / 1e6;
+    /// Power from two energy counter readings `elapsed` µs apart, a counter that is lower than at
+    /// the start wrapped around at `max_energy`
+    fn watts(start: u64, end: u64, max_energy: u64, elapsed: u64) -> f64 {
+        let energy = if end >= start {
+            end - start
+        } else {
+            max_energy - start + end
+        };
+        let entry = energy as f64 / 1e6;
         entry / (elapsed as f64 / 1e6)
     }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
-pub struct RaplConfig;
+#[serde(deny_unknown_fields)]
+pub struct RaplConfig {
+    /// Time between the two energy reads of a sample in microseconds, defaults to 1000
+    pub interval_us: Option<u64>,
+}
 
 #[typetag::serde]
 impl SensorArgs for RaplConfig {
     fn name(&self) -> SensorKind {
         SensorKind::Rapl
     }
+
+    fn sample_interval(&self) -> Option<Duration> {
+        Some(Duration::from_micros(
+            self.interval_us.unwrap_or(DEFAULT_INTERVAL_US),
+        ))
+    }
 }
 
-const RAPL_FILENAME: &str = "rapl.csv";
+const RAPL_FILENAME: &str = common::sensor::RAPL_FILENAME;
+const DEFAULT_INTERVAL_US: u64 = 1000;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Rapl;
@@ -108,7 +277,8 @@ impl Sensor for Rapl {
     fn start(
         &self,
         args: &dyn common::sensor::SensorArgs,
-        _: &Settings,
+        settings: &Settings,
+        filename: &str,
         rx: Receiver<SensorRequest>,
         tx: Sender<SensorReply>,
     ) -> Result<JoinHandle<Result<()>>> {
@@ -117,12 +287,15 @@ impl Sensor for Rapl {
             .context("Invalid sensor args, expected args for Rapl")?;
 
         let args = args.clone();
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
         let handle = spawn(async move {
             tokio::task::block_in_place(move || {
                 if let Err(err) = blocking_sensor_reader(
                     rx,
                     tx,
-                    RAPL_FILENAME,
+                    &filename,
+                    flush_rows,
                     args,
                     init_rapl,
                     |args, sensor, _, last_time| -> Result<Vec<f64>, SensorError> {
@@ -139,7 +312,10 @@ impl Sensor for Rapl {
     }
 }
 
-fn init_rapl(_: RaplConfig) -> Result<(InternalRapl, Vec<String>)> {
+fn init_rapl(config: RaplConfig) -> Result<(InternalRapl, Vec<String>)> {
+    if config.interval_us == Some(0) {
+        bail!("interval_us of Rapl must be greater than 0");
+    }
     let sensor = InternalRapl::new()?;
     let mut sensor_names = sensor
         .packages
@@ -152,30 +328,33 @@ fn init_rapl(_: RaplConfig) -> Result<(InternalRapl, Vec<String>)> {
 }
 
 fn read_rapl(
-    _: &RaplConfig,
+    config: &RaplConfig,
     sensor: &mut InternalRapl,
     _: Instant,
 ) -> Result<Vec<f64>, SensorError> {
-    let mut start = vec![(0u64, 0u64); sensor.files.len()];
-    let mut end = vec![(0u64, 0u64); sensor.files.len()];
+    let mut start = vec![(0u64, 0u64); sensor.len()];
+    let mut end = vec![(0u64, 0u64); sensor.len()];
     let sensor_read_time = Instant::now();
     sensor.read(&mut start);
-    std::thread::sleep(Duration::from_micros(1000));
+    std::thread::sleep(Duration::from_micros(
+        config.interval_us.unwrap_or(DEFAULT_INTERVAL_US),
+    ));
     sensor.read(&mut end);
     let sensor_end_time = sensor_read_time.elapsed().as_micros() as u64;
 
-    let no_changes = start.iter().zip(end.iter()).any(|(x, y)| x.0 >= y.0);
-    if no_changes {
-        return Err(SensorError::NoChanges);
+    let stalled = sensor.newly_stalled(&start, &end)?;
+    if !stalled.is_empty() {
+        warn!("RAPL energy counters of packages {stalled:?} did not change");
     }
 
     let mut readings = start
         .iter()
         .zip(&end)
-        .flat_map(|(start, end)| {
+        .zip(&sensor.max_energy)
+        .flat_map(|((start, end), max_energy)| {
             [
-                InternalRapl::watts(start.0, end.0, sensor_end_time),
-                InternalRapl::watts(start.1, end.1, sensor_end_time),
+                InternalRapl::watts(start.0, end.0, max_energy.0, sensor_end_time),
+                InternalRapl::watts(start.1, end.1, max_energy.1, sensor_end_time),
             ]
         })
         .collect::<Vec<_>>();
@@ -183,6 +362,81 @@ fn read_rapl(
     Ok(readings)
 }
 
+/// `max_energy_range_uj` of a powercap zone, ie. `intel-rapl:0`
+fn max_energy_range(zone: &str) -> Result<u64, RaplError> {
+    read_to_string(format!("/sys/class/powercap/{zone}/max_energy_range_uj"))?
+        .trim()
+        .parse()
+        .map_err(|e| {
+            RaplError::CreationFailed(format!("Invalid max_energy_range_uj of {zone}: {e}"))
+        })
+}
+
+/// Config and scale of a RAPL perf event, ie. `energy-pkg`, from `events/<name>` (`event=0x02`)
+/// and `events/<name>.scale`
+fn perf_event(name: &str) -> Result<(u64, f64), RaplError> {
+    let config = read_to_string(format!("{PERF_POWER_DIR}/events/{name}"))?;
+    let event = parse_perf_event_config(&config)
+        .ok_or_else(|| RaplError::CreationFailed(format!("Invalid perf event {name}: {config}")))?;
+    let scale = read_to_string(format!("{PERF_POWER_DIR}/events/{name}.scale"))?
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| RaplError::CreationFailed(e.to_string()))?;
+    Ok((event, scale))
+}
+
+/// Event of a perf event config, ie. `event=0x02`, other terms like `umask=0x0` are ignored
+fn parse_perf_event_config(config: &str) -> Option<u64> {
+    config
+        .trim()
+        .split(',')
+        .find_map(|x| x.strip_prefix("event="))
+        .and_then(|x| u64::from_str_radix(x.trim_start_matches("0x"), 16).ok())
+}
+
+/// Opens a system wide counter of `event` of the RAPL PMU on `cpu`
+fn open_perf_counter(pmu_type: u32, event: u64, cpu: u32) -> Result<File, RaplError> {
+    use perf_event_open_sys::{bindings, perf_event_open};
+
+    let mut attr = bindings::perf_event_attr {
+        type_: pmu_type,
+        size: size_of::<bindings::perf_event_attr>() as u32,
+        config: event,
+        ..Default::default()
+    };
+    // SAFETY: attr is a valid perf_event_attr that outlives the call
+    let fd = unsafe {
+        perf_event_open(
+            &mut attr,
+            -1,
+            cpu as i32,
+            -1,
+            bindings::PERF_FLAG_FD_CLOEXEC as u64,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    // SAFETY: fd is a newly opened file descriptor owned by nothing else
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// CPUs of a sysfs CPU list, ie. `0,28-29`
+fn parse_cpu_list(list: &str) -> Result<Vec<u32>, RaplError> {
+    let parse = |x: &str| {
+        x.parse::<u32>()
+            .map_err(|_| RaplError::CreationFailed(format!("Invalid CPU list {list}")))
+    };
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|x| !x.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => cpus.extend(parse(start)?..=parse(end)?),
+            None => cpus.push(parse(range)?),
+        }
+    }
+    Ok(cpus)
+}
+
 pub static RAPL_PLOT_AXIS: LazyLock<[TimeSeriesAxis; 1]> = LazyLock::new(|| {
     [TimeSeriesAxis::sensor(
         RAPL_FILENAME,
@@ -191,3 +445,69 @@ pub static RAPL_PLOT_AXIS: LazyLock<[TimeSeriesAxis; 1]> = LazyLock::new(|| {
         "CPU Power (Watts)",
     )]
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_wraps_mid_interval() {
+        let max_energy = 262_143_328_850;
+        // 0.4 J before and 0.6 J after the wrap, in 1 s
+        assert_eq!(
+            InternalRapl::watts(max_energy - 400_000, 600_000, max_energy, 1_000_000),
+            1.0
+        );
+        assert_eq!(
+            InternalRapl::watts(1_000_000, 3_000_000, max_energy, 1_000_000),
+            2.0
+        );
+    }
+
+    #[test]
+    fn warns_about_stalled_packages_once() {
+        let mut sensor = InternalRapl {
+            packages: vec![0, 1],
+            interface: RaplInterface::Powercap(Vec::new()),
+            max_energy: vec![(u64::MAX, u64::MAX); 2],
+            warned_stalled: HashSet::new(),
+        };
+        let start = [(10, 10), (20, 20)];
+        assert_eq!(
+            sensor.newly_stalled(&start, &[(11, 11), (20, 21)]).unwrap(),
+            vec![1]
+        );
+        assert!(
+            sensor
+                .newly_stalled(&start, &[(11, 11), (20, 21)])
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            sensor.newly_stalled(&start, &[(10, 11), (21, 21)]).unwrap(),
+            vec![0]
+        );
+        assert!(matches!(
+            sensor.newly_stalled(&start, &start),
+            Err(SensorError::NoChanges)
+        ));
+    }
+
+    #[test]
+    fn parses_perf_event_config() {
+        assert_eq!(parse_perf_event_config("event=0x02\n"), Some(2));
+        assert_eq!(parse_perf_event_config("umask=0x0,event=0x1b"), Some(0x1b));
+        assert_eq!(parse_perf_event_config("umask=0x0"), None);
+        assert_eq!(parse_perf_event_config("event=0xzz"), None);
+    }
+
+    #[test]
+    fn parses_cpu_lists() {
+        assert_eq!(parse_cpu_list("0\n").unwrap(), vec![0]);
+        assert_eq!(parse_cpu_list("0,28-30").unwrap(), vec![0, 28, 29, 30]);
+        assert!(parse_cpu_list("").unwrap().is_empty());
+        for list in ["0-", "a", "0,x-2"] {
+            assert!(parse_cpu_list(list).is_err(), "{list}");
+        }
+    }
+}
@@ -1,4 +1,11 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    fs::{File, read_dir, read_to_string},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use common::{
     config::Settings,
@@ -21,6 +28,7 @@ use tracing::error;
 #[serde(deny_unknown_fields)]
 pub struct SysinfoConfig {
     pub interval: u64,
+    pub cstates: Option<bool>,
 }
 
 #[typetag::serde]
@@ -28,6 +36,10 @@ impl SensorArgs for SysinfoConfig {
     fn name(&self) -> SensorKind {
         SensorKind::Sysinfo
     }
+
+    fn sample_interval(&self) -> Option<Duration> {
+        Some(Duration::from_millis(self.interval))
+    }
 }
 
 const SYSINFO_FILENAME: &str = "sysinfo.csv";
@@ -48,7 +60,8 @@ impl Sensor for Sysinfo {
     fn start(
         &self,
         args: &dyn SensorArgs,
-        _: &Settings,
+        settings: &Settings,
+        filename: &str,
         rx: Receiver<SensorRequest>,
         tx: Sender<SensorReply>,
     ) -> Result<JoinHandle<Result<()>>> {
@@ -57,15 +70,18 @@ impl Sensor for Sysinfo {
             .context("Invalid sensor args, expected args for Sysinfo")?;
 
         let args = args.clone();
+        let flush_rows = settings.sensor_flush_rows;
+        let filename = filename.to_owned();
         let handle = spawn(async move {
             if let Err(err) = sensor_reader(
                 rx,
                 tx,
-                SYSINFO_FILENAME,
+                &filename,
+                flush_rows,
                 args,
                 init_sysinfo,
                 |args: &SysinfoConfig,
-                 sensor: &mut Arc<Mutex<System>>,
+                 sensor: &mut Arc<Mutex<SysinfoState>>,
                  request: &SensorRequest,
                  _|
                  -> std::pin::Pin<
@@ -90,7 +106,103 @@ impl Sensor for Sysinfo {
     }
 }
 
-async fn init_sysinfo(_: SysinfoConfig) -> Result<(Arc<Mutex<System>>, Vec<String>)> {
+struct SysinfoState {
+    sys: System,
+    cstates: Option<CStateResidency>,
+}
+
+/// Tracks cumulative cpuidle residency counters so that each reading can be
+/// turned into the percentage of time spent in each C-state since the last one.
+struct CStateResidency {
+    files: Vec<Vec<PathBuf>>,
+    last: Vec<Vec<u64>>,
+    last_time: Instant,
+}
+
+impl CStateResidency {
+    fn new(num_cpus: usize) -> Result<(Self, Vec<String>)> {
+        let cpuidle =
+            |cpu: usize| PathBuf::from(format!("/sys/devices/system/cpu/cpu{cpu}/cpuidle"));
+        let mut states = read_dir(cpuidle(0))
+            .context("Reading cpuidle states")?
+            .filter_map(|x| x.ok())
+            .filter_map(|x| {
+                x.file_name()
+                    .to_str()?
+                    .strip_prefix("state")?
+                    .parse::<usize>()
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        states.sort();
+
+        let names = states
+            .iter()
+            .map(|state| {
+                let name = read_to_string(cpuidle(0).join(format!("state{state}/name")))
+                    .context("Reading cpuidle state name")?;
+                Ok(format!(
+                    "cstate-{}",
+                    name.trim().replace(char::is_whitespace, "_")
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let files = (0..num_cpus)
+            .map(|cpu| {
+                states
+                    .iter()
+                    .map(|state| cpuidle(cpu).join(format!("state{state}/time")))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut residency = Self {
+            last: vec![vec![0; states.len()]; num_cpus],
+            files,
+            last_time: Instant::now(),
+        };
+        residency.last = residency.read_counters()?;
+        Ok((residency, names))
+    }
+
+    fn read_counters(&self) -> Result<Vec<Vec<u64>>> {
+        self.files
+            .iter()
+            .map(|cpu| {
+                cpu.iter()
+                    .map(|file| {
+                        read_to_string(file)
+                            .context("Reading cpuidle state time")?
+                            .trim()
+                            .parse::<u64>()
+                            .context("Parsing cpuidle state time")
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect()
+    }
+
+    /// Residency of each C-state since the previous call, as a percentage of
+    /// wall-clock time averaged over all CPUs.
+    fn read(&mut self) -> Result<Vec<f64>> {
+        let counters = self.read_counters()?;
+        let elapsed = self.last_time.elapsed().as_micros() as f64;
+        self.last_time = Instant::now();
+
+        let num_cpus = counters.len() as f64;
+        let mut residency = vec![0f64; self.last.first().map(|x| x.len()).unwrap_or(0)];
+        for (now, last) in counters.iter().zip(&self.last) {
+            for (r, (n, l)) in residency.iter_mut().zip(now.iter().zip(last)) {
+                *r += n.saturating_sub(*l) as f64 / elapsed * 100.0 / num_cpus;
+            }
+        }
+        self.last = counters;
+        Ok(residency)
+    }
+}
+
+async fn init_sysinfo(args: SysinfoConfig) -> Result<(Arc<Mutex<SysinfoState>>, Vec<String>)> {
     let mut sys = System::new_all();
     sys.refresh_all();
 
@@ -102,35 +214,53 @@ async fn init_sysinfo(_: SysinfoConfig) -> Result<(Arc<Mutex<System>>, Vec<Strin
         .map(|x| format!("cpu-{x}-load"))
         .collect::<Vec<_>>();
 
+    let (cstates, cstate_names) = if args.cstates.unwrap_or(false) {
+        let (cstates, names) = CStateResidency::new(num_cpus)?;
+        (Some(cstates), names)
+    } else {
+        (None, Vec::new())
+    };
+
     Ok((
-        Arc::new(Mutex::new(sys)),
-        cpu_names.into_iter().chain(load_names).collect(),
+        Arc::new(Mutex::new(SysinfoState { sys, cstates })),
+        cpu_names
+            .into_iter()
+            .chain(load_names)
+            .chain(cstate_names)
+            .collect(),
     ))
 }
 
 async fn read_sysinfo(
     config: &SysinfoConfig,
-    sensor: Arc<Mutex<System>>,
+    sensor: Arc<Mutex<SysinfoState>>,
     _: u32,
 ) -> Result<Vec<f64>, SensorError> {
-    let (cpu_freq, load) = spawn_blocking(move || {
-        let mut sys = sensor.blocking_lock_owned();
-        sys.refresh_cpu_all();
-        let cpu_freq = sys
+    let (cpu_freq, load, cstates) = spawn_blocking(move || {
+        let mut state = sensor.blocking_lock_owned();
+        state.sys.refresh_cpu_all();
+        let cpu_freq = state
+            .sys
             .cpus()
             .iter()
             .map(|cpu| cpu.frequency())
             .collect::<Vec<_>>();
-        let load = sys
+        let load = state
+            .sys
             .cpus()
             .iter()
             .map(|cpu| cpu.cpu_usage())
             .collect::<Vec<_>>();
+        let cstates = match state.cstates.as_mut() {
+            Some(cstates) => cstates.read()?,
+            None => Vec::new(),
+        };
 
-        (cpu_freq, load)
+        Ok::<_, eyre::Error>((cpu_freq, load, cstates))
     })
     .await
     .context("Fetching sysinfo")
+    .map_err(SensorError::MajorFailure)?
     .map_err(SensorError::MajorFailure)?;
 
     async_io::Timer::after(Duration::from_millis(config.interval)).await;
@@ -138,6 +268,7 @@ async fn read_sysinfo(
         .into_iter()
         .map(|x| x as f64)
         .chain(load.into_iter().map(|x| x as f64))
+        .chain(cstates)
         .collect::<Vec<_>>();
     Ok(readings)
 }
@@ -169,3 +300,28 @@ pub fn sysinfo_load_plot_axis(cpu_topology: &HashMap<u32, u32>) -> Vec<TimeSerie
         })
         .collect()
 }
+
+/// C-state residency axes for every `cstate-*` column recorded in the run's
+/// `sysinfo.csv`, empty if C-state sampling was disabled.
+pub fn sysinfo_cstate_plot_axis(data_path: &Path) -> Vec<TimeSeriesAxis> {
+    let mut header = String::new();
+    if File::open(data_path.join(SYSINFO_FILENAME))
+        .and_then(|file| BufReader::new(file).read_line(&mut header))
+        .is_err()
+    {
+        return Vec::new();
+    }
+    header
+        .trim_end()
+        .split(',')
+        .filter_map(|x| {
+            let state = x.strip_prefix("cstate-")?;
+            Some(TimeSeriesAxis::sensor(
+                SYSINFO_FILENAME,
+                x,
+                format!("{state} residency"),
+                "C-state residency (%)",
+            ))
+        })
+        .collect()
+}